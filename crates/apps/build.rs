@@ -51,6 +51,19 @@ fn main() {
         }
     };
 
+    if let Ok(val) = env::var("COMPILE_PROTO") {
+        if val.to_ascii_lowercase() == "false" {
+            // Skip compiling proto files
+            return;
+        }
+    }
+
     // Tell Cargo that if the given file changes, to rerun this build script.
     println!("cargo:rerun-if-changed={}", PROTO_SRC);
+
+    tonic_build::configure()
+        .out_dir("src/lib/node/ledger/grpc/generated")
+        .protoc_arg("--experimental_allow_proto3_optional")
+        .compile(&[format!("{}/query.proto", PROTO_SRC)], &[PROTO_SRC])
+        .unwrap();
 }
@@ -33,11 +33,26 @@ pub fn main() -> Result<()> {
                 let chain_ctx = ctx.take_chain_or_exit();
                 ledger::dump_db(chain_ctx.config.ledger, args);
             }
-            cmds::Ledger::RollBack(_) => {
+            cmds::Ledger::ExportState(cmds::LedgerExportState(args)) => {
                 let chain_ctx = ctx.take_chain_or_exit();
-                ledger::rollback(chain_ctx.config.ledger)
+                ledger::export_state(chain_ctx.config.ledger, args)
+                    .wrap_err("Failed to export the state")?;
+            }
+            cmds::Ledger::DebugShell(_) => {
+                let chain_ctx = ctx.take_chain_or_exit();
+                ledger::debug_shell(chain_ctx.config.ledger)
+                    .wrap_err("Failed to run the debug shell")?;
+            }
+            cmds::Ledger::RollBack(cmds::LedgerRollBack(args)) => {
+                let chain_ctx = ctx.take_chain_or_exit();
+                ledger::rollback(chain_ctx.config.ledger, args.checkpoint)
                     .wrap_err("Failed to rollback the Namada node")?;
             }
+            cmds::Ledger::MigrateDb(cmds::LedgerMigrateDb(args)) => {
+                let chain_ctx = ctx.take_chain_or_exit();
+                ledger::migrate_db(chain_ctx.config.ledger, args.dry_run)
+                    .wrap_err("Failed to migrate the Namada node's DB")?;
+            }
         },
         cmds::NamadaNode::Config(sub) => match sub {
             cmds::Config::Gen(cmds::ConfigGen) => {
@@ -168,7 +168,8 @@ impl Default for BenchShell {
             None,
             50 * 1024 * 1024, // 50 kiB
             50 * 1024 * 1024, // 50 kiB
-        );
+        )
+        .unwrap();
         let mut bench_shell = BenchShell {
             inner: shell,
             last_block_masp_txs: vec![],
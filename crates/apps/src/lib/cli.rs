@@ -256,6 +256,7 @@ pub mod cmds {
                 .subcommand(QueryFindValidator::def().display_order(5))
                 .subcommand(QueryResult::def().display_order(5))
                 .subcommand(QueryRawBytes::def().display_order(5))
+                .subcommand(QueryDecode::def().display_order(5))
                 .subcommand(QueryProposal::def().display_order(5))
                 .subcommand(QueryProposalVotes::def().display_order(5))
                 .subcommand(QueryProposalResult::def().display_order(5))
@@ -265,6 +266,7 @@ pub mod cmds {
                 .subcommand(QueryCommissionRate::def().display_order(5))
                 .subcommand(QueryRewards::def().display_order(5))
                 .subcommand(QueryMetaData::def().display_order(5))
+                .subcommand(WatchAddress::def().display_order(5))
                 // Actions
                 .subcommand(SignTx::def().display_order(6))
                 .subcommand(GenIbcShieldedTransafer::def().display_order(6))
@@ -330,6 +332,7 @@ pub mod cmds {
                 Self::parse_with_ctx(matches, QueryFindValidator);
             let query_result = Self::parse_with_ctx(matches, QueryResult);
             let query_raw_bytes = Self::parse_with_ctx(matches, QueryRawBytes);
+            let query_decode = Self::parse_with_ctx(matches, QueryDecode);
             let query_proposal = Self::parse_with_ctx(matches, QueryProposal);
             let query_proposal_votes =
                 Self::parse_with_ctx(matches, QueryProposalVotes);
@@ -343,6 +346,7 @@ pub mod cmds {
             let query_commission =
                 Self::parse_with_ctx(matches, QueryCommissionRate);
             let query_metadata = Self::parse_with_ctx(matches, QueryMetaData);
+            let watch_address = Self::parse_with_ctx(matches, WatchAddress);
             let add_to_eth_bridge_pool =
                 Self::parse_with_ctx(matches, AddToEthBridgePool);
             let sign_tx = Self::parse_with_ctx(matches, SignTx);
@@ -387,6 +391,7 @@ pub mod cmds {
                 .or(query_find_validator)
                 .or(query_result)
                 .or(query_raw_bytes)
+                .or(query_decode)
                 .or(query_proposal)
                 .or(query_proposal_votes)
                 .or(query_proposal_result)
@@ -396,6 +401,7 @@ pub mod cmds {
                 .or(query_commission)
                 .or(query_metadata)
                 .or(query_account)
+                .or(watch_address)
                 .or(sign_tx)
                 .or(gen_ibc_shielded)
                 .or(utils)
@@ -475,6 +481,7 @@ pub mod cmds {
         QueryDelegations(QueryDelegations),
         QueryFindValidator(QueryFindValidator),
         QueryRawBytes(QueryRawBytes),
+        QueryDecode(QueryDecode),
         QueryProposal(QueryProposal),
         QueryProposalVotes(QueryProposalVotes),
         QueryProposalResult(QueryProposalResult),
@@ -482,6 +489,7 @@ pub mod cmds {
         QueryPgf(QueryPgf),
         QueryValidatorState(QueryValidatorState),
         QueryRewards(QueryRewards),
+        WatchAddress(WatchAddress),
         SignTx(SignTx),
         GenIbcShieldedTransafer(GenIbcShieldedTransafer),
     }
@@ -798,7 +806,10 @@ pub mod cmds {
         RunUntil(LedgerRunUntil),
         Reset(LedgerReset),
         DumpDb(LedgerDumpDb),
+        ExportState(LedgerExportState),
+        DebugShell(LedgerDebugShell),
         RollBack(LedgerRollBack),
+        MigrateDb(LedgerMigrateDb),
     }
 
     impl SubCmd for Ledger {
@@ -809,11 +820,18 @@ pub mod cmds {
                 let run = SubCmd::parse(matches).map(Self::Run);
                 let reset = SubCmd::parse(matches).map(Self::Reset);
                 let dump_db = SubCmd::parse(matches).map(Self::DumpDb);
+                let export_state =
+                    SubCmd::parse(matches).map(Self::ExportState);
+                let debug_shell = SubCmd::parse(matches).map(Self::DebugShell);
                 let rollback = SubCmd::parse(matches).map(Self::RollBack);
+                let migrate_db = SubCmd::parse(matches).map(Self::MigrateDb);
                 let run_until = SubCmd::parse(matches).map(Self::RunUntil);
                 run.or(reset)
                     .or(dump_db)
+                    .or(export_state)
+                    .or(debug_shell)
                     .or(rollback)
+                    .or(migrate_db)
                     .or(run_until)
                     // The `run` command is the default if no sub-command given
                     .or(Some(Self::Run(LedgerRun(args::LedgerRun {
@@ -832,7 +850,10 @@ pub mod cmds {
                 .subcommand(LedgerRunUntil::def())
                 .subcommand(LedgerReset::def())
                 .subcommand(LedgerDumpDb::def())
+                .subcommand(LedgerExportState::def())
+                .subcommand(LedgerDebugShell::def())
                 .subcommand(LedgerRollBack::def())
+                .subcommand(LedgerMigrateDb::def())
         }
     }
 
@@ -915,10 +936,34 @@ pub mod cmds {
     }
 
     #[derive(Clone, Debug)]
-    pub struct LedgerRollBack;
+    pub struct LedgerExportState(pub args::LedgerExportState);
 
-    impl SubCmd for LedgerRollBack {
-        const CMD: &'static str = "rollback";
+    impl SubCmd for LedgerExportState {
+        const CMD: &'static str = "export-state";
+
+        fn parse(matches: &ArgMatches) -> Option<Self> {
+            matches
+                .subcommand_matches(Self::CMD)
+                .map(|matches| Self(args::LedgerExportState::parse(matches)))
+        }
+
+        fn def() -> App {
+            App::new(Self::CMD)
+                .about(
+                    "Export the parameters, balances, bonds and validity \
+                     predicates of Namada ledger node's state at a given \
+                     height into a JSON file, e.g. to construct a new genesis \
+                     after a hard fork.",
+                )
+                .add_args::<args::LedgerExportState>()
+        }
+    }
+
+    #[derive(Clone, Debug)]
+    pub struct LedgerDebugShell;
+
+    impl SubCmd for LedgerDebugShell {
+        const CMD: &'static str = "debug-shell";
 
         fn parse(matches: &ArgMatches) -> Option<Self> {
             matches.subcommand_matches(Self::CMD).map(|_matches| Self)
@@ -926,14 +971,61 @@ pub mod cmds {
 
         fn def() -> App {
             App::new(Self::CMD).about(
-                "Roll Namada state back to the previous height. This command \
-                 does not create a backup of neither the Namada nor the \
-                 Tendermint state before execution: for extra safety, it is \
-                 recommended to make a backup in advance.",
+                "Open an interactive shell to inspect Namada ledger node's \
+                 DB. The DB is opened in read-only mode, so this can also be \
+                 used while the node is running.",
             )
         }
     }
 
+    #[derive(Clone, Debug)]
+    pub struct LedgerRollBack(pub args::LedgerRollBack);
+
+    impl SubCmd for LedgerRollBack {
+        const CMD: &'static str = "rollback";
+
+        fn parse(matches: &ArgMatches) -> Option<Self> {
+            matches
+                .subcommand_matches(Self::CMD)
+                .map(|matches| Self(args::LedgerRollBack::parse(matches)))
+        }
+
+        fn def() -> App {
+            App::new(Self::CMD)
+                .about(
+                    "Roll Namada state back to the previous height, or to the \
+                     checkpoint of an epoch. This command does not create a \
+                     backup of neither the Namada nor the Tendermint state \
+                     before execution: for extra safety, it is recommended to \
+                     make a backup in advance.",
+                )
+                .add_args::<args::LedgerRollBack>()
+        }
+    }
+
+    #[derive(Clone, Debug)]
+    pub struct LedgerMigrateDb(pub args::LedgerMigrateDb);
+
+    impl SubCmd for LedgerMigrateDb {
+        const CMD: &'static str = "migrate-db";
+
+        fn parse(matches: &ArgMatches) -> Option<Self> {
+            matches
+                .subcommand_matches(Self::CMD)
+                .map(|matches| Self(args::LedgerMigrateDb::parse(matches)))
+        }
+
+        fn def() -> App {
+            App::new(Self::CMD)
+                .about(
+                    "Apply the pending migrations of Namada ledger node's DB \
+                     format. The migrations are also applied when the node is \
+                     started.",
+                )
+                .add_args::<args::LedgerMigrateDb>()
+        }
+    }
+
     #[derive(Clone, Debug)]
     pub enum Config {
         Gen(ConfigGen),
@@ -1463,6 +1555,42 @@ pub mod cmds {
         }
     }
 
+    #[derive(Clone, Debug)]
+    pub struct WatchAddress(pub args::WatchAddress<args::CliTypes>);
+
+    impl WatchAddress {
+        const SUB_CMD: &'static str = "address";
+    }
+
+    impl SubCmd for WatchAddress {
+        const CMD: &'static str = "watch";
+
+        fn parse(matches: &ArgMatches) -> Option<Self> {
+            matches
+                .subcommand_matches(Self::CMD)
+                .and_then(|matches| matches.subcommand_matches(Self::SUB_CMD))
+                .map(|matches| WatchAddress(args::WatchAddress::parse(matches)))
+        }
+
+        fn def() -> App {
+            App::new(Self::CMD)
+                .about(
+                    "Watch the events of the new blocks with a WebSocket \
+                     subscription to the node.",
+                )
+                .subcommand_required(true)
+                .arg_required_else_help(true)
+                .subcommand(
+                    App::new(Self::SUB_CMD)
+                        .about(
+                            "Print the events of an address as the blocks are \
+                             committed.",
+                        )
+                        .add_args::<args::WatchAddress<args::CliTypes>>(),
+                )
+        }
+    }
+
     #[derive(Clone, Debug)]
     pub struct QueryAccount(pub args::QueryAccount<args::CliTypes>);
 
@@ -1811,6 +1939,28 @@ pub mod cmds {
         }
     }
 
+    #[derive(Clone, Debug)]
+    pub struct QueryDecode(pub args::QueryDecode<args::CliTypes>);
+
+    impl SubCmd for QueryDecode {
+        const CMD: &'static str = "query-decode";
+
+        fn parse(matches: &ArgMatches) -> Option<Self> {
+            matches
+                .subcommand_matches(Self::CMD)
+                .map(|matches| QueryDecode(args::QueryDecode::parse(matches)))
+        }
+
+        fn def() -> App {
+            App::new(Self::CMD)
+                .about(
+                    "Query the value of a given storage key and decode it as \
+                     the given type.",
+                )
+                .add_args::<args::QueryDecode<args::CliTypes>>()
+        }
+    }
+
     #[derive(Clone, Debug)]
     pub struct TxInitProposal(pub args::InitProposal<args::CliTypes>);
 
@@ -2840,6 +2990,7 @@ pub mod args {
     use namada::types::token;
     use namada::types::token::NATIVE_MAX_DECIMAL_PLACES;
     pub use namada_sdk::args::*;
+    use namada_sdk::events::EventType;
     pub use namada_sdk::tx::{
         TX_BECOME_VALIDATOR_WASM, TX_BOND_WASM, TX_BRIDGE_POOL_WASM,
         TX_CHANGE_COMMISSION_WASM, TX_CHANGE_CONSENSUS_KEY_WASM,
@@ -2904,6 +3055,7 @@ pub mod args {
     pub const CHAIN_ID_OPT: ArgOpt<ChainId> = CHAIN_ID.opt();
     pub const CHAIN_ID_PREFIX: Arg<ChainIdPrefix> = arg("chain-prefix");
     pub const CHANNEL_ID: Arg<ChannelId> = arg("channel-id");
+    pub const CHECKPOINT_EPOCH: ArgOpt<Epoch> = arg_opt("checkpoint");
     pub const CODE_PATH: Arg<PathBuf> = arg("code-path");
     pub const CODE_PATH_OPT: ArgOpt<PathBuf> = CODE_PATH.opt();
     pub const COMMISSION_RATE: Arg<Dec> = arg("commission-rate");
@@ -2932,6 +3084,7 @@ pub mod args {
     pub const DRY_RUN_WRAPPER_TX: ArgFlag = flag("dry-run-wrapper");
     pub const DUMP_TX: ArgFlag = flag("dump-tx");
     pub const EPOCH: ArgOpt<Epoch> = arg_opt("epoch");
+    pub const EVENT_TYPE_OPT: ArgOpt<EventType> = arg_opt("event-type");
     pub const ERC20: Arg<EthAddress> = arg("erc20");
     pub const ETH_CONFIRMATIONS: Arg<u64> = arg("confirmations");
     pub const ETH_GAS: ArgOpt<u64> = arg_opt("eth-gas");
@@ -3095,9 +3248,11 @@ pub mod args {
     pub const VALIDATOR_ETH_HOT_KEY: ArgOpt<WalletPublicKey> =
         arg_opt("eth-hot-key");
     pub const VALUE: Arg<String> = arg("value");
+    pub const VALUE_TYPE: Arg<String> = arg("value-type");
     pub const VOTER_OPT: ArgOpt<WalletAddress> = arg_opt("voter");
     pub const VIEWING_KEY: Arg<WalletViewingKey> = arg("key");
     pub const VP: ArgOpt<String> = arg_opt("vp");
+    pub const WAIT: ArgFlag = flag("wait");
     pub const WALLET_ALIAS_FORCE: ArgFlag = flag("wallet-alias-force");
     pub const WASM_CHECKSUMS_PATH: Arg<PathBuf> = arg("wasm-checksums-path");
     pub const WASM_DIR: ArgOpt<PathBuf> = arg_opt("wasm-dir");
@@ -3263,6 +3418,76 @@ pub mod args {
         }
     }
 
+    #[derive(Clone, Debug)]
+    pub struct LedgerExportState {
+        pub block_height: Option<BlockHeight>,
+        pub out_file_path: Option<PathBuf>,
+    }
+
+    impl Args for LedgerExportState {
+        fn parse(matches: &ArgMatches) -> Self {
+            let block_height = BLOCK_HEIGHT_OPT.parse(matches);
+            let out_file_path = OUT_FILE_PATH_OPT.parse(matches);
+
+            Self {
+                block_height,
+                out_file_path,
+            }
+        }
+
+        fn def(app: App) -> App {
+            app.arg(BLOCK_HEIGHT_OPT.def().help(
+                "The block height to export. Defaults to the last committed \
+                 block.",
+            ))
+            .arg(OUT_FILE_PATH_OPT.def().help(
+                "Path for the output file. Defaults to \
+                 \"state_export_{block_height}.json\" in the current working \
+                 directory.",
+            ))
+        }
+    }
+
+    #[derive(Clone, Debug)]
+    pub struct LedgerRollBack {
+        pub checkpoint: Option<Epoch>,
+    }
+
+    impl Args for LedgerRollBack {
+        fn parse(matches: &ArgMatches) -> Self {
+            let checkpoint = CHECKPOINT_EPOCH.parse(matches);
+            Self { checkpoint }
+        }
+
+        fn def(app: App) -> App {
+            app.arg(CHECKPOINT_EPOCH.def().help(
+                "Roll Namada state back to the checkpoint saved at the \
+                 beginning of the given epoch, instead of the previous \
+                 height. The Tendermint state is kept, so the blocks after \
+                 the checkpoint are replayed from it when the node is \
+                 restarted. Requires the `checkpoints_to_keep` config.",
+            ))
+        }
+    }
+
+    #[derive(Clone, Debug)]
+    pub struct LedgerMigrateDb {
+        pub dry_run: bool,
+    }
+
+    impl Args for LedgerMigrateDb {
+        fn parse(matches: &ArgMatches) -> Self {
+            let dry_run = DRY_RUN_TX.parse(matches);
+            Self { dry_run }
+        }
+
+        fn def(app: App) -> App {
+            app.arg(DRY_RUN_TX.def().help(
+                "Only list the pending migrations, without changing the DB.",
+            ))
+        }
+    }
+
     #[derive(Clone, Debug)]
     pub struct UpdateLocalConfig {
         pub config_path: PathBuf,
@@ -5124,6 +5349,43 @@ pub mod args {
         }
     }
 
+    impl CliToSdk<WatchAddress<SdkTypes>> for WatchAddress<CliTypes> {
+        fn to_sdk(self, ctx: &mut Context) -> WatchAddress<SdkTypes> {
+            WatchAddress::<SdkTypes> {
+                query: self.query.to_sdk(ctx),
+                address: ctx.borrow_chain_or_exit().get(&self.address),
+                event_type: self.event_type,
+            }
+        }
+    }
+
+    impl Args for WatchAddress<CliTypes> {
+        fn parse(matches: &ArgMatches) -> Self {
+            let query = Query::parse(matches);
+            let address = ADDRESS.parse(matches);
+            let event_type = EVENT_TYPE_OPT.parse(matches);
+            Self {
+                query,
+                address,
+                event_type,
+            }
+        }
+
+        fn def(app: App) -> App {
+            app.add_args::<Query<CliTypes>>()
+                .arg(
+                    ADDRESS
+                        .def()
+                        .help("The address whose events are watched.")
+                        .required(true),
+                )
+                .arg(EVENT_TYPE_OPT.def().help(
+                    "Only watch the events of the given type, e.g. \
+                     `balance_change`.",
+                ))
+        }
+    }
+
     impl CliToSdk<QueryBalance<SdkTypes>> for QueryBalance<CliTypes> {
         fn to_sdk(self, ctx: &mut Context) -> QueryBalance<SdkTypes> {
             let query = self.query.to_sdk(ctx);
@@ -5861,6 +6123,7 @@ pub mod args {
             QueryRawBytes::<SdkTypes> {
                 query: self.query.to_sdk(ctx),
                 storage_key: self.storage_key,
+                height: self.height,
             }
         }
     }
@@ -5868,13 +6131,62 @@ pub mod args {
     impl Args for QueryRawBytes<CliTypes> {
         fn parse(matches: &ArgMatches) -> Self {
             let storage_key = STORAGE_KEY.parse(matches);
+            let height = BLOCK_HEIGHT_OPT.parse(matches);
             let query = Query::parse(matches);
-            Self { storage_key, query }
+            Self {
+                storage_key,
+                height,
+                query,
+            }
         }
 
         fn def(app: App) -> App {
             app.add_args::<Query<CliTypes>>()
                 .arg(STORAGE_KEY.def().help("Storage key"))
+                .arg(BLOCK_HEIGHT_OPT.def().help(
+                    "The block height at which to query. Defaults to the last \
+                     committed block.",
+                ))
+        }
+    }
+
+    impl CliToSdk<QueryDecode<SdkTypes>> for QueryDecode<CliTypes> {
+        fn to_sdk(self, ctx: &mut Context) -> QueryDecode<SdkTypes> {
+            QueryDecode::<SdkTypes> {
+                query: self.query.to_sdk(ctx),
+                storage_key: self.storage_key,
+                value_type: self.value_type,
+                height: self.height,
+            }
+        }
+    }
+
+    impl Args for QueryDecode<CliTypes> {
+        fn parse(matches: &ArgMatches) -> Self {
+            let storage_key = STORAGE_KEY.parse(matches);
+            let value_type = VALUE_TYPE.parse(matches);
+            let height = BLOCK_HEIGHT_OPT.parse(matches);
+            let query = Query::parse(matches);
+            Self {
+                storage_key,
+                value_type,
+                height,
+                query,
+            }
+        }
+
+        fn def(app: App) -> App {
+            app.add_args::<Query<CliTypes>>()
+                .arg(STORAGE_KEY.def().help("Storage key"))
+                .arg(VALUE_TYPE.def().help(
+                    "The type to decode the value as. One of: address, \
+                     amount, bool, dec, epoch, hash, height, public-key, \
+                     string, u64.",
+                ))
+                .arg(BLOCK_HEIGHT_OPT.def().help(
+                    "The block height at which to query. Defaults to the last \
+                     committed block.",
+                ))
         }
     }
 
@@ -5908,6 +6220,7 @@ pub mod args {
                 output_folder: self.output_folder,
                 force: self.force,
                 broadcast_only: self.broadcast_only,
+                wait: self.wait,
                 ledger_address: ctx.get(&self.ledger_address),
                 initialized_account_alias: self.initialized_account_alias,
                 wallet_alias_force: self.wallet_alias_force,
@@ -5966,6 +6279,15 @@ pub mod args {
                 "Do not wait for the transaction to be applied. This will \
                  return once the transaction is added to the mempool.",
             ))
+            .arg(
+                WAIT.def()
+                    .help(
+                        "Wait for the results of the transaction with a \
+                         WebSocket subscription to the events of the node, \
+                         instead of polling the node.",
+                    )
+                    .conflicts_with(BROADCAST_ONLY.name),
+            )
             .arg(
                 CONFIG_RPC_LEDGER_ADDRESS
                     .def()
@@ -6063,6 +6385,7 @@ pub mod args {
             let dump_tx = DUMP_TX.parse(matches);
             let force = FORCE.parse(matches);
             let broadcast_only = BROADCAST_ONLY.parse(matches);
+            let wait = WAIT.parse(matches);
             let ledger_address = CONFIG_RPC_LEDGER_ADDRESS.parse(matches);
             let initialized_account_alias = ALIAS_OPT.parse(matches);
             let fee_amount =
@@ -6089,6 +6412,7 @@ pub mod args {
                 dump_tx,
                 force,
                 broadcast_only,
+                wait,
                 ledger_address,
                 initialized_account_alias,
                 wallet_alias_force,
@@ -39,6 +39,7 @@ use namada::ledger::pos::PosParams;
 use namada::ledger::queries::RPC;
 use namada::proof_of_stake::types::{ValidatorState, WeightedValidator};
 use namada::types::address::{Address, InternalAddress, MASP};
+use namada::types::dec::Dec;
 use namada::types::hash::Hash;
 use namada::types::ibc::{is_ibc_denom, IbcTokenHash};
 use namada::types::io::Io;
@@ -52,6 +53,7 @@ use namada::{state as storage, token};
 use namada_sdk::error::{
     is_pinned_error, Error, PinnedBalanceError, QueryError,
 };
+use namada_sdk::events::subscription::{EventFilter, EventSubscription};
 use namada_sdk::masp::{Conversions, MaspChange, MaspTokenRewardData};
 use namada_sdk::proof_of_stake::types::ValidatorMetaData;
 use namada_sdk::rpc::{
@@ -277,7 +279,7 @@ pub async fn query_raw_bytes<N: Namada>(
             .storage_value(
                 context.client(),
                 None,
-                None,
+                args.height,
                 false,
                 &args.storage_key,
             )
@@ -298,6 +300,72 @@ pub async fn query_raw_bytes<N: Namada>(
     }
 }
 
+/// Query the value of given storage key and decode it as the given type
+pub async fn query_decode<N: Namada>(context: &N, args: args::QueryDecode) {
+    let response = unwrap_client_response::<N::Client, _>(
+        RPC.shell()
+            .storage_value(
+                context.client(),
+                None,
+                args.height,
+                false,
+                &args.storage_key,
+            )
+            .await,
+    );
+    if response.data.is_empty() {
+        display_line!(
+            context.io(),
+            "No data found for key {}",
+            args.storage_key
+        );
+        return;
+    }
+    match decode_storage_value(&args.value_type, &response.data) {
+        Ok(value) => {
+            display_line!(context.io(), "{}: {}", args.value_type, value)
+        }
+        Err(err) => {
+            edisplay_line!(
+                context.io(),
+                "Failed to decode the value of key {} as {}: {}",
+                args.storage_key,
+                args.value_type,
+                err
+            );
+            cli::safe_exit(1)
+        }
+    }
+}
+
+/// Decode the given storage value bytes as the type with the given name and
+/// format it for display.
+fn decode_storage_value(
+    value_type: &str,
+    bytes: &[u8],
+) -> Result<String, String> {
+    fn decode<T: BorshDeserialize>(bytes: &[u8]) -> Result<T, String> {
+        T::try_from_slice(bytes).map_err(|err| err.to_string())
+    }
+    match value_type {
+        "address" => decode::<Address>(bytes).map(|value| value.to_string()),
+        "amount" => {
+            decode::<token::Amount>(bytes).map(|value| value.to_string())
+        }
+        "bool" => decode::<bool>(bytes).map(|value| value.to_string()),
+        "dec" => decode::<Dec>(bytes).map(|value| value.to_string()),
+        "epoch" => decode::<Epoch>(bytes).map(|value| value.to_string()),
+        "hash" => decode::<Hash>(bytes).map(|value| value.to_string()),
+        "height" => decode::<BlockHeight>(bytes).map(|value| value.to_string()),
+        "public-key" => {
+            decode::<common::PublicKey>(bytes).map(|value| value.to_string())
+        }
+        "string" => decode::<String>(bytes),
+        "u64" => decode::<u64>(bytes).map(|value| value.to_string()),
+        _ => Err(format!("Unknown value type {value_type}")),
+    }
+}
+
 /// Query token balance(s)
 pub async fn query_balance(context: &impl Namada, args: args::QueryBalance) {
     // Query the balances of shielded or transparent account types depending on
@@ -1318,6 +1386,34 @@ pub async fn query_account(context: &impl Namada, args: args::QueryAccount) {
     }
 }
 
+/// Print the events of an address as the blocks are committed, until the
+/// subscription to the node is closed
+pub async fn watch_address(
+    context: &impl Namada,
+    args: args::WatchAddress,
+) -> Result<(), error::Error> {
+    let mut filters = vec![EventFilter::Address(args.address.clone())];
+    filters.extend(args.event_type.map(EventFilter::EventType));
+    let mut subscription =
+        EventSubscription::connect(&args.query.ledger_address).await?;
+    display_line!(
+        context.io(),
+        "Watching the events of {}. Press Ctrl-C to stop.",
+        args.address
+    );
+    while let Some((height, event)) = subscription.next_event(&filters).await? {
+        let attributes: BTreeMap<_, _> = event.attributes.iter().collect();
+        display_line!(
+            context.io(),
+            "Block {height}: {} {:?}",
+            event.event_type,
+            attributes
+        );
+    }
+    display_line!(context.io(), "The subscription to the node was closed.");
+    Ok(())
+}
+
 pub async fn query_pgf(context: &impl Namada, _args: args::QueryPgf) {
     let stewards = query_pgf_stewards(context.client()).await;
     let fundings = query_pgf_fundings(context.client()).await;
@@ -1505,6 +1601,18 @@ pub async fn query_protocol_parameters(
         fee_unshielding_descriptions_limit
     );
 
+    let key = param_storage::get_storage_rent_per_byte_key();
+    let storage_rent_per_byte: token::Amount =
+        query_storage_value(context.client(), &key)
+            .await
+            .expect("Parameter should be defined.");
+    display_line!(
+        context.io(),
+        "{:4}Storage rent per byte: {}",
+        "",
+        storage_rent_per_byte.to_string_native()
+    );
+
     let key = param_storage::get_gas_cost_key();
     let gas_cost_table: BTreeMap<Address, token::Amount> =
         query_storage_value(context.client(), &key)
@@ -8,7 +8,7 @@ use namada::ledger::parameters::EpochDuration;
 use namada::types::address::{
     Address, EstablishedAddress, EstablishedAddressGen,
 };
-use namada::types::chain::{ChainId, ChainIdPrefix};
+use namada::types::chain::{ChainId, ChainIdPrefix, CHAIN_ID_PREFIX_SEP};
 use namada::types::dec::Dec;
 use namada::types::hash::Hash;
 use namada::types::key::{common, RefTo};
@@ -61,6 +61,30 @@ pub trait DeriveEstablishedAddress {
 }
 
 impl Finalized {
+    /// Check that the chain ID is derived from the hash of the rest of the
+    /// genesis, in the same way as in [`finalize`].
+    pub fn validate_chain_id(&self) -> Result<(), String> {
+        let chain_id = &self.metadata.chain_id;
+        let prefix = chain_id
+            .as_str()
+            .rsplit_once(CHAIN_ID_PREFIX_SEP)
+            .map(|(prefix, _hash)| prefix)
+            .unwrap_or_default();
+        let chain_id_prefix = ChainIdPrefix::from_str(prefix)
+            .map_err(|err| format!("Invalid chain ID {chain_id}: {err}"))?;
+        let to_finalize = self.clone().with_chain_id(chain_id_prefix);
+        let errors = chain_id.validate(to_finalize.serialize_to_vec());
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors
+                .iter()
+                .map(|err| err.to_string())
+                .collect::<Vec<_>>()
+                .join(", "))
+        }
+    }
+
     /// Write all genesis and the chain metadata TOML files to the given
     /// directory.
     pub fn write_toml_files(&self, output_dir: &Path) -> eyre::Result<()> {
@@ -273,6 +297,7 @@ impl Finalized {
             max_block_gas,
             minimum_gas_price,
             max_tx_bytes,
+            storage_rent_per_byte,
             ..
         } = self.parameters.parameters.clone();
 
@@ -326,6 +351,7 @@ impl Finalized {
                     )
                 })
                 .collect(),
+            storage_rent_per_byte,
         }
     }
 
@@ -485,34 +511,7 @@ pub fn finalize(
     let chain_id = ChainId::from_genesis(chain_id_prefix, to_finalize_bytes);
 
     // Construct the `Finalized` chain
-    let ToFinalize {
-        vps,
-        tokens,
-        balances,
-        parameters,
-        transactions,
-        metadata,
-    } = to_finalize;
-    let Metadata {
-        chain_id: _,
-        genesis_time,
-        consensus_timeout_commit,
-        address_gen,
-    } = metadata;
-    let metadata = Metadata {
-        chain_id,
-        genesis_time,
-        consensus_timeout_commit,
-        address_gen,
-    };
-    Finalized {
-        metadata,
-        vps,
-        tokens,
-        balances,
-        parameters,
-        transactions,
-    }
+    to_finalize.with_chain_id(chain_id)
 }
 
 /// Chain genesis config to be finalized. This struct is used to derive the
@@ -564,6 +563,39 @@ pub struct Chain<ID> {
     pub metadata: Metadata<ID>,
 }
 
+impl<ID> Chain<ID> {
+    /// Replace the chain ID, or its prefix, of the chain.
+    pub fn with_chain_id<T>(self, chain_id: T) -> Chain<T> {
+        let Chain {
+            vps,
+            tokens,
+            balances,
+            parameters,
+            transactions,
+            metadata:
+                Metadata {
+                    chain_id: _,
+                    genesis_time,
+                    consensus_timeout_commit,
+                    address_gen,
+                },
+        } = self;
+        Chain {
+            vps,
+            tokens,
+            balances,
+            parameters,
+            transactions,
+            metadata: Metadata {
+                chain_id,
+                genesis_time,
+                consensus_timeout_commit,
+                address_gen,
+            },
+        }
+    }
+}
+
 #[derive(
     Clone,
     Debug,
@@ -833,4 +865,38 @@ mod test {
 
         pretty_assertions::assert_eq!(finalized_0, finalized_1);
     }
+
+    /// Test that the chain ID of a finalized genesis is validated against its
+    /// contents after a round-trip through the TOML files.
+    #[test]
+    fn test_validate_chain_id() {
+        let templates_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .parent()
+            .unwrap()
+            .parent()
+            .unwrap()
+            .join("genesis/localnet");
+        let templates = templates::load_and_validate(&templates_dir).unwrap();
+        let chain_id_prefix: ChainIdPrefix =
+            FromStr::from_str("test-prefix").unwrap();
+        let genesis_time =
+            DateTimeUtc::from_str("2021-12-31T00:00:00Z").unwrap();
+        let consensus_timeout_commit =
+            crate::facade::tendermint::Timeout::from_str("1s").unwrap();
+        let finalized = finalize(
+            templates,
+            chain_id_prefix,
+            genesis_time,
+            consensus_timeout_commit,
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        finalized.write_toml_files(dir.path()).unwrap();
+        let mut finalized = Finalized::read_toml_files(dir.path()).unwrap();
+        assert!(finalized.validate_chain_id().is_ok());
+
+        // Changing the genesis contents invalidates the chain ID
+        finalized.parameters.parameters.max_block_gas += 1;
+        assert!(finalized.validate_chain_id().is_err());
+    }
 }
@@ -288,6 +288,10 @@ pub struct ChainParams<T: TemplateValidation> {
     pub fee_unshielding_descriptions_limit: u64,
     /// Map of the cost per gas unit for every token allowed for fee payment
     pub minimum_gas_price: T::GasMinimums,
+    /// Rent in the smallest units of the native token charged on every new
+    /// epoch for each byte stored in an account's subspace
+    #[serde(default)]
+    pub storage_rent_per_byte: token::Amount,
 }
 
 impl ChainParams<Unvalidated> {
@@ -310,6 +314,7 @@ impl ChainParams<Unvalidated> {
             fee_unshielding_gas_limit,
             fee_unshielding_descriptions_limit,
             minimum_gas_price,
+            storage_rent_per_byte,
         } = self;
         let mut min_gas_prices = BTreeMap::default();
         for (token, amount) in minimum_gas_price.into_iter() {
@@ -355,6 +360,7 @@ impl ChainParams<Unvalidated> {
             fee_unshielding_gas_limit,
             fee_unshielding_descriptions_limit,
             minimum_gas_price: min_gas_prices,
+            storage_rent_per_byte,
         })
     }
 }
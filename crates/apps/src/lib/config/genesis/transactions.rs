@@ -73,6 +73,7 @@ fn get_tx_args(use_device: bool) -> TxArgs {
         output_folder: None,
         force: false,
         broadcast_only: false,
+        wait: false,
         ledger_address: tendermint_config::net::Address::from_str(
             "127.0.0.1:26657",
         )
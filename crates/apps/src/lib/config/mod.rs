@@ -8,6 +8,7 @@ pub mod utils;
 use std::collections::HashMap;
 use std::fs::{create_dir_all, File};
 use std::io::Write;
+use std::net::SocketAddr;
 use std::path::{Path, PathBuf};
 
 use directories::ProjectDirs;
@@ -65,6 +66,163 @@ impl TendermintMode {
     }
 }
 
+/// The storage backend used by the ledger.
+#[derive(
+    Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq,
+)]
+pub enum DbBackend {
+    /// Persistent RocksDB storage
+    #[default]
+    RocksDB,
+    /// In-memory storage, useful for testing. The state is lost when the node
+    /// is stopped.
+    Memory,
+}
+
+/// The tuning profile of the RocksDB storage backend.
+#[derive(
+    Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq,
+)]
+pub enum DbProfile {
+    /// Tuned for reading the current state with low latency, e.g. for
+    /// validators
+    #[default]
+    Validator,
+    /// Tuned for storing the full history of the state, e.g. for archive
+    /// nodes. The heights at which every key changed are indexed, so that
+    /// the history of a key can be queried. The diffs of old blocks can't be
+    /// pruned with this profile.
+    Archive,
+}
+
+/// The compaction style of a RocksDB column family.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub enum DbCompactionStyle {
+    /// Leveled compaction, minimizes the read amplification
+    Level,
+    /// Universal compaction, minimizes the write amplification
+    Universal,
+}
+
+/// The tuning options of the RocksDB storage backend. The options that are
+/// not set default to the values of the selected profile.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RocksDBConfig {
+    /// The tuning profile. Defaults to the validator profile.
+    #[serde(default)]
+    pub profile: DbProfile,
+    /// The number of bits per key of the bloom filters. Set to 0 to disable
+    /// the bloom filters.
+    pub bloom_filter_bits_per_key: Option<u32>,
+    /// The compaction style of the subspace column family, which holds the
+    /// current state.
+    pub subspace_compaction_style: Option<DbCompactionStyle>,
+    /// The maximum total size of the write-ahead log files in bytes. When
+    /// exceeded, the column families are flushed so that the oldest log files
+    /// can be removed.
+    pub max_total_wal_size_bytes: Option<u64>,
+    /// When set, the write-ahead log files are synced incrementally every
+    /// time the given number of bytes is written.
+    pub wal_bytes_per_sync: Option<u64>,
+}
+
+impl RocksDBConfig {
+    /// The number of bits per key of the bloom filters
+    pub fn bloom_filter_bits_per_key(&self) -> u32 {
+        self.bloom_filter_bits_per_key
+            .unwrap_or(match self.profile {
+                DbProfile::Validator => 10,
+                // Most reads of an archive node are for past heights, which
+                // are iterated from the diffs, so the filters would only
+                // take up memory
+                DbProfile::Archive => 0,
+            })
+    }
+
+    /// The compaction style of the subspace column family
+    pub fn subspace_compaction_style(&self) -> DbCompactionStyle {
+        self.subspace_compaction_style
+            .unwrap_or(match self.profile {
+                DbProfile::Validator => DbCompactionStyle::Level,
+                DbProfile::Archive => DbCompactionStyle::Universal,
+            })
+    }
+
+    /// The size of the table blocks in bytes
+    pub fn block_size(&self) -> usize {
+        match self.profile {
+            DbProfile::Validator => 16 * 1024,
+            // Larger blocks keep the indices of a large DB smaller
+            DbProfile::Archive => 64 * 1024,
+        }
+    }
+}
+
+/// The Prometheus metrics exported by the node
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct MetricsConfig {
+    /// When true, the metrics are served under `/metrics` on the listen
+    /// address
+    #[serde(default)]
+    pub enabled: bool,
+    /// The address to listen for Prometheus collector connections
+    #[serde(default = "MetricsConfig::default_listen_addr")]
+    pub listen_addr: SocketAddr,
+}
+
+impl MetricsConfig {
+    fn default_listen_addr() -> SocketAddr {
+        // The CometBFT metrics use port 26660
+        SocketAddr::from(([127, 0, 0, 1], 26670))
+    }
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            listen_addr: Self::default_listen_addr(),
+        }
+    }
+}
+
+/// The gRPC query service of the node
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct GrpcConfig {
+    /// When true, the gRPC query service is served on the listen address
+    #[serde(default)]
+    pub enabled: bool,
+    /// The address to listen for gRPC connections
+    #[serde(default = "GrpcConfig::default_listen_addr")]
+    pub listen_addr: SocketAddr,
+}
+
+impl GrpcConfig {
+    fn default_listen_addr() -> SocketAddr {
+        // Next to the Prometheus metrics of the node
+        SocketAddr::from(([127, 0, 0, 1], 26671))
+    }
+}
+
+impl Default for GrpcConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            listen_addr: Self::default_listen_addr(),
+        }
+    }
+}
+
+/// The sink to which the committed blocks are streamed by the indexer
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "sink", rename_all = "snake_case")]
+pub enum IndexerConfig {
+    /// Write the blocks as JSON lines to the standard output
+    Stdout,
+    /// Append the blocks as JSON lines to a file
+    File { path: PathBuf },
+}
+
 /// An action to be performed at a
 /// certain block height.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -99,9 +257,14 @@ pub struct Ledger {
 pub struct Shell {
     pub base_dir: PathBuf,
     // pub ledger_address: SocketAddr,
+    /// The storage backend. Defaults to RocksDB.
+    pub db_backend: DbBackend,
     /// RocksDB block cache maximum size in bytes.
     /// When not set, defaults to 1/3 of the available memory.
     pub block_cache_bytes: Option<u64>,
+    /// RocksDB tuning options
+    #[serde(default)]
+    pub rocksdb: RocksDBConfig,
     /// VP WASM compilation cache maximum size in bytes.
     /// When not set, defaults to 1/6 of the available memory.
     pub vp_wasm_compilation_cache_bytes: Option<u64>,
@@ -111,6 +274,43 @@ pub struct Shell {
     /// When set, will limit the how many block heights in the past can the
     /// storage be queried for reading values.
     pub storage_read_past_height_limit: Option<u64>,
+    /// When set, the diffs of blocks older than the given number of last
+    /// blocks will be pruned from the DB. Values cannot be read at the pruned
+    /// heights anymore. Cannot be set with the archive DB profile.
+    pub retain_last_n_blocks: Option<u64>,
+    /// When set, a snapshot of the state is taken every given number of
+    /// blocks and offered to other nodes for state sync.
+    pub snapshot_interval: Option<u64>,
+    /// When set, a checkpoint of the DB is saved at the beginning of every
+    /// epoch and the given number of the most recent checkpoints is kept.
+    /// The storage can be rolled back to any of the kept checkpoints.
+    pub checkpoints_to_keep: Option<u64>,
+    /// When set, the storage values read from the DB are cached in memory up
+    /// to the given maximum size in bytes
+    pub read_cache_bytes: Option<u64>,
+    /// Record every block in a write-ahead log, synced to disk before the
+    /// block is written to the DB. RocksDB's own log is not synced on every
+    /// write, so on a power loss the last committed block may be lost and has
+    /// to be executed again from CometBFT's block store. With this log, the
+    /// block is restored from its recorded writes instead. Defaults to
+    /// `false`, because of the cost of syncing every block. Cannot be enabled
+    /// with the memory backend.
+    #[serde(default)]
+    pub write_ahead_log: bool,
+    /// Prometheus metrics of the node
+    #[serde(default)]
+    pub metrics: MetricsConfig,
+    /// gRPC query service of the node
+    #[serde(default)]
+    pub grpc: GrpcConfig,
+    /// When set and the node is built with the `indexer` feature, every
+    /// committed block is streamed to the given sink
+    pub indexer: Option<IndexerConfig>,
+    /// Allow initializing the chain from genesis files that don't match the
+    /// chain ID, e.g. when they were edited after the chain ID was derived.
+    /// Defaults to `false`, in which case the mismatch is an error.
+    #[serde(default)]
+    pub allow_chain_id_mismatch: bool,
     /// Use the [`Ledger::db_dir()`] method to read the value.
     db_dir: PathBuf,
     /// Use the [`Ledger::cometbft_dir()`] method to read the value.
@@ -138,11 +338,22 @@ impl Ledger {
             chain_id,
             shell: Shell {
                 base_dir: base_dir.as_ref().to_owned(),
+                db_backend: DbBackend::default(),
                 block_cache_bytes: None,
+                rocksdb: RocksDBConfig::default(),
                 vp_wasm_compilation_cache_bytes: None,
                 tx_wasm_compilation_cache_bytes: None,
                 // Default corresponds to 1 hour of past blocks at 1 block/sec
                 storage_read_past_height_limit: Some(3600),
+                retain_last_n_blocks: None,
+                snapshot_interval: None,
+                checkpoints_to_keep: None,
+                read_cache_bytes: None,
+                write_ahead_log: false,
+                metrics: MetricsConfig::default(),
+                grpc: GrpcConfig::default(),
+                indexer: None,
+                allow_chain_id_mismatch: false,
                 db_dir: DB_DIR.into(),
                 cometbft_dir: COMETBFT_DIR.into(),
                 action_at_height: None,
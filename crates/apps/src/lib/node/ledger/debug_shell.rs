@@ -0,0 +1,196 @@
+//! An interactive shell to inspect the state of a ledger node's DB. The DB is
+//! opened in read-only mode, so the shell can be used on a running node too.
+
+use std::io::{self, BufRead, Write};
+
+use data_encoding::HEXLOWER;
+use namada::state::{DBIter, DB};
+use namada::types::address;
+use namada::types::hash::Hash;
+use namada::types::storage::{BlockHeight, Key};
+
+use crate::config;
+use crate::node::ledger::shell::is_merklized_storage_key;
+use crate::node::ledger::storage::{
+    PersistentDB, PersistentDBCache, PersistentStorage,
+};
+
+const HELP: &str = "\
+Commands:
+  height                     Show the last committed block height and epoch
+  prefix <prefix>            List the keys with the prefix and value sizes
+  get <key> [height]         Dump the value of a key in hex
+  root [height]              Rebuild the Merkle tree and check its root
+                             against the app hash of the next block
+  diff <prefix> <from> <to>  List the keys with the prefix whose values
+                             differ between the two heights (only the keys
+                             present at the last height are compared)
+  help                       Show this message
+  exit                       Exit the shell";
+
+/// Run the debug shell on the DB of the given ledger config until the input
+/// is closed or the `exit` command is given.
+pub fn run(config: config::Ledger) -> eyre::Result<()> {
+    let db_path = config.shell.db_dir(&config.chain_id);
+    let db_cache = PersistentDBCache::offline(&config.shell);
+    let db = PersistentDB::open_read_only(db_path, Some(&db_cache))?;
+    // The native token isn't used by any of the commands
+    let mut state = PersistentStorage::open_with_db(
+        db,
+        config.chain_id,
+        address::nam(),
+        None,
+        is_merklized_storage_key,
+    );
+    state.load_last_state()?;
+
+    println!("{HELP}");
+    let stdin = io::stdin();
+    loop {
+        print!("> ");
+        io::stdout().flush()?;
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+        let args: Vec<&str> = line.split_whitespace().collect();
+        match args.as_slice() {
+            [] => {}
+            ["exit"] | ["quit"] => return Ok(()),
+            ["help"] => println!("{HELP}"),
+            args => {
+                if let Err(err) = exec(&state, args) {
+                    println!("Error: {err}");
+                }
+            }
+        }
+    }
+}
+
+/// Execute a single command of the shell
+fn exec(state: &PersistentStorage, args: &[&str]) -> Result<(), String> {
+    let last_height = state.get_last_block_height();
+    match args {
+        ["height"] => {
+            println!(
+                "Last committed block height: {last_height}, epoch: {}",
+                state.last_epoch
+            );
+        }
+        ["prefix", prefix] => {
+            let prefix = parse_key(prefix)?;
+            let mut count = 0_u64;
+            for (key, value, _gas) in state.db.iter_prefix(Some(&prefix)) {
+                println!("{key} ({} bytes)", value.len());
+                count += 1;
+            }
+            println!("Found {count} keys");
+        }
+        ["get", key, height @ ..] => {
+            let key = parse_key(key)?;
+            let height = parse_opt_height(height)?;
+            match read_value(state, &key, height, last_height)? {
+                Some(value) => println!("0x{}", HEXLOWER.encode(&value)),
+                None => println!("No value found for key {key}"),
+            }
+        }
+        ["root", height @ ..] => {
+            let height = parse_opt_height(height)?.unwrap_or(last_height);
+            let tree = state
+                .get_merkle_tree(height, None)
+                .map_err(|e| e.to_string())?;
+            let root = Hash::from(tree.root());
+            println!("Merkle root at height {height}: {root}");
+            match state
+                .db
+                .read_block_header(height.next_height())
+                .map_err(|e| e.to_string())?
+            {
+                Some(header) if header.hash == root => {
+                    println!(
+                        "The root matches the app hash of the block at height \
+                         {}",
+                        height.next_height()
+                    )
+                }
+                Some(header) => println!(
+                    "MISMATCH: the app hash of the block at height {} is {}",
+                    height.next_height(),
+                    header.hash
+                ),
+                None => println!(
+                    "The block at height {} isn't committed, the root cannot \
+                     be checked against its app hash",
+                    height.next_height()
+                ),
+            }
+        }
+        ["diff", prefix, from, to] => {
+            let prefix = parse_key(prefix)?;
+            let from = parse_height(from)?;
+            let to = parse_height(to)?;
+            for (key, _value, _gas) in state.db.iter_prefix(Some(&prefix)) {
+                let key = parse_key(&key)?;
+                let old = read_value(state, &key, Some(from), last_height)?;
+                let new = read_value(state, &key, Some(to), last_height)?;
+                match (old, new) {
+                    (None, Some(_)) => println!("+ {key}"),
+                    (Some(_), None) => println!("- {key}"),
+                    (Some(old), Some(new)) if old != new => {
+                        println!("~ {key}")
+                    }
+                    _ => {}
+                }
+            }
+        }
+        _ => {
+            return Err(format!(
+                "Unknown command or invalid arguments: {}. Type `help` to \
+                 show the available commands.",
+                args.join(" ")
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Read the value of the key at the given height or at the last height
+fn read_value(
+    state: &PersistentStorage,
+    key: &Key,
+    height: Option<BlockHeight>,
+    last_height: BlockHeight,
+) -> Result<Option<Vec<u8>>, String> {
+    match height {
+        Some(height) if height < last_height => state
+            .db
+            .read_subspace_val_with_height(key, height, last_height),
+        Some(height) if height > last_height => {
+            return Err(format!(
+                "The height {height} is above the last committed height \
+                 {last_height}"
+            ));
+        }
+        _ => state.db.read_subspace_val(key),
+    }
+    .map_err(|e| e.to_string())
+}
+
+fn parse_key(key: &str) -> Result<Key, String> {
+    Key::parse(key).map_err(|e| format!("Invalid key {key}: {e}"))
+}
+
+fn parse_height(height: &str) -> Result<BlockHeight, String> {
+    height
+        .parse::<u64>()
+        .map(BlockHeight)
+        .map_err(|e| format!("Invalid height {height}: {e}"))
+}
+
+fn parse_opt_height(args: &[&str]) -> Result<Option<BlockHeight>, String> {
+    match args {
+        [] => Ok(None),
+        [height] => parse_height(height).map(Some),
+        _ => Err(format!("Unexpected arguments: {}", args.join(" "))),
+    }
+}
@@ -0,0 +1,314 @@
+//! Export of the state of a ledger node's DB at a given height into a JSON
+//! file, to help constructing a new genesis after a hard fork.
+//!
+//! The export contains the protocol parameters, the token balances, the PoS
+//! bonds and the validity predicates' code hashes. The subspace is walked
+//! using the keys present at the last committed height, so keys deleted
+//! before that height are not exported.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::path::PathBuf;
+use std::vec::IntoIter;
+
+use namada::ledger::parameters;
+use namada::proof_of_stake::storage_key::is_bond_key;
+use namada::state::{DBIter, StorageError, StorageRead, StorageResult, DB};
+use namada::token::storage_key::is_any_token_balance_key;
+use namada::token::{
+    self, DenominatedAmount, Denomination, NATIVE_MAX_DECIMAL_PLACES,
+};
+use namada::types::address::Address;
+use namada::types::hash::Hash;
+use namada::types::storage::{
+    BlockHash, BlockHeight, Epoch, Epochs, Header, Key, TxIndex,
+};
+use serde::Serialize;
+
+use crate::config;
+use crate::config::genesis::chain::Finalized;
+use crate::node::ledger::shell::is_merklized_storage_key;
+use crate::node::ledger::storage::{
+    PersistentDB, PersistentDBCache, PersistentStorage,
+};
+
+/// The exported state
+#[derive(Debug, Serialize)]
+struct StateExport {
+    chain_id: String,
+    height: BlockHeight,
+    epoch: Epoch,
+    parameters: ExportedParameters,
+    /// Balances by token and owner
+    balances: BTreeMap<String, BTreeMap<String, String>>,
+    bonds: Vec<ExportedBond>,
+    /// Validity predicate code hashes by account address
+    validity_predicates: BTreeMap<String, String>,
+}
+
+/// The protocol parameters that are set in genesis
+#[derive(Debug, Serialize)]
+struct ExportedParameters {
+    max_tx_bytes: u32,
+    min_num_of_blocks: u64,
+    min_duration: u64,
+    max_expected_time_per_block: u64,
+    max_proposal_bytes: u64,
+    max_block_gas: u64,
+    vp_allowlist: Vec<String>,
+    tx_allowlist: Vec<String>,
+    implicit_vp_code_hash: Option<String>,
+    epochs_per_year: u64,
+    max_signatures_per_transaction: u8,
+    fee_unshielding_gas_limit: u64,
+    fee_unshielding_descriptions_limit: u64,
+    /// Minimum gas prices by token
+    minimum_gas_price: BTreeMap<String, String>,
+}
+
+/// The total bonded amount of a source to a validator
+#[derive(Debug, Serialize)]
+struct ExportedBond {
+    source: String,
+    validator: String,
+    amount: String,
+}
+
+/// Export the state at the given height, or at the last committed height,
+/// into a JSON file. Returns the path of the written file.
+pub fn run(
+    config: config::Ledger,
+    height: Option<BlockHeight>,
+    out_file_path: Option<PathBuf>,
+) -> eyre::Result<PathBuf> {
+    let chain_dir = config.shell.base_dir.join(config.chain_id.as_str());
+    let native_token = Finalized::read_toml_files(&chain_dir)?
+        .get_native_token()
+        .clone();
+    let db_path = config.shell.db_dir(&config.chain_id);
+    let db_cache = PersistentDBCache::offline(&config.shell);
+    let db = PersistentDB::open_read_only(db_path, Some(&db_cache))?;
+    let mut state = PersistentStorage::open_with_db(
+        db,
+        config.chain_id,
+        native_token,
+        None,
+        is_merklized_storage_key,
+    );
+    state.load_last_state()?;
+
+    let last_height = state.get_last_block_height();
+    let height = height.unwrap_or(last_height);
+    if height > last_height {
+        eyre::bail!(
+            "The height {height} is above the last committed height \
+             {last_height}"
+        );
+    }
+    let export = export(&StateAtHeight {
+        state: &state,
+        height,
+    })?;
+
+    let out_file_path = out_file_path.unwrap_or_else(|| {
+        PathBuf::from(format!("state_export_{height}.json"))
+    });
+    let file = File::create(&out_file_path)?;
+    serde_json::to_writer_pretty(file, &export)?;
+    Ok(out_file_path)
+}
+
+/// Collect the exported state from the given storage
+fn export(storage: &StateAtHeight<'_>) -> StorageResult<StateExport> {
+    let params = parameters::read(storage)?;
+    let parameters = ExportedParameters {
+        max_tx_bytes: params.max_tx_bytes,
+        min_num_of_blocks: params.epoch_duration.min_num_of_blocks,
+        min_duration: params.epoch_duration.min_duration.0,
+        max_expected_time_per_block: params.max_expected_time_per_block.0,
+        max_proposal_bytes: params.max_proposal_bytes.get(),
+        max_block_gas: params.max_block_gas,
+        vp_allowlist: params.vp_allowlist,
+        tx_allowlist: params.tx_allowlist,
+        implicit_vp_code_hash: params
+            .implicit_vp_code_hash
+            .map(|hash| hash.to_string()),
+        epochs_per_year: params.epochs_per_year,
+        max_signatures_per_transaction: params.max_signatures_per_transaction,
+        fee_unshielding_gas_limit: params.fee_unshielding_gas_limit,
+        fee_unshielding_descriptions_limit: params
+            .fee_unshielding_descriptions_limit,
+        minimum_gas_price: params
+            .minimum_gas_price
+            .iter()
+            .map(|(token, amount)| {
+                Ok((
+                    token.to_string(),
+                    denominated(storage, token, *amount)?.to_string(),
+                ))
+            })
+            .collect::<StorageResult<_>>()?,
+    };
+
+    let mut balances: BTreeMap<String, BTreeMap<String, String>> =
+        BTreeMap::new();
+    let mut bonds: BTreeMap<(Address, Address), token::Amount> =
+        BTreeMap::new();
+    let mut validity_predicates = BTreeMap::new();
+    // Walk the whole subspace and only read the values of the exported keys
+    for (key, _value, _gas) in storage.state.db.iter_prefix(None) {
+        let key = Key::parse(key).map_err(StorageError::new)?;
+        if let Some([token, owner]) = is_any_token_balance_key(&key) {
+            let Some(amount) = storage.read::<token::Amount>(&key)? else {
+                continue;
+            };
+            balances.entry(token.to_string()).or_default().insert(
+                owner.to_string(),
+                denominated(storage, token, amount)?.to_string(),
+            );
+        } else if let Some((bond_id, _epoch)) = is_bond_key(&key) {
+            let Some(amount) = storage.read::<token::Amount>(&key)? else {
+                continue;
+            };
+            let bond = bonds
+                .entry((bond_id.source, bond_id.validator))
+                .or_default();
+            *bond = bond.checked_add(amount).ok_or_else(|| {
+                StorageError::new_const("Bond amount overflow")
+            })?;
+        } else if let Some(address) = key.is_validity_predicate() {
+            let Some(hash) = storage.read::<Hash>(&key)? else {
+                continue;
+            };
+            validity_predicates.insert(address.to_string(), hash.to_string());
+        }
+    }
+    let native_token = storage.get_native_token()?;
+    let bonds = bonds
+        .into_iter()
+        .map(|((source, validator), amount)| {
+            Ok(ExportedBond {
+                source: source.to_string(),
+                validator: validator.to_string(),
+                amount: denominated(storage, &native_token, amount)?
+                    .to_string(),
+            })
+        })
+        .collect::<StorageResult<_>>()?;
+
+    Ok(StateExport {
+        chain_id: storage.get_chain_id()?,
+        height: storage.height,
+        epoch: storage.get_block_epoch()?,
+        parameters,
+        balances,
+        bonds,
+        validity_predicates,
+    })
+}
+
+/// Denominate an amount of the given token
+fn denominated(
+    storage: &StateAtHeight<'_>,
+    token: &Address,
+    amount: token::Amount,
+) -> StorageResult<DenominatedAmount> {
+    let denom = token::read_denom(storage, token)?
+        .unwrap_or(Denomination(NATIVE_MAX_DECIMAL_PLACES));
+    Ok(DenominatedAmount::new(amount, denom))
+}
+
+/// Read-only access to the storage at a past height. The prefix iterators
+/// only contain the keys present at the last committed height.
+struct StateAtHeight<'a> {
+    state: &'a PersistentStorage,
+    height: BlockHeight,
+}
+
+impl StorageRead for StateAtHeight<'_> {
+    type PrefixIter<'iter> = IntoIter<(String, Vec<u8>)> where Self: 'iter;
+
+    fn read_bytes(&self, key: &Key) -> StorageResult<Option<Vec<u8>>> {
+        let last_height = self.state.get_last_block_height();
+        if self.height < last_height {
+            self.state.db.read_subspace_val_with_height(
+                key,
+                self.height,
+                last_height,
+            )
+        } else {
+            self.state.db.read_subspace_val(key)
+        }
+        .map_err(StorageError::new)
+    }
+
+    fn has_key(&self, key: &Key) -> StorageResult<bool> {
+        Ok(self.read_bytes(key)?.is_some())
+    }
+
+    fn iter_prefix<'iter>(
+        &'iter self,
+        prefix: &Key,
+    ) -> StorageResult<Self::PrefixIter<'iter>> {
+        let mut values = vec![];
+        for (key, _value, _gas) in self.state.db.iter_prefix(Some(prefix)) {
+            let parsed_key = Key::parse(&key).map_err(StorageError::new)?;
+            if let Some(value) = self.read_bytes(&parsed_key)? {
+                values.push((key, value));
+            }
+        }
+        Ok(values.into_iter())
+    }
+
+    fn iter_next<'iter>(
+        &'iter self,
+        iter: &mut Self::PrefixIter<'iter>,
+    ) -> StorageResult<Option<(String, Vec<u8>)>> {
+        Ok(iter.next())
+    }
+
+    fn get_chain_id(&self) -> StorageResult<String> {
+        Ok(self.state.chain_id.to_string())
+    }
+
+    fn get_block_height(&self) -> StorageResult<BlockHeight> {
+        Ok(self.height)
+    }
+
+    fn get_block_header(
+        &self,
+        height: BlockHeight,
+    ) -> StorageResult<Option<Header>> {
+        self.state
+            .db
+            .read_block_header(height)
+            .map_err(StorageError::new)
+    }
+
+    fn get_block_hash(&self) -> StorageResult<BlockHash> {
+        Err(StorageError::new_const(
+            "The block hash of a past height is not available",
+        ))
+    }
+
+    fn get_block_epoch(&self) -> StorageResult<Epoch> {
+        Ok(self
+            .state
+            .block
+            .pred_epochs
+            .get_epoch(self.height)
+            .unwrap_or_default())
+    }
+
+    fn get_pred_epochs(&self) -> StorageResult<Epochs> {
+        Ok(self.state.block.pred_epochs.clone())
+    }
+
+    fn get_tx_index(&self) -> StorageResult<TxIndex> {
+        Ok(TxIndex::default())
+    }
+
+    fn get_native_token(&self) -> StorageResult<Address> {
+        Ok(self.state.native_token.clone())
+    }
+}
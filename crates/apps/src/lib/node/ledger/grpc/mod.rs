@@ -0,0 +1,468 @@
+//! The gRPC query service of the node, defined in `proto/query.proto`, for
+//! the clients that don't speak ABCI. The queries are forwarded to the info
+//! connection of the ABCI service, so they are answered by the same RPC
+//! handlers as the ABCI queries.
+
+mod generated;
+
+use std::collections::BTreeSet;
+use std::net::SocketAddr;
+use std::str::FromStr;
+
+use borsh::BorshDeserialize;
+use namada::ledger::queries::RPC;
+use namada::proof_of_stake::types::WeightedValidator;
+use namada::token::{self, DenominatedAmount, Denomination};
+use namada::tx::data::TxReceipt;
+use namada::types::address::Address;
+use namada::types::hash::Hash;
+use namada::types::storage::{self, Epoch};
+use tonic::{Request, Response, Status};
+use tower::{Service, ServiceExt};
+
+pub use self::generated::query::query_server::{Query, QueryServer};
+pub use self::generated::query::*;
+use crate::facade::tendermint::v0_37::abci::{
+    request, response, InfoRequest, InfoResponse,
+};
+use crate::facade::tower_abci::BoxError;
+
+/// Serve the gRPC query service on the given address until the shutdown
+/// signal is received
+pub async fn serve<S>(
+    listen_addr: SocketAddr,
+    info: S,
+    shutdown: tokio::sync::oneshot::Receiver<()>,
+) -> Result<(), tonic::transport::Error>
+where
+    S: Service<InfoRequest, Response = InfoResponse, Error = BoxError>
+        + Clone
+        + Send
+        + Sync
+        + 'static,
+    S::Future: Send,
+{
+    tracing::info!("Serving the gRPC query service on {listen_addr}");
+    tonic::transport::Server::builder()
+        .add_service(QueryServer::new(QueryService { info }))
+        .serve_with_shutdown(listen_addr, async {
+            let _ = shutdown.await;
+        })
+        .await
+}
+
+/// The gRPC query service, forwarding the queries to the ABCI info service
+#[derive(Clone)]
+pub struct QueryService<S> {
+    info: S,
+}
+
+impl<S> QueryService<S>
+where
+    S: Service<InfoRequest, Response = InfoResponse, Error = BoxError>
+        + Clone
+        + Send
+        + Sync
+        + 'static,
+    S::Future: Send,
+{
+    /// Send an ABCI query at the given path. The height `0` queries the last
+    /// committed height.
+    async fn abci_query(
+        &self,
+        path: String,
+        height: u64,
+        prove: bool,
+    ) -> Result<response::Query, Status> {
+        let height = height
+            .try_into()
+            .map_err(|_| Status::invalid_argument("Invalid height"))?;
+        let req = InfoRequest::Query(request::Query {
+            data: Default::default(),
+            path,
+            height,
+            prove,
+        });
+        let resp = self
+            .info
+            .clone()
+            .ready()
+            .await
+            .map_err(|err| Status::unavailable(err.to_string()))?
+            .call(req)
+            .await
+            .map_err(|err| Status::unavailable(err.to_string()))?;
+        match resp {
+            InfoResponse::Query(resp) if resp.code.is_ok() => Ok(resp),
+            InfoResponse::Query(resp) => {
+                Err(Status::invalid_argument(resp.info))
+            }
+            _ => Err(Status::internal("Unexpected ABCI response")),
+        }
+    }
+
+    /// Send an ABCI query at the given path at the last committed height and
+    /// decode the response
+    async fn query<T: BorshDeserialize>(
+        &self,
+        path: String,
+    ) -> Result<T, Status> {
+        let resp = self.abci_query(path, 0, false).await?;
+        decode(&resp.value)
+    }
+
+    /// Read the value of a storage key at the last committed height, if any
+    async fn read<T: BorshDeserialize>(
+        &self,
+        key: &storage::Key,
+    ) -> Result<Option<T>, Status> {
+        let path = RPC.shell().storage_value_path(key);
+        let resp = self.abci_query(path, 0, false).await?;
+        if resp.value.is_empty() {
+            Ok(None)
+        } else {
+            decode(&resp.value).map(Some)
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl<S> Query for QueryService<S>
+where
+    S: Service<InfoRequest, Response = InfoResponse, Error = BoxError>
+        + Clone
+        + Send
+        + Sync
+        + 'static,
+    S::Future: Send,
+{
+    async fn balance(
+        &self,
+        request: Request<BalanceRequest>,
+    ) -> Result<Response<BalanceResponse>, Status> {
+        let BalanceRequest { token, owner } = request.into_inner();
+        let token = parse_address(&token)?;
+        let owner = parse_address(&owner)?;
+        let amount: token::Amount = self
+            .read(&token::storage_key::balance_key(&token, &owner))
+            .await?
+            .unwrap_or_default();
+        let denom: Denomination = self
+            .read(&token::storage_key::denom_key(&token))
+            .await?
+            .ok_or_else(|| {
+                Status::not_found(format!("Unknown token {token}"))
+            })?;
+        Ok(Response::new(BalanceResponse {
+            amount: DenominatedAmount::new(amount, denom).to_string(),
+        }))
+    }
+
+    async fn epoch(
+        &self,
+        _request: Request<EpochRequest>,
+    ) -> Result<Response<EpochResponse>, Status> {
+        let epoch: Epoch = self.query(RPC.shell().epoch_path()).await?;
+        Ok(Response::new(EpochResponse { epoch: epoch.0 }))
+    }
+
+    async fn storage_value(
+        &self,
+        request: Request<StorageValueRequest>,
+    ) -> Result<Response<StorageValueResponse>, Status> {
+        let StorageValueRequest { key, height, prove } = request.into_inner();
+        let key = storage::Key::parse(&key).map_err(|err| {
+            Status::invalid_argument(format!("Invalid storage key: {err}"))
+        })?;
+        let path = RPC.shell().storage_value_path(&key);
+        let resp = self
+            .abci_query(path, height.unwrap_or_default(), prove)
+            .await?;
+        let proof_ops = resp
+            .proof
+            .map(|proof| {
+                proof
+                    .ops
+                    .into_iter()
+                    .map(|op| ProofOp {
+                        r#type: op.field_type,
+                        key: op.key,
+                        data: op.data,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        Ok(Response::new(StorageValueResponse {
+            value: (!resp.value.is_empty()).then(|| resp.value.to_vec()),
+            proof_ops,
+        }))
+    }
+
+    async fn tx_status(
+        &self,
+        request: Request<TxStatusRequest>,
+    ) -> Result<Response<TxStatusResponse>, Status> {
+        let hash =
+            Hash::from_str(&request.into_inner().hash).map_err(|err| {
+                Status::invalid_argument(format!("Invalid tx hash: {err}"))
+            })?;
+        let receipt: Option<TxReceipt> =
+            self.query(RPC.shell().tx_receipt_path(&hash)).await?;
+        let status = match receipt {
+            Some(receipt) => TxStatusResponse {
+                applied: true,
+                height: receipt.height.0,
+                index: receipt.index.0,
+                accepted: receipt.is_accepted(),
+                gas_used: receipt.result.gas_used.to_string(),
+            },
+            None => TxStatusResponse::default(),
+        };
+        Ok(Response::new(status))
+    }
+
+    async fn validator_set(
+        &self,
+        request: Request<ValidatorSetRequest>,
+    ) -> Result<Response<ValidatorSetResponse>, Status> {
+        let epoch = request.into_inner().epoch.map(Epoch);
+        let validators: BTreeSet<WeightedValidator> = self
+            .query(RPC.vp().pos().consensus_validator_set_path(&epoch))
+            .await?;
+        let validators = validators
+            .into_iter()
+            .map(|validator| Validator {
+                address: validator.address.to_string(),
+                stake: validator.bonded_stake.to_string_native(),
+            })
+            .collect();
+        Ok(Response::new(ValidatorSetResponse { validators }))
+    }
+}
+
+fn parse_address(address: &str) -> Result<Address, Status> {
+    Address::decode(address).map_err(|err| {
+        Status::invalid_argument(format!("Invalid address {address}: {err}"))
+    })
+}
+
+fn decode<T: BorshDeserialize>(bytes: &[u8]) -> Result<T, Status> {
+    T::try_from_slice(bytes).map_err(|err| {
+        Status::internal(format!("Cannot decode the ABCI response: {err}"))
+    })
+}
+
+#[cfg(test)]
+mod test_grpc {
+    use std::sync::{Arc, Mutex};
+    use std::task::{Context, Poll};
+
+    use borsh_ext::BorshSerializeExt;
+    use namada::state::StorageWrite;
+    use namada::types::address::testing::{
+        established_address_1, established_address_2,
+    };
+
+    use super::*;
+    use crate::node::ledger::shell::test_utils::{self, TestShell};
+
+    /// An ABCI info service answering the queries with a test shell
+    #[derive(Clone)]
+    struct TestInfo(Arc<Mutex<TestShell>>);
+
+    impl Service<InfoRequest> for TestInfo {
+        type Error = BoxError;
+        type Future = std::future::Ready<Result<InfoResponse, BoxError>>;
+        type Response = InfoResponse;
+
+        fn poll_ready(
+            &mut self,
+            _cx: &mut Context<'_>,
+        ) -> Poll<Result<(), BoxError>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, req: InfoRequest) -> Self::Future {
+            std::future::ready(match req {
+                InfoRequest::Query(query) => {
+                    let shell = self.0.lock().unwrap();
+                    Ok(InfoResponse::Query(shell.query(query)))
+                }
+                _ => Err("Unexpected ABCI info request".into()),
+            })
+        }
+    }
+
+    /// Setup a test shell and a query service answering with it
+    fn setup() -> (Arc<Mutex<TestShell>>, QueryService<TestInfo>) {
+        let (shell, _recv, _, _) = test_utils::setup();
+        let shell = Arc::new(Mutex::new(shell));
+        let service = QueryService {
+            info: TestInfo(shell.clone()),
+        };
+        (shell, service)
+    }
+
+    /// Commit the writes to the test shell's storage, so that they're visible
+    /// to the queries
+    fn commit(shell: &Mutex<TestShell>) {
+        let mut shell = shell.lock().unwrap();
+        shell.wl_storage.commit_tx();
+        shell.wl_storage.commit_block().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_balance() {
+        let (shell, service) = setup();
+        let token = established_address_1();
+        let owner = established_address_2();
+        let request = || {
+            Request::new(BalanceRequest {
+                token: token.encode(),
+                owner: owner.encode(),
+            })
+        };
+
+        let status = service.balance(request()).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::NotFound);
+
+        let denom = Denomination(6);
+        let amount = token::Amount::native_whole(1000);
+        {
+            let mut shell = shell.lock().unwrap();
+            let denom_key = token::storage_key::denom_key(&token);
+            shell.wl_storage.write(&denom_key, denom).unwrap();
+            let balance_key = token::storage_key::balance_key(&token, &owner);
+            shell.wl_storage.write(&balance_key, amount).unwrap();
+        }
+        commit(&shell);
+        let response = service.balance(request()).await.unwrap().into_inner();
+        assert_eq!(
+            response.amount,
+            DenominatedAmount::new(amount, denom).to_string()
+        );
+
+        let status = service
+            .balance(Request::new(BalanceRequest {
+                token: "invalid".to_string(),
+                owner: owner.encode(),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn test_epoch() {
+        let (shell, service) = setup();
+        let response = service
+            .epoch(Request::new(EpochRequest {}))
+            .await
+            .unwrap()
+            .into_inner();
+        let last_epoch = shell.lock().unwrap().wl_storage.storage.last_epoch;
+        assert_eq!(response.epoch, last_epoch.0);
+    }
+
+    #[tokio::test]
+    async fn test_storage_value() {
+        let (shell, service) = setup();
+        let key = token::storage_key::balance_key(
+            &established_address_1(),
+            &established_address_2(),
+        );
+        let request = |prove| {
+            Request::new(StorageValueRequest {
+                key: key.to_string(),
+                height: None,
+                prove,
+            })
+        };
+
+        let response = service
+            .storage_value(request(false))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(response.value.is_none());
+        assert!(response.proof_ops.is_empty());
+
+        let amount = token::Amount::native_whole(1000);
+        shell
+            .lock()
+            .unwrap()
+            .wl_storage
+            .write(&key, amount)
+            .unwrap();
+        commit(&shell);
+        let response = service
+            .storage_value(request(true))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(response.value, Some(amount.serialize_to_vec()));
+        assert!(!response.proof_ops.is_empty());
+
+        let status = service
+            .storage_value(Request::new(StorageValueRequest {
+                key: String::new(),
+                height: None,
+                prove: false,
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn test_tx_status() {
+        let (shell, service) = setup();
+        let hash = Hash::sha256(b"tx");
+        let request = || {
+            Request::new(TxStatusRequest {
+                hash: hash.to_string(),
+            })
+        };
+
+        let response = service.tx_status(request()).await.unwrap().into_inner();
+        assert!(!response.applied);
+
+        let height = {
+            let mut shell = shell.lock().unwrap();
+            let height = shell.wl_storage.storage.get_block_height().0;
+            shell.wl_storage.storage.block.receipts.push(TxReceipt {
+                hash,
+                height,
+                ..Default::default()
+            });
+            height
+        };
+        commit(&shell);
+        let response = service.tx_status(request()).await.unwrap().into_inner();
+        assert!(response.applied);
+        assert_eq!(response.height, height.0);
+        assert_eq!(response.index, 0);
+
+        let status = service
+            .tx_status(Request::new(TxStatusRequest {
+                hash: "invalid".to_string(),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn test_validator_set() {
+        let (_shell, service) = setup();
+        let response = service
+            .validator_set(Request::new(ValidatorSetRequest { epoch: None }))
+            .await
+            .unwrap()
+            .into_inner();
+        // The test shell is set up with a single validator
+        assert_eq!(response.validators.len(), 1);
+        let validator = &response.validators[0];
+        assert!(Address::decode(&validator.address).is_ok());
+    }
+}
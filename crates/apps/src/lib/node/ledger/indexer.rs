@@ -0,0 +1,310 @@
+//! Streaming of the committed blocks to an external indexer, e.g. a block
+//! explorer, enabled with the `indexer` feature. Every block is sent to a
+//! [`BlockSink`] with its header, the decoded txs, their receipts, the events
+//! and the balances changed in the block, so that the indexers don't have to
+//! decode the txs and query the state themselves.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+
+use namada::ledger::events::Event;
+use namada::state::{DBIter, StorageHasher, WlStorage, DB};
+use namada::token;
+use namada::tx::data::{hash_tx, TxReceipt};
+use namada::tx::Tx;
+use namada::types::address::Address;
+use namada::types::hash::Hash;
+use namada::types::storage::{BlockHash, BlockHeight, Header};
+use namada::types::time::DateTimeUtc;
+use serde::Serialize;
+
+use crate::config::IndexerConfig;
+use crate::node::ledger::shims::abcipp_shim_types::shim;
+
+/// A committed block, as streamed to a [`BlockSink`]
+#[derive(Clone, Debug, Serialize)]
+pub struct IndexedBlock {
+    pub height: BlockHeight,
+    pub hash: BlockHash,
+    pub time: DateTimeUtc,
+    /// The app hash of the previous block
+    pub app_hash: Hash,
+    /// The address of the proposer in hex
+    pub proposer_address: String,
+    pub txs: Vec<IndexedTx>,
+    /// The receipts of the txs applied in the block
+    pub receipts: Vec<TxReceipt>,
+    pub events: Vec<IndexedEvent>,
+    /// The balances changed in the block, after the block
+    pub balances: Vec<IndexedBalance>,
+}
+
+/// A tx included in a block
+#[derive(Clone, Debug, Serialize)]
+pub struct IndexedTx {
+    /// The hash of the tx bytes
+    pub hash: Hash,
+    /// The result code of the tx in the block proposal
+    pub code: u32,
+    /// The decoded tx or `None` if the bytes cannot be decoded
+    pub tx: Option<Tx>,
+}
+
+/// An event emitted in a block
+#[derive(Clone, Debug, Serialize)]
+pub struct IndexedEvent {
+    pub event_type: String,
+    pub attributes: BTreeMap<String, String>,
+}
+
+/// The balance of an owner in a token
+#[derive(Clone, Debug, Serialize)]
+pub struct IndexedBalance {
+    pub token: Address,
+    pub owner: Address,
+    pub amount: token::Amount,
+}
+
+/// A sink of the committed blocks. The errors of a sink are logged and don't
+/// stop the node.
+pub trait BlockSink: Send {
+    /// Index a committed block
+    fn index_block(&mut self, block: &IndexedBlock) -> io::Result<()>;
+}
+
+/// A sink writing every block as a line of JSON
+pub struct JsonLinesSink<W: Write + Send>(pub W);
+
+impl<W: Write + Send> BlockSink for JsonLinesSink<W> {
+    fn index_block(&mut self, block: &IndexedBlock) -> io::Result<()> {
+        serde_json::to_writer(&mut self.0, block)?;
+        self.0.write_all(b"\n")?;
+        self.0.flush()
+    }
+}
+
+/// Builds the finalized blocks and sends them to the sink once committed
+pub struct Indexer {
+    sink: Box<dyn BlockSink>,
+    /// The block being finalized
+    pending: Option<IndexedBlock>,
+}
+
+impl std::fmt::Debug for Indexer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Indexer")
+            .field("pending", &self.pending)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Indexer {
+    /// Create an indexer streaming to the configured sink
+    pub fn new(config: &IndexerConfig) -> io::Result<Self> {
+        let sink: Box<dyn BlockSink> = match config {
+            IndexerConfig::Stdout => Box::new(JsonLinesSink(io::stdout())),
+            IndexerConfig::File { path } => {
+                let file: File =
+                    OpenOptions::new().create(true).append(true).open(path)?;
+                Box::new(JsonLinesSink(file))
+            }
+        };
+        Ok(Self::with_sink(sink))
+    }
+
+    /// Create an indexer streaming to the given sink
+    pub fn with_sink(sink: Box<dyn BlockSink>) -> Self {
+        Self {
+            sink,
+            pending: None,
+        }
+    }
+
+    /// Start indexing a block that is being finalized
+    pub fn begin_block(&mut self, req: &shim::request::FinalizeBlock) {
+        let txs = req
+            .txs
+            .iter()
+            .map(|processed| IndexedTx {
+                hash: hash_tx(&processed.tx),
+                code: processed.result.code,
+                tx: Tx::try_from(processed.tx.as_ref()).ok(),
+            })
+            .collect();
+        let Header { hash, time, .. } = req.header.clone();
+        self.pending = Some(IndexedBlock {
+            height: BlockHeight::default(),
+            hash: req.hash.clone(),
+            time,
+            app_hash: hash,
+            proposer_address: data_encoding::HEXUPPER
+                .encode(&req.proposer_address),
+            txs,
+            receipts: vec![],
+            events: vec![],
+            balances: vec![],
+        });
+    }
+
+    /// Add the results of the finalized block. Must be called before the
+    /// block is committed, while its receipts are still available.
+    pub fn end_block<D, H>(
+        &mut self,
+        wl_storage: &WlStorage<D, H>,
+        events: &[Event],
+    ) where
+        D: DB + for<'iter> DBIter<'iter> + Sync + 'static,
+        H: StorageHasher + Sync + 'static,
+    {
+        let Some(block) = self.pending.as_mut() else {
+            return;
+        };
+        block.height = wl_storage.storage.block.height;
+        block.receipts = wl_storage.storage.block.receipts.clone();
+        block.events = events
+            .iter()
+            .map(|event| IndexedEvent {
+                event_type: event.event_type.to_string(),
+                attributes: event.attributes.clone().into_iter().collect(),
+            })
+            .collect();
+        // The balances are found from the keys changed in the block, since
+        // not every balance change emits an event
+        let changed: BTreeSet<(Address, Address)> = wl_storage
+            .write_log
+            .get_block_keys()
+            .filter_map(|key| {
+                let [token, owner] =
+                    token::storage_key::is_any_token_balance_key(key)?;
+                Some((token.clone(), owner.clone()))
+            })
+            .collect();
+        block.balances = changed
+            .into_iter()
+            .filter_map(|(token, owner)| {
+                match token::read_balance(wl_storage, &token, &owner) {
+                    Ok(amount) => Some(IndexedBalance {
+                        token,
+                        owner,
+                        amount,
+                    }),
+                    Err(err) => {
+                        tracing::error!(
+                            "Cannot read the balance of {owner} in {token} \
+                             for the indexer: {err}"
+                        );
+                        None
+                    }
+                }
+            })
+            .collect();
+    }
+
+    /// Send the finalized block to the sink once it's committed
+    pub fn commit(&mut self) {
+        let Some(block) = self.pending.take() else {
+            return;
+        };
+        if let Err(err) = self.sink.index_block(&block) {
+            tracing::error!(
+                "Cannot index the block at height {}: {err}",
+                block.height
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_indexer {
+    use std::sync::{Arc, Mutex};
+
+    use namada::state::testing::TestWlStorage;
+    use namada::types::address;
+
+    use super::*;
+
+    /// A sink writing into a shared buffer
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Test that a block is sent to the sink with its txs and the balances
+    /// changed in the block once it's committed
+    #[test]
+    fn test_index_block() {
+        let buffer = Buffer::default();
+        let mut indexer =
+            Indexer::with_sink(Box::new(JsonLinesSink(buffer.clone())));
+        let mut wl_storage = TestWlStorage::default();
+        wl_storage.storage.block.height = BlockHeight(2);
+        let token = address::nam();
+        let owner = address::testing::established_address_1();
+        token::credit_tokens(
+            &mut wl_storage,
+            &token,
+            &owner,
+            token::Amount::native_whole(10),
+        )
+        .unwrap();
+
+        let req = shim::request::FinalizeBlock {
+            hash: BlockHash::default(),
+            header: Header {
+                hash: Hash::default(),
+                time: DateTimeUtc::now(),
+                next_validators_hash: Hash::default(),
+            },
+            byzantine_validators: vec![],
+            txs: vec![shim::request::ProcessedTx {
+                tx: vec![1, 2, 3].into(),
+                result: shim::response::TxResult {
+                    code: 1,
+                    info: String::new(),
+                },
+            }],
+            proposer_address: vec![0xab],
+            votes: vec![],
+        };
+        indexer.begin_block(&req);
+        // The balance of an owner whose balance key didn't change in the
+        // block is not indexed, even with an event
+        let events = vec![Event::new_balance_change_event(
+            "hash",
+            2,
+            &token,
+            &address::testing::established_address_2(),
+        )];
+        indexer.end_block(&wl_storage, &events);
+        // Nothing is indexed before the block is committed
+        assert!(buffer.0.lock().unwrap().is_empty());
+
+        indexer.commit();
+        let output =
+            String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 1);
+        let block: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(block["height"], 2);
+        assert_eq!(block["proposer_address"], "AB");
+        assert_eq!(block["txs"][0]["code"], 1);
+        // The bytes are not a valid tx
+        assert!(block["txs"][0]["tx"].is_null());
+        assert_eq!(block["events"][0]["event_type"], "balance_change");
+        assert_eq!(block["balances"].as_array().unwrap().len(), 1);
+        assert_eq!(block["balances"][0]["owner"], owner.to_string());
+        assert_eq!(
+            block["balances"][0]["amount"],
+            serde_json::to_value(token::Amount::native_whole(10)).unwrap()
+        );
+    }
+}
@@ -0,0 +1,295 @@
+//! Prometheus metrics of the ledger, served over HTTP under `/metrics`.
+
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use namada::ledger::events::{Event, EventType};
+use namada::types::storage::BlockHeight;
+use once_cell::sync::Lazy;
+use prometheus::{
+    Encoder, Histogram, HistogramOpts, IntCounterVec, IntGauge, IntGaugeVec,
+    Opts, Registry, TextEncoder,
+};
+use warp::http::header::CONTENT_TYPE;
+use warp::{Filter, Rejection, Reply};
+
+/// The prefix of the names of all the metrics
+const NAMESPACE: &str = "namada";
+
+/// The metrics of this node
+pub static METRICS: Lazy<Metrics> = Lazy::new(Metrics::new);
+
+/// The metrics of the ledger, registered in their own registry
+pub struct Metrics {
+    registry: Registry,
+    /// The last finalized block height
+    last_block_height: IntGauge,
+    /// The time taken to finalize a block
+    block_processing_time: Histogram,
+    /// The number of txs by their result code
+    txs: IntCounterVec,
+    /// The gas used by the txs of the last finalized block
+    block_gas_used: IntGauge,
+    /// The size of the DB files on disk
+    storage_size_bytes: IntGauge,
+    /// The number of WASM compilation cache fetches by cache and result
+    wasm_cache_fetches: IntGaugeVec,
+    /// The time taken to commit a block to the DB
+    block_commit_time: Histogram,
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new();
+        let last_block_height = IntGauge::with_opts(
+            Opts::new("last_block_height", "The last finalized block height")
+                .namespace(NAMESPACE),
+        )
+        .unwrap();
+        let block_processing_time = Histogram::with_opts(
+            HistogramOpts::new(
+                "block_processing_seconds",
+                "The time taken to finalize a block",
+            )
+            .namespace(NAMESPACE),
+        )
+        .unwrap();
+        let txs = IntCounterVec::new(
+            Opts::new("txs", "The number of txs by their result code")
+                .namespace(NAMESPACE),
+            &["code"],
+        )
+        .unwrap();
+        let block_gas_used = IntGauge::with_opts(
+            Opts::new(
+                "block_gas_used",
+                "The gas used by the txs of the last finalized block",
+            )
+            .namespace(NAMESPACE),
+        )
+        .unwrap();
+        let storage_size_bytes = IntGauge::with_opts(
+            Opts::new("storage_size_bytes", "The size of the DB files on disk")
+                .namespace(NAMESPACE),
+        )
+        .unwrap();
+        let wasm_cache_fetches = IntGaugeVec::new(
+            Opts::new(
+                "wasm_cache_fetches",
+                "The number of WASM compilation cache fetches by cache and \
+                 result",
+            )
+            .namespace(NAMESPACE),
+            &["cache", "result"],
+        )
+        .unwrap();
+        let block_commit_time = Histogram::with_opts(
+            HistogramOpts::new(
+                "block_commit_seconds",
+                "The time taken to commit a block to the DB",
+            )
+            .namespace(NAMESPACE),
+        )
+        .unwrap();
+
+        registry
+            .register(Box::new(last_block_height.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(block_processing_time.clone()))
+            .unwrap();
+        registry.register(Box::new(txs.clone())).unwrap();
+        registry.register(Box::new(block_gas_used.clone())).unwrap();
+        registry
+            .register(Box::new(storage_size_bytes.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(wasm_cache_fetches.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(block_commit_time.clone()))
+            .unwrap();
+
+        Self {
+            registry,
+            last_block_height,
+            block_processing_time,
+            txs,
+            block_gas_used,
+            storage_size_bytes,
+            wasm_cache_fetches,
+            block_commit_time,
+        }
+    }
+
+    /// Record a finalized block from the events of its txs
+    pub fn record_finalized_block(
+        &self,
+        height: BlockHeight,
+        processing_time: Duration,
+        events: &[Event],
+    ) {
+        self.last_block_height.set(height.0 as i64);
+        self.block_processing_time
+            .observe(processing_time.as_secs_f64());
+        let mut gas_used: u64 = 0;
+        for event in events.iter().filter(|event| {
+            matches!(event.event_type, EventType::Accepted | EventType::Applied)
+        }) {
+            if let Some(code) = event.attributes.get("code") {
+                self.txs.with_label_values(&[code]).inc();
+            }
+            if let Some(gas) = event
+                .attributes
+                .get("gas_used")
+                .and_then(|gas| gas.parse::<u64>().ok())
+            {
+                gas_used = gas_used.saturating_add(gas);
+            }
+        }
+        self.block_gas_used.set(gas_used as i64);
+    }
+
+    /// Record the number of fetches of a WASM compilation cache
+    pub fn set_wasm_cache_fetches(
+        &self,
+        cache: &str,
+        (hits, misses): (u64, u64),
+    ) {
+        self.wasm_cache_fetches
+            .with_label_values(&[cache, "hit"])
+            .set(hits as i64);
+        self.wasm_cache_fetches
+            .with_label_values(&[cache, "miss"])
+            .set(misses as i64);
+    }
+
+    /// Record a block committed to the DB and the resulting DB size, if the
+    /// DB is stored on disk
+    pub fn record_block_commit(
+        &self,
+        commit_time: Duration,
+        size_bytes: Option<u64>,
+    ) {
+        self.block_commit_time.observe(commit_time.as_secs_f64());
+        if let Some(size_bytes) = size_bytes {
+            self.storage_size_bytes.set(size_bytes as i64);
+        }
+    }
+
+    /// Encode the metrics in the Prometheus text format
+    fn encode(&self) -> Vec<u8> {
+        let mut buffer = vec![];
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .expect("Encoding the metrics shouldn't fail");
+        buffer
+    }
+}
+
+/// Serve the metrics under `/metrics` on the given address, until the abort
+/// signal is received.
+pub async fn serve(
+    listen_addr: SocketAddr,
+    abort_recv: tokio::sync::oneshot::Receiver<()>,
+) -> Result<(), warp::Error> {
+    let (listen_addr, server) = warp::serve(routes())
+        .try_bind_with_graceful_shutdown(listen_addr, async move {
+            let _ = abort_recv.await;
+        })?;
+    tracing::info!("Serving metrics on http://{listen_addr}/metrics");
+    server.await;
+    Ok(())
+}
+
+/// The routes of the metrics server. Only `GET /metrics` is supported.
+fn routes() -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    warp::get()
+        .and(warp::path("metrics"))
+        .and(warp::path::end())
+        .map(|| {
+            warp::reply::with_header(
+                METRICS.encode(),
+                CONTENT_TYPE,
+                TextEncoder::new().format_type(),
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use namada::ledger::events::EventLevel;
+
+    use super::*;
+
+    /// Test that the tx results and gas of a finalized block are recorded
+    #[test]
+    fn test_record_finalized_block() {
+        let metrics = Metrics::new();
+        let tx_event = |code: &str, gas_used: &str| Event {
+            event_type: EventType::Applied,
+            level: EventLevel::Tx,
+            attributes: HashMap::from([
+                ("code".to_string(), code.to_string()),
+                ("gas_used".to_string(), gas_used.to_string()),
+            ]),
+        };
+        let events = [
+            tx_event("0", "10"),
+            tx_event("0", "20"),
+            tx_event("1", "5"),
+            Event {
+                event_type: EventType::NewEpoch,
+                level: EventLevel::Block,
+                attributes: HashMap::new(),
+            },
+        ];
+        metrics.record_finalized_block(
+            BlockHeight(2),
+            Duration::from_millis(100),
+            &events,
+        );
+
+        assert_eq!(metrics.last_block_height.get(), 2);
+        assert_eq!(metrics.block_gas_used.get(), 35);
+        assert_eq!(metrics.txs.with_label_values(&["0"]).get(), 2);
+        assert_eq!(metrics.txs.with_label_values(&["1"]).get(), 1);
+        assert_eq!(metrics.block_processing_time.get_sample_count(), 1);
+
+        let encoded = String::from_utf8(metrics.encode()).unwrap();
+        assert!(encoded.contains("namada_block_gas_used 35"));
+    }
+
+    /// Test that the metrics are served under `/metrics` only
+    #[tokio::test]
+    async fn test_metrics_routes() {
+        let res = warp::test::request()
+            .method("GET")
+            .path("/metrics")
+            .reply(&routes())
+            .await;
+        assert_eq!(res.status(), warp::http::StatusCode::OK);
+        assert_eq!(
+            res.headers()[CONTENT_TYPE],
+            TextEncoder::new().format_type()
+        );
+        let encoded = String::from_utf8(res.body().to_vec()).unwrap();
+        assert!(encoded.contains("namada_last_block_height"));
+
+        let res = warp::test::request()
+            .method("GET")
+            .path("/other")
+            .reply(&routes())
+            .await;
+        assert_eq!(res.status(), warp::http::StatusCode::NOT_FOUND);
+
+        let res = warp::test::request()
+            .method("POST")
+            .path("/metrics")
+            .reply(&routes())
+            .await;
+        assert_eq!(res.status(), warp::http::StatusCode::METHOD_NOT_ALLOWED);
+    }
+}
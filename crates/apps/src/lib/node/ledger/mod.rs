@@ -1,6 +1,12 @@
 mod abortable;
 mod broadcaster;
+mod debug_shell;
 pub mod ethereum_oracle;
+mod export_state;
+mod grpc;
+#[cfg(feature = "indexer")]
+pub mod indexer;
+mod metrics;
 pub mod shell;
 pub mod shims;
 pub mod storage;
@@ -11,12 +17,13 @@ use std::net::SocketAddr;
 use std::path::PathBuf;
 use std::str::FromStr;
 use std::thread;
+use std::time::Instant;
 
 use byte_unit::Byte;
 use futures::future::TryFutureExt;
 use namada::eth_bridge::ethers::providers::{Http, Provider};
 use namada::governance::storage::keys as governance_storage;
-use namada::types::storage::Key;
+use namada::types::storage::{Epoch, Key};
 use namada::types::time::{DateTimeUtc, Utc};
 use namada_sdk::tendermint::abci::request::CheckTxKind;
 use once_cell::unsync::Lazy;
@@ -94,6 +101,15 @@ impl Shell {
     }
 
     fn call(&mut self, req: Request) -> Result<Response, Error> {
+        if matches!(
+            req,
+            Request::PrepareProposal(_)
+                | Request::ProcessProposal(_)
+                | Request::FinalizeBlock(_)
+        ) {
+            // Halt at the height of an upgrade to another version
+            self.check_upgrade_height()?;
+        }
         match req {
             Request::InitChain(init) => {
                 tracing::debug!("Request InitChain");
@@ -127,13 +143,37 @@ impl Shell {
                 Ok(Response::RevertProposal(self.revert_proposal(_req)))
             }
             Request::FinalizeBlock(finalize) => {
+                let height = self
+                    .wl_storage
+                    .storage
+                    .get_last_block_height()
+                    .next_height();
+                let _span =
+                    tracing::info_span!("finalize_block", %height).entered();
                 tracing::debug!("Request FinalizeBlock");
+                let start = Instant::now();
                 self.load_proposals();
-                self.finalize_block(finalize).map(Response::FinalizeBlock)
+                let res = self.finalize_block(finalize);
+                if let Ok(response) = &res {
+                    metrics::METRICS.record_finalized_block(
+                        self.wl_storage.storage.block.height,
+                        start.elapsed(),
+                        &response.events,
+                    );
+                }
+                res.map(Response::FinalizeBlock)
             }
             Request::Commit => {
+                let height = self.wl_storage.storage.block.height;
+                let _span = tracing::info_span!("commit", %height).entered();
                 tracing::debug!("Request Commit");
-                Ok(Response::Commit(self.commit()))
+                let start = Instant::now();
+                let response = self.commit();
+                metrics::METRICS.record_block_commit(
+                    start.elapsed(),
+                    self.wl_storage.storage.db.size_bytes(),
+                );
+                Ok(Response::Commit(response))
             }
             Request::Flush => Ok(Response::Flush),
             Request::Echo(msg) => Ok(Response::Echo(response::Echo {
@@ -148,16 +188,16 @@ impl Shell {
                 Ok(Response::CheckTx(self.mempool_validate(&tx.tx, r#type)))
             }
             Request::ListSnapshots => {
-                Ok(Response::ListSnapshots(Default::default()))
+                Ok(Response::ListSnapshots(self.list_snapshots()))
             }
-            Request::OfferSnapshot(_) => {
-                Ok(Response::OfferSnapshot(Default::default()))
+            Request::OfferSnapshot(req) => {
+                Ok(Response::OfferSnapshot(self.offer_snapshot(req)))
             }
-            Request::LoadSnapshotChunk(_) => {
-                Ok(Response::LoadSnapshotChunk(Default::default()))
+            Request::LoadSnapshotChunk(req) => {
+                Ok(Response::LoadSnapshotChunk(self.load_snapshot_chunk(req)))
             }
-            Request::ApplySnapshotChunk(_) => {
-                Ok(Response::ApplySnapshotChunk(Default::default()))
+            Request::ApplySnapshotChunk(req) => {
+                Ok(Response::ApplySnapshotChunk(self.apply_snapshot_chunk(req)))
             }
         }
     }
@@ -220,13 +260,47 @@ pub fn dump_db(
     let chain_id = config.chain_id;
     let db_path = config.shell.db_dir(&chain_id);
 
-    let db = storage::PersistentDB::open(db_path, None);
+    let db_cache = storage::PersistentDBCache::offline(&config.shell);
+    let db = storage::PersistentDB::open(db_path, Some(&db_cache));
     db.dump_block(out_file_path, historic, block_height);
 }
 
-/// Roll Namada state back to the previous height
-pub fn rollback(config: config::Ledger) -> Result<(), shell::Error> {
-    shell::rollback(config)
+/// Run an interactive shell to inspect Namada ledger node's DB
+pub fn debug_shell(config: config::Ledger) -> eyre::Result<()> {
+    debug_shell::run(config)
+}
+
+/// Export Namada ledger node's state at a given height into a JSON file
+pub fn export_state(
+    config: config::Ledger,
+    args::LedgerExportState {
+        block_height,
+        out_file_path,
+    }: args::LedgerExportState,
+) -> eyre::Result<()> {
+    let out_file_path = export_state::run(config, block_height, out_file_path)?;
+    println!("Exported the state to {}", out_file_path.to_string_lossy());
+    Ok(())
+}
+
+/// Roll Namada state back to the previous height, or to the checkpoint of the
+/// given epoch
+pub fn rollback(
+    config: config::Ledger,
+    checkpoint: Option<Epoch>,
+) -> Result<(), shell::Error> {
+    match checkpoint {
+        Some(epoch) => shell::rollback_to_checkpoint(config, epoch),
+        None => shell::rollback(config),
+    }
+}
+
+/// Apply the pending migrations of the DB format
+pub fn migrate_db(
+    config: config::Ledger,
+    dry_run: bool,
+) -> Result<(), shell::Error> {
+    shell::migrate_db(config, dry_run)
 }
 
 /// Runs and monitors a few concurrent tasks.
@@ -270,6 +344,9 @@ async fn run_aux(config: config::Ledger, wasm_dir: PathBuf) {
     let _ = namada_sdk::masp::preload_verifying_keys();
     tracing::info!("Done loading MASP verifying keys.");
 
+    // Start the metrics server if enabled
+    let metrics = maybe_start_metrics(&mut spawner, &config);
+
     // Start ABCI server and broadcaster (the latter only if we are a validator
     // node)
     let (abci, broadcaster, shell_handler) = start_abci_broadcaster_shell(
@@ -284,10 +361,16 @@ async fn run_aux(config: config::Ledger, wasm_dir: PathBuf) {
     let aborted = spawner.wait_for_abort().await.child_terminated();
 
     // Wait for all managed tasks to finish.
-    let res = tokio::try_join!(tendermint_node, abci, eth_oracle, broadcaster);
+    let res = tokio::try_join!(
+        tendermint_node,
+        abci,
+        eth_oracle,
+        broadcaster,
+        metrics
+    );
 
     match res {
-        Ok((tendermint_res, abci_res, _, _)) => {
+        Ok((tendermint_res, abci_res, _, _, _)) => {
             // we ignore errors on user-initiated shutdown
             if aborted {
                 if let Err(err) = tendermint_res {
@@ -469,15 +552,23 @@ fn start_abci_broadcaster_shell(
     };
 
     // Setup DB cache, it must outlive the DB instance that's in the shell
-    let db_cache =
-        rocksdb::Cache::new_lru_cache(db_block_cache_size_bytes as usize);
+    let db_cache = storage::PersistentDBCache::new(
+        config.shell.db_backend,
+        db_block_cache_size_bytes,
+        config.shell.rocksdb.clone(),
+    );
 
     // Construct our ABCI application.
     let tendermint_mode = config.shell.tendermint_mode.clone();
     let proxy_app_address =
         convert_tm_addr_to_socket_addr(&config.cometbft.proxy_app);
+    let grpc_listen_addr = config
+        .shell
+        .grpc
+        .enabled
+        .then_some(config.shell.grpc.listen_addr);
 
-    let (shell, abci_service, service_handle) = AbcippShim::new(
+    let (shell, abci_service, service_handle) = match AbcippShim::new(
         config,
         wasm_dir,
         broadcaster_sender,
@@ -485,7 +576,21 @@ fn start_abci_broadcaster_shell(
         &db_cache,
         vp_wasm_compilation_cache,
         tx_wasm_compilation_cache,
-    );
+    ) {
+        Ok(shim) => shim,
+        Err(err) => {
+            tracing::error!("Cannot start the shell: {err}");
+            // Terminate the ABCI task with the error to shut the node down
+            let abci = spawner
+                .spawn_abortable("ABCI", move |aborter| async move {
+                    drop(aborter);
+                    Err(err)
+                })
+                .with_no_cleanup();
+            let shell_handler = thread::spawn(|| ());
+            return (abci, broadcaster, shell_handler);
+        }
+    };
 
     // Channel for signalling shut down to ABCI server
     let (abci_abort_send, abci_abort_recv) = tokio::sync::oneshot::channel();
@@ -497,6 +602,7 @@ fn start_abci_broadcaster_shell(
                 abci_service,
                 service_handle,
                 proxy_app_address,
+                grpc_listen_addr,
                 abci_abort_recv,
             )
             .await;
@@ -529,16 +635,34 @@ fn start_abci_broadcaster_shell(
 }
 
 /// Runs the an asynchronous ABCI server with four sub-components for consensus,
-/// mempool, snapshot, and info.
+/// mempool, snapshot, and info. If a gRPC listen address is given, the gRPC
+/// query service is served with the info component alongside.
 async fn run_abci(
     abci_service: AbciService,
     service_handle: tokio::sync::broadcast::Sender<()>,
     proxy_app_address: SocketAddr,
+    grpc_listen_addr: Option<SocketAddr>,
     abort_recv: tokio::sync::oneshot::Receiver<()>,
 ) -> shell::Result<()> {
     // Split it into components.
     let (consensus, mempool, snapshot, info) = split::service(abci_service, 5);
 
+    // The gRPC server shuts down when this sender is dropped, together with
+    // the ABCI server
+    let _grpc_abort_send = grpc_listen_addr.map(|listen_addr| {
+        let (grpc_abort_send, grpc_abort_recv) =
+            tokio::sync::oneshot::channel();
+        let info = info.clone();
+        tokio::spawn(async move {
+            if let Err(err) =
+                grpc::serve(listen_addr, info, grpc_abort_recv).await
+            {
+                tracing::error!("The gRPC query service failed: {err}");
+            }
+        });
+        grpc_abort_send
+    });
+
     // Hand those components to the ABCI server, but customize request behavior
     // for each category
     let server = Server::builder()
@@ -637,6 +761,36 @@ fn start_tendermint(
         })
 }
 
+/// Starts a server of the Prometheus metrics, if enabled in the config.
+fn maybe_start_metrics(
+    spawner: &mut AbortableSpawner,
+    config: &config::Ledger,
+) -> task::JoinHandle<()> {
+    if !config.shell.metrics.enabled {
+        return spawn_dummy_task(());
+    }
+    let listen_addr = config.shell.metrics.listen_addr;
+
+    // Channel for signalling shut down to the metrics server
+    let (metrics_abort_send, metrics_abort_recv) =
+        tokio::sync::oneshot::channel::<()>();
+
+    spawner
+        .spawn_abortable("Metrics", move |aborter| async move {
+            if let Err(err) =
+                metrics::serve(listen_addr, metrics_abort_recv).await
+            {
+                tracing::error!("Metrics server error: {err}");
+            }
+            tracing::info!("Metrics server is no longer running.");
+
+            drop(aborter);
+        })
+        .with_cleanup(async move {
+            let _ = metrics_abort_send.send(());
+        })
+}
+
 /// Represents a [`tokio::task`] in which an Ethereum oracle may be running, and
 /// if so, channels for communicating with it.
 enum EthereumOracleTask {
@@ -777,7 +931,8 @@ pub fn test_genesis_files(
         None,
         50 * 1024 * 1024,
         50 * 1024 * 1024,
-    );
+    )
+    .expect("Cannot create the shell to test the genesis files");
     let mut initializer = shell::InitChainValidation::new(&mut shell, true);
     initializer.run_validation(chain_id, genesis);
     initializer.report();
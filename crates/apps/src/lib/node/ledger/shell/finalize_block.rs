@@ -18,17 +18,25 @@ use namada::state::{
     ResultExt, StorageRead, StorageWrite, EPOCH_SWITCH_BLOCKS_DELAY,
 };
 use namada::token::conversion::update_allowed_conversions;
+use namada::token::storage_key::is_any_token_balance_key;
 use namada::tx::data::protocol::ProtocolTxType;
+use namada::tx::data::TxReceipt;
 use namada::types::key::tm_raw_hash_to_string;
-use namada::types::storage::{BlockHash, BlockResults, Epoch, Header};
+use namada::types::storage::{
+    BlockHash, BlockResults, DbKeySeg, Epoch, Header,
+};
 use namada::vote_ext::ethereum_events::MultiSignedEthEvent;
 use namada::vote_ext::ethereum_tx_data_variants;
 
 use super::governance::execute_governance_proposals;
 use super::*;
 use crate::facade::tendermint::abci::types::{Misbehavior, VoteInfo};
+use crate::node::ledger::metrics::METRICS;
 use crate::node::ledger::shell::stats::InternalStats;
 
+/// The maximum number of accounts charged the storage rent in a block
+const STORAGE_RENT_ACCOUNTS_PER_BLOCK: usize = 100;
+
 impl<D, H> Shell<D, H>
 where
     D: DB + for<'iter> DBIter<'iter> + Sync + 'static,
@@ -60,6 +68,11 @@ where
     ) -> Result<shim::response::FinalizeBlock> {
         let mut response = shim::response::FinalizeBlock::default();
 
+        #[cfg(feature = "indexer")]
+        if let Some(indexer) = self.indexer.as_mut() {
+            indexer.begin_block(&req);
+        }
+
         // Begin the new block and check if a new epoch has begun
         let (height, new_epoch) =
             self.update_state(req.header, req.hash, req.byzantine_validators);
@@ -80,6 +93,8 @@ where
             self.wl_storage.storage.update_epoch_blocks_delay
         );
 
+        self.apply_scheduled_upgrade(height, &upgrade::all())?;
+
         // Finalize the transactions' hashes from the previous block
         for hash in self.wl_storage.storage.iter_replay_protection() {
             self.wl_storage
@@ -138,7 +153,9 @@ where
             // the rewards in the current epoch.
             self.process_slashes();
             self.apply_inflation(current_epoch, &mut response)?;
+            self.start_storage_rent_charge()?;
         }
+        self.charge_storage_rent()?;
 
         // Consensus set liveness check
         if !votes.is_empty() {
@@ -197,6 +214,7 @@ where
 
         // Tracks the accepted transactions
         self.wl_storage.storage.block.results = BlockResults::default();
+        self.wl_storage.storage.block.receipts.clear();
         let mut changed_keys = BTreeSet::new();
         for (tx_index, processed_tx) in req.txs.iter().enumerate() {
             let tx = if let Ok(tx) = Tx::try_from(processed_tx.tx.as_ref()) {
@@ -209,6 +227,8 @@ where
                 );
                 continue;
             };
+            let _tx_span =
+                tracing::info_span!("tx", hash = %tx.header_hash()).entered();
             // If [`process_proposal`] rejected a Tx due to invalid signature,
             // emit an event here and move on to next tx.
             if ResultCode::from_u32(processed_tx.result.code).unwrap()
@@ -408,16 +428,22 @@ where
                 },
             };
 
+            let index = TxIndex(
+                tx_index
+                    .try_into()
+                    .expect("transaction index out of bounds"),
+            );
+            // The receipt of the tx is kept under the same hash as its event
+            let tx_hash = match &tx_header.tx_type {
+                TxType::Decrypted(_) => tx.raw_header_hash(),
+                _ => tx.header_hash(),
+            };
             let tx_result = protocol::check_tx_allowed(&tx, &self.wl_storage)
                 .and_then(|()| {
                     protocol::dispatch_tx(
                         tx,
                         processed_tx.tx.as_ref(),
-                        TxIndex(
-                            tx_index
-                                .try_into()
-                                .expect("transaction index out of bounds"),
-                        ),
+                        index,
                         &mut tx_gas_meter,
                         &mut self.wl_storage,
                         &mut self.vp_wasm_cache,
@@ -426,6 +452,8 @@ where
                     )
                 })
                 .map_err(Error::TxApply);
+            // The events of the effects of an accepted tx follow its event
+            let mut effect_events = vec![];
             match tx_result {
                 Ok(result) => {
                     if result.is_accepted() {
@@ -463,6 +491,28 @@ where
                             }
                             changed_keys
                                 .extend(result.changed_keys.iter().cloned());
+                            // Events of the effects of the tx
+                            let tx_hash = &tx_event["hash"];
+                            effect_events.extend(
+                                result.initialized_accounts.iter().map(
+                                    |address| {
+                                        Event::new_init_account_event(
+                                            tx_hash, height.0, address,
+                                        )
+                                    },
+                                ),
+                            );
+                            effect_events.extend(
+                                result
+                                    .changed_keys
+                                    .iter()
+                                    .filter_map(is_any_token_balance_key)
+                                    .map(|[token, owner]| {
+                                        Event::new_balance_change_event(
+                                            tx_hash, height.0, token, owner,
+                                        )
+                                    }),
+                            );
                             stats.increment_successful_txs();
                             if let Some(wrapper) = embedding_wrapper {
                                 self.commit_inner_tx_hash(wrapper);
@@ -522,6 +572,13 @@ where
                     tx_event["gas_used"] = result.gas_used.to_string();
                     tx_event["info"] = "Check inner_tx for result.".to_string();
                     tx_event["inner_tx"] = result.to_string();
+                    self.wl_storage.storage.block.receipts.push(TxReceipt {
+                        hash: tx_hash,
+                        height,
+                        index,
+                        result,
+                        error: None,
+                    });
                 }
                 Err(msg) => {
                     tracing::info!(
@@ -582,9 +639,29 @@ where
                     } else {
                         tx_event["code"] = ResultCode::WasmRuntimeError.into();
                     }
+                    // A replayed tx keeps the receipt of its first application
+                    if !matches!(
+                        msg,
+                        Error::TxApply(protocol::Error::ReplayAttempt(_))
+                    ) {
+                        self.wl_storage.storage.block.receipts.push(
+                            TxReceipt {
+                                hash: tx_hash,
+                                height,
+                                index,
+                                result: namada::tx::data::TxResult {
+                                    gas_used: tx_gas_meter
+                                        .get_tx_consumed_gas(),
+                                    ..Default::default()
+                                },
+                                error: Some(msg.to_string()),
+                            },
+                        );
+                    }
                 }
             }
             response.events.push(tx_event);
+            response.events.extend(effect_events);
         }
 
         stats.set_tx_cache_size(
@@ -595,6 +672,14 @@ where
             self.vp_wasm_cache.get_size(),
             self.vp_wasm_cache.get_cache_size(),
         );
+        METRICS.set_wasm_cache_fetches(
+            "tx",
+            self.tx_wasm_cache.get_hits_and_misses(),
+        );
+        METRICS.set_wasm_cache_fetches(
+            "vp",
+            self.vp_wasm_cache.get_hits_and_misses(),
+        );
 
         tracing::info!("{}", stats);
         tracing::info!("{}", stats.format_tx_executed());
@@ -624,6 +709,21 @@ where
             native_block_proposer_address,
         )?;
 
+        // The account storage sizes are written after every other write of
+        // the block, so that they count all of them
+        self.wl_storage.write_account_storage_sizes()?;
+
+        if new_epoch {
+            response
+                .events
+                .push(Event::new_epoch_event(current_epoch, height.0));
+        }
+
+        #[cfg(feature = "indexer")]
+        if let Some(indexer) = self.indexer.as_mut() {
+            indexer.end_block(&self.wl_storage, &response.events);
+        }
+
         self.event_log_mut().log_events(response.events.clone());
         tracing::debug!("End finalize_block {height} of epoch {current_epoch}");
 
@@ -727,6 +827,140 @@ where
         Ok(())
     }
 
+    /// Start charging the rent for the bytes stored in the subspaces of the
+    /// non-internal accounts on a new epoch. The accounts are charged over
+    /// the following blocks by [`Shell::charge_storage_rent`]. If the charge
+    /// of the last epoch is still in progress, the rent of this epoch is
+    /// owed and charged once it's complete.
+    fn start_storage_rent_charge(&mut self) -> Result<()> {
+        let cursor_key = parameters::storage::get_storage_rent_cursor_key();
+        let cursor = match self
+            .wl_storage
+            .read::<parameters::StorageRentCursor>(&cursor_key)?
+        {
+            Some(cursor) => parameters::StorageRentCursor {
+                owed_epochs: cursor.owed_epochs + 1,
+                ..cursor
+            },
+            None => parameters::StorageRentCursor {
+                last_charged: None,
+                epochs: 1,
+                owed_epochs: 0,
+            },
+        };
+        self.wl_storage.write(&cursor_key, cursor)?;
+        Ok(())
+    }
+
+    /// Charge the storage rent of the accounts following the last charged
+    /// account, if a charge is in progress, up to
+    /// [`STORAGE_RENT_ACCOUNTS_PER_BLOCK`] accounts per block. The rent of
+    /// the cursor's epochs is burned from the account's balance of the native
+    /// token, or whatever is left of it. Once every account is charged, the
+    /// charge of the owed epochs, if any, is started.
+    fn charge_storage_rent(&mut self) -> Result<()> {
+        let cursor_key = parameters::storage::get_storage_rent_cursor_key();
+        let Some(cursor) = self
+            .wl_storage
+            .read::<parameters::StorageRentCursor>(&cursor_key)?
+        else {
+            return Ok(());
+        };
+        let rent_per_byte =
+            parameters::read_storage_rent_per_byte(&self.wl_storage)?;
+        if rent_per_byte.is_zero() {
+            self.wl_storage.delete(&cursor_key)?;
+            return Ok(());
+        }
+
+        // The charge is resumed after the last charged account. The sizes'
+        // keys are all ordered before their prefix with `'0'` appended to it,
+        // as `'/'` < `'0'`.
+        let prefix = parameters::storage::get_account_storage_size_prefix();
+        let start = match cursor.last_charged.as_ref() {
+            Some(owner) => {
+                parameters::storage::get_account_storage_size_key(owner)
+            }
+            None => prefix.clone(),
+        };
+        let mut end = prefix;
+        if let Some(last) = end.segments.last_mut() {
+            *last = DbKeySeg::StringSeg(format!("{}0", last.raw()));
+        }
+        let (iter, _gas) = namada::state::iter_range_post(
+            &self.wl_storage.write_log,
+            &self.wl_storage.storage,
+            &start,
+            &end,
+        );
+        let mut sizes = Vec::new();
+        for (key, value, _gas) in iter {
+            let key = Key::parse(key).into_storage_result()?;
+            let owner =
+                match parameters::storage::is_account_storage_size_key(&key) {
+                    Some(owner)
+                        if Some(owner) != cursor.last_charged.as_ref() =>
+                    {
+                        owner.clone()
+                    }
+                    _ => continue,
+                };
+            let size = u64::try_from_slice(&value).into_storage_result()?;
+            sizes.push((owner, size));
+            if sizes.len() == STORAGE_RENT_ACCOUNTS_PER_BLOCK {
+                break;
+            }
+        }
+
+        let native_token = self.wl_storage.storage.native_token.clone();
+        for (owner, size) in &sizes {
+            // The rent is capped by the balance, and the accounts without any
+            // balance left are skipped, so that their balance isn't written
+            let balance =
+                token::read_balance(&self.wl_storage, &native_token, owner)?;
+            let rent = rent_per_byte
+                .checked_mul(token::Amount::from_u64(*size))
+                .and_then(|rent| {
+                    rent.checked_mul(token::Amount::from_u64(cursor.epochs))
+                })
+                .unwrap_or_else(token::Amount::max)
+                .min(balance);
+            if rent.is_zero() {
+                continue;
+            }
+            tracing::debug!(
+                "Charging storage rent of {} for {size} bytes from {owner}",
+                rent.to_string_native()
+            );
+            token::burn_tokens(
+                &mut self.wl_storage,
+                &native_token,
+                owner,
+                rent,
+            )?;
+        }
+
+        if sizes.len() < STORAGE_RENT_ACCOUNTS_PER_BLOCK {
+            if cursor.owed_epochs == 0 {
+                self.wl_storage.delete(&cursor_key)?;
+            } else {
+                let cursor = parameters::StorageRentCursor {
+                    last_charged: None,
+                    epochs: cursor.owed_epochs,
+                    owed_epochs: 0,
+                };
+                self.wl_storage.write(&cursor_key, cursor)?;
+            }
+        } else {
+            let cursor = parameters::StorageRentCursor {
+                last_charged: sizes.last().map(|(owner, _size)| owner.clone()),
+                ..cursor
+            };
+            self.wl_storage.write(&cursor_key, cursor)?;
+        }
+        Ok(())
+    }
+
     // Process the proposer and votes in the block to assign their PoS rewards.
     fn log_block_rewards(
         &mut self,
@@ -3008,6 +3242,12 @@ mod test_finalize_block {
         let code = event[1].attributes.get("code").unwrap().as_str();
         assert_eq!(code, String::from(ResultCode::WasmRuntimeError).as_str());
 
+        // The replay doesn't overwrite the receipt of the applied tx
+        let receipts = &shell.wl_storage.storage.block.receipts;
+        assert_eq!(receipts.len(), 1);
+        assert_eq!(receipts[0].hash, inner.raw_header_hash());
+        assert!(receipts[0].is_accepted());
+
         for (inner, wrapper) in [(inner, wrapper), (new_inner, new_wrapper)] {
             assert!(
                 shell
@@ -3175,6 +3415,19 @@ mod test_finalize_block {
         let code = event[4].attributes.get("code").unwrap().as_str();
         assert_eq!(code, String::from(ResultCode::WasmRuntimeError).as_str());
 
+        // The failed txs have a receipt with their error
+        let receipt = shell
+            .wl_storage
+            .storage
+            .block
+            .receipts
+            .iter()
+            .find(|receipt| receipt.hash == failing_inner.raw_header_hash())
+            .expect("Test failed");
+        assert_eq!(receipt.index, TxIndex(4));
+        assert!(receipt.error.is_some());
+        assert!(!receipt.is_accepted());
+
         for (invalid_inner, valid_wrapper) in [
             (out_of_gas_inner, out_of_gas_wrapper),
             (undecryptable_inner, undecryptable_wrapper),
@@ -3355,6 +3608,133 @@ mod test_finalize_block {
         assert_eq!(balance, 0.into())
     }
 
+    /// Test that the storage rent is burned from the balances of the accounts
+    /// with some data in their subspace
+    #[test]
+    fn test_charge_storage_rent() {
+        let (mut shell, _, _, _) = setup();
+        let native_token = shell.wl_storage.storage.native_token.clone();
+        let owner = address::testing::established_address_1();
+        let balance = |shell: &TestShell, owner: &Address| {
+            token::read_balance(&shell.wl_storage, &native_token, owner)
+                .unwrap()
+        };
+        let charge_storage_rent = |shell: &mut TestShell| {
+            shell.start_storage_rent_charge().unwrap();
+            shell.charge_storage_rent().unwrap();
+        };
+        let cursor_key = parameters::storage::get_storage_rent_cursor_key();
+
+        // No rent is charged by default
+        let key = Key::from(owner.to_db_key())
+            .push(&"data".to_owned())
+            .unwrap();
+        shell.wl_storage.write_bytes(&key, [0; 10]).unwrap();
+        token::credit_tokens(
+            &mut shell.wl_storage,
+            &native_token,
+            &owner,
+            Amount::from_u64(100),
+        )
+        .unwrap();
+        shell.wl_storage.write_account_storage_sizes().unwrap();
+        shell.wl_storage.commit_block().unwrap();
+        charge_storage_rent(&mut shell);
+        assert_eq!(balance(&shell, &owner), Amount::from_u64(100));
+        assert!(!shell.wl_storage.has_key(&cursor_key).unwrap());
+
+        let rent_key = parameters::storage::get_storage_rent_per_byte_key();
+        shell
+            .wl_storage
+            .write(&rent_key, Amount::from_u64(2))
+            .unwrap();
+        charge_storage_rent(&mut shell);
+        assert_eq!(balance(&shell, &owner), Amount::from_u64(80));
+        assert!(!shell.wl_storage.has_key(&cursor_key).unwrap());
+
+        // The rent is capped by the balance
+        shell
+            .wl_storage
+            .write(&rent_key, Amount::from_u64(10))
+            .unwrap();
+        charge_storage_rent(&mut shell);
+        assert_eq!(balance(&shell, &owner), Amount::zero());
+
+        // The balance isn't written once it's empty
+        shell.wl_storage.commit_block().unwrap();
+        charge_storage_rent(&mut shell);
+        let balance_key =
+            token::storage_key::balance_key(&native_token, &owner);
+        assert!(shell.wl_storage.write_log.read(&balance_key).0.is_none());
+
+        // The charge is spread over the blocks following the new epoch
+        shell
+            .wl_storage
+            .write(&rent_key, Amount::from_u64(1))
+            .unwrap();
+        let owners: Vec<Address> = (0..STORAGE_RENT_ACCOUNTS_PER_BLOCK)
+            .map(|_| address::testing::gen_established_address())
+            .collect();
+        for owner in &owners {
+            let key = Key::from(owner.to_db_key())
+                .push(&"data".to_owned())
+                .unwrap();
+            shell.wl_storage.write_bytes(&key, [0; 10]).unwrap();
+            token::credit_tokens(
+                &mut shell.wl_storage,
+                &native_token,
+                owner,
+                Amount::from_u64(100),
+            )
+            .unwrap();
+        }
+        shell.wl_storage.write_account_storage_sizes().unwrap();
+        shell.wl_storage.commit_block().unwrap();
+        charge_storage_rent(&mut shell);
+        let cursor = |shell: &TestShell| {
+            shell
+                .wl_storage
+                .read::<parameters::StorageRentCursor>(&cursor_key)
+                .unwrap()
+        };
+        let in_progress = cursor(&shell).expect("Test failed");
+        assert!(in_progress.last_charged.is_some());
+        assert_eq!((in_progress.epochs, in_progress.owed_epochs), (1, 0));
+        // A new epoch doesn't restart the charge in progress, its rent is
+        // owed and charged once every account is charged
+        charge_storage_rent(&mut shell);
+        assert_eq!(
+            cursor(&shell),
+            Some(parameters::StorageRentCursor {
+                last_charged: None,
+                epochs: 1,
+                owed_epochs: 0,
+            })
+        );
+        for owner in &owners {
+            assert_eq!(balance(&shell, owner), Amount::from_u64(90));
+        }
+        shell.charge_storage_rent().unwrap();
+        shell.charge_storage_rent().unwrap();
+        assert_eq!(cursor(&shell), None);
+        for owner in &owners {
+            assert_eq!(balance(&shell, owner), Amount::from_u64(80));
+        }
+
+        // A missing rent parameter defaults to zero
+        shell.wl_storage.delete(&rent_key).unwrap();
+        shell.wl_storage.commit_block().unwrap();
+        token::credit_tokens(
+            &mut shell.wl_storage,
+            &native_token,
+            &owner,
+            Amount::from_u64(100),
+        )
+        .unwrap();
+        charge_storage_rent(&mut shell);
+        assert_eq!(balance(&shell, &owner), Amount::from_u64(100));
+    }
+
     // Test that the fees collected from a block are withdrew from the wrapper
     // signer and credited to the block proposer
     #[test]
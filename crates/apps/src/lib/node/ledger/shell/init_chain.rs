@@ -104,8 +104,28 @@ where
         ))]
         let genesis = {
             let chain_dir = self.base_dir.join(chain_id);
-            genesis::chain::Finalized::read_toml_files(&chain_dir)
-                .expect("Missing genesis files")
+            let genesis =
+                genesis::chain::Finalized::read_toml_files(&chain_dir)
+                    .expect("Missing genesis files");
+            match genesis.validate_chain_id() {
+                Err(err) if self.allow_chain_id_mismatch => {
+                    tracing::warn!(
+                        "The chain ID doesn't match the genesis files, they \
+                         may have been modified after the chain ID was \
+                         derived: {err}"
+                    );
+                }
+                Err(err) => {
+                    return Err(Error::ChainId(format!(
+                        "The chain ID doesn't match the genesis files, they \
+                         may have been modified after the chain ID was \
+                         derived: {err}. Set `allow_chain_id_mismatch` in the \
+                         ledger's shell config to start the chain anyway."
+                    )));
+                }
+                Ok(()) => {}
+            }
+            genesis
         };
         #[cfg(all(
             any(test, feature = "benches"),
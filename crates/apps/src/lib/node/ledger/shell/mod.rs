@@ -14,10 +14,12 @@ use namada_sdk::tx::data::GasLimit;
 pub mod prepare_proposal;
 pub mod process_proposal;
 pub(super) mod queries;
+mod snapshots;
 mod stats;
 #[cfg(any(test, feature = "testing"))]
 #[allow(dead_code)]
 pub mod testing;
+pub mod upgrade;
 pub mod utils;
 mod vote_extensions;
 
@@ -51,12 +53,13 @@ use namada::parameters::validate_tx_bytes;
 use namada::proof_of_stake::slashing::{process_slashes, slash};
 use namada::proof_of_stake::storage::read_pos_params;
 use namada::proof_of_stake::{self};
+use namada::state::read_cache::ReadCache;
 use namada::state::tx_queue::{ExpiredTx, TxInQueue};
 use namada::state::wl_storage::WriteLogAndStorage;
 use namada::state::write_log::WriteLog;
 use namada::state::{
-    DBIter, Sha256Hasher, State, StorageHasher, StorageRead, TempWlStorage,
-    WlStorage, DB, EPOCH_SWITCH_BLOCKS_DELAY,
+    migrations, DBIter, Sha256Hasher, State, StorageHasher, StorageRead,
+    TempWlStorage, WlStorage, DB, EPOCH_SWITCH_BLOCKS_DELAY,
 };
 use namada::token;
 pub use namada::tx::data::ResultCode;
@@ -67,7 +70,7 @@ use namada::types::address::Address;
 use namada::types::chain::ChainId;
 use namada::types::ethereum_events::EthereumEvent;
 use namada::types::key::*;
-use namada::types::storage::{BlockHeight, Key, TxIndex};
+use namada::types::storage::{BlockHeight, Epoch, Key, TxIndex};
 use namada::types::time::DateTimeUtc;
 use namada::vm::wasm::{TxCache, VpCache};
 use namada::vm::{WasmCacheAccess, WasmCacheRwAccess};
@@ -129,6 +132,15 @@ pub enum Error {
     Storage(#[from] namada::state::StorageError),
     #[error("Transaction replay attempt: {0}")]
     ReplayAttempt(String),
+    #[error("Invalid indexer config: {0}")]
+    Indexer(String),
+    #[error("Invalid storage config: {0}")]
+    StorageConfig(String),
+    #[error(
+        "Reached the upgrade height {0}, the node must be upgraded to the \
+         version with the version hash {1}"
+    )]
+    UpgradeHeight(BlockHeight, namada::types::hash::Hash),
 }
 
 impl From<Error> for TxResult {
@@ -163,13 +175,64 @@ pub fn rollback(config: config::Ledger) -> Result<()> {
 
     // Rollback Namada state
     let db_path = config.shell.db_dir(&config.chain_id);
-    let mut db = storage::PersistentDB::open(db_path, None);
+    // The last block must not be replayed from the write-ahead log
+    storage::write_ahead_log(&db_path)
+        .clear()
+        .map_err(|e| Error::Storage(namada::state::StorageError::new(e)))?;
+    let db_cache = storage::PersistentDBCache::offline(&config.shell);
+    let mut db = storage::PersistentDB::open(db_path, Some(&db_cache));
     tracing::info!("Rollback Namada state");
 
     db.rollback(tendermint_block_height)
         .map_err(|e| Error::Storage(namada::state::StorageError::new(e)))
 }
 
+/// Roll Namada state back to the checkpoint of the given epoch. The Tendermint
+/// state is kept, so that the blocks after the checkpoint are replayed from
+/// its block store on the next start.
+pub fn rollback_to_checkpoint(
+    config: config::Ledger,
+    epoch: Epoch,
+) -> Result<()> {
+    let db_path = config.shell.db_dir(&config.chain_id);
+    let db_cache = storage::PersistentDBCache::offline(&config.shell);
+    let db = storage::PersistentDB::open(&db_path, Some(&db_cache));
+    // The native token isn't used by the rollback
+    let mut state = storage::PersistentStorage::open_with_db(
+        db,
+        config.chain_id,
+        address::nam(),
+        None,
+        is_merklized_storage_key,
+    );
+    state.wal = Some(storage::write_ahead_log(&db_path));
+    state
+        .load_last_state()
+        .map_err(|e| Error::Storage(namada::state::StorageError::new(e)))?;
+    tracing::info!("Rollback Namada state to the checkpoint of epoch {epoch}");
+    state
+        .rollback_to_checkpoint(epoch)
+        .map_err(|e| Error::Storage(namada::state::StorageError::new(e)))
+}
+
+/// Apply the pending migrations of the DB format. With `dry_run`, only list
+/// the pending migrations.
+pub fn migrate_db(config: config::Ledger, dry_run: bool) -> Result<()> {
+    let db_path = config.shell.db_dir(&config.chain_id);
+    let db_cache = storage::PersistentDBCache::offline(&config.shell);
+    let mut db = storage::PersistentDB::open(db_path, Some(&db_cache));
+    let migrated = migrations::migrate(&mut db, &migrations::all(), dry_run)
+        .map_err(|e| Error::Storage(namada::state::StorageError::new(e)))?;
+    if migrated.is_empty() {
+        tracing::info!("The DB format is up to date");
+    } else if dry_run {
+        tracing::info!("Pending DB migrations to the versions {migrated:?}");
+    } else {
+        tracing::info!("Applied DB migrations to the versions {migrated:?}");
+    }
+    Ok(())
+}
+
 #[derive(Debug)]
 #[allow(dead_code, clippy::large_enum_variant)]
 pub(super) enum ShellMode {
@@ -361,6 +424,14 @@ where
     pub proposal_data: HashSet<u64>,
     /// Log of events emitted by `FinalizeBlock` ABCI calls.
     event_log: EventLog,
+    /// Snapshots of the state for state sync
+    snapshots: snapshots::Snapshots,
+    /// Streams the committed blocks to an external indexer
+    #[cfg(feature = "indexer")]
+    indexer: Option<super::indexer::Indexer>,
+    /// Taken from config `allow_chain_id_mismatch`. When set, the genesis
+    /// files that don't match the chain ID are only warned about.
+    allow_chain_id_mismatch: bool,
 }
 
 /// Merkle tree storage key filter. Return `false` for keys that shouldn't be
@@ -399,6 +470,7 @@ where
 {
     /// Create a new shell from a path to a database and a chain id. Looks
     /// up the database with this data and tries to load the last state.
+    /// Fails if the node cannot process the next block.
     #[allow(clippy::too_many_arguments)]
     pub fn new(
         config: config::Ledger,
@@ -408,13 +480,14 @@ where
         db_cache: Option<&D::Cache>,
         vp_wasm_compilation_cache: u64,
         tx_wasm_compilation_cache: u64,
-    ) -> Self {
+    ) -> Result<Self> {
         let chain_id = config.chain_id;
         let db_path = config.shell.db_dir(&chain_id);
         let base_dir = config.shell.base_dir;
         let mode = config.shell.tendermint_mode;
         let storage_read_past_height_limit =
             config.shell.storage_read_past_height_limit;
+        let allow_chain_id_mismatch = config.shell.allow_chain_id_mismatch;
         if !Path::new(&base_dir).is_dir() {
             std::fs::create_dir(&base_dir)
                 .expect("Creating directory for Namada should not fail");
@@ -431,6 +504,51 @@ where
             address::nam()
         };
 
+        #[cfg(feature = "indexer")]
+        let indexer = config
+            .shell
+            .indexer
+            .as_ref()
+            .map(super::indexer::Indexer::new)
+            .transpose()
+            .map_err(|err| {
+                Error::Indexer(format!("Cannot open the indexer sink: {err}"))
+            })?;
+        #[cfg(not(feature = "indexer"))]
+        if config.shell.indexer.is_some() {
+            tracing::warn!(
+                "The indexer is configured, but the node is built without the \
+                 `indexer` feature"
+            );
+        }
+
+        // The history index of the archive profile is kept for every height,
+        // so it can't be combined with the pruning of the diffs
+        if config.shell.retain_last_n_blocks.is_some()
+            && config.shell.rocksdb.profile == config::DbProfile::Archive
+        {
+            return Err(Error::StorageConfig(
+                "`retain_last_n_blocks` cannot be set with the archive DB \
+                 profile"
+                    .to_string(),
+            ));
+        }
+        // The in-memory DB is lost with the node, so there's no block to
+        // restore from the write-ahead log
+        if config.shell.write_ahead_log
+            && config.shell.db_backend == config::DbBackend::Memory
+        {
+            return Err(Error::StorageConfig(
+                "`write_ahead_log` cannot be enabled with the memory DB \
+                 backend"
+                    .to_string(),
+            ));
+        }
+
+        let wal = config
+            .shell
+            .write_ahead_log
+            .then(|| storage::write_ahead_log(&db_path));
         // load last state from storage
         let mut storage = State::open(
             db_path,
@@ -440,12 +558,27 @@ where
             config.shell.storage_read_past_height_limit,
             is_merklized_storage_key,
         );
+        storage.retain_last_n_blocks = config.shell.retain_last_n_blocks;
+        storage.checkpoints_to_keep = config.shell.checkpoints_to_keep;
+        storage.read_cache = config
+            .shell
+            .read_cache_bytes
+            .filter(|bytes| *bytes > 0)
+            .map(|bytes| ReadCache::new(bytes as usize));
+        storage.wal = wal;
+        let db_migrations = migrations::all();
+        migrations::init_version(&mut storage.db, &db_migrations)
+            .and_then(|()| {
+                migrations::migrate(&mut storage.db, &db_migrations, false)
+            })
+            .map_err(|e| Error::Storage(namada::state::StorageError::new(e)))?;
         storage
             .load_last_state()
-            .map_err(|e| {
-                tracing::error!("Cannot load the last state from the DB {}", e);
-            })
-            .expect("PersistentStorage cannot be initialized");
+            .map_err(|e| Error::Storage(namada::state::StorageError::new(e)))?;
+        let snapshots = snapshots::Snapshots::new(
+            base_dir.join(chain_id.as_str()).join("snapshots"),
+            config.shell.snapshot_interval,
+        );
         let vp_wasm_cache_dir =
             base_dir.join(chain_id.as_str()).join("vp_wasm_cache");
         let tx_wasm_cache_dir =
@@ -514,10 +647,14 @@ where
             TendermintMode::Seed => ShellMode::Seed,
         };
 
-        let wl_storage = WlStorage {
+        let mut wl_storage = WlStorage {
             storage,
             write_log: WriteLog::default(),
         };
+        // Replay the last block if the node stopped before it was persisted
+        if let Some(height) = wl_storage.replay_wal()? {
+            tracing::info!("Replayed the block at height {height}");
+        }
         let mut shell = Self {
             chain_id,
             wl_storage,
@@ -537,9 +674,14 @@ where
             proposal_data: HashSet::new(),
             // TODO: config event log params
             event_log: EventLog::default(),
+            snapshots,
+            #[cfg(feature = "indexer")]
+            indexer,
+            allow_chain_id_mismatch,
         };
         shell.update_eth_oracle(&Default::default());
-        shell
+        shell.check_upgrade_height()?;
+        Ok(shell)
     }
 
     /// Return a reference to the [`EventLog`].
@@ -789,6 +931,11 @@ where
         );
         response.data = root.0.to_vec().into();
 
+        #[cfg(feature = "indexer")]
+        if let Some(indexer) = self.indexer.as_mut() {
+            indexer.commit();
+        }
+        self.take_snapshot();
         self.bump_last_processed_eth_block();
         self.broadcast_queued_txs();
 
@@ -1179,6 +1326,12 @@ where
                 }
             },
             TxType::Wrapper(wrapper) => {
+                // Validate against a speculative view of the state that
+                // includes the modifications of the current block that are not
+                // committed yet
+                let mut temp_wl_storage =
+                    TempWlStorage::new_speculative(&self.wl_storage);
+
                 // Tx gas limit
                 let mut gas_meter = TxGasMeter::new(wrapper.gas_limit);
                 if gas_meter.add_wrapper_gas(tx_bytes).is_err() {
@@ -1204,9 +1357,7 @@ where
 
                 // Replay protection check
                 let inner_tx_hash = tx.raw_header_hash();
-                if self
-                    .wl_storage
-                    .storage
+                if temp_wl_storage
                     .has_replay_protection_entry(&tx.raw_header_hash())
                     .expect("Error while checking inner tx hash key in storage")
                 {
@@ -1222,9 +1373,7 @@ where
                 let tx = Tx::try_from(tx_bytes)
                     .expect("Deserialization shouldn't fail");
                 let wrapper_hash = &tx.header_hash();
-                if self
-                    .wl_storage
-                    .storage
+                if temp_wl_storage
                     .has_replay_protection_entry(wrapper_hash)
                     .expect(
                         "Error while checking wrapper tx hash key in storage",
@@ -1243,7 +1392,7 @@ where
                 if let Err(e) = mempool_fee_check(
                     &wrapper,
                     get_fee_unshielding_transaction(&tx, &wrapper),
-                    &mut TempWlStorage::new(&self.wl_storage.storage),
+                    &mut temp_wl_storage,
                     &mut self.vp_wasm_cache.clone(),
                     &mut self.tx_wasm_cache.clone(),
                 ) {
@@ -1517,6 +1666,10 @@ where
     // from being passed/triggering VPs) but we cannot
     // commit the tx write log yet cause the tx could still
     // be invalid.
+    // The wasm host environment only reads the temp write log, so it must
+    // also hold the modifications of a speculative view, for the unshielding
+    // to see the same state as the other fee checks.
+    temp_wl_storage.detach_block_write_log();
     temp_wl_storage.write_log.precommit_tx();
 
     let result = apply_wasm_tx(
@@ -1549,7 +1702,7 @@ where
 
 /// for the shell
 #[cfg(test)]
-mod test_utils {
+pub(in crate::node::ledger) mod test_utils {
     use std::ops::{Deref, DerefMut};
     use std::path::PathBuf;
 
@@ -1686,7 +1839,7 @@ mod test_utils {
     /// Drop so as to clean up the files that it
     /// generates. Also allows illegal state
     /// modifications for testing purposes
-    pub(super) struct TestShell {
+    pub(in crate::node::ledger) struct TestShell {
         pub shell: Shell<MockDB, Sha256Hasher>,
     }
 
@@ -1753,7 +1906,8 @@ mod test_utils {
                 None,
                 vp_wasm_compilation_cache,
                 tx_wasm_compilation_cache,
-            );
+            )
+            .unwrap();
             shell.wl_storage.storage.block.height = height.into();
             (Self { shell }, receiver, eth_sender, control_receiver)
         }
@@ -2008,7 +2162,7 @@ mod test_utils {
     /// Same as [`setup_with_cfg`], but returns a shell at block height 0,
     /// with a single validator.
     #[inline]
-    pub(super) fn setup() -> (
+    pub(in crate::node::ledger) fn setup() -> (
         TestShell,
         UnboundedReceiver<Vec<u8>>,
         Sender<EthereumEvent>,
@@ -2085,7 +2239,8 @@ mod test_utils {
             None,
             vp_wasm_compilation_cache,
             tx_wasm_compilation_cache,
-        );
+        )
+        .unwrap();
         shell
             .wl_storage
             .storage
@@ -2140,6 +2295,7 @@ mod test_utils {
             fee_unshielding_gas_limit: 0,
             fee_unshielding_descriptions_limit: 0,
             minimum_gas_price: Default::default(),
+            storage_rent_per_byte: Default::default(),
         };
         parameters::init_storage(&params, &mut shell.wl_storage)
             .expect("Test failed");
@@ -2173,10 +2329,88 @@ mod test_utils {
             None,
             vp_wasm_compilation_cache,
             tx_wasm_compilation_cache,
-        );
+        )
+        .unwrap();
         assert!(!shell.wl_storage.storage.tx_queue.is_empty());
     }
 
+    /// Test that the shell refuses to prune the diffs with the archive DB
+    /// profile
+    #[test]
+    fn test_archive_profile_with_retained_blocks() {
+        let base_dir = tempdir().unwrap().as_ref().canonicalize().unwrap();
+        let (sender, _) = tokio::sync::mpsc::unbounded_channel();
+        let mut config = config::Ledger::new(
+            base_dir,
+            Default::default(),
+            TendermintMode::Validator,
+        );
+        config.shell.rocksdb.profile = config::DbProfile::Archive;
+        config.shell.retain_last_n_blocks = Some(100);
+        let res = Shell::<MockDB, Sha256Hasher>::new(
+            config,
+            top_level_directory().join("wasm"),
+            sender,
+            None,
+            None,
+            50 * 1024 * 1024,
+            50 * 1024 * 1024,
+        );
+        assert!(matches!(res, Err(Error::StorageConfig(_))));
+    }
+
+    /// Test that the shell refuses to enable the write-ahead log with the
+    /// memory DB backend
+    #[test]
+    fn test_memory_backend_with_write_ahead_log() {
+        let base_dir = tempdir().unwrap().as_ref().canonicalize().unwrap();
+        let (sender, _) = tokio::sync::mpsc::unbounded_channel();
+        let mut config = config::Ledger::new(
+            base_dir,
+            Default::default(),
+            TendermintMode::Full,
+        );
+        config.shell.db_backend = config::DbBackend::Memory;
+        config.shell.write_ahead_log = true;
+        let res = Shell::<MockDB, Sha256Hasher>::new(
+            config,
+            top_level_directory().join("wasm"),
+            sender,
+            None,
+            None,
+            50 * 1024 * 1024,
+            50 * 1024 * 1024,
+        );
+        assert!(matches!(res, Err(Error::StorageConfig(_))));
+    }
+
+    /// Test that the shell fails to start when the write-ahead log cannot be
+    /// replayed
+    #[test]
+    fn test_invalid_write_ahead_log() {
+        let base_dir = tempdir().unwrap().as_ref().canonicalize().unwrap();
+        let (sender, _) = tokio::sync::mpsc::unbounded_channel();
+        let mut config = config::Ledger::new(
+            base_dir,
+            Default::default(),
+            TendermintMode::Full,
+        );
+        config.shell.write_ahead_log = true;
+        let db_path = config.db_dir();
+        std::fs::create_dir_all(&db_path).unwrap();
+        std::fs::write(db_path.join("block.wal"), b"invalid").unwrap();
+        let res = Shell::<PersistentDB, PersistentStorageHasher>::new(
+            config,
+            top_level_directory().join("wasm"),
+            sender,
+            None,
+            None,
+            50 * 1024 * 1024,
+            50 * 1024 * 1024,
+        );
+        assert!(matches!(res, Err(Error::Storage(_))));
+    }
+
     pub(super) fn get_pkh_from_address<S>(
         storage: &S,
         params: &PosParams,
@@ -2758,6 +2992,59 @@ mod shell_tests {
         )
     }
 
+    /// Test that the mempool validation sees the replay protection entries of
+    /// the current block that are not committed yet
+    #[test]
+    fn test_replay_attack_uncommitted() {
+        let (mut shell, _recv, _, _) = test_utils::setup();
+
+        let keypair = super::test_utils::gen_keypair();
+
+        let mut wrapper =
+            Tx::from_type(TxType::Wrapper(Box::new(WrapperTx::new(
+                Fee {
+                    amount_per_gas_unit: DenominatedAmount::native(
+                        token::Amount::from_uint(100, 0)
+                            .expect("This can't fail"),
+                    ),
+                    token: shell.wl_storage.storage.native_token.clone(),
+                },
+                keypair.ref_to(),
+                Epoch(0),
+                GAS_LIMIT_MULTIPLIER.into(),
+                None,
+            ))));
+        wrapper.header.chain_id = shell.chain_id.clone();
+        wrapper.set_code(Code::new("wasm_code".as_bytes().to_owned(), None));
+        wrapper.set_data(Data::new("transaction data".as_bytes().to_owned()));
+        wrapper.add_section(Section::Signature(Signature::new(
+            wrapper.sechashes(),
+            [(0, keypair)].into_iter().collect(),
+            None,
+        )));
+
+        // Write the wrapper hash to the block write log only
+        let wrapper_hash = wrapper.header_hash();
+        shell
+            .wl_storage
+            .write_tx_hash(wrapper_hash)
+            .expect("Test failed");
+
+        let result = shell.mempool_validate(
+            wrapper.to_bytes().as_ref(),
+            MempoolTxType::NewTransaction,
+        );
+        assert_eq!(result.code, ResultCode::ReplayTx.into());
+        assert_eq!(
+            result.log,
+            format!(
+                "Mempool validation failed: Wrapper transaction hash {} \
+                 already in storage, replay attempt",
+                wrapper_hash
+            )
+        );
+    }
+
     /// Check that a transaction with a wrong chain id gets discarded
     #[test]
     fn test_wrong_chain_id() {
@@ -0,0 +1,560 @@
+//! Shell methods for state sync. The shell takes snapshots of the state to
+//! serve them to other nodes and restores the state of a new node from a
+//! snapshot fetched from its peers.
+
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::thread::{self, JoinHandle};
+
+use namada::state::merkle_tree::MerkleRoot;
+use namada::state::snapshot::{
+    read_raw_chunk, write_raw_chunk, SnapshotMetadata, SNAPSHOT_FORMAT,
+};
+use namada::state::State;
+use namada::types::hash::Hash;
+
+use super::*;
+use crate::facade::tendermint::abci::types::Snapshot;
+
+/// The number of the most recent snapshots that are kept on disk
+const SNAPSHOTS_TO_KEEP: usize = 2;
+
+/// The file with the chunks of a snapshot
+const CHUNKS_FILE: &str = "chunks";
+
+/// The file with the metadata of a snapshot, which is only written once all
+/// the chunks have been written
+const METADATA_FILE: &str = "metadata";
+
+/// The directory into which the chunks of a snapshot being restored are
+/// written
+const RESTORE_DIR: &str = "restore";
+
+/// Snapshots of the state for state sync. Every snapshot is stored in a
+/// sub-directory named by its height.
+#[derive(Debug)]
+pub struct Snapshots {
+    /// The directory holding the snapshots
+    dir: PathBuf,
+    /// When set, a snapshot is taken every given number of blocks
+    interval: Option<u64>,
+    /// The snapshot that is being restored from the chunks applied by
+    /// CometBFT
+    restoring: Option<RestoringSnapshot>,
+    /// The thread exporting the last snapshot that has been taken
+    exporting: Option<JoinHandle<()>>,
+}
+
+/// A snapshot accepted for state sync, whose chunks are being applied
+#[derive(Debug)]
+struct RestoringSnapshot {
+    /// The metadata of the snapshot
+    metadata: SnapshotMetadata,
+    /// The trusted app hash at the height of the snapshot
+    app_hash: [u8; 32],
+    /// The file into which the applied chunks are written
+    chunks: BufWriter<File>,
+    /// The index of the next chunk to be applied
+    next_chunk: u32,
+}
+
+impl Snapshots {
+    /// Snapshots stored in the given directory
+    pub fn new(dir: PathBuf, interval: Option<u64>) -> Self {
+        Self {
+            dir,
+            interval,
+            restoring: None,
+            exporting: None,
+        }
+    }
+
+    /// Check if a snapshot should be taken at the given height
+    fn is_due(&self, height: BlockHeight) -> bool {
+        match self.interval {
+            Some(interval) => interval > 0 && height.0 % interval == 0,
+            None => false,
+        }
+    }
+
+    fn snapshot_dir(&self, height: BlockHeight) -> PathBuf {
+        self.dir.join(height.to_string())
+    }
+
+    /// Write a snapshot with the given export function
+    fn write(
+        &self,
+        height: BlockHeight,
+        export: impl FnOnce(
+            &mut BufWriter<File>,
+        ) -> namada::state::Result<SnapshotMetadata>,
+    ) -> std::result::Result<(), String> {
+        let dir = self.snapshot_dir(height);
+        fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+        let mut chunks = BufWriter::new(
+            File::create(dir.join(CHUNKS_FILE)).map_err(|e| e.to_string())?,
+        );
+        let metadata = export(&mut chunks).map_err(|e| e.to_string())?;
+        chunks
+            .into_inner()
+            .map_err(|e| e.to_string())?
+            .sync_all()
+            .map_err(|e| e.to_string())?;
+        fs::write(dir.join(METADATA_FILE), metadata.serialize_to_vec())
+            .map_err(|e| e.to_string())
+    }
+
+    /// The metadata of the complete snapshots, ordered by their heights
+    fn list(&self) -> io::Result<Vec<SnapshotMetadata>> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                return Ok(vec![]);
+            }
+            Err(err) => return Err(err),
+        };
+        let mut snapshots = vec![];
+        for entry in entries {
+            let path = entry?.path().join(METADATA_FILE);
+            if path.is_file() {
+                let metadata =
+                    SnapshotMetadata::try_from_slice(&fs::read(path)?)?;
+                snapshots.push(metadata);
+            }
+        }
+        snapshots.sort_by_key(|metadata| metadata.height);
+        Ok(snapshots)
+    }
+
+    /// Remove all but the most recent snapshots
+    fn prune(&self) -> io::Result<()> {
+        let snapshots = self.list()?;
+        let to_remove = snapshots.len().saturating_sub(SNAPSHOTS_TO_KEEP);
+        for metadata in &snapshots[..to_remove] {
+            fs::remove_dir_all(self.snapshot_dir(metadata.height))?;
+        }
+        Ok(())
+    }
+
+    /// Read the chunk with the given index of the snapshot at the given
+    /// height, if any
+    fn load_chunk(
+        &self,
+        height: BlockHeight,
+        index: u32,
+    ) -> io::Result<Option<Vec<u8>>> {
+        let dir = self.snapshot_dir(height);
+        if !dir.join(METADATA_FILE).is_file() {
+            return Ok(None);
+        }
+        let mut chunks = BufReader::new(File::open(dir.join(CHUNKS_FILE))?);
+        for _ in 0..index {
+            if read_raw_chunk(&mut chunks)?.is_none() {
+                return Ok(None);
+            }
+        }
+        read_raw_chunk(&mut chunks)
+    }
+}
+
+/// Describe the snapshot for CometBFT. The hashes of the chunks are included
+/// in the snapshot's metadata to verify the applied chunks.
+fn abci_snapshot(metadata: &SnapshotMetadata) -> Snapshot {
+    Snapshot {
+        height: metadata.height.0.try_into().expect("Invalid block height"),
+        format: metadata.format,
+        chunks: metadata.chunks(),
+        hash: metadata.hash().0.to_vec().into(),
+        metadata: metadata.chunk_hashes.serialize_to_vec().into(),
+    }
+}
+
+impl<D, H> Shell<D, H>
+where
+    D: DB + for<'iter> DBIter<'iter> + Sync + 'static,
+    H: StorageHasher + Sync + 'static,
+{
+    /// Take a snapshot of the last committed block, if one is due at its
+    /// height, and remove the old snapshots. The snapshot is exported from a
+    /// read-only view of the DB in a background thread, so that it doesn't
+    /// hold up the commit.
+    pub(super) fn take_snapshot(&mut self) {
+        let height = self.wl_storage.storage.get_last_block_height();
+        if !self.snapshots.is_due(height) {
+            return;
+        }
+        if let Some(exporting) = self.snapshots.exporting.take() {
+            if !exporting.is_finished() {
+                tracing::warn!(
+                    "Skipping the snapshot at {height}, the previous snapshot \
+                     is still being exported"
+                );
+                self.snapshots.exporting = Some(exporting);
+                return;
+            }
+        }
+        let storage = &self.wl_storage.storage;
+        let db = match storage.db.read_only_view() {
+            Ok(db) => db,
+            Err(err) => {
+                tracing::error!(
+                    "Failed to take a snapshot of the state at {height}: {err}"
+                );
+                return;
+            }
+        };
+        let chain_id = storage.chain_id.clone();
+        let native_token = storage.native_token.clone();
+        let merkle_tree_key_filter = storage.merkle_tree_key_filter;
+        let snapshots = Snapshots::new(self.snapshots.dir.clone(), None);
+        self.snapshots.exporting = Some(thread::spawn(move || {
+            let mut state = State::<D, H>::open_with_db(
+                db,
+                chain_id,
+                native_token,
+                None,
+                merkle_tree_key_filter,
+            );
+            let result = state
+                .load_last_state()
+                .map_err(|e| e.to_string())
+                .and_then(|()| {
+                    snapshots.write(height, |writer| {
+                        state.export_snapshot(height, writer)
+                    })
+                });
+            match result {
+                Ok(()) => {
+                    tracing::info!("Took a snapshot of the state at {height}");
+                    if let Err(err) = snapshots.prune() {
+                        tracing::error!("Failed to prune old snapshots: {err}");
+                    }
+                }
+                Err(err) => tracing::error!(
+                    "Failed to take a snapshot of the state at {height}: {err}"
+                ),
+            }
+        }));
+    }
+
+    /// Wait for the snapshot being exported, if any
+    #[cfg(test)]
+    fn wait_for_snapshot(&mut self) {
+        if let Some(exporting) = self.snapshots.exporting.take() {
+            exporting
+                .join()
+                .expect("The snapshot export must not panic");
+        }
+    }
+
+    /// List the snapshots available to other nodes
+    pub fn list_snapshots(&self) -> response::ListSnapshots {
+        let snapshots = self.snapshots.list().unwrap_or_else(|err| {
+            tracing::error!("Failed to list the snapshots: {err}");
+            vec![]
+        });
+        response::ListSnapshots {
+            snapshots: snapshots.iter().map(abci_snapshot).collect(),
+        }
+    }
+
+    /// Accept a snapshot offered by CometBFT to restore the state from, if
+    /// the node doesn't have any state yet
+    pub fn offer_snapshot(
+        &mut self,
+        req: request::OfferSnapshot,
+    ) -> response::OfferSnapshot {
+        if self.wl_storage.storage.last_block.is_some() {
+            tracing::info!(
+                "Rejecting a snapshot, the node already has some state"
+            );
+            return response::OfferSnapshot::Abort;
+        }
+        let snapshot = req.snapshot;
+        if snapshot.format != SNAPSHOT_FORMAT {
+            return response::OfferSnapshot::RejectFormat;
+        }
+        let metadata = match Vec::<Hash>::try_from_slice(&snapshot.metadata) {
+            Ok(chunk_hashes) => SnapshotMetadata {
+                height: snapshot.height.value().into(),
+                format: snapshot.format,
+                chunk_hashes,
+            },
+            Err(_) => return response::OfferSnapshot::Reject,
+        };
+        let app_hash: [u8; 32] = match req.app_hash.as_bytes().try_into() {
+            Ok(app_hash) => app_hash,
+            Err(_) => return response::OfferSnapshot::Reject,
+        };
+        if metadata.hash().0.as_slice() != snapshot.hash.as_ref()
+            || metadata.chunks() != snapshot.chunks
+            || metadata.chunks() == 0
+        {
+            return response::OfferSnapshot::Reject;
+        }
+
+        let dir = self.snapshots.dir.join(RESTORE_DIR);
+        let chunks = fs::remove_dir_all(&dir)
+            .or_else(|err| match err.kind() {
+                io::ErrorKind::NotFound => Ok(()),
+                _ => Err(err),
+            })
+            .and_then(|()| fs::create_dir_all(&dir))
+            .and_then(|()| File::create(dir.join(CHUNKS_FILE)));
+        match chunks {
+            Ok(chunks) => {
+                tracing::info!(
+                    "Accepted a snapshot at height {} with {} chunks",
+                    metadata.height,
+                    metadata.chunks()
+                );
+                self.snapshots.restoring = Some(RestoringSnapshot {
+                    metadata,
+                    app_hash,
+                    chunks: BufWriter::new(chunks),
+                    next_chunk: 0,
+                });
+                response::OfferSnapshot::Accept
+            }
+            Err(err) => {
+                tracing::error!("Failed to restore a snapshot: {err}");
+                response::OfferSnapshot::Abort
+            }
+        }
+    }
+
+    /// Load a chunk of a snapshot requested by another node
+    pub fn load_snapshot_chunk(
+        &self,
+        req: request::LoadSnapshotChunk,
+    ) -> response::LoadSnapshotChunk {
+        let chunk = if req.format == SNAPSHOT_FORMAT {
+            self.snapshots
+                .load_chunk(req.height.value().into(), req.chunk)
+                .unwrap_or_else(|err| {
+                    tracing::error!("Failed to load a snapshot chunk: {err}");
+                    None
+                })
+        } else {
+            None
+        };
+        response::LoadSnapshotChunk {
+            chunk: chunk.unwrap_or_default().into(),
+        }
+    }
+
+    /// Apply a chunk of the accepted snapshot. Once all the chunks have been
+    /// applied, the state is restored from the snapshot.
+    pub fn apply_snapshot_chunk(
+        &mut self,
+        req: request::ApplySnapshotChunk,
+    ) -> response::ApplySnapshotChunk {
+        let mut response = response::ApplySnapshotChunk {
+            result: response::ApplySnapshotChunkResult::Accept,
+            ..Default::default()
+        };
+        let Some(restoring) = self.snapshots.restoring.as_mut() else {
+            response.result = response::ApplySnapshotChunkResult::Abort;
+            return response;
+        };
+        if req.index != restoring.next_chunk {
+            // The chunks are written in order
+            response.result = response::ApplySnapshotChunkResult::Retry;
+            response.refetch_chunks = vec![restoring.next_chunk];
+            return response;
+        }
+        let expected_hash = restoring.metadata.chunk_hashes[req.index as usize];
+        if Hash::sha256(&req.chunk) != expected_hash {
+            response.result = response::ApplySnapshotChunkResult::Retry;
+            response.refetch_chunks = vec![req.index];
+            response.reject_senders = vec![req.sender];
+            return response;
+        }
+        if let Err(err) = write_raw_chunk(&mut restoring.chunks, &req.chunk) {
+            tracing::error!("Failed to write a snapshot chunk: {err}");
+            response.result = response::ApplySnapshotChunkResult::Abort;
+            return response;
+        }
+        restoring.next_chunk += 1;
+        if restoring.next_chunk < restoring.metadata.chunks() {
+            return response;
+        }
+
+        // All the chunks have been applied
+        let restoring = self
+            .snapshots
+            .restoring
+            .take()
+            .expect("The restoring snapshot must be set");
+        let dir = self.snapshots.dir.join(RESTORE_DIR);
+        let result = restoring
+            .chunks
+            .into_inner()
+            .map_err(|err| err.into_error())
+            .and_then(|mut file| file.flush())
+            .and_then(|()| File::open(dir.join(CHUNKS_FILE)))
+            .map_err(namada::state::Error::SnapshotIo)
+            .and_then(|chunks| {
+                self.wl_storage.storage.import_snapshot(
+                    BufReader::new(chunks),
+                    &MerkleRoot(restoring.app_hash),
+                )
+            });
+        match result {
+            Ok(()) => tracing::info!(
+                "Restored the state from a snapshot at height {}",
+                restoring.metadata.height
+            ),
+            Err(err) => {
+                tracing::error!("Failed to restore a snapshot: {err}");
+                response.result =
+                    response::ApplySnapshotChunkResult::RejectSnapshot;
+            }
+        }
+        if let Err(err) = fs::remove_dir_all(dir) {
+            tracing::error!("Failed to remove the restored snapshot: {err}");
+        }
+        response
+    }
+}
+
+#[cfg(test)]
+mod test_snapshots {
+    use namada::types::storage::{BlockHash, Key};
+
+    use super::*;
+    use crate::node::ledger::shell::test_utils::{self, TestShell};
+
+    /// Commit blocks that write the height into a key each
+    fn commit_blocks(shell: &mut TestShell, heights: std::ops::Range<u64>) {
+        for height in heights {
+            let key = Key::parse(format!("key_{height}")).unwrap();
+            shell
+                .wl_storage
+                .storage
+                .begin_block(BlockHash::default(), BlockHeight(height))
+                .unwrap();
+            shell
+                .wl_storage
+                .write_log
+                .protocol_write(&key, height.serialize_to_vec())
+                .unwrap();
+            shell.wl_storage.commit_block().unwrap();
+            shell.take_snapshot();
+            shell.wait_for_snapshot();
+        }
+    }
+
+    /// Test that a snapshot taken by a node restores the state of another
+    /// node through the state sync handlers
+    #[test]
+    fn test_state_sync() {
+        let (mut shell, _recv, _, _) = test_utils::setup();
+        let dir = tempfile::tempdir().unwrap();
+        shell.snapshots = Snapshots::new(dir.path().to_path_buf(), Some(2));
+        commit_blocks(&mut shell, 1..7);
+
+        // Only the most recent snapshots are kept
+        let snapshots = shell.list_snapshots().snapshots;
+        let heights: Vec<u64> =
+            snapshots.iter().map(|s| s.height.value()).collect();
+        assert_eq!(heights, vec![4, 6]);
+        let snapshot = snapshots.last().unwrap().clone();
+        let app_hash = shell.wl_storage.storage.merkle_root();
+
+        // A new node without any state
+        let (mut new_shell, _recv, _, _) = TestShell::new();
+        let new_dir = tempfile::tempdir().unwrap();
+        new_shell.snapshots =
+            Snapshots::new(new_dir.path().to_path_buf(), None);
+        let offered = new_shell.offer_snapshot(request::OfferSnapshot {
+            snapshot: snapshot.clone(),
+            app_hash: AppHash::try_from(app_hash.0.to_vec()).unwrap(),
+        });
+        assert_eq!(offered, response::OfferSnapshot::Accept);
+
+        for index in 0..snapshot.chunks {
+            let chunk = shell
+                .load_snapshot_chunk(request::LoadSnapshotChunk {
+                    height: snapshot.height,
+                    format: snapshot.format,
+                    chunk: index,
+                })
+                .chunk;
+            let applied =
+                new_shell.apply_snapshot_chunk(request::ApplySnapshotChunk {
+                    index,
+                    chunk,
+                    sender: String::new(),
+                });
+            assert_eq!(
+                applied.result,
+                response::ApplySnapshotChunkResult::Accept
+            );
+        }
+
+        let state = new_shell.last_state();
+        assert_eq!(state.last_block_height.value(), 6);
+        assert_eq!(state.last_block_app_hash.as_bytes(), app_hash.0);
+        let key = Key::parse("key_5").unwrap();
+        assert_eq!(new_shell.read_storage_key::<u64>(&key), Some(5));
+    }
+
+    /// Test that a snapshot is rejected when a chunk doesn't match its hash or
+    /// when the restored state doesn't match the app hash
+    #[test]
+    fn test_state_sync_rejected() {
+        let (mut shell, _recv, _, _) = test_utils::setup();
+        let dir = tempfile::tempdir().unwrap();
+        shell.snapshots = Snapshots::new(dir.path().to_path_buf(), Some(1));
+        commit_blocks(&mut shell, 1..2);
+        let snapshot = shell.list_snapshots().snapshots[0].clone();
+
+        let (mut new_shell, _recv, _, _) = TestShell::new();
+        let new_dir = tempfile::tempdir().unwrap();
+        new_shell.snapshots =
+            Snapshots::new(new_dir.path().to_path_buf(), None);
+        let offered = new_shell.offer_snapshot(request::OfferSnapshot {
+            snapshot: snapshot.clone(),
+            app_hash: AppHash::try_from(vec![0_u8; 32]).unwrap(),
+        });
+        assert_eq!(offered, response::OfferSnapshot::Accept);
+
+        // A chunk that doesn't match its hash has to be refetched
+        let applied =
+            new_shell.apply_snapshot_chunk(request::ApplySnapshotChunk {
+                index: 0,
+                chunk: vec![0_u8].into(),
+                sender: "sender".to_string(),
+            });
+        assert_eq!(applied.result, response::ApplySnapshotChunkResult::Retry);
+        assert_eq!(applied.refetch_chunks, vec![0]);
+        assert_eq!(applied.reject_senders, vec!["sender".to_string()]);
+
+        let mut result = None;
+        for index in 0..snapshot.chunks {
+            let chunk = shell
+                .load_snapshot_chunk(request::LoadSnapshotChunk {
+                    height: snapshot.height,
+                    format: snapshot.format,
+                    chunk: index,
+                })
+                .chunk;
+            result = Some(
+                new_shell
+                    .apply_snapshot_chunk(request::ApplySnapshotChunk {
+                        index,
+                        chunk,
+                        sender: String::new(),
+                    })
+                    .result,
+            );
+        }
+        // The app hash doesn't match
+        assert_eq!(
+            result,
+            Some(response::ApplySnapshotChunkResult::RejectSnapshot)
+        );
+        assert!(new_shell.wl_storage.storage.last_block.is_none());
+    }
+}
@@ -0,0 +1,226 @@
+//! Shell methods for the upgrades of the node scheduled by governance. The
+//! node refuses to process the block at the upgrade height unless its version
+//! hash matches the scheduled one. The new binary applies its state
+//! migrations at the beginning of that block.
+
+use namada::governance::storage::upgrade::{
+    clear_upgrade_schedule, read_upgrade_schedule,
+};
+use namada::types::hash::Hash;
+
+use super::*;
+
+/// The name of the last protocol upgrade implemented by this version of the
+/// node. It must be changed along with the state migrations of a new upgrade.
+pub const PROTOCOL_UPGRADE_NAME: &str = "account-storage-sizes";
+
+/// A migration of the state applied at the height of an upgrade to this
+/// version of the node
+pub struct StateMigration<S> {
+    /// A short description of the migration for the logs
+    pub description: &'static str,
+    /// Apply the migration to the state
+    pub migrate: fn(&mut S) -> namada::state::StorageResult<()>,
+}
+
+/// All the state migrations of this version of the node, in the order in
+/// which they are applied
+pub fn all<D, H>() -> Vec<StateMigration<WlStorage<D, H>>>
+where
+    D: 'static + DB + for<'iter> DBIter<'iter>,
+    H: 'static + StorageHasher,
+{
+    // The account storage sizes are tracked from genesis on a new chain, but
+    // a chain upgraded to this version has to count the sizes of its subspace
+    vec![StateMigration {
+        description: "Seed the account storage sizes charged the storage rent",
+        migrate: WlStorage::seed_account_storage_sizes,
+    }]
+}
+
+/// The version hash of this node, to be used in an upgrade schedule. It is
+/// the SHA-256 hash of the crate's version and of the
+/// [`PROTOCOL_UPGRADE_NAME`], which are both set on purpose for a release, so
+/// that builds of the same release from a tarball or from a git checkout
+/// (possibly with local patches) have the same hash.
+pub fn version_hash() -> Hash {
+    let version =
+        format!("{}/{}", env!("CARGO_PKG_VERSION"), PROTOCOL_UPGRADE_NAME);
+    Hash::sha256(version.as_bytes())
+}
+
+impl<D, H> Shell<D, H>
+where
+    D: DB + for<'iter> DBIter<'iter> + Sync + 'static,
+    H: StorageHasher + Sync + 'static,
+{
+    /// Check that this node can process the next block, i.e. that the block
+    /// isn't at or after the height of a scheduled upgrade to another
+    /// version of the node
+    pub fn check_upgrade_height(&self) -> Result<()> {
+        let height = self
+            .wl_storage
+            .storage
+            .get_last_block_height()
+            .next_height();
+        match read_upgrade_schedule(&self.wl_storage)? {
+            Some(schedule)
+                if height >= schedule.height
+                    && schedule.version_hash != version_hash() =>
+            {
+                tracing::error!(
+                    "Reached the upgrade height {}, halting. Restart the node \
+                     with the version whose hash is {}.",
+                    schedule.height,
+                    schedule.version_hash
+                );
+                Err(Error::UpgradeHeight(
+                    schedule.height,
+                    schedule.version_hash,
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Apply the given state migrations if the upgrade is scheduled at or
+    /// before the height of the current block and clear the schedule
+    pub(super) fn apply_scheduled_upgrade(
+        &mut self,
+        height: BlockHeight,
+        migrations: &[StateMigration<WlStorage<D, H>>],
+    ) -> Result<()> {
+        match read_upgrade_schedule(&self.wl_storage)? {
+            Some(schedule) if height >= schedule.height => {
+                tracing::info!(
+                    "Applying the upgrade scheduled at height {height}"
+                );
+                for migration in migrations {
+                    tracing::info!(
+                        "Applying the state migration: {}",
+                        migration.description
+                    );
+                    (migration.migrate)(&mut self.wl_storage)?;
+                }
+                clear_upgrade_schedule(&mut self.wl_storage)?;
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_upgrade {
+    use namada::governance::storage::upgrade::{
+        schedule_upgrade, UpgradeSchedule,
+    };
+    use namada::state::StorageWrite;
+    use namada::types::storage::KeySeg;
+
+    use super::*;
+    use crate::node::ledger::shell::test_utils;
+
+    /// Test that the node halts at the upgrade height unless it runs the
+    /// scheduled version
+    #[test]
+    fn test_check_upgrade_height() {
+        let (mut shell, _recv, _, _) = test_utils::setup_at_height(3);
+        assert!(shell.check_upgrade_height().is_ok());
+
+        let mut schedule = UpgradeSchedule {
+            height: BlockHeight(5),
+            version_hash: Hash::zero(),
+        };
+        // The upgrade must be after the current block height
+        assert!(schedule_upgrade(
+            &mut shell.wl_storage,
+            UpgradeSchedule {
+                height: BlockHeight(3),
+                ..schedule.clone()
+            }
+        )
+        .is_err());
+        assert!(shell.check_upgrade_height().is_ok());
+
+        schedule_upgrade(&mut shell.wl_storage, schedule.clone()).unwrap();
+        // Before the upgrade height
+        assert!(shell.check_upgrade_height().is_ok());
+
+        schedule.height = BlockHeight(4);
+        schedule_upgrade(&mut shell.wl_storage, schedule.clone()).unwrap();
+        assert!(matches!(
+            shell.check_upgrade_height(),
+            Err(Error::UpgradeHeight(BlockHeight(4), _))
+        ));
+
+        // The scheduled version can process the block
+        schedule.version_hash = version_hash();
+        schedule_upgrade(&mut shell.wl_storage, schedule).unwrap();
+        assert!(shell.check_upgrade_height().is_ok());
+    }
+
+    /// Test that the state migrations are applied at the upgrade height only
+    #[test]
+    fn test_apply_scheduled_upgrade() {
+        let (mut shell, _recv, _, _) = test_utils::setup();
+        let key = Key::parse("migrated").unwrap();
+        let migrations = vec![StateMigration {
+            description: "Write a test key",
+            migrate: |storage: &mut WlStorage<_, _>| {
+                storage.write(&Key::parse("migrated").unwrap(), true)
+            },
+        }];
+        let schedule = UpgradeSchedule {
+            height: BlockHeight(5),
+            version_hash: version_hash(),
+        };
+        schedule_upgrade(&mut shell.wl_storage, schedule.clone()).unwrap();
+
+        shell
+            .apply_scheduled_upgrade(BlockHeight(4), &migrations)
+            .unwrap();
+        assert!(!shell.wl_storage.has_key(&key).unwrap());
+        assert_eq!(
+            read_upgrade_schedule(&shell.wl_storage).unwrap(),
+            Some(schedule)
+        );
+
+        shell
+            .apply_scheduled_upgrade(BlockHeight(5), &migrations)
+            .unwrap();
+        assert!(shell.wl_storage.has_key(&key).unwrap());
+        assert_eq!(read_upgrade_schedule(&shell.wl_storage).unwrap(), None);
+    }
+
+    /// Test that the upgrade to this version seeds the account storage sizes
+    #[test]
+    fn test_seed_account_storage_sizes() {
+        let (mut shell, _recv, _, _) = test_utils::setup();
+        let owner = address::testing::established_address_1();
+        let key = Key::from(owner.to_db_key())
+            .push(&"data".to_owned())
+            .unwrap();
+        // A value written before the sizes were tracked
+        let height = shell.wl_storage.storage.get_last_block_height();
+        shell
+            .wl_storage
+            .storage
+            .db
+            .write_subspace_val(height, &key, [0; 10], true)
+            .unwrap();
+        let size_key =
+            parameters::storage::get_account_storage_size_key(&owner);
+        assert_eq!(shell.wl_storage.read::<u64>(&size_key).unwrap(), None);
+
+        let schedule = UpgradeSchedule {
+            height: height.next_height(),
+            version_hash: version_hash(),
+        };
+        schedule_upgrade(&mut shell.wl_storage, schedule).unwrap();
+        shell
+            .apply_scheduled_upgrade(height.next_height(), &all())
+            .unwrap();
+        assert_eq!(shell.wl_storage.read::<u64>(&size_key).unwrap(), Some(10));
+    }
+}
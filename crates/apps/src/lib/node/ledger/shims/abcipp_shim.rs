@@ -25,7 +25,8 @@ use crate::facade::tendermint::v0_37::abci::{
 };
 use crate::facade::tendermint_proto::v0_37::abci::ResponseDeliverTx;
 use crate::facade::tower_abci::BoxError;
-use crate::node::ledger::shell::{EthereumOracleChannels, Shell};
+use crate::node::ledger::shell::{self, EthereumOracleChannels, Shell};
+use crate::node::ledger::storage::PersistentDBCache;
 
 /// The shim wraps the shell, which implements ABCI++.
 /// The shim makes a crude translation between the ABCI interface currently used
@@ -43,24 +44,24 @@ pub struct AbcippShim {
 
 impl AbcippShim {
     /// Create a shell with a ABCI service that passes messages to and from the
-    /// shell.
+    /// shell. Fails if the shell cannot be created.
     #[allow(clippy::too_many_arguments)]
     pub fn new(
         config: config::Ledger,
         wasm_dir: PathBuf,
         broadcast_sender: UnboundedSender<Vec<u8>>,
         eth_oracle: Option<EthereumOracleChannels>,
-        db_cache: &rocksdb::Cache,
+        db_cache: &PersistentDBCache,
         vp_wasm_compilation_cache: u64,
         tx_wasm_compilation_cache: u64,
-    ) -> (Self, AbciService, broadcast::Sender<()>) {
+    ) -> shell::Result<(Self, AbciService, broadcast::Sender<()>)> {
         // We can use an unbounded channel here, because tower-abci limits the
         // the number of requests that can come in
 
         let (shell_send, shell_recv) = std::sync::mpsc::channel();
         let (server_shutdown, _) = broadcast::channel::<()>(1);
         let action_at_height = config.shell.action_at_height.clone();
-        (
+        Ok((
             Self {
                 service: Shell::new(
                     config,
@@ -70,7 +71,7 @@ impl AbcippShim {
                     Some(db_cache),
                     vp_wasm_compilation_cache,
                     tx_wasm_compilation_cache,
-                ),
+                )?,
                 begin_block_request: None,
                 delivered_txs: vec![],
                 shell_recv,
@@ -82,7 +83,7 @@ impl AbcippShim {
                 suspended: false,
             },
             server_shutdown,
-        )
+        ))
     }
 
     /// Get the hash of the txs in the block
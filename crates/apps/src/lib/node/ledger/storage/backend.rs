@@ -0,0 +1,637 @@
+//! A DB backend that is selected at runtime from the node's configuration.
+//!
+//! [`PersistentDB`] wraps the supported DB implementations and dispatches the
+//! [`DB`] and [`DBIter`] traits to the selected one, so that the storage and
+//! the shell don't have to be re-compiled to switch backends. The backend is
+//! chosen by the variant of the [`PersistentDBCache`] given to [`DB::open`].
+
+use itertools::Either;
+use namada::state::merkle_tree::MerkleTreeStoresRead;
+use namada::state::mockdb::{MockDB, MockDBWriteBatch, MockPrefixIterator};
+use namada::state::{
+    BlockStateRead, BlockStateWrite, DBIter, DBWriteBatch, DbError as Error,
+    DbResult as Result, StoreType, DB,
+};
+use namada::tx::data::TxReceipt;
+use namada::types::ethereum_events;
+use namada::types::hash::Hash;
+use namada::types::storage::{BlockHeight, BlockResults, Epoch, Header, Key};
+
+use super::rocksdb::{
+    PersistentPrefixIterator, RocksDB, RocksDBCache, RocksDBWriteBatch,
+};
+use crate::config::{DbBackend, RocksDBConfig, Shell};
+
+/// The default RocksDB block cache size of the commands operating on the DB of
+/// a stopped node, which don't serve any queries
+const OFFLINE_BLOCK_CACHE_BYTES: u64 = 64 << 20;
+
+/// A DB backend selected at runtime
+#[derive(Debug)]
+pub enum PersistentDB {
+    /// RocksDB backend
+    RocksDB(RocksDB),
+    /// In-memory backend. Its state is lost when the node is stopped.
+    Memory(MockDB),
+}
+
+/// The cache of a [`PersistentDB`]. The variant of the cache determines which
+/// backend gets opened. When no cache is given, RocksDB is used.
+pub enum PersistentDBCache {
+    /// RocksDB block cache and tuning options
+    RocksDB(RocksDBCache),
+    /// The in-memory backend doesn't use a cache
+    Memory,
+}
+
+impl PersistentDBCache {
+    /// Setup a cache for the given backend. The `block_cache_bytes` and the
+    /// `rocksdb_config` are only used by RocksDB.
+    pub fn new(
+        backend: DbBackend,
+        block_cache_bytes: u64,
+        rocksdb_config: RocksDBConfig,
+    ) -> Self {
+        match backend {
+            DbBackend::RocksDB => Self::RocksDB(RocksDBCache {
+                block_cache: rocksdb::Cache::new_lru_cache(
+                    block_cache_bytes as usize,
+                ),
+                config: rocksdb_config,
+            }),
+            DbBackend::Memory => Self::Memory,
+        }
+    }
+
+    /// Setup a cache for the commands operating on the DB of a stopped node
+    /// (e.g. `rollback` or `dump-db`), with the backend and the RocksDB
+    /// tuning options of the node's configuration
+    pub fn offline(config: &Shell) -> Self {
+        Self::new(
+            config.db_backend,
+            config
+                .block_cache_bytes
+                .unwrap_or(OFFLINE_BLOCK_CACHE_BYTES),
+            config.rocksdb.clone(),
+        )
+    }
+}
+
+/// Handle for batch writes of a [`PersistentDB`]. Only the batch matching the
+/// opened backend is used.
+#[derive(Default)]
+pub struct PersistentDBWriteBatch {
+    rocksdb: RocksDBWriteBatch,
+    memory: MockDBWriteBatch,
+}
+
+impl DBWriteBatch for PersistentDBWriteBatch {}
+
+impl PersistentDB {
+    /// Open RocksDB in read-only mode, with the options of the given cache.
+    /// The in-memory backend cannot be opened in read-only mode.
+    pub fn open_read_only(
+        db_path: impl AsRef<std::path::Path>,
+        cache: Option<&PersistentDBCache>,
+    ) -> Result<Self> {
+        match cache {
+            Some(PersistentDBCache::RocksDB(cache)) => {
+                super::rocksdb::open_read_only(db_path, Some(cache))
+                    .map(Self::RocksDB)
+            }
+            Some(PersistentDBCache::Memory) => Err(Error::DBError(
+                "The in-memory DB backend cannot be opened in read-only mode"
+                    .to_string(),
+            )),
+            None => {
+                super::rocksdb::open_read_only(db_path, None).map(Self::RocksDB)
+            }
+        }
+    }
+
+    /// Dump last known block. Only supported by RocksDB.
+    pub fn dump_block(
+        &self,
+        out_file_path: std::path::PathBuf,
+        historic: bool,
+        height: Option<BlockHeight>,
+    ) {
+        match self {
+            Self::RocksDB(db) => db.dump_block(out_file_path, historic, height),
+            Self::Memory(_) => {
+                println!("The in-memory DB backend cannot be dumped.")
+            }
+        }
+    }
+
+    /// Rollback to previous block. Only supported by RocksDB.
+    pub fn rollback(
+        &mut self,
+        tendermint_block_height: BlockHeight,
+    ) -> Result<()> {
+        match self {
+            Self::RocksDB(db) => db.rollback(tendermint_block_height),
+            Self::Memory(_) => Err(Error::DBError(
+                "The in-memory DB backend cannot be rolled back".to_string(),
+            )),
+        }
+    }
+
+    /// The size of the DB files on disk in bytes. Only available with
+    /// RocksDB.
+    pub fn size_bytes(&self) -> Option<u64> {
+        match self {
+            Self::RocksDB(db) => Some(db.size_bytes()),
+            Self::Memory(_) => None,
+        }
+    }
+}
+
+impl DB for PersistentDB {
+    type Cache = PersistentDBCache;
+    type WriteBatch = PersistentDBWriteBatch;
+
+    fn open(
+        db_path: impl AsRef<std::path::Path>,
+        cache: Option<&Self::Cache>,
+    ) -> Self {
+        match cache {
+            Some(PersistentDBCache::RocksDB(cache)) => {
+                Self::RocksDB(RocksDB::open(db_path, Some(cache)))
+            }
+            Some(PersistentDBCache::Memory) => {
+                tracing::warn!(
+                    "Using the in-memory DB backend, the state will be lost \
+                     when the node is stopped."
+                );
+                Self::Memory(MockDB::open(db_path, None))
+            }
+            None => Self::RocksDB(RocksDB::open(db_path, None)),
+        }
+    }
+
+    fn flush(&self, wait: bool) -> Result<()> {
+        match self {
+            Self::RocksDB(db) => db.flush(wait),
+            Self::Memory(db) => db.flush(wait),
+        }
+    }
+
+    fn read_last_block(&self) -> Result<Option<BlockStateRead>> {
+        match self {
+            Self::RocksDB(db) => db.read_last_block(),
+            Self::Memory(db) => db.read_last_block(),
+        }
+    }
+
+    fn add_block_to_batch(
+        &self,
+        state: BlockStateWrite,
+        batch: &mut Self::WriteBatch,
+        is_full_commit: bool,
+    ) -> Result<()> {
+        match self {
+            Self::RocksDB(db) => {
+                db.add_block_to_batch(state, &mut batch.rocksdb, is_full_commit)
+            }
+            Self::Memory(db) => {
+                db.add_block_to_batch(state, &mut batch.memory, is_full_commit)
+            }
+        }
+    }
+
+    fn read_block_header(&self, height: BlockHeight) -> Result<Option<Header>> {
+        match self {
+            Self::RocksDB(db) => db.read_block_header(height),
+            Self::Memory(db) => db.read_block_header(height),
+        }
+    }
+
+    fn read_merkle_tree_stores(
+        &self,
+        epoch: Epoch,
+        base_height: BlockHeight,
+        store_type: Option<StoreType>,
+    ) -> Result<Option<MerkleTreeStoresRead>> {
+        match self {
+            Self::RocksDB(db) => {
+                db.read_merkle_tree_stores(epoch, base_height, store_type)
+            }
+            Self::Memory(db) => {
+                db.read_merkle_tree_stores(epoch, base_height, store_type)
+            }
+        }
+    }
+
+    fn has_replay_protection_entry(&self, hash: &Hash) -> Result<bool> {
+        match self {
+            Self::RocksDB(db) => db.has_replay_protection_entry(hash),
+            Self::Memory(db) => db.has_replay_protection_entry(hash),
+        }
+    }
+
+    fn read_tx_receipt(&self, hash: &Hash) -> Result<Option<TxReceipt>> {
+        match self {
+            Self::RocksDB(db) => db.read_tx_receipt(hash),
+            Self::Memory(db) => db.read_tx_receipt(hash),
+        }
+    }
+
+    fn read_subspace_val(&self, key: &Key) -> Result<Option<Vec<u8>>> {
+        match self {
+            Self::RocksDB(db) => db.read_subspace_val(key),
+            Self::Memory(db) => db.read_subspace_val(key),
+        }
+    }
+
+    fn read_subspace_val_with_height(
+        &self,
+        key: &Key,
+        height: BlockHeight,
+        last_height: BlockHeight,
+    ) -> Result<Option<Vec<u8>>> {
+        match self {
+            Self::RocksDB(db) => {
+                db.read_subspace_val_with_height(key, height, last_height)
+            }
+            Self::Memory(db) => {
+                db.read_subspace_val_with_height(key, height, last_height)
+            }
+        }
+    }
+
+    fn read_diffs_val(
+        &self,
+        key: &Key,
+        height: BlockHeight,
+        is_old: bool,
+    ) -> Result<Option<Vec<u8>>> {
+        match self {
+            Self::RocksDB(db) => db.read_diffs_val(key, height, is_old),
+            Self::Memory(db) => db.read_diffs_val(key, height, is_old),
+        }
+    }
+
+    fn write_subspace_val(
+        &mut self,
+        height: BlockHeight,
+        key: &Key,
+        value: impl AsRef<[u8]>,
+        persist_diffs: bool,
+    ) -> Result<i64> {
+        match self {
+            Self::RocksDB(db) => {
+                db.write_subspace_val(height, key, value, persist_diffs)
+            }
+            Self::Memory(db) => {
+                db.write_subspace_val(height, key, value, persist_diffs)
+            }
+        }
+    }
+
+    fn delete_subspace_val(
+        &mut self,
+        height: BlockHeight,
+        key: &Key,
+        persist_diffs: bool,
+    ) -> Result<i64> {
+        match self {
+            Self::RocksDB(db) => {
+                db.delete_subspace_val(height, key, persist_diffs)
+            }
+            Self::Memory(db) => {
+                db.delete_subspace_val(height, key, persist_diffs)
+            }
+        }
+    }
+
+    fn batch() -> Self::WriteBatch {
+        PersistentDBWriteBatch::default()
+    }
+
+    fn exec_batch(&mut self, batch: Self::WriteBatch) -> Result<()> {
+        match self {
+            Self::RocksDB(db) => DB::exec_batch(db, batch.rocksdb),
+            Self::Memory(db) => DB::exec_batch(db, batch.memory),
+        }
+    }
+
+    fn batch_write_subspace_val(
+        &self,
+        batch: &mut Self::WriteBatch,
+        height: BlockHeight,
+        key: &Key,
+        value: impl AsRef<[u8]>,
+        persist_diffs: bool,
+    ) -> Result<i64> {
+        match self {
+            Self::RocksDB(db) => db.batch_write_subspace_val(
+                &mut batch.rocksdb,
+                height,
+                key,
+                value,
+                persist_diffs,
+            ),
+            Self::Memory(db) => db.batch_write_subspace_val(
+                &mut batch.memory,
+                height,
+                key,
+                value,
+                persist_diffs,
+            ),
+        }
+    }
+
+    fn batch_delete_subspace_val(
+        &self,
+        batch: &mut Self::WriteBatch,
+        height: BlockHeight,
+        key: &Key,
+        persist_diffs: bool,
+    ) -> Result<i64> {
+        match self {
+            Self::RocksDB(db) => db.batch_delete_subspace_val(
+                &mut batch.rocksdb,
+                height,
+                key,
+                persist_diffs,
+            ),
+            Self::Memory(db) => db.batch_delete_subspace_val(
+                &mut batch.memory,
+                height,
+                key,
+                persist_diffs,
+            ),
+        }
+    }
+
+    fn prune_merkle_tree_store(
+        &mut self,
+        batch: &mut Self::WriteBatch,
+        store_type: &StoreType,
+        pruned_epoch: Epoch,
+    ) -> Result<()> {
+        match self {
+            Self::RocksDB(db) => db.prune_merkle_tree_store(
+                &mut batch.rocksdb,
+                store_type,
+                pruned_epoch,
+            ),
+            Self::Memory(db) => db.prune_merkle_tree_store(
+                &mut batch.memory,
+                store_type,
+                pruned_epoch,
+            ),
+        }
+    }
+
+    fn prune_below(
+        &mut self,
+        batch: &mut Self::WriteBatch,
+        height: BlockHeight,
+    ) -> Result<()> {
+        match self {
+            Self::RocksDB(db) => db.prune_below(&mut batch.rocksdb, height),
+            Self::Memory(db) => db.prune_below(&mut batch.memory, height),
+        }
+    }
+
+    fn read_diffs_pruned_height(&self) -> Result<Option<BlockHeight>> {
+        match self {
+            Self::RocksDB(db) => db.read_diffs_pruned_height(),
+            Self::Memory(db) => db.read_diffs_pruned_height(),
+        }
+    }
+
+    fn read_key_history(
+        &self,
+        key: &Key,
+        from_height: BlockHeight,
+        limit: usize,
+    ) -> Result<Vec<BlockHeight>> {
+        match self {
+            Self::RocksDB(db) => db.read_key_history(key, from_height, limit),
+            Self::Memory(db) => db.read_key_history(key, from_height, limit),
+        }
+    }
+
+    fn read_last_change_height(
+        &self,
+        key: &Key,
+        height: BlockHeight,
+    ) -> Result<Option<BlockHeight>> {
+        match self {
+            Self::RocksDB(db) => db.read_last_change_height(key, height),
+            Self::Memory(db) => db.read_last_change_height(key, height),
+        }
+    }
+
+    fn read_db_version(&self) -> Result<Option<u64>> {
+        match self {
+            Self::RocksDB(db) => db.read_db_version(),
+            Self::Memory(db) => db.read_db_version(),
+        }
+    }
+
+    fn write_db_version(
+        &mut self,
+        batch: &mut Self::WriteBatch,
+        version: u64,
+    ) -> Result<()> {
+        match self {
+            Self::RocksDB(db) => {
+                db.write_db_version(&mut batch.rocksdb, version)
+            }
+            Self::Memory(db) => db.write_db_version(&mut batch.memory, version),
+        }
+    }
+
+    fn read_bridge_pool_signed_nonce(
+        &self,
+        height: BlockHeight,
+        last_height: BlockHeight,
+    ) -> Result<Option<ethereum_events::Uint>> {
+        match self {
+            Self::RocksDB(db) => {
+                db.read_bridge_pool_signed_nonce(height, last_height)
+            }
+            Self::Memory(db) => {
+                db.read_bridge_pool_signed_nonce(height, last_height)
+            }
+        }
+    }
+
+    fn write_replay_protection_entry(
+        &mut self,
+        batch: &mut Self::WriteBatch,
+        key: &Key,
+    ) -> Result<()> {
+        match self {
+            Self::RocksDB(db) => {
+                db.write_replay_protection_entry(&mut batch.rocksdb, key)
+            }
+            Self::Memory(db) => {
+                db.write_replay_protection_entry(&mut batch.memory, key)
+            }
+        }
+    }
+
+    fn delete_replay_protection_entry(
+        &mut self,
+        batch: &mut Self::WriteBatch,
+        key: &Key,
+    ) -> Result<()> {
+        match self {
+            Self::RocksDB(db) => {
+                db.delete_replay_protection_entry(&mut batch.rocksdb, key)
+            }
+            Self::Memory(db) => {
+                db.delete_replay_protection_entry(&mut batch.memory, key)
+            }
+        }
+    }
+
+    fn write_block_header(
+        &mut self,
+        batch: &mut Self::WriteBatch,
+        height: BlockHeight,
+        header: &Header,
+    ) -> Result<()> {
+        match self {
+            Self::RocksDB(db) => {
+                db.write_block_header(&mut batch.rocksdb, height, header)
+            }
+            Self::Memory(db) => {
+                db.write_block_header(&mut batch.memory, height, header)
+            }
+        }
+    }
+
+    fn write_block_results(
+        &mut self,
+        batch: &mut Self::WriteBatch,
+        height: BlockHeight,
+        results: &BlockResults,
+    ) -> Result<()> {
+        match self {
+            Self::RocksDB(db) => {
+                db.write_block_results(&mut batch.rocksdb, height, results)
+            }
+            Self::Memory(db) => {
+                db.write_block_results(&mut batch.memory, height, results)
+            }
+        }
+    }
+
+    fn write_checkpoint(&self, epoch: Epoch) -> Result<()> {
+        match self {
+            Self::RocksDB(db) => db.write_checkpoint(epoch),
+            Self::Memory(db) => db.write_checkpoint(epoch),
+        }
+    }
+
+    fn restore_checkpoint(&mut self, epoch: Epoch) -> Result<()> {
+        match self {
+            Self::RocksDB(db) => db.restore_checkpoint(epoch),
+            Self::Memory(db) => db.restore_checkpoint(epoch),
+        }
+    }
+
+    fn delete_checkpoint(&self, epoch: Epoch) -> Result<()> {
+        match self {
+            Self::RocksDB(db) => db.delete_checkpoint(epoch),
+            Self::Memory(db) => db.delete_checkpoint(epoch),
+        }
+    }
+
+    fn checkpoints(&self) -> Result<Vec<Epoch>> {
+        match self {
+            Self::RocksDB(db) => db.checkpoints(),
+            Self::Memory(db) => db.checkpoints(),
+        }
+    }
+
+    fn read_only_view(&self) -> Result<Self> {
+        match self {
+            Self::RocksDB(db) => db.read_only_view().map(Self::RocksDB),
+            Self::Memory(db) => db.read_only_view().map(Self::Memory),
+        }
+    }
+}
+
+impl<'iter> DBIter<'iter> for PersistentDB {
+    type PrefixIter =
+        Either<PersistentPrefixIterator<'iter>, MockPrefixIterator>;
+
+    fn iter_prefix(&'iter self, prefix: Option<&Key>) -> Self::PrefixIter {
+        match self {
+            Self::RocksDB(db) => Either::Left(db.iter_prefix(prefix)),
+            Self::Memory(db) => Either::Right(db.iter_prefix(prefix)),
+        }
+    }
+
+    fn rev_iter_prefix(&'iter self, prefix: Option<&Key>) -> Self::PrefixIter {
+        match self {
+            Self::RocksDB(db) => Either::Left(db.rev_iter_prefix(prefix)),
+            Self::Memory(db) => Either::Right(db.rev_iter_prefix(prefix)),
+        }
+    }
+
+    fn iter_range(&'iter self, start: &Key, end: &Key) -> Self::PrefixIter {
+        match self {
+            Self::RocksDB(db) => Either::Left(db.iter_range(start, end)),
+            Self::Memory(db) => Either::Right(db.iter_range(start, end)),
+        }
+    }
+
+    fn iter_results(&'iter self) -> Self::PrefixIter {
+        match self {
+            Self::RocksDB(db) => Either::Left(db.iter_results()),
+            Self::Memory(db) => Either::Right(db.iter_results()),
+        }
+    }
+
+    fn iter_old_diffs(
+        &'iter self,
+        height: BlockHeight,
+        prefix: Option<&'iter Key>,
+    ) -> Self::PrefixIter {
+        match self {
+            Self::RocksDB(db) => {
+                Either::Left(db.iter_old_diffs(height, prefix))
+            }
+            Self::Memory(db) => {
+                Either::Right(db.iter_old_diffs(height, prefix))
+            }
+        }
+    }
+
+    fn iter_new_diffs(
+        &'iter self,
+        height: BlockHeight,
+        prefix: Option<&'iter Key>,
+    ) -> Self::PrefixIter {
+        match self {
+            Self::RocksDB(db) => {
+                Either::Left(db.iter_new_diffs(height, prefix))
+            }
+            Self::Memory(db) => {
+                Either::Right(db.iter_new_diffs(height, prefix))
+            }
+        }
+    }
+
+    fn iter_replay_protection(&'iter self) -> Self::PrefixIter {
+        match self {
+            Self::RocksDB(db) => Either::Left(db.iter_replay_protection()),
+            Self::Memory(db) => Either::Right(db.iter_replay_protection()),
+        }
+    }
+
+    fn iter_all_replay_protection(&'iter self) -> Self::PrefixIter {
+        match self {
+            Self::RocksDB(db) => Either::Left(db.iter_all_replay_protection()),
+            Self::Memory(db) => Either::Right(db.iter_all_replay_protection()),
+        }
+    }
+}
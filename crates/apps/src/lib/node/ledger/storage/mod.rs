@@ -1,21 +1,24 @@
 //! The storage module handles both the current state in-memory and the stored
 //! state in DB.
 
+mod backend;
 mod rocksdb;
 
 use std::fmt;
+use std::path::Path;
 
 use arse_merkle_tree::blake2b::Blake2bHasher;
 use arse_merkle_tree::traits::Hasher;
 use arse_merkle_tree::H256;
 use blake2b_rs::{Blake2b, Blake2bBuilder};
+use namada::state::wal::WriteAheadLog;
 use namada::state::{State, StorageHasher};
 
+pub use self::backend::{PersistentDB, PersistentDBCache};
+
 #[derive(Default)]
 pub struct PersistentStorageHasher(Blake2bHasher);
 
-pub type PersistentDB = rocksdb::RocksDB;
-
 pub type PersistentStorage = State<PersistentDB, PersistentStorageHasher>;
 
 impl Hasher for PersistentStorageHasher {
@@ -48,6 +51,12 @@ fn new_blake2b() -> Blake2b {
     Blake2bBuilder::new(32).personal(b"namada storage").build()
 }
 
+/// The write-ahead log of the DB at the given path. It's kept inside of the DB
+/// directory, so that it gets removed together with the DB.
+pub fn write_ahead_log(db_path: impl AsRef<Path>) -> WriteAheadLog {
+    WriteAheadLog::new(db_path.as_ref().join("block.wal"))
+}
+
 #[cfg(test)]
 mod tests {
     use std::collections::HashMap;
@@ -59,9 +68,10 @@ mod tests {
     use namada::ledger::gas::STORAGE_ACCESS_GAS_PER_BYTE;
     use namada::ledger::ibc::storage::ibc_key;
     use namada::ledger::parameters::{EpochDuration, Parameters};
+    use namada::state::merkle_tree::Proof;
     use namada::state::write_log::WriteLog;
     use namada::state::{
-        self, StorageRead, StorageWrite, StoreType, WlStorage, DB,
+        self, Sha256Hasher, StorageRead, StorageWrite, StoreType, WlStorage, DB,
     };
     use namada::token::conversion::update_allowed_conversions;
     use namada::types::chain::ChainId;
@@ -133,6 +143,37 @@ mod tests {
         assert_eq!(result, None);
     }
 
+    #[test]
+    fn test_memory_backend() {
+        let db_path =
+            TempDir::new().expect("Unable to create a temporary DB directory");
+        let mut storage = PersistentStorage::open(
+            db_path.path(),
+            ChainId::default(),
+            address::nam(),
+            Some(&PersistentDBCache::Memory),
+            None,
+            is_merklized_storage_key,
+        );
+        assert!(matches!(storage.db, PersistentDB::Memory(_)));
+
+        let key = Key::parse("key").expect("cannot parse the key string");
+        let value_bytes = types::encode(&1_u64);
+        storage
+            .write(&key, value_bytes.clone())
+            .expect("write failed");
+        let (result, _) = storage.read(&key).expect("read failed");
+        assert_eq!(result, Some(value_bytes));
+
+        // nothing is written to the DB directory
+        assert!(
+            std::fs::read_dir(db_path.path())
+                .expect("cannot read the DB directory")
+                .next()
+                .is_none()
+        );
+    }
+
     #[test]
     fn test_commit_block() {
         let db_path =
@@ -172,6 +213,7 @@ mod tests {
             fee_unshielding_gas_limit: 0,
             fee_unshielding_descriptions_limit: 0,
             minimum_gas_price: Default::default(),
+            storage_rent_per_byte: Default::default(),
         };
         parameters::init_storage(&params, &mut wl_storage)
             .expect("Test failed");
@@ -267,6 +309,53 @@ mod tests {
         }
     }
 
+    /// Test that the proofs of the storage are verified with its Blake2b
+    /// hasher
+    #[test]
+    fn test_verify_proof() {
+        let db_path =
+            TempDir::new().expect("Unable to create a temporary DB directory");
+        let mut storage = PersistentStorage::open(
+            db_path.path(),
+            ChainId::default(),
+            address::nam(),
+            None,
+            None,
+            is_merklized_storage_key,
+        );
+        storage
+            .begin_block(BlockHash::default(), BlockHeight(100))
+            .expect("begin_block failed");
+        let key = Key::parse("key").expect("cannot parse the key string");
+        let value_bytes = types::encode(&1_u64);
+        storage
+            .write(&key, value_bytes.clone())
+            .expect("write failed");
+        let batch = PersistentStorage::batch();
+        storage.commit_block(batch).expect("commit failed");
+        let root = storage.merkle_root();
+
+        // existence proof
+        let (value, proof) = storage
+            .get_with_proof(&key, BlockHeight(100))
+            .expect("no proof");
+        assert_eq!(value, Some(value_bytes.clone()));
+        let proof = Proof::try_from(proof).expect("invalid proof");
+        let value = Some(&value_bytes[..]);
+        assert!(proof.verify::<PersistentStorageHasher>(value, &root));
+        assert!(!proof.verify::<Sha256Hasher>(value, &root));
+
+        // non-existence proof
+        let other_key = Key::parse("other").expect("cannot parse the key");
+        let (value, proof) = storage
+            .get_with_proof(&other_key, BlockHeight(100))
+            .expect("no proof");
+        assert!(value.is_none());
+        let proof = Proof::try_from(proof).expect("invalid proof");
+        assert!(proof.verify::<PersistentStorageHasher>(None, &root));
+        assert!(!proof.verify::<Sha256Hasher>(None, &root));
+    }
+
     #[test]
     fn test_validity_predicate() {
         let db_path =
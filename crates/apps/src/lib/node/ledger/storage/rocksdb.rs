@@ -18,13 +18,20 @@
 //!     - `next_epoch_min_start_height`
 //!     - `next_epoch_min_start_time`
 //!   - `conversion_state`: MASP conversion state
+//!   - `db_version`: the version of the DB format
+//!   - `diffs_pruned_height`: the height below which the diffs are pruned
 //! - `subspace`: accounts sub-spaces
 //!   - `{address}/{dyn}`: any byte data associated with accounts
 //! - `diffs`: diffs in account subspaces' key-vals
 //!   - `new/{dyn}`: value set in block height `h`
 //!   - `old/{dyn}`: value from predecessor block height
+//!   - `history/{hash}/{h}`: the key with the hash was changed in block height
+//!     `h`, only indexed by archive nodes
 //! - `block`: block state
 //!   - `results/{h}`: block results at height `h`
+//!   - `receipts/{h}/{hash}`: receipt of the tx with the hash applied at height
+//!     `h`
+//!   - `receipt_heights/{hash}`: height of the receipt of the tx with the hash
 //!   - `h`: for each block at height `h`:
 //!     - `tree`: merkle tree
 //!       - `root`: root hash
@@ -40,8 +47,7 @@
 
 use std::fs::File;
 use std::io::{BufWriter, Write};
-use std::path::Path;
-use std::str::FromStr;
+use std::path::{Path, PathBuf};
 use std::sync::Mutex;
 
 use borsh::BorshDeserialize;
@@ -51,21 +57,22 @@ use itertools::Either;
 use namada::eth_bridge::storage::proof::BridgePoolRootProof;
 use namada::ledger::eth_bridge::storage::bridge_pool;
 use namada::ledger::replay_protection;
-use namada::ledger::storage::tx_queue::TxQueue;
-use namada::state::merkle_tree::{base_tree_key_prefix, subtree_key_prefix};
+use namada::state::db_key::{
+    diffs_prefix, history_prefix, receipts_prefix, results_prefix, BlockField,
+    DbKey, Namespace, StateKey, TreeField,
+};
 use namada::state::types::PrefixIterator;
 use namada::state::{
     BlockStateRead, BlockStateWrite, DBIter, DBWriteBatch, DbError as Error,
     DbResult as Result, MerkleTreeStoresRead, StoreType, DB,
 };
+use namada::tx::data::TxReceipt;
 use namada::types;
+use namada::types::ethereum_events;
+use namada::types::hash::Hash;
 use namada::types::storage::{
-    BlockHeight, BlockResults, Epoch, EthEventsQueue, Header, Key, KeySeg,
-    KEY_SEGMENT_SEPARATOR,
+    BlockHeight, BlockResults, Epoch, Header, Key, KeySeg,
 };
-use namada::types::time::DateTimeUtc;
-use namada::types::token::ConversionState;
-use namada::types::{ethereum_events, ethereum_structs};
 use rayon::prelude::*;
 use rocksdb::{
     BlockBasedOptions, ColumnFamily, ColumnFamilyDescriptor, DBCompactionStyle,
@@ -74,26 +81,53 @@ use rocksdb::{
 };
 
 use crate::config::utils::num_of_threads;
-
-// TODO the DB schema will probably need some kind of versioning
+use crate::config::{
+    DbCompactionStyle as CompactionStyle, DbProfile, RocksDBConfig,
+};
 
 /// Env. var to set a number of Rayon global worker threads
 const ENV_VAR_ROCKSDB_COMPACTION_THREADS: &str =
     "NAMADA_ROCKSDB_COMPACTION_THREADS";
 
-/// Column family names
-const SUBSPACE_CF: &str = "subspace";
-const DIFFS_CF: &str = "diffs";
-const STATE_CF: &str = "state";
-const BLOCK_CF: &str = "block";
-const REPLAY_PROTECTION_CF: &str = "replay_protection";
-
-const OLD_DIFF_PREFIX: &str = "old";
-const NEW_DIFF_PREFIX: &str = "new";
+/// Column family names, one for each of the DB namespaces
+const SUBSPACE_CF: &str = Namespace::Subspace.name();
+const DIFFS_CF: &str = Namespace::Diffs.name();
+const STATE_CF: &str = Namespace::State.name();
+const BLOCK_CF: &str = Namespace::Block.name();
+const REPLAY_PROTECTION_CF: &str = Namespace::ReplayProtection.name();
+const COLUMN_FAMILIES: [&str; 5] = [
+    SUBSPACE_CF,
+    DIFFS_CF,
+    STATE_CF,
+    BLOCK_CF,
+    REPLAY_PROTECTION_CF,
+];
+
+/// The maximum size of the write batches used to restore a checkpoint
+const RESTORE_BATCH_BYTES: usize = 64 << 20;
 
 /// RocksDB handle
 #[derive(Debug)]
-pub struct RocksDB(rocksdb::DB);
+pub struct RocksDB {
+    inner: rocksdb::DB,
+    /// Whether to index the heights at which the subspace keys changed, set
+    /// with the archive profile
+    archive: bool,
+    /// Whether the DB is opened in read-only mode
+    read_only: bool,
+    /// The directory of the checkpoint that a read-only view of another DB
+    /// has been opened from, which is removed when the view is dropped
+    view_dir: Option<PathBuf>,
+}
+
+/// The block cache and the tuning options that a RocksDB instance is opened
+/// with
+pub struct RocksDBCache {
+    /// The block cache shared by all the column families
+    pub block_cache: rocksdb::Cache,
+    /// The tuning options
+    pub config: RocksDBConfig,
+}
 
 /// DB Handle for batch writes.
 #[derive(Default)]
@@ -102,8 +136,54 @@ pub struct RocksDBWriteBatch(WriteBatch);
 /// Open RocksDB for the DB
 pub fn open(
     path: impl AsRef<Path>,
-    cache: Option<&rocksdb::Cache>,
+    cache: Option<&RocksDBCache>,
 ) -> Result<RocksDB> {
+    let config = cache.map(|cache| cache.config.clone()).unwrap_or_default();
+    let (db_opts, cfs) = db_options(&config, cache);
+    let mut db = rocksdb::DB::open_cf_descriptors(&db_opts, path, cfs)
+        .map(|inner| RocksDB {
+            inner,
+            archive: config.profile == DbProfile::Archive,
+            read_only: false,
+            view_dir: None,
+        })
+        .map_err(|e| Error::DBError(e.into_string()))?;
+    // Finish the restore of a checkpoint that has been interrupted
+    if let Some(epoch) = db.interrupted_restore()? {
+        tracing::info!(
+            "Resuming the interrupted restore of the checkpoint of the epoch \
+             {epoch}"
+        );
+        db.restore_checkpoint(epoch)?;
+    }
+    Ok(db)
+}
+
+/// Open RocksDB in read-only mode, with the same options as [`open`]. This
+/// can be used while another process, e.g. a running node, has the DB open
+/// for writing. The opened DB doesn't see any writes made after it's been
+/// opened.
+pub fn open_read_only(
+    path: impl AsRef<Path>,
+    cache: Option<&RocksDBCache>,
+) -> Result<RocksDB> {
+    let config = cache.map(|cache| cache.config.clone()).unwrap_or_default();
+    let (db_opts, cfs) = db_options(&config, cache);
+    rocksdb::DB::open_cf_descriptors_read_only(&db_opts, path, cfs, false)
+        .map(|inner| RocksDB {
+            inner,
+            archive: config.profile == DbProfile::Archive,
+            read_only: true,
+            view_dir: None,
+        })
+        .map_err(|e| Error::DBError(e.into_string()))
+}
+
+/// The DB options and the column families that the DB is opened with
+fn db_options(
+    config: &RocksDBConfig,
+    cache: Option<&RocksDBCache>,
+) -> (Options, Vec<ColumnFamilyDescriptor>) {
     let logical_cores = num_cpus::get();
     let compaction_threads = num_of_threads(
         ENV_VAR_ROCKSDB_COMPACTION_THREADS,
@@ -124,6 +204,12 @@ pub fn open(
 
     db_opts.set_bytes_per_sync(1048576);
     set_max_open_files(&mut db_opts);
+    if let Some(size) = config.max_total_wal_size_bytes {
+        db_opts.set_max_total_wal_size(size);
+    }
+    if let Some(bytes) = config.wal_bytes_per_sync {
+        db_opts.set_wal_bytes_per_sync(bytes);
+    }
 
     // TODO the recommended default `options.compaction_pri =
     // kMinOverlappingRatio` doesn't seem to be available in Rust
@@ -134,11 +220,15 @@ pub fn open(
 
     let mut cfs = Vec::new();
     let mut table_opts = BlockBasedOptions::default();
-    table_opts.set_block_size(16 * 1024);
+    table_opts.set_block_size(config.block_size());
     table_opts.set_cache_index_and_filter_blocks(true);
     table_opts.set_pin_l0_filter_and_index_blocks_in_cache(true);
+    let bloom_filter_bits_per_key = config.bloom_filter_bits_per_key();
+    if bloom_filter_bits_per_key > 0 {
+        table_opts.set_bloom_filter(bloom_filter_bits_per_key as f64, false);
+    }
     if let Some(cache) = cache {
-        table_opts.set_block_cache(cache);
+        table_opts.set_block_cache(&cache.block_cache);
     }
     // latest format versions https://github.com/facebook/rocksdb/blob/d1c510baecc1aef758f91f786c4fbee3bc847a63/include/rocksdb/table.h#L394
     table_opts.set_format_version(5);
@@ -147,9 +237,16 @@ pub fn open(
     let mut subspace_cf_opts = Options::default();
     subspace_cf_opts.set_compression_type(DBCompressionType::Zstd);
     subspace_cf_opts.set_compression_options(0, 0, 0, 1024 * 1024);
-    // ! recommended initial setup https://github.com/facebook/rocksdb/wiki/Setup-Options-and-Basic-Tuning#other-general-options
-    subspace_cf_opts.set_level_compaction_dynamic_level_bytes(true);
-    subspace_cf_opts.set_compaction_style(DBCompactionStyle::Level);
+    match config.subspace_compaction_style() {
+        CompactionStyle::Level => {
+            // ! recommended initial setup https://github.com/facebook/rocksdb/wiki/Setup-Options-and-Basic-Tuning#other-general-options
+            subspace_cf_opts.set_level_compaction_dynamic_level_bytes(true);
+            subspace_cf_opts.set_compaction_style(DBCompactionStyle::Level);
+        }
+        CompactionStyle::Universal => {
+            subspace_cf_opts.set_compaction_style(DBCompactionStyle::Universal);
+        }
+    }
     subspace_cf_opts.set_block_based_table_factory(&table_opts);
     cfs.push(ColumnFamilyDescriptor::new(SUBSPACE_CF, subspace_cf_opts));
 
@@ -190,24 +287,150 @@ pub fn open(
         replay_protection_cf_opts,
     ));
 
-    rocksdb::DB::open_cf_descriptors(&db_opts, path, cfs)
-        .map(RocksDB)
-        .map_err(|e| Error::DBError(e.into_string()))
+    (db_opts, cfs)
 }
 
 impl Drop for RocksDB {
     fn drop(&mut self) {
-        self.flush(true).expect("flush failed");
+        // A read-only DB cannot be flushed
+        if !self.read_only {
+            self.flush(true).expect("flush failed");
+        }
+        if let Some(view_dir) = self.view_dir.take() {
+            if let Err(err) = std::fs::remove_dir_all(&view_dir) {
+                tracing::error!(
+                    "Failed to remove the checkpoint of a DB view at {}: {err}",
+                    view_dir.to_string_lossy()
+                );
+            }
+        }
     }
 }
 
 impl RocksDB {
+    /// The directory with the checkpoints of the DB, which are kept inside of
+    /// the DB's directory
+    fn checkpoints_dir(&self) -> PathBuf {
+        self.inner.path().join("checkpoints")
+    }
+
+    /// The directory of the checkpoint of the given epoch
+    fn checkpoint_dir(&self, epoch: Epoch) -> PathBuf {
+        self.checkpoints_dir().join(epoch.0.to_string())
+    }
+
+    /// The directory of the checkpoint that a read-only view is opened from
+    fn view_dir(&self) -> PathBuf {
+        self.checkpoints_dir().join("view")
+    }
+
+    /// The file with the epoch of the checkpoint being restored, which only
+    /// exists while a restore is in progress
+    fn restore_marker_path(&self) -> PathBuf {
+        self.checkpoints_dir().join("restoring")
+    }
+
+    /// The epoch of the checkpoint whose restore has been interrupted, if any
+    fn interrupted_restore(&self) -> Result<Option<Epoch>> {
+        let marker_path = self.restore_marker_path();
+        if !marker_path.is_file() {
+            return Ok(None);
+        }
+        let marker = std::fs::read_to_string(marker_path)
+            .map_err(|e| Error::DBError(e.to_string()))?;
+        let epoch = marker.trim().parse().map_err(|e| {
+            Error::DBError(format!("Invalid checkpoint restore marker: {e}"))
+        })?;
+        Ok(Some(Epoch(epoch)))
+    }
+
+    /// Delete all the keys of the given column family
+    fn clear_column_family(&self, cf: &ColumnFamily) -> Result<()> {
+        let mut first = self.inner.iterator_cf(cf, IteratorMode::Start);
+        let mut last = self.inner.iterator_cf(cf, IteratorMode::End);
+        if let (Some(first), Some(last)) = (first.next(), last.next()) {
+            let (first, _val) =
+                first.map_err(|e| Error::DBError(e.into_string()))?;
+            let (last, _val) =
+                last.map_err(|e| Error::DBError(e.into_string()))?;
+            // The end of the range is exclusive
+            let mut end = last.into_vec();
+            end.push(0);
+            self.inner
+                .delete_range_cf(cf, first, end)
+                .map_err(|e| Error::DBError(e.into_string()))?;
+        }
+        Ok(())
+    }
+
     fn get_column_family(&self, cf_name: &str) -> Result<&ColumnFamily> {
-        self.0
+        self.inner
             .cf_handle(cf_name)
             .ok_or(Error::DBError("No {cf_name} column family".to_string()))
     }
 
+    /// Read the value of the given key from its column family
+    fn read_value(&self, key: DbKey) -> Result<Option<Vec<u8>>> {
+        let cf = self.get_column_family(key.namespace().name())?;
+        self.inner
+            .get_cf(cf, key.key())
+            .map_err(|e| Error::DBError(e.into_string()))
+    }
+
+    /// Read and decode the value of the given key
+    fn read_decoded<T: BorshDeserialize>(
+        &self,
+        key: DbKey,
+    ) -> Result<Option<T>> {
+        self.read_value(key)?
+            .map(|bytes| {
+                types::decode(bytes).map_err(|error| Error::DecodeFailed {
+                    key: format!("{}/{}", key.namespace(), key.key()),
+                    error,
+                })
+            })
+            .transpose()
+    }
+
+    /// Read and decode a value of the latest ledger state. A missing value
+    /// is logged as an error.
+    fn read_state_value<T: BorshDeserialize>(
+        &self,
+        key: StateKey,
+    ) -> Result<Option<T>> {
+        let value = self.read_decoded(DbKey::State(key))?;
+        if value.is_none() {
+            tracing::error!("Couldn't load {} from the DB", key.name());
+        }
+        Ok(value)
+    }
+
+    /// Add a write of the given key to the batch
+    fn batch_put(
+        &self,
+        batch: &mut WriteBatch,
+        key: DbKey,
+        value: impl AsRef<[u8]>,
+    ) -> Result<()> {
+        let cf = self.get_column_family(key.namespace().name())?;
+        batch.put_cf(cf, key.key(), value);
+        Ok(())
+    }
+
+    /// Add a write of a value of the latest ledger state to the batch. The
+    /// current value is kept as its predecessor for rollback.
+    fn batch_put_state_with_pred(
+        &self,
+        batch: &mut WriteBatch,
+        key: StateKey,
+        value: impl AsRef<[u8]>,
+    ) -> Result<()> {
+        if let Some(current_value) = self.read_value(DbKey::State(key))? {
+            self.batch_put(batch, DbKey::PredState(key), current_value)?;
+        }
+        self.batch_put(batch, DbKey::State(key), value)
+    }
+
     /// Persist the diff of an account subspace key-val under the height where
     /// it was changed.
     fn write_subspace_diff(
@@ -222,17 +445,23 @@ impl RocksDB {
         let (old_val_key, new_val_key) = old_and_new_diff_key(key, height)?;
 
         if let Some(old_value) = old_value {
-            self.0
+            self.inner
                 .put_cf(cf, old_val_key, old_value)
                 .map_err(|e| Error::DBError(e.into_string()))?;
         }
 
         if let Some(new_value) = new_value {
-            self.0
+            self.inner
                 .put_cf(cf, new_val_key, new_value)
                 .map_err(|e| Error::DBError(e.into_string()))?;
         }
 
+        if persist_diffs && self.archive {
+            self.inner
+                .put_cf(cf, DbKey::History { key, height }.key(), vec![])
+                .map_err(|e| Error::DBError(e.into_string()))?;
+        }
+
         // If not persisting the diffs, remove the last diffs.
         if !persist_diffs && height > BlockHeight::first() {
             let mut height = height.prev_height();
@@ -240,22 +469,22 @@ impl RocksDB {
                 let (old_diff_key, new_diff_key) =
                     old_and_new_diff_key(key, height)?;
                 let has_old_diff = self
-                    .0
+                    .inner
                     .get_cf(cf, &old_diff_key)
                     .map_err(|e| Error::DBError(e.into_string()))?
                     .is_some();
                 let has_new_diff = self
-                    .0
+                    .inner
                     .get_cf(cf, &new_diff_key)
                     .map_err(|e| Error::DBError(e.into_string()))?
                     .is_some();
                 if has_old_diff {
-                    self.0
+                    self.inner
                         .delete_cf(cf, old_diff_key)
                         .map_err(|e| Error::DBError(e.into_string()))?;
                 }
                 if has_new_diff {
-                    self.0
+                    self.inner
                         .delete_cf(cf, new_diff_key)
                         .map_err(|e| Error::DBError(e.into_string()))?;
                 }
@@ -290,6 +519,12 @@ impl RocksDB {
             batch.0.put_cf(cf, new_val_key, new_value);
         }
 
+        if persist_diffs && self.archive {
+            batch
+                .0
+                .put_cf(cf, DbKey::History { key, height }.key(), vec![]);
+        }
+
         // If not persisting the diffs, remove the last diffs.
         if !persist_diffs && height > BlockHeight::first() {
             let mut height = height.prev_height();
@@ -297,12 +532,12 @@ impl RocksDB {
                 let (old_diff_key, new_diff_key) =
                     old_and_new_diff_key(key, height)?;
                 let has_old_diff = self
-                    .0
+                    .inner
                     .get_cf(cf, &old_diff_key)
                     .map_err(|e| Error::DBError(e.into_string()))?
                     .is_some();
                 let has_new_diff = self
-                    .0
+                    .inner
                     .get_cf(cf, &new_diff_key)
                     .map_err(|e| Error::DBError(e.into_string()))?
                     .is_some();
@@ -322,11 +557,25 @@ impl RocksDB {
     }
 
     fn exec_batch(&mut self, batch: WriteBatch) -> Result<()> {
-        self.0
+        self.inner
             .write(batch)
             .map_err(|e| Error::DBError(e.into_string()))
     }
 
+    /// The total size of the table files of all the column families in bytes
+    pub fn size_bytes(&self) -> u64 {
+        COLUMN_FAMILIES
+            .iter()
+            .filter_map(|cf_name| {
+                let cf = self.get_column_family(cf_name).ok()?;
+                self.inner
+                    .property_int_value_cf(cf, "rocksdb.total-sst-files-size")
+                    .ok()
+                    .flatten()
+            })
+            .sum()
+    }
+
     /// Dump last known block
     pub fn dump_block(
         &self,
@@ -335,17 +584,10 @@ impl RocksDB {
         height: Option<BlockHeight>,
     ) {
         // Find the last block height
-        let state_cf = self
-            .get_column_family(STATE_CF)
-            .expect("State column family should exist");
-
-        let last_height: BlockHeight = types::decode(
-            self.0
-                .get_cf(state_cf, "height")
-                .expect("Unable to read DB")
-                .expect("No block height found"),
-        )
-        .expect("Unable to decode block height");
+        let last_height: BlockHeight = self
+            .read_decoded(DbKey::State(StateKey::Height))
+            .expect("Unable to read DB")
+            .expect("No block height found");
 
         let height = height.unwrap_or(last_height);
 
@@ -454,13 +696,14 @@ impl RocksDB {
     ) {
         let read_opts = make_iter_read_opts(prefix.clone());
         let iter = if let Some(prefix) = prefix {
-            self.0.iterator_cf_opt(
+            self.inner.iterator_cf_opt(
                 cf,
                 read_opts,
                 IteratorMode::From(prefix.as_bytes(), Direction::Forward),
             )
         } else {
-            self.0.iterator_cf_opt(cf, read_opts, IteratorMode::Start)
+            self.inner
+                .iterator_cf_opt(cf, read_opts, IteratorMode::Start)
         };
 
         let mut buf = BufWriter::new(file);
@@ -507,26 +750,34 @@ impl RocksDB {
         let previous_height =
             BlockHeight::from(u64::from(last_block.height) - 1);
 
-        let state_cf = self.get_column_family(STATE_CF)?;
         // Revert the non-height-prepended metadata storage keys which get
         // updated with every block. Because of the way we save these
         // three keys in storage we can only perform one rollback before
         // restarting the chain
         tracing::info!("Reverting non-height-prepended metadata keys");
-        batch.put_cf(state_cf, "height", types::encode(&previous_height));
+        self.batch_put(
+            &mut batch,
+            DbKey::State(StateKey::Height),
+            types::encode(&previous_height),
+        )?;
         for metadata_key in [
-            "next_epoch_min_start_height",
-            "next_epoch_min_start_time",
-            "tx_queue",
+            StateKey::NextEpochMinStartHeight,
+            StateKey::NextEpochMinStartTime,
+            StateKey::TxQueue,
         ] {
-            let previous_key = format!("pred/{}", metadata_key);
-            let previous_value = self
-                .0
-                .get_cf(state_cf, previous_key.as_bytes())
-                .map_err(|e| Error::DBError(e.to_string()))?
-                .ok_or(Error::UnknownKey { key: previous_key })?;
+            let previous_key = DbKey::PredState(metadata_key);
+            let previous_value =
+                self.read_value(previous_key)?.ok_or_else(|| {
+                    Error::UnknownKey {
+                        key: previous_key.key(),
+                    }
+                })?;
 
-            batch.put_cf(state_cf, metadata_key, previous_value);
+            self.batch_put(
+                &mut batch,
+                DbKey::State(metadata_key),
+                previous_value,
+            )?;
             // NOTE: we cannot restore the "pred/" keys themselves since we
             // don't have their predecessors in storage, but there's no need to
             // since we cannot do more than one rollback anyway because of
@@ -537,19 +788,45 @@ impl RocksDB {
         if last_block.pred_epochs.get_epoch(previous_height)
             != Some(last_block.epoch)
         {
-            let previous_key = "pred/conversion_state".to_string();
-            let previous_value = self
-                .0
-                .get_cf(state_cf, previous_key.as_bytes())
-                .map_err(|e| Error::DBError(e.to_string()))?
-                .ok_or(Error::UnknownKey { key: previous_key })?;
-            batch.put_cf(state_cf, "conversion_state", previous_value);
+            let previous_key = DbKey::PredState(StateKey::ConversionState);
+            let previous_value =
+                self.read_value(previous_key)?.ok_or_else(|| {
+                    Error::UnknownKey {
+                        key: previous_key.key(),
+                    }
+                })?;
+            self.batch_put(
+                &mut batch,
+                DbKey::State(StateKey::ConversionState),
+                previous_value,
+            )?;
         }
 
         // Delete block results for the last block
         let block_cf = self.get_column_family(BLOCK_CF)?;
         tracing::info!("Removing last block results");
-        batch.delete_cf(block_cf, format!("results/{}", last_block.height));
+        batch.delete_cf(block_cf, DbKey::Results(last_block.height).key());
+
+        // Delete the receipts of the txs applied in the last block
+        tracing::info!("Removing last block tx receipts");
+        let prefix = receipts_prefix(last_block.height);
+        let iter = self.inner.iterator_cf_opt(
+            block_cf,
+            make_iter_read_opts(Some(prefix.clone())),
+            IteratorMode::From(prefix.as_bytes(), Direction::Forward),
+        );
+        for (hash, _value, _gas) in
+            PersistentPrefixIterator(PrefixIterator::new(iter, prefix))
+        {
+            let hash = Hash::try_from(hash.as_str())
+                .map_err(|e| Error::DBError(e.to_string()))?;
+            let receipt_key = DbKey::TxReceipt {
+                height: last_block.height,
+                hash: &hash,
+            };
+            batch.delete_cf(block_cf, receipt_key.key());
+            batch.delete_cf(block_cf, DbKey::TxReceiptHeight(&hash).key());
+        }
 
         // Delete the tx hashes included in the last block
         let reprot_cf = self.get_column_family(REPLAY_PROTECTION_CF)?;
@@ -584,21 +861,43 @@ impl RocksDB {
         )?;
 
         // Look for diffs in this block to find what has been deleted
-        let diff_new_key_prefix = Key {
-            segments: vec![
-                last_block.height.to_db_key(),
-                NEW_DIFF_PREFIX.to_string().to_db_key(),
-            ],
-        };
         {
             let mut batch_guard = batch.lock().unwrap();
             let subspace_cf = self.get_column_family(SUBSPACE_CF)?;
+            let diffs_cf = self.get_column_family(DIFFS_CF)?;
+            // Remove the last height from the history index of the changed
+            // keys
+            for (key, _val, _) in
+                iter_diffs_prefix(self, last_block.height, None, false)
+            {
+                let key = Key::parse(key).unwrap();
+                batch_guard.delete_cf(
+                    diffs_cf,
+                    DbKey::History {
+                        key: &key,
+                        height: last_block.height,
+                    }
+                    .key(),
+                );
+            }
             for (key, val, _) in
                 iter_diffs_prefix(self, last_block.height, None, true)
             {
                 let key = Key::parse(key).unwrap();
-                let diff_new_key = diff_new_key_prefix.join(&key);
-                if self.read_subspace_val(&diff_new_key)?.is_none() {
+                batch_guard.delete_cf(
+                    diffs_cf,
+                    DbKey::History {
+                        key: &key,
+                        height: last_block.height,
+                    }
+                    .key(),
+                );
+                let diff_new_key = DbKey::Diff {
+                    height: last_block.height,
+                    is_old: false,
+                    key: &key,
+                };
+                if self.read_value(diff_new_key)?.is_none() {
                     // If there is no new value, it has been deleted in this
                     // block and we have to restore it
                     batch_guard.put_cf(subspace_cf, key.to_string(), val)
@@ -611,7 +910,7 @@ impl RocksDB {
         let prefix = last_block.height.to_string();
         let mut delete_keys = |cf: &ColumnFamily| {
             let read_opts = make_iter_read_opts(Some(prefix.clone()));
-            let iter = self.0.iterator_cf_opt(
+            let iter = self.inner.iterator_cf_opt(
                 cf,
                 read_opts,
                 IteratorMode::From(prefix.as_bytes(), Direction::Forward),
@@ -636,7 +935,7 @@ impl RocksDB {
 }
 
 impl DB for RocksDB {
-    type Cache = rocksdb::Cache;
+    type Cache = RocksDBCache;
     type WriteBatch = RocksDBWriteBatch;
 
     fn open(
@@ -649,266 +948,115 @@ impl DB for RocksDB {
     fn flush(&self, wait: bool) -> Result<()> {
         let mut flush_opts = FlushOptions::default();
         flush_opts.set_wait(wait);
-        self.0
+        self.inner
             .flush_opt(&flush_opts)
             .map_err(|e| Error::DBError(e.into_string()))
     }
 
     fn read_last_block(&self) -> Result<Option<BlockStateRead>> {
         // Block height
-        let state_cf = self.get_column_family(STATE_CF)?;
-        let height: BlockHeight = match self
-            .0
-            .get_cf(state_cf, "height")
-            .map_err(|e| Error::DBError(e.into_string()))?
-        {
-            Some(bytes) => {
-                // TODO if there's an issue decoding this height, should we try
-                // load its predecessor instead?
-                types::decode(bytes).map_err(Error::CodingError)?
-            }
-            None => return Ok(None),
-        };
+        // TODO if there's an issue decoding this height, should we try load
+        // its predecessor instead?
+        let height: BlockHeight =
+            match self.read_decoded(DbKey::State(StateKey::Height))? {
+                Some(height) => height,
+                None => return Ok(None),
+            };
 
         // Block results
-        let block_cf = self.get_column_family(BLOCK_CF)?;
-        let results_path = format!("results/{}", height.raw());
-        let results: BlockResults = match self
-            .0
-            .get_cf(block_cf, results_path)
-            .map_err(|e| Error::DBError(e.into_string()))?
-        {
-            Some(bytes) => types::decode(bytes).map_err(Error::CodingError)?,
-            None => return Ok(None),
-        };
-
-        // Epoch start height and time
-        let next_epoch_min_start_height: BlockHeight = match self
-            .0
-            .get_cf(state_cf, "next_epoch_min_start_height")
-            .map_err(|e| Error::DBError(e.into_string()))?
-        {
-            Some(bytes) => types::decode(bytes).map_err(Error::CodingError)?,
-            None => {
-                tracing::error!(
-                    "Couldn't load next epoch start height from the DB"
-                );
-                return Ok(None);
-            }
-        };
-        let next_epoch_min_start_time: DateTimeUtc = match self
-            .0
-            .get_cf(state_cf, "next_epoch_min_start_time")
-            .map_err(|e| Error::DBError(e.into_string()))?
-        {
-            Some(bytes) => types::decode(bytes).map_err(Error::CodingError)?,
-            None => {
-                tracing::error!(
-                    "Couldn't load next epoch start time from the DB"
-                );
-                return Ok(None);
-            }
-        };
-        let update_epoch_blocks_delay: Option<u32> = match self
-            .0
-            .get_cf(state_cf, "update_epoch_blocks_delay")
-            .map_err(|e| Error::DBError(e.into_string()))?
-        {
-            Some(bytes) => types::decode(bytes).map_err(Error::CodingError)?,
-            None => {
-                tracing::error!(
-                    "Couldn't load epoch update block delay from the DB"
-                );
-                return Ok(None);
-            }
-        };
-        let conversion_state: ConversionState = match self
-            .0
-            .get_cf(state_cf, "conversion_state")
-            .map_err(|e| Error::DBError(e.into_string()))?
-        {
-            Some(bytes) => types::decode(bytes).map_err(Error::CodingError)?,
-            None => {
-                tracing::error!("Couldn't load conversion state from the DB");
-                return Ok(None);
-            }
-        };
-        let tx_queue: TxQueue = match self
-            .0
-            .get_cf(state_cf, "tx_queue")
-            .map_err(|e| Error::DBError(e.into_string()))?
-        {
-            Some(bytes) => types::decode(bytes).map_err(Error::CodingError)?,
-            None => {
-                tracing::error!("Couldn't load tx queue from the DB");
-                return Ok(None);
-            }
-        };
-
-        let ethereum_height: Option<ethereum_structs::BlockHeight> = match self
-            .0
-            .get_cf(state_cf, "ethereum_height")
-            .map_err(|e| Error::DBError(e.into_string()))?
-        {
-            Some(bytes) => types::decode(bytes).map_err(Error::CodingError)?,
-            None => {
-                tracing::error!("Couldn't load ethereum height from the DB");
-                return Ok(None);
-            }
-        };
-
-        let eth_events_queue: EthEventsQueue = match self
-            .0
-            .get_cf(state_cf, "eth_events_queue")
-            .map_err(|e| Error::DBError(e.into_string()))?
-        {
-            Some(bytes) => types::decode(bytes).map_err(Error::CodingError)?,
-            None => {
-                tracing::error!(
-                    "Couldn't load the eth events queue from the DB"
-                );
-                return Ok(None);
-            }
-        };
+        let results: BlockResults =
+            match self.read_decoded(DbKey::Results(height))? {
+                Some(results) => results,
+                None => return Ok(None),
+            };
 
-        // Load data at the height
-        let prefix = format!("{}/", height.raw());
-        let mut read_opts = ReadOptions::default();
-        read_opts.set_total_order_seek(false);
-        let next_height_prefix = format!("{}/", height.next_height().raw());
-        read_opts.set_iterate_upper_bound(next_height_prefix);
-        let mut merkle_tree_stores = MerkleTreeStoresRead::default();
-        let mut hash = None;
-        let mut time = None;
-        let mut epoch: Option<Epoch> = None;
-        let mut pred_epochs = None;
-        let mut address_gen = None;
-        for value in self.0.iterator_cf_opt(
-            block_cf,
-            read_opts,
-            IteratorMode::From(prefix.as_bytes(), Direction::Forward),
+        // The latest ledger state
+        let (
+            next_epoch_min_start_height,
+            next_epoch_min_start_time,
+            update_epoch_blocks_delay,
+            conversion_state,
+            tx_queue,
+            ethereum_height,
+            eth_events_queue,
+        ) = match (
+            self.read_state_value(StateKey::NextEpochMinStartHeight)?,
+            self.read_state_value(StateKey::NextEpochMinStartTime)?,
+            self.read_state_value(StateKey::UpdateEpochBlocksDelay)?,
+            self.read_state_value(StateKey::ConversionState)?,
+            self.read_state_value(StateKey::TxQueue)?,
+            self.read_state_value(StateKey::EthereumHeight)?,
+            self.read_state_value(StateKey::EthEventsQueue)?,
         ) {
-            let (key, bytes) = match value {
-                Ok(data) => data,
-                Err(e) => return Err(Error::DBError(e.into_string())),
-            };
-            let path = &String::from_utf8((*key).to_vec()).map_err(|e| {
-                Error::Temporary {
-                    error: format!(
-                        "Cannot convert path from utf8 bytes to string: {}",
-                        e
-                    ),
-                }
-            })?;
-            let segments: Vec<&str> =
-                path.split(KEY_SEGMENT_SEPARATOR).collect();
-            match segments.get(1) {
-                Some(prefix) => match *prefix {
-                    // Restore the base tree of Merkle tree
-                    "tree" => match segments.get(2) {
-                        Some(s) => {
-                            let st = StoreType::from_str(s)?;
-                            match segments.get(3) {
-                                Some(&"root") => merkle_tree_stores.set_root(
-                                    &st,
-                                    types::decode(bytes)
-                                        .map_err(Error::CodingError)?,
-                                ),
-                                Some(&"store") => merkle_tree_stores
-                                    .set_store(st.decode_store(bytes)?),
-                                _ => unknown_key_error(path)?,
-                            }
-                        }
-                        None => unknown_key_error(path)?,
-                    },
-                    "header" => {
-                        // the block header doesn't have to be restored
-                    }
-                    "hash" => {
-                        hash = Some(
-                            types::decode(bytes).map_err(Error::CodingError)?,
-                        )
-                    }
-                    "time" => {
-                        time = Some(
-                            types::decode(bytes).map_err(Error::CodingError)?,
-                        )
-                    }
-                    "epoch" => {
-                        epoch = Some(
-                            types::decode(bytes).map_err(Error::CodingError)?,
-                        )
-                    }
-                    "pred_epochs" => {
-                        pred_epochs = Some(
-                            types::decode(bytes).map_err(Error::CodingError)?,
-                        )
-                    }
-                    "address_gen" => {
-                        address_gen = Some(
-                            types::decode(bytes).map_err(Error::CodingError)?,
-                        );
-                    }
-                    _ => unknown_key_error(path)?,
-                },
-                None => unknown_key_error(path)?,
-            }
-        }
-        // Restore subtrees of Merkle tree
-        if let Some(epoch) = epoch {
-            for st in StoreType::iter_subtrees() {
-                let key_prefix = subtree_key_prefix(st, epoch);
-                let root_key =
-                    key_prefix.clone().with_segment("root".to_owned());
-                if let Some(bytes) = self
-                    .0
-                    .get_cf(block_cf, &root_key.to_string())
-                    .map_err(|e| Error::DBError(e.into_string()))?
-                {
-                    merkle_tree_stores.set_root(
-                        st,
-                        types::decode(bytes).map_err(Error::CodingError)?,
-                    );
-                }
-                let store_key = key_prefix.with_segment("store".to_owned());
-                if let Some(bytes) = self
-                    .0
-                    .get_cf(block_cf, &store_key.to_string())
-                    .map_err(|e| Error::DBError(e.into_string()))?
-                {
-                    merkle_tree_stores.set_store(st.decode_store(bytes)?);
-                }
-            }
-        }
-        match (hash, time, epoch, pred_epochs, address_gen) {
             (
-                Some(hash),
-                Some(time),
-                Some(epoch),
-                Some(pred_epochs),
-                Some(address_gen),
-            ) => Ok(Some(BlockStateRead {
-                merkle_tree_stores,
-                hash,
-                height,
-                time,
-                epoch,
-                pred_epochs,
-                results,
-                conversion_state,
+                Some(next_epoch_min_start_height),
+                Some(next_epoch_min_start_time),
+                Some(update_epoch_blocks_delay),
+                Some(conversion_state),
+                Some(tx_queue),
+                Some(ethereum_height),
+                Some(eth_events_queue),
+            ) => (
                 next_epoch_min_start_height,
                 next_epoch_min_start_time,
                 update_epoch_blocks_delay,
-                address_gen,
+                conversion_state,
                 tx_queue,
                 ethereum_height,
                 eth_events_queue,
-            })),
-            _ => Err(Error::Temporary {
-                error: "Essential data couldn't be read from the DB"
-                    .to_string(),
-            }),
+            ),
+            _ => return Ok(None),
+        };
+
+        // Load data at the height
+        let block_key = |field| DbKey::Block { height, field };
+        let missing =
+            |component| Error::MissingBlockComponent { height, component };
+        let hash = self
+            .read_decoded(block_key(BlockField::Hash))?
+            .ok_or_else(|| missing("hash"))?;
+        let time = self
+            .read_decoded(block_key(BlockField::Time))?
+            .ok_or_else(|| missing("time"))?;
+        let epoch: Epoch = self
+            .read_decoded(block_key(BlockField::Epoch))?
+            .ok_or_else(|| missing("epoch"))?;
+        let pred_epochs = self
+            .read_decoded(block_key(BlockField::PredEpochs))?
+            .ok_or_else(|| missing("predecessor epochs"))?;
+        let address_gen = self
+            .read_decoded(block_key(BlockField::AddressGen))?
+            .ok_or_else(|| missing("address generator"))?;
+
+        // Restore the base tree of the height and the subtrees of the
+        // epoch of Merkle tree
+        let mut merkle_tree_stores = MerkleTreeStoresRead::default();
+        for st in StoreType::iter() {
+            let tree_key = |field| DbKey::merkle_tree(st, epoch, height, field);
+            if let Some(root) = self.read_decoded(tree_key(TreeField::Root))? {
+                merkle_tree_stores.set_root(st, root);
+            }
+            if let Some(bytes) = self.read_value(tree_key(TreeField::Store))? {
+                merkle_tree_stores.set_store(st.decode_store(bytes)?);
+            }
         }
+        Ok(Some(BlockStateRead {
+            merkle_tree_stores,
+            hash,
+            height,
+            time,
+            epoch,
+            pred_epochs,
+            results,
+            conversion_state,
+            next_epoch_min_start_height,
+            next_epoch_min_start_time,
+            update_epoch_blocks_delay,
+            address_gen,
+            tx_queue,
+            ethereum_height,
+            eth_events_queue,
+        }))
     }
 
     fn add_block_to_batch(
@@ -930,6 +1078,7 @@ impl DB for RocksDB {
             update_epoch_blocks_delay,
             address_gen,
             results,
+            receipts,
             conversion_state,
             tx_queue,
             ethereum_height,
@@ -937,215 +1086,139 @@ impl DB for RocksDB {
         }: BlockStateWrite = state;
 
         // Epoch start height and time
-        let state_cf = self.get_column_family(STATE_CF)?;
-        if let Some(current_value) = self
-            .0
-            .get_cf(state_cf, "next_epoch_min_start_height")
-            .map_err(|e| Error::DBError(e.into_string()))?
-        {
-            // Write the predecessor value for rollback
-            batch.0.put_cf(
-                state_cf,
-                "pred/next_epoch_min_start_height",
-                current_value,
-            );
-        }
-        batch.0.put_cf(
-            state_cf,
-            "next_epoch_min_start_height",
+        self.batch_put_state_with_pred(
+            &mut batch.0,
+            StateKey::NextEpochMinStartHeight,
             types::encode(&next_epoch_min_start_height),
-        );
-
-        if let Some(current_value) = self
-            .0
-            .get_cf(state_cf, "next_epoch_min_start_time")
-            .map_err(|e| Error::DBError(e.into_string()))?
-        {
-            // Write the predecessor value for rollback
-            batch.0.put_cf(
-                state_cf,
-                "pred/next_epoch_min_start_time",
-                current_value,
-            );
-        }
-        batch.0.put_cf(
-            state_cf,
-            "next_epoch_min_start_time",
+        )?;
+        self.batch_put_state_with_pred(
+            &mut batch.0,
+            StateKey::NextEpochMinStartTime,
             types::encode(&next_epoch_min_start_time),
-        );
-        if let Some(current_value) = self
-            .0
-            .get_cf(state_cf, "update_epoch_blocks_delay")
-            .map_err(|e| Error::DBError(e.into_string()))?
-        {
-            // Write the predecessor value for rollback
-            batch.0.put_cf(
-                state_cf,
-                "pred/update_epoch_blocks_delay",
-                current_value,
-            );
-        }
-        batch.0.put_cf(
-            state_cf,
-            "update_epoch_blocks_delay",
+        )?;
+        self.batch_put_state_with_pred(
+            &mut batch.0,
+            StateKey::UpdateEpochBlocksDelay,
             types::encode(&update_epoch_blocks_delay),
-        );
+        )?;
 
         // Save the conversion state when the epoch is updated
         if is_full_commit {
-            if let Some(current_value) = self
-                .0
-                .get_cf(state_cf, "conversion_state")
-                .map_err(|e| Error::DBError(e.into_string()))?
-            {
-                // Write the predecessor value for rollback
-                batch.0.put_cf(
-                    state_cf,
-                    "pred/conversion_state",
-                    current_value,
-                );
-            }
-            batch.0.put_cf(
-                state_cf,
-                "conversion_state",
+            self.batch_put_state_with_pred(
+                &mut batch.0,
+                StateKey::ConversionState,
                 types::encode(conversion_state),
-            );
+            )?;
         }
 
         // Tx queue
-        if let Some(pred_tx_queue) = self
-            .0
-            .get_cf(state_cf, "tx_queue")
-            .map_err(|e| Error::DBError(e.into_string()))?
-        {
-            // Write the predecessor value for rollback
-            batch.0.put_cf(state_cf, "pred/tx_queue", pred_tx_queue);
-        }
-        batch
-            .0
-            .put_cf(state_cf, "tx_queue", types::encode(&tx_queue));
-        batch.0.put_cf(
-            state_cf,
-            "ethereum_height",
+        self.batch_put_state_with_pred(
+            &mut batch.0,
+            StateKey::TxQueue,
+            types::encode(&tx_queue),
+        )?;
+        self.batch_put(
+            &mut batch.0,
+            DbKey::State(StateKey::EthereumHeight),
             types::encode(&ethereum_height),
-        );
-        batch.0.put_cf(
-            state_cf,
-            "eth_events_queue",
+        )?;
+        self.batch_put(
+            &mut batch.0,
+            DbKey::State(StateKey::EthEventsQueue),
             types::encode(&eth_events_queue),
-        );
+        )?;
 
-        let block_cf = self.get_column_family(BLOCK_CF)?;
-        let prefix_key = Key::from(height.to_db_key());
         // Merkle tree
-        {
-            for st in StoreType::iter() {
-                if *st == StoreType::Base || is_full_commit {
-                    let key_prefix = if *st == StoreType::Base {
-                        base_tree_key_prefix(height)
-                    } else {
-                        subtree_key_prefix(st, epoch)
-                    };
-                    let root_key =
-                        key_prefix.clone().with_segment("root".to_owned());
-                    batch.0.put_cf(
-                        block_cf,
-                        root_key.to_string(),
-                        types::encode(merkle_tree_stores.root(st)),
-                    );
-                    let store_key = key_prefix.with_segment("store".to_owned());
-                    batch.0.put_cf(
-                        block_cf,
-                        store_key.to_string(),
-                        merkle_tree_stores.store(st).encode(),
-                    );
-                }
+        for st in StoreType::iter() {
+            if *st == StoreType::Base || is_full_commit {
+                let tree_key =
+                    |field| DbKey::merkle_tree(st, epoch, height, field);
+                self.batch_put(
+                    &mut batch.0,
+                    tree_key(TreeField::Root),
+                    types::encode(merkle_tree_stores.root(st)),
+                )?;
+                self.batch_put(
+                    &mut batch.0,
+                    tree_key(TreeField::Store),
+                    merkle_tree_stores.store(st).encode(),
+                )?;
             }
         }
+
+        let block_key = |field| DbKey::Block { height, field };
         // Block header
-        {
-            if let Some(h) = header {
-                let key = prefix_key
-                    .push(&"header".to_owned())
-                    .map_err(Error::KeyError)?;
-                batch
-                    .0
-                    .put_cf(block_cf, key.to_string(), h.serialize_to_vec());
-            }
+        if let Some(h) = header {
+            self.batch_put(
+                &mut batch.0,
+                block_key(BlockField::Header),
+                h.serialize_to_vec(),
+            )?;
         }
         // Block hash
-        {
-            let key = prefix_key
-                .push(&"hash".to_owned())
-                .map_err(Error::KeyError)?;
-            batch
-                .0
-                .put_cf(block_cf, key.to_string(), types::encode(&hash));
-        }
+        self.batch_put(
+            &mut batch.0,
+            block_key(BlockField::Hash),
+            types::encode(&hash),
+        )?;
         // Block time
-        {
-            let key = prefix_key
-                .push(&"time".to_owned())
-                .map_err(Error::KeyError)?;
-            batch
-                .0
-                .put_cf(block_cf, key.to_string(), types::encode(&time));
-        }
+        self.batch_put(
+            &mut batch.0,
+            block_key(BlockField::Time),
+            types::encode(&time),
+        )?;
         // Block epoch
-        {
-            let key = prefix_key
-                .push(&"epoch".to_owned())
-                .map_err(Error::KeyError)?;
-            batch
-                .0
-                .put_cf(block_cf, key.to_string(), types::encode(&epoch));
-        }
+        self.batch_put(
+            &mut batch.0,
+            block_key(BlockField::Epoch),
+            types::encode(&epoch),
+        )?;
         // Block results
-        {
-            let results_path = format!("results/{}", height.raw());
-            batch
-                .0
-                .put_cf(block_cf, results_path, types::encode(&results));
+        self.batch_put(
+            &mut batch.0,
+            DbKey::Results(height),
+            types::encode(&results),
+        )?;
+        // Receipts of the applied txs
+        for receipt in receipts {
+            let hash = &receipt.hash;
+            self.batch_put(
+                &mut batch.0,
+                DbKey::TxReceipt { height, hash },
+                types::encode(receipt),
+            )?;
+            self.batch_put(
+                &mut batch.0,
+                DbKey::TxReceiptHeight(hash),
+                types::encode(&height),
+            )?;
         }
         // Predecessor block epochs
-        {
-            let key = prefix_key
-                .push(&"pred_epochs".to_owned())
-                .map_err(Error::KeyError)?;
-            batch.0.put_cf(
-                block_cf,
-                key.to_string(),
-                types::encode(&pred_epochs),
-            );
-        }
+        self.batch_put(
+            &mut batch.0,
+            block_key(BlockField::PredEpochs),
+            types::encode(&pred_epochs),
+        )?;
         // Address gen
-        {
-            let key = prefix_key
-                .push(&"address_gen".to_owned())
-                .map_err(Error::KeyError)?;
-            batch.0.put_cf(
-                block_cf,
-                key.to_string(),
-                types::encode(&address_gen),
-            );
-        }
+        self.batch_put(
+            &mut batch.0,
+            block_key(BlockField::AddressGen),
+            types::encode(&address_gen),
+        )?;
 
         // Block height
-        batch.0.put_cf(state_cf, "height", types::encode(&height));
-
-        Ok(())
+        self.batch_put(
+            &mut batch.0,
+            DbKey::State(StateKey::Height),
+            types::encode(&height),
+        )
     }
 
     fn read_block_header(&self, height: BlockHeight) -> Result<Option<Header>> {
-        let block_cf = self.get_column_family(BLOCK_CF)?;
-        let prefix_key = Key::from(height.to_db_key());
-        let key = prefix_key
-            .push(&"header".to_owned())
-            .map_err(Error::KeyError)?;
-        let value = self
-            .0
-            .get_cf(block_cf, key.to_string())
-            .map_err(|e| Error::DBError(e.into_string()))?;
+        let value = self.read_value(DbKey::Block {
+            height,
+            field: BlockField::Header,
+        })?;
         match value {
             Some(v) => Ok(Some(
                 Header::try_from_slice(&v[..])
@@ -1162,23 +1235,15 @@ impl DB for RocksDB {
         store_type: Option<StoreType>,
     ) -> Result<Option<MerkleTreeStoresRead>> {
         // Get the latest height at which the tree stores were written
-        let block_cf = self.get_column_family(BLOCK_CF)?;
         let mut merkle_tree_stores = MerkleTreeStoresRead::default();
         let store_types = store_type
             .as_ref()
             .map(|st| Either::Left(std::iter::once(st)))
             .unwrap_or_else(|| Either::Right(StoreType::iter()));
         for st in store_types {
-            let key_prefix = if *st == StoreType::Base {
-                base_tree_key_prefix(base_height)
-            } else {
-                subtree_key_prefix(st, epoch)
-            };
-            let root_key = key_prefix.clone().with_segment("root".to_owned());
-            let bytes = self
-                .0
-                .get_cf(block_cf, root_key.to_string())
-                .map_err(|e| Error::DBError(e.into_string()))?;
+            let tree_key =
+                |field| DbKey::merkle_tree(st, epoch, base_height, field);
+            let bytes = self.read_value(tree_key(TreeField::Root))?;
             match bytes {
                 Some(b) => {
                     let root = types::decode(b).map_err(Error::CodingError)?;
@@ -1187,11 +1252,7 @@ impl DB for RocksDB {
                 None => return Ok(None),
             }
 
-            let store_key = key_prefix.with_segment("store".to_owned());
-            let bytes = self
-                .0
-                .get_cf(block_cf, store_key.to_string())
-                .map_err(|e| Error::DBError(e.into_string()))?;
+            let bytes = self.read_value(tree_key(TreeField::Store))?;
             match bytes {
                 Some(b) => {
                     merkle_tree_stores.set_store(st.decode_store(b)?);
@@ -1214,7 +1275,7 @@ impl DB for RocksDB {
             replay_protection::all_key(hash),
         ] {
             if self
-                .0
+                .inner
                 .get_pinned_cf(replay_protection_cf, key.to_string())
                 .map_err(|e| Error::DBError(e.into_string()))?
                 .is_some()
@@ -1225,6 +1286,15 @@ impl DB for RocksDB {
         Ok(false)
     }
 
+    fn read_tx_receipt(&self, hash: &Hash) -> Result<Option<TxReceipt>> {
+        match self.read_decoded(DbKey::TxReceiptHeight(hash))? {
+            Some(height) => {
+                self.read_decoded(DbKey::TxReceipt { height, hash })
+            }
+            None => Ok(None),
+        }
+    }
+
     fn read_diffs_val(
         &self,
         key: &Key,
@@ -1238,14 +1308,14 @@ impl DB for RocksDB {
             old_and_new_diff_key(key, height)?.1
         };
 
-        self.0
+        self.inner
             .get_cf(diffs_cf, key)
             .map_err(|e| Error::DBError(e.into_string()))
     }
 
     fn read_subspace_val(&self, key: &Key) -> Result<Option<Vec<u8>>> {
         let subspace_cf = self.get_column_family(SUBSPACE_CF)?;
-        self.0
+        self.inner
             .get_cf(subspace_cf, key.to_string())
             .map_err(|e| Error::DBError(e.into_string()))
     }
@@ -1262,7 +1332,7 @@ impl DB for RocksDB {
 
         // If it has a "new" val, it was written at this height
         match self
-            .0
+            .inner
             .get_cf(diffs_cf, new_val_key)
             .map_err(|e| Error::DBError(e.into_string()))?
         {
@@ -1271,10 +1341,10 @@ impl DB for RocksDB {
             }
             None => {
                 // If it has an "old" val, it was deleted at this height
-                if self.0.key_may_exist_cf(diffs_cf, &old_val_key) {
+                if self.inner.key_may_exist_cf(diffs_cf, &old_val_key) {
                     // check if it actually exists
                     if self
-                        .0
+                        .inner
                         .get_cf(diffs_cf, old_val_key)
                         .map_err(|e| Error::DBError(e.into_string()))?
                         .is_some()
@@ -1293,7 +1363,7 @@ impl DB for RocksDB {
             let (old_val_key, new_val_key) =
                 old_and_new_diff_key(key, BlockHeight(raw_height))?;
             let old_val = self
-                .0
+                .inner
                 .get_cf(diffs_cf, &old_val_key)
                 .map_err(|e| Error::DBError(e.into_string()))?;
             // If it has an "old" val, it's the one we're looking for
@@ -1302,10 +1372,10 @@ impl DB for RocksDB {
                 None => {
                     // Check if the value was created at this height instead,
                     // which would mean that it wasn't present before
-                    if self.0.key_may_exist_cf(diffs_cf, &new_val_key) {
+                    if self.inner.key_may_exist_cf(diffs_cf, &new_val_key) {
                         // check if it actually exists
                         if self
-                            .0
+                            .inner
                             .get_cf(diffs_cf, new_val_key)
                             .map_err(|e| Error::DBError(e.into_string()))?
                             .is_some()
@@ -1335,7 +1405,7 @@ impl DB for RocksDB {
         let subspace_cf = self.get_column_family(SUBSPACE_CF)?;
         let value = value.as_ref();
         let size_diff = match self
-            .0
+            .inner
             .get_cf(subspace_cf, key.to_string())
             .map_err(|e| Error::DBError(e.into_string()))?
         {
@@ -1363,7 +1433,7 @@ impl DB for RocksDB {
         };
 
         // Write the new key-val
-        self.0
+        self.inner
             .put_cf(subspace_cf, key.to_string(), value)
             .map_err(|e| Error::DBError(e.into_string()))?;
 
@@ -1380,7 +1450,7 @@ impl DB for RocksDB {
 
         // Check the length of previous value, if any
         let prev_len = match self
-            .0
+            .inner
             .get_cf(subspace_cf, key.to_string())
             .map_err(|e| Error::DBError(e.into_string()))?
         {
@@ -1399,7 +1469,7 @@ impl DB for RocksDB {
         };
 
         // Delete the key-val
-        self.0
+        self.inner
             .delete_cf(subspace_cf, key.to_string())
             .map_err(|e| Error::DBError(e.into_string()))?;
 
@@ -1425,7 +1495,7 @@ impl DB for RocksDB {
         let value = value.as_ref();
         let subspace_cf = self.get_column_family(SUBSPACE_CF)?;
         let size_diff = match self
-            .0
+            .inner
             .get_cf(subspace_cf, key.to_string())
             .map_err(|e| Error::DBError(e.into_string()))?
         {
@@ -1472,7 +1542,7 @@ impl DB for RocksDB {
 
         // Check the length of previous value, if any
         let prev_len = match self
-            .0
+            .inner
             .get_cf(subspace_cf, key.to_string())
             .map_err(|e| Error::DBError(e.into_string()))?
         {
@@ -1505,14 +1575,101 @@ impl DB for RocksDB {
         epoch: Epoch,
     ) -> Result<()> {
         let block_cf = self.get_column_family(BLOCK_CF)?;
-        let key_prefix = subtree_key_prefix(store_type, epoch);
-        let root_key = key_prefix.clone().with_segment("root".to_owned());
-        batch.0.delete_cf(block_cf, root_key.to_string());
-        let store_key = key_prefix.with_segment("store".to_owned());
-        batch.0.delete_cf(block_cf, store_key.to_string());
+        for field in [TreeField::Root, TreeField::Store] {
+            let key = DbKey::Subtree {
+                store_type,
+                epoch,
+                field,
+            };
+            batch.0.delete_cf(block_cf, key.key());
+        }
         Ok(())
     }
 
+    fn prune_below(
+        &mut self,
+        batch: &mut Self::WriteBatch,
+        height: BlockHeight,
+    ) -> Result<()> {
+        let diffs_cf = self.get_column_family(DIFFS_CF)?;
+        // The diffs keys are prefixed with the height of their block, encoded
+        // as a fixed-width key segment that preserves the order of the
+        // heights, so the diffs below the height are a single range of keys.
+        // The entries of the history index are ordered after every height.
+        batch
+            .0
+            .delete_range_cf(diffs_cf, BlockHeight(0).raw(), height.raw());
+        self.batch_put(
+            &mut batch.0,
+            DbKey::State(StateKey::DiffsPrunedHeight),
+            types::encode(&height),
+        )
+    }
+
+    fn read_diffs_pruned_height(&self) -> Result<Option<BlockHeight>> {
+        self.read_decoded(DbKey::State(StateKey::DiffsPrunedHeight))
+    }
+
+    fn read_key_history(
+        &self,
+        key: &Key,
+        from_height: BlockHeight,
+        limit: usize,
+    ) -> Result<Vec<BlockHeight>> {
+        let diffs_cf = self.get_column_family(DIFFS_CF)?;
+        let prefix = format!("{}/", history_prefix(key));
+        let read_opts = make_iter_read_opts(Some(prefix.clone()));
+        let from = DbKey::History {
+            key,
+            height: from_height,
+        }
+        .key();
+        let iter = self.inner.iterator_cf_opt(
+            diffs_cf,
+            read_opts,
+            IteratorMode::From(from.as_bytes(), Direction::Forward),
+        );
+        let mut heights = vec![];
+        for result in iter.take(limit) {
+            let (db_key, _val) =
+                result.map_err(|e| Error::DBError(e.into_string()))?;
+            heights.push(parse_history_height(&db_key, &prefix)?);
+        }
+        Ok(heights)
+    }
+
+    fn read_last_change_height(
+        &self,
+        key: &Key,
+        height: BlockHeight,
+    ) -> Result<Option<BlockHeight>> {
+        let diffs_cf = self.get_column_family(DIFFS_CF)?;
+        let prefix = format!("{}/", history_prefix(key));
+        let read_opts = make_iter_read_opts(Some(prefix.clone()));
+        let mut iter = self.inner.raw_iterator_cf_opt(diffs_cf, read_opts);
+        iter.seek_for_prev(DbKey::History { key, height }.key());
+        iter.status().map_err(|e| Error::DBError(e.into_string()))?;
+        iter.key()
+            .map(|db_key| parse_history_height(db_key, &prefix))
+            .transpose()
+    }
+
+    fn read_db_version(&self) -> Result<Option<u64>> {
+        self.read_decoded(DbKey::State(StateKey::DbVersion))
+    }
+
+    fn write_db_version(
+        &mut self,
+        batch: &mut Self::WriteBatch,
+        version: u64,
+    ) -> Result<()> {
+        self.batch_put(
+            &mut batch.0,
+            DbKey::State(StateKey::DbVersion),
+            types::encode(&version),
+        )
+    }
+
     fn read_bridge_pool_signed_nonce(
         &self,
         height: BlockHeight,
@@ -1549,18 +1706,159 @@ impl DB for RocksDB {
         Ok(())
     }
 
-    fn delete_replay_protection_entry(
+    fn delete_replay_protection_entry(
+        &mut self,
+        batch: &mut Self::WriteBatch,
+        key: &Key,
+    ) -> Result<()> {
+        let replay_protection_cf =
+            self.get_column_family(REPLAY_PROTECTION_CF)?;
+
+        batch.0.delete_cf(replay_protection_cf, key.to_string());
+
+        Ok(())
+    }
+
+    fn write_block_header(
+        &mut self,
+        batch: &mut Self::WriteBatch,
+        height: BlockHeight,
+        header: &Header,
+    ) -> Result<()> {
+        self.batch_put(
+            &mut batch.0,
+            DbKey::Block {
+                height,
+                field: BlockField::Header,
+            },
+            header.serialize_to_vec(),
+        )
+    }
+
+    fn write_block_results(
         &mut self,
         batch: &mut Self::WriteBatch,
-        key: &Key,
+        height: BlockHeight,
+        results: &BlockResults,
     ) -> Result<()> {
-        let replay_protection_cf =
-            self.get_column_family(REPLAY_PROTECTION_CF)?;
+        self.batch_put(
+            &mut batch.0,
+            DbKey::Results(height),
+            types::encode(results),
+        )
+    }
 
-        batch.0.delete_cf(replay_protection_cf, key.to_string());
+    fn write_checkpoint(&self, epoch: Epoch) -> Result<()> {
+        // RocksDB requires that the checkpoint's directory doesn't exist yet
+        self.delete_checkpoint(epoch)?;
+        std::fs::create_dir_all(self.checkpoints_dir())
+            .map_err(|e| Error::DBError(e.to_string()))?;
+        rocksdb::checkpoint::Checkpoint::new(&self.inner)
+            .and_then(|checkpoint| {
+                checkpoint.create_checkpoint(self.checkpoint_dir(epoch))
+            })
+            .map_err(|e| Error::DBError(e.into_string()))
+    }
+
+    fn restore_checkpoint(&mut self, epoch: Epoch) -> Result<()> {
+        let checkpoint_dir = self.checkpoint_dir(epoch);
+        if !checkpoint_dir.is_dir() {
+            return Err(Error::NoCheckpoint { epoch });
+        }
+        let checkpoint = rocksdb::DB::open_cf_for_read_only(
+            &Options::default(),
+            checkpoint_dir,
+            COLUMN_FAMILIES,
+            false,
+        )
+        .map_err(|e| Error::DBError(e.into_string()))?;
+
+        // The content is replaced in bounded batches, so the restore is
+        // recorded to be resumed on the next start if it gets interrupted. It
+        // is idempotent, so it can be restarted from the beginning.
+        let marker_path = self.restore_marker_path();
+        std::fs::write(&marker_path, epoch.0.to_string())
+            .map_err(|e| Error::DBError(e.to_string()))?;
+        for cf_name in COLUMN_FAMILIES {
+            let cf = self.get_column_family(cf_name)?;
+            self.clear_column_family(cf)?;
+            let checkpoint_cf =
+                checkpoint.cf_handle(cf_name).ok_or_else(|| {
+                    Error::DBError(format!("No {cf_name} column family"))
+                })?;
+            let mut batch = WriteBatch::default();
+            for result in
+                checkpoint.iterator_cf(checkpoint_cf, IteratorMode::Start)
+            {
+                let (key, val) =
+                    result.map_err(|e| Error::DBError(e.into_string()))?;
+                batch.put_cf(cf, key, val);
+                if batch.size_in_bytes() >= RESTORE_BATCH_BYTES {
+                    self.inner
+                        .write(std::mem::take(&mut batch))
+                        .map_err(|e| Error::DBError(e.into_string()))?;
+                }
+            }
+            self.inner
+                .write(batch)
+                .map_err(|e| Error::DBError(e.into_string()))?;
+        }
+        self.flush(true)?;
+        std::fs::remove_file(marker_path)
+            .map_err(|e| Error::DBError(e.to_string()))
+    }
 
+    fn delete_checkpoint(&self, epoch: Epoch) -> Result<()> {
+        let checkpoint_dir = self.checkpoint_dir(epoch);
+        if checkpoint_dir.exists() {
+            std::fs::remove_dir_all(checkpoint_dir)
+                .map_err(|e| Error::DBError(e.to_string()))?;
+        }
         Ok(())
     }
+
+    fn checkpoints(&self) -> Result<Vec<Epoch>> {
+        let checkpoints_dir = self.checkpoints_dir();
+        if !checkpoints_dir.is_dir() {
+            return Ok(vec![]);
+        }
+        let mut epochs = vec![];
+        for entry in std::fs::read_dir(checkpoints_dir)
+            .map_err(|e| Error::DBError(e.to_string()))?
+        {
+            let entry = entry.map_err(|e| Error::DBError(e.to_string()))?;
+            if let Some(epoch) = entry
+                .file_name()
+                .to_str()
+                .and_then(|name| name.parse().ok())
+            {
+                epochs.push(Epoch(epoch));
+            }
+        }
+        epochs.sort();
+        Ok(epochs)
+    }
+
+    fn read_only_view(&self) -> Result<Self> {
+        // The view is opened from a hard-linked checkpoint rather than from
+        // this DB's directory, so that the compactions of this DB cannot
+        // remove the files that the view is still reading. Only one view is
+        // used at a time, so a leftover checkpoint is from a view that hasn't
+        // been removed on a crash.
+        let view_dir = self.view_dir();
+        if view_dir.exists() {
+            std::fs::remove_dir_all(&view_dir)
+                .map_err(|e| Error::DBError(e.to_string()))?;
+        }
+        std::fs::create_dir_all(self.checkpoints_dir())
+            .map_err(|e| Error::DBError(e.to_string()))?;
+        rocksdb::checkpoint::Checkpoint::new(&self.inner)
+            .and_then(|checkpoint| checkpoint.create_checkpoint(&view_dir))
+            .map_err(|e| Error::DBError(e.into_string()))?;
+        let mut view = open_read_only(&view_dir, None)?;
+        view.view_dir = Some(view_dir);
+        Ok(view)
+    }
 }
 
 impl<'iter> DBIter<'iter> for RocksDB {
@@ -1570,18 +1868,47 @@ impl<'iter> DBIter<'iter> for RocksDB {
         &'iter self,
         prefix: Option<&Key>,
     ) -> PersistentPrefixIterator<'iter> {
-        iter_subspace_prefix(self, prefix)
+        iter_subspace_prefix(self, prefix, false)
+    }
+
+    fn rev_iter_prefix(
+        &'iter self,
+        prefix: Option<&Key>,
+    ) -> PersistentPrefixIterator<'iter> {
+        iter_subspace_prefix(self, prefix, true)
+    }
+
+    fn iter_range(
+        &'iter self,
+        start: &Key,
+        end: &Key,
+    ) -> PersistentPrefixIterator<'iter> {
+        let subspace_cf = self
+            .get_column_family(SUBSPACE_CF)
+            .expect("{SUBSPACE_CF} column family should exist");
+        let start = start.to_string();
+        let mut read_opts = ReadOptions::default();
+        // don't use the prefix bloom filter
+        read_opts.set_total_order_seek(true);
+        read_opts.set_iterate_lower_bound(start.as_bytes());
+        read_opts.set_iterate_upper_bound(end.to_string().into_bytes());
+        let iter = self.inner.iterator_cf_opt(
+            subspace_cf,
+            read_opts,
+            IteratorMode::From(start.as_bytes(), Direction::Forward),
+        );
+        PersistentPrefixIterator(PrefixIterator::new(iter, "".to_owned()))
     }
 
     fn iter_results(&'iter self) -> PersistentPrefixIterator<'iter> {
-        let db_prefix = "results/".to_owned();
-        let prefix = "results".to_owned();
+        let db_prefix = results_prefix();
+        let prefix = db_prefix.clone();
 
         let block_cf = self
             .get_column_family(BLOCK_CF)
             .expect("{BLOCK_CF} column family should exist");
         let read_opts = make_iter_read_opts(Some(prefix.clone()));
-        let iter = self.0.iterator_cf_opt(
+        let iter = self.inner.iterator_cf_opt(
             block_cf,
             read_opts,
             IteratorMode::From(prefix.as_bytes(), Direction::Forward),
@@ -1611,19 +1938,34 @@ impl<'iter> DBIter<'iter> for RocksDB {
             .expect("{REPLAY_PROTECTION_CF} column family should exist");
 
         let stripped_prefix = Some(replay_protection::last_prefix());
-        iter_prefix(self, replay_protection_cf, stripped_prefix.as_ref(), None)
+        iter_prefix(
+            self,
+            replay_protection_cf,
+            stripped_prefix.as_ref(),
+            None,
+            false,
+        )
+    }
+
+    fn iter_all_replay_protection(&'iter self) -> Self::PrefixIter {
+        let replay_protection_cf = self
+            .get_column_family(REPLAY_PROTECTION_CF)
+            .expect("{REPLAY_PROTECTION_CF} column family should exist");
+
+        iter_prefix(self, replay_protection_cf, None, None, false)
     }
 }
 
 fn iter_subspace_prefix<'iter>(
     db: &'iter RocksDB,
     prefix: Option<&Key>,
+    reverse: bool,
 ) -> PersistentPrefixIterator<'iter> {
     let subspace_cf = db
         .get_column_family(SUBSPACE_CF)
         .expect("{SUBSPACE_CF} column family should exist");
     let stripped_prefix = None;
-    iter_prefix(db, subspace_cf, stripped_prefix, prefix)
+    iter_prefix(db, subspace_cf, stripped_prefix, prefix, reverse)
 }
 
 fn iter_diffs_prefix<'a>(
@@ -1635,29 +1977,22 @@ fn iter_diffs_prefix<'a>(
     let diffs_cf = db
         .get_column_family(DIFFS_CF)
         .expect("{DIFFS_CF} column family should exist");
-    let kind = if is_old {
-        OLD_DIFF_PREFIX
-    } else {
-        NEW_DIFF_PREFIX
-    };
-    let stripped_prefix = Some(
-        Key::from(height.to_db_key())
-            .push(&kind.to_string())
-            .unwrap(),
-    );
+    let stripped_prefix = Some(diffs_prefix(height, is_old));
     // get keys without the `stripped_prefix`
-    iter_prefix(db, diffs_cf, stripped_prefix.as_ref(), prefix)
+    iter_prefix(db, diffs_cf, stripped_prefix.as_ref(), prefix, false)
 }
 
 /// Create an iterator over key-vals in the given CF matching the given
 /// prefix(es). If any, the `stripped_prefix` is matched first and will be
 /// removed from the matched keys. If any, the second `prefix` is matched
-/// against the stripped keys and remains in the matched keys.
+/// against the stripped keys and remains in the matched keys. When `reverse`
+/// is set, the matched keys are iterated in descending order.
 fn iter_prefix<'a>(
     db: &'a RocksDB,
     cf: &'a ColumnFamily,
     stripped_prefix: Option<&Key>,
     prefix: Option<&Key>,
+    reverse: bool,
 ) -> PersistentPrefixIterator<'a> {
     let stripped_prefix = match stripped_prefix {
         Some(p) if !p.is_empty() => format!("{p}/"),
@@ -1670,11 +2005,14 @@ fn iter_prefix<'a>(
         _ => stripped_prefix.clone(),
     };
     let read_opts = make_iter_read_opts(Some(prefix.clone()));
-    let iter = db.0.iterator_cf_opt(
-        cf,
-        read_opts,
-        IteratorMode::From(prefix.as_bytes(), Direction::Forward),
-    );
+    let mode = if reverse {
+        // The iterator is bounded by the prefix, so the last key is the
+        // last matching key
+        IteratorMode::End
+    } else {
+        IteratorMode::From(prefix.as_bytes(), Direction::Forward)
+    };
+    let iter = db.inner.iterator_cf_opt(cf, read_opts, mode);
     PersistentPrefixIterator(PrefixIterator::new(iter, stripped_prefix))
 }
 
@@ -1712,6 +2050,20 @@ impl<'a> Iterator for PersistentPrefixIterator<'a> {
     }
 }
 
+/// Parse the height of an entry of the history index with the given prefix
+fn parse_history_height(db_key: &[u8], prefix: &str) -> Result<BlockHeight> {
+    let height = std::str::from_utf8(db_key)
+        .ok()
+        .and_then(|db_key| db_key.strip_prefix(prefix))
+        .ok_or_else(|| {
+            Error::DBError(format!(
+                "Invalid entry of the history index {}",
+                String::from_utf8_lossy(db_key)
+            ))
+        })?;
+    BlockHeight::parse(height.to_owned()).map_err(Error::KeyError)
+}
+
 /// Make read options for RocksDB iterator with the given prefix
 fn make_iter_read_opts(prefix: Option<String>) -> ReadOptions {
     let mut read_opts = ReadOptions::default();
@@ -1719,6 +2071,9 @@ fn make_iter_read_opts(prefix: Option<String>) -> ReadOptions {
     read_opts.set_total_order_seek(true);
 
     if let Some(prefix) = prefix {
+        if !prefix.is_empty() {
+            read_opts.set_iterate_lower_bound(prefix.as_bytes());
+        }
         let mut upper_prefix = prefix.into_bytes();
         if let Some(last) = upper_prefix.last_mut() {
             *last += 1;
@@ -1735,22 +2090,12 @@ fn old_and_new_diff_key(
     key: &Key,
     height: BlockHeight,
 ) -> Result<(String, String)> {
-    let key_prefix = Key::from(height.to_db_key());
-    let old = key_prefix
-        .push(&OLD_DIFF_PREFIX.to_owned())
-        .map_err(Error::KeyError)?
-        .join(key);
-    let new = key_prefix
-        .push(&NEW_DIFF_PREFIX.to_owned())
-        .map_err(Error::KeyError)?
-        .join(key);
-    Ok((old.to_string(), new.to_string()))
-}
-
-fn unknown_key_error(key: &str) -> Result<()> {
-    Err(Error::UnknownKey {
-        key: key.to_owned(),
-    })
+    let diff_key = |is_old| DbKey::Diff {
+        height,
+        is_old,
+        key,
+    };
+    Ok((diff_key(true).key(), diff_key(false).key()))
 }
 
 /// Try to increase NOFILE limit and set the `max_open_files` limit to it in
@@ -1814,11 +2159,15 @@ mod imp {
 
 #[cfg(test)]
 mod test {
+    use namada::ledger::storage::tx_queue::TxQueue;
     use namada::state::{MerkleTree, Sha256Hasher};
     use namada::types::address::{
         gen_established_address, EstablishedAddressGen,
     };
-    use namada::types::storage::{BlockHash, Epoch, Epochs};
+    use namada::types::ethereum_structs;
+    use namada::types::storage::{BlockHash, Epoch, Epochs, EthEventsQueue};
+    use namada::types::time::DateTimeUtc;
+    use namada::types::token::ConversionState;
     use tempfile::tempdir;
     use test_log::test;
 
@@ -1858,6 +2207,178 @@ mod test {
             .expect("Block should have been written");
     }
 
+    /// Test that a missing or corrupted block field is reported with the
+    /// block height and the DB key.
+    #[test]
+    fn test_load_state_errors() {
+        let dir = tempdir().unwrap();
+        let db = open(dir.path(), None).unwrap();
+
+        let height = BlockHeight::default();
+        let mut batch = RocksDB::batch();
+        add_block_to_batch(
+            &db,
+            &mut batch,
+            height,
+            Epoch::default(),
+            Epochs::default(),
+            &ConversionState::default(),
+        )
+        .unwrap();
+        db.exec_batch(batch.0).unwrap();
+
+        // Corrupt the block time
+        let time_key = DbKey::Block {
+            height,
+            field: BlockField::Time,
+        };
+        let mut batch = RocksDB::batch();
+        db.batch_put(&mut batch.0, time_key, [1_u8]).unwrap();
+        db.exec_batch(batch.0).unwrap();
+        let err = db.read_last_block().unwrap_err();
+        assert!(
+            matches!(
+                &err,
+                Error::DecodeFailed { key, .. } if key.ends_with("/time")
+            ),
+            "Unexpected error {err}"
+        );
+
+        // Remove the block time
+        let mut batch = RocksDB::batch();
+        let cf = db.get_column_family(time_key.namespace().name()).unwrap();
+        batch.0.delete_cf(cf, time_key.key());
+        db.exec_batch(batch.0).unwrap();
+        let err = db.read_last_block().unwrap_err();
+        assert!(
+            matches!(
+                err,
+                Error::MissingBlockComponent {
+                    height: err_height,
+                    component: "time",
+                } if err_height == height
+            ),
+            "Unexpected error {err}"
+        );
+    }
+
+    /// Test that nothing written to a batch is visible until the batch is
+    /// executed.
+    #[test]
+    fn test_batch_is_atomic() {
+        let dir = tempdir().unwrap();
+        let mut db = open(dir.path(), None).unwrap();
+
+        let key = Key::parse("test").unwrap();
+        let mut batch = RocksDB::batch();
+        db.batch_write_subspace_val(
+            &mut batch,
+            BlockHeight::default(),
+            &key,
+            vec![1_u8, 1, 1, 1],
+            true,
+        )
+        .unwrap();
+        add_block_to_batch(
+            &db,
+            &mut batch,
+            BlockHeight::default(),
+            Epoch::default(),
+            Epochs::default(),
+            &ConversionState::default(),
+        )
+        .unwrap();
+
+        // Nothing is written before the batch is executed
+        assert!(db.read_subspace_val(&key).unwrap().is_none());
+        assert!(db.read_last_block().unwrap().is_none());
+
+        db.exec_batch(batch.0).unwrap();
+
+        assert_eq!(
+            db.read_subspace_val(&key).unwrap(),
+            Some(vec![1_u8, 1, 1, 1])
+        );
+        assert!(db.read_last_block().unwrap().is_some());
+    }
+
+    /// Test that the DB can be opened and used with the tuning options of
+    /// each profile
+    #[test]
+    fn test_open_with_profiles() {
+        for profile in [DbProfile::Validator, DbProfile::Archive] {
+            let dir = tempdir().unwrap();
+            let cache = RocksDBCache {
+                block_cache: rocksdb::Cache::new_lru_cache(1024 * 1024),
+                config: RocksDBConfig {
+                    profile,
+                    max_total_wal_size_bytes: Some(64 * 1024 * 1024),
+                    wal_bytes_per_sync: Some(1024 * 1024),
+                    ..Default::default()
+                },
+            };
+            let mut db = open(dir.path(), Some(&cache)).unwrap();
+
+            let key = Key::parse("test").unwrap();
+            db.write_subspace_val(BlockHeight(1), &key, vec![1_u8], true)
+                .unwrap();
+            assert_eq!(db.read_subspace_val(&key).unwrap(), Some(vec![1_u8]));
+        }
+    }
+
+    /// Test that a DB can be read in read-only mode while it's open for
+    /// writing
+    #[test]
+    fn test_open_read_only() {
+        let dir = tempdir().unwrap();
+        let mut db = open(dir.path(), None).unwrap();
+
+        let key = Key::parse("test").unwrap();
+        db.write_subspace_val(BlockHeight(1), &key, vec![1_u8], true)
+            .unwrap();
+        db.flush(true).unwrap();
+
+        let mut read_only_db = open_read_only(dir.path(), None).unwrap();
+        assert_eq!(
+            read_only_db.read_subspace_val(&key).unwrap(),
+            Some(vec![1_u8])
+        );
+        assert!(
+            read_only_db
+                .write_subspace_val(BlockHeight(2), &key, vec![2_u8], true)
+                .is_err()
+        );
+        // Dropping the read-only DB must not try to flush it
+        drop(read_only_db);
+    }
+
+    /// Test that a read-only view doesn't see the later writes and that its
+    /// checkpoint is removed when it's dropped
+    #[test]
+    fn test_read_only_view() {
+        let dir = tempdir().unwrap();
+        let mut db = open(dir.path(), None).unwrap();
+
+        let key = Key::parse("test").unwrap();
+        db.write_subspace_val(BlockHeight(1), &key, vec![1_u8], true)
+            .unwrap();
+
+        let view = db.read_only_view().unwrap();
+        let view_dir = db.view_dir();
+        assert!(view_dir.is_dir());
+
+        db.write_subspace_val(BlockHeight(2), &key, vec![2_u8], true)
+            .unwrap();
+        db.flush(true).unwrap();
+        assert_eq!(view.read_subspace_val(&key).unwrap(), Some(vec![1_u8]));
+        assert_eq!(db.read_subspace_val(&key).unwrap(), Some(vec![2_u8]));
+
+        drop(view);
+        assert!(!view_dir.exists());
+        // The view's checkpoint isn't listed as an epoch's checkpoint
+        assert!(db.checkpoints().unwrap().is_empty());
+    }
+
     #[test]
     fn test_read() {
         let dir = tempdir().unwrap();
@@ -2010,6 +2531,81 @@ mod test {
         itertools::assert_equal(all_keys, itered_keys);
     }
 
+    #[test]
+    fn test_rev_prefix_iter() {
+        let dir = tempdir().unwrap();
+        let mut db = open(dir.path(), None).unwrap();
+
+        let prefix_0 = Key::parse("0").unwrap();
+        let key_0_a = prefix_0.push(&"a".to_string()).unwrap();
+        let key_0_b = prefix_0.push(&"b".to_string()).unwrap();
+        let key_0_c = prefix_0.push(&"c".to_string()).unwrap();
+        let prefix_01 = Key::parse("01").unwrap();
+        let key_01_a = prefix_01.push(&"a".to_string()).unwrap();
+        let key_1_a = Key::parse("1").unwrap().push(&"a".to_string()).unwrap();
+
+        let keys_0 = vec![key_0_a, key_0_b, key_0_c];
+        let all_keys =
+            vec![keys_0.clone(), vec![key_01_a], vec![key_1_a]].concat();
+
+        // Write the keys
+        let mut batch = RocksDB::batch();
+        let height = BlockHeight(1);
+        for key in &all_keys {
+            db.batch_write_subspace_val(&mut batch, height, key, [0_u8], true)
+                .unwrap();
+        }
+        db.exec_batch(batch.0).unwrap();
+
+        // Prefix "0" shouldn't match prefix "01" or the keys before and
+        // after it
+        let itered_keys: Vec<Key> = db
+            .rev_iter_prefix(Some(&prefix_0))
+            .map(|(key, _val, _)| Key::parse(key).unwrap())
+            .collect();
+        itertools::assert_equal(keys_0.into_iter().rev(), itered_keys);
+
+        let itered_keys: Vec<Key> = db
+            .rev_iter_prefix(None)
+            .map(|(key, _val, _)| Key::parse(key).unwrap())
+            .collect();
+        itertools::assert_equal(all_keys.into_iter().rev(), itered_keys);
+    }
+
+    #[test]
+    fn test_range_iter() {
+        let dir = tempdir().unwrap();
+        let mut db = open(dir.path(), None).unwrap();
+
+        let keys: Vec<Key> = ["a", "b", "c", "d"]
+            .iter()
+            .map(|seg| Key::parse(*seg).unwrap())
+            .collect();
+
+        // Write the keys
+        let mut batch = RocksDB::batch();
+        let height = BlockHeight(1);
+        for key in &keys {
+            db.batch_write_subspace_val(&mut batch, height, key, [0_u8], true)
+                .unwrap();
+        }
+        db.exec_batch(batch.0).unwrap();
+
+        // The start is inclusive and the end is exclusive
+        let itered_keys: Vec<Key> = db
+            .iter_range(&keys[1], &keys[3])
+            .map(|(key, _val, _)| Key::parse(key).unwrap())
+            .collect();
+        itertools::assert_equal(keys[1..3].iter().cloned(), itered_keys);
+
+        // An empty range
+        let itered_keys: Vec<Key> = db
+            .iter_range(&keys[2], &keys[1])
+            .map(|(key, _val, _)| Key::parse(key).unwrap())
+            .collect();
+        assert!(itered_keys.is_empty());
+    }
+
     #[test]
     fn test_rollback() {
         let dir = tempdir().unwrap();
@@ -2117,10 +2713,11 @@ mod test {
         assert_eq!(deleted, Some(to_delete_val));
         // Check the conversion state
         let state_cf = db.get_column_family(STATE_CF).unwrap();
-        let conversion_state =
-            db.0.get_cf(state_cf, "conversion_state".as_bytes())
-                .unwrap()
-                .unwrap();
+        let conversion_state = db
+            .inner
+            .get_cf(state_cf, "conversion_state".as_bytes())
+            .unwrap()
+            .unwrap();
         assert_eq!(conversion_state, types::encode(&conversion_state_0));
     }
 
@@ -2162,14 +2759,14 @@ mod test {
             // Diffs new key for `key_with_diffs` at height_0 must be present
             let (old_with_h0, new_with_h0) =
                 old_and_new_diff_key(&key_with_diffs, height_0).unwrap();
-            assert!(db.0.get_cf(diffs_cf, old_with_h0).unwrap().is_none());
-            assert!(db.0.get_cf(diffs_cf, new_with_h0).unwrap().is_some());
+            assert!(db.inner.get_cf(diffs_cf, old_with_h0).unwrap().is_none());
+            assert!(db.inner.get_cf(diffs_cf, new_with_h0).unwrap().is_some());
 
             // Diffs new key for `key_without_diffs` at height_0 must be present
             let (old_wo_h0, new_wo_h0) =
                 old_and_new_diff_key(&key_without_diffs, height_0).unwrap();
-            assert!(db.0.get_cf(diffs_cf, old_wo_h0).unwrap().is_none());
-            assert!(db.0.get_cf(diffs_cf, new_wo_h0).unwrap().is_some());
+            assert!(db.inner.get_cf(diffs_cf, old_wo_h0).unwrap().is_none());
+            assert!(db.inner.get_cf(diffs_cf, new_wo_h0).unwrap().is_some());
         }
 
         // Write second block
@@ -2199,26 +2796,26 @@ mod test {
             // Diffs keys for `key_with_diffs` at height_0 must be present
             let (old_with_h0, new_with_h0) =
                 old_and_new_diff_key(&key_with_diffs, height_0).unwrap();
-            assert!(db.0.get_cf(diffs_cf, old_with_h0).unwrap().is_none());
-            assert!(db.0.get_cf(diffs_cf, new_with_h0).unwrap().is_some());
+            assert!(db.inner.get_cf(diffs_cf, old_with_h0).unwrap().is_none());
+            assert!(db.inner.get_cf(diffs_cf, new_with_h0).unwrap().is_some());
 
             // Diffs keys for `key_without_diffs` at height_0 must be gone
             let (old_wo_h0, new_wo_h0) =
                 old_and_new_diff_key(&key_without_diffs, height_0).unwrap();
-            assert!(db.0.get_cf(diffs_cf, old_wo_h0).unwrap().is_none());
-            assert!(db.0.get_cf(diffs_cf, new_wo_h0).unwrap().is_none());
+            assert!(db.inner.get_cf(diffs_cf, old_wo_h0).unwrap().is_none());
+            assert!(db.inner.get_cf(diffs_cf, new_wo_h0).unwrap().is_none());
 
             // Diffs keys for `key_with_diffs` at height_1 must be present
             let (old_with_h1, new_with_h1) =
                 old_and_new_diff_key(&key_with_diffs, height_1).unwrap();
-            assert!(db.0.get_cf(diffs_cf, old_with_h1).unwrap().is_some());
-            assert!(db.0.get_cf(diffs_cf, new_with_h1).unwrap().is_some());
+            assert!(db.inner.get_cf(diffs_cf, old_with_h1).unwrap().is_some());
+            assert!(db.inner.get_cf(diffs_cf, new_with_h1).unwrap().is_some());
 
             // Diffs keys for `key_without_diffs` at height_1 must be present
             let (old_wo_h1, new_wo_h1) =
                 old_and_new_diff_key(&key_without_diffs, height_1).unwrap();
-            assert!(db.0.get_cf(diffs_cf, old_wo_h1).unwrap().is_some());
-            assert!(db.0.get_cf(diffs_cf, new_wo_h1).unwrap().is_some());
+            assert!(db.inner.get_cf(diffs_cf, old_wo_h1).unwrap().is_some());
+            assert!(db.inner.get_cf(diffs_cf, new_wo_h1).unwrap().is_some());
         }
 
         // Write third block
@@ -2248,26 +2845,177 @@ mod test {
             // Diffs keys for `key_with_diffs` at height_1 must be present
             let (old_with_h1, new_with_h1) =
                 old_and_new_diff_key(&key_with_diffs, height_1).unwrap();
-            assert!(db.0.get_cf(diffs_cf, old_with_h1).unwrap().is_some());
-            assert!(db.0.get_cf(diffs_cf, new_with_h1).unwrap().is_some());
+            assert!(db.inner.get_cf(diffs_cf, old_with_h1).unwrap().is_some());
+            assert!(db.inner.get_cf(diffs_cf, new_with_h1).unwrap().is_some());
 
             // Diffs keys for `key_without_diffs` at height_1 must be gone
             let (old_wo_h1, new_wo_h1) =
                 old_and_new_diff_key(&key_without_diffs, height_1).unwrap();
-            assert!(db.0.get_cf(diffs_cf, old_wo_h1).unwrap().is_none());
-            assert!(db.0.get_cf(diffs_cf, new_wo_h1).unwrap().is_none());
+            assert!(db.inner.get_cf(diffs_cf, old_wo_h1).unwrap().is_none());
+            assert!(db.inner.get_cf(diffs_cf, new_wo_h1).unwrap().is_none());
 
             // Diffs keys for `key_with_diffs` at height_2 must be present
             let (old_with_h2, new_with_h2) =
                 old_and_new_diff_key(&key_with_diffs, height_2).unwrap();
-            assert!(db.0.get_cf(diffs_cf, old_with_h2).unwrap().is_some());
-            assert!(db.0.get_cf(diffs_cf, new_with_h2).unwrap().is_some());
+            assert!(db.inner.get_cf(diffs_cf, old_with_h2).unwrap().is_some());
+            assert!(db.inner.get_cf(diffs_cf, new_with_h2).unwrap().is_some());
 
             // Diffs keys for `key_without_diffs` at height_2 must be present
             let (old_wo_h2, new_wo_h2) =
                 old_and_new_diff_key(&key_without_diffs, height_2).unwrap();
-            assert!(db.0.get_cf(diffs_cf, old_wo_h2).unwrap().is_some());
-            assert!(db.0.get_cf(diffs_cf, new_wo_h2).unwrap().is_some());
+            assert!(db.inner.get_cf(diffs_cf, old_wo_h2).unwrap().is_some());
+            assert!(db.inner.get_cf(diffs_cf, new_wo_h2).unwrap().is_some());
+        }
+    }
+
+    #[test]
+    fn test_prune_below() {
+        let dir = tempdir().unwrap();
+        let mut db = open(dir.path(), None).unwrap();
+
+        let key = Key::parse("test").unwrap();
+        // The encoded height segments of "5" and "10" contain letters
+        let heights = [1, 5, 10].map(BlockHeight);
+        for (i, height) in heights.iter().enumerate() {
+            db.write_subspace_val(*height, &key, [i as u8], true)
+                .unwrap();
+        }
+
+        let mut batch = RocksDB::batch();
+        db.prune_below(&mut batch, BlockHeight(10)).unwrap();
+        db.exec_batch(batch.0).unwrap();
+
+        for height in [BlockHeight(1), BlockHeight(5)] {
+            assert!(db.read_diffs_val(&key, height, false).unwrap().is_none());
+        }
+        assert_eq!(
+            db.read_diffs_val(&key, BlockHeight(10), true).unwrap(),
+            Some(vec![1_u8])
+        );
+        assert_eq!(
+            db.read_diffs_val(&key, BlockHeight(10), false).unwrap(),
+            Some(vec![2_u8])
+        );
+        assert_eq!(db.read_subspace_val(&key).unwrap(), Some(vec![2_u8]));
+    }
+
+    /// Test that a checkpoint is restored and that an interrupted restore is
+    /// resumed when the DB is opened
+    #[test]
+    fn test_restore_checkpoint() {
+        let dir = tempdir().unwrap();
+        let mut db = open(dir.path(), None).unwrap();
+
+        let key = Key::parse("test").unwrap();
+        let new_key = Key::parse("new").unwrap();
+        db.write_subspace_val(BlockHeight(1), &key, vec![1_u8], true)
+            .unwrap();
+        db.write_checkpoint(Epoch(1)).unwrap();
+        db.write_subspace_val(BlockHeight(2), &key, vec![2_u8], true)
+            .unwrap();
+        db.write_subspace_val(BlockHeight(2), &new_key, vec![2_u8], true)
+            .unwrap();
+
+        db.restore_checkpoint(Epoch(1)).unwrap();
+        assert_eq!(db.read_subspace_val(&key).unwrap(), Some(vec![1_u8]));
+        assert!(db.read_subspace_val(&new_key).unwrap().is_none());
+        assert!(!db.restore_marker_path().exists());
+
+        // Simulate a restore interrupted after a write
+        db.write_subspace_val(BlockHeight(2), &new_key, vec![2_u8], true)
+            .unwrap();
+        std::fs::write(db.restore_marker_path(), "1").unwrap();
+        drop(db);
+
+        let db = open(dir.path(), None).unwrap();
+        assert_eq!(db.read_subspace_val(&key).unwrap(), Some(vec![1_u8]));
+        assert!(db.read_subspace_val(&new_key).unwrap().is_none());
+        assert!(!db.restore_marker_path().exists());
+        assert_eq!(db.checkpoints().unwrap(), vec![Epoch(1)]);
+    }
+
+    /// Test that the archive nodes index the heights at which keys changed
+    #[test]
+    fn test_key_history() {
+        for profile in [DbProfile::Validator, DbProfile::Archive] {
+            let dir = tempdir().unwrap();
+            let cache = RocksDBCache {
+                block_cache: rocksdb::Cache::new_lru_cache(1024 * 1024),
+                config: RocksDBConfig {
+                    profile,
+                    ..Default::default()
+                },
+            };
+            let mut db = open(dir.path(), Some(&cache)).unwrap();
+
+            let key = Key::parse("test").unwrap();
+            let child_key = key.push(&"child".to_owned()).unwrap();
+            let not_persisted_key = Key::parse("not_persisted").unwrap();
+            db.write_subspace_val(BlockHeight(1), &key, [1_u8], true)
+                .unwrap();
+            db.write_subspace_val(BlockHeight(2), &child_key, [1_u8], true)
+                .unwrap();
+            db.write_subspace_val(
+                BlockHeight(2),
+                &not_persisted_key,
+                [1_u8],
+                false,
+            )
+            .unwrap();
+            let mut batch = RocksDB::batch();
+            db.batch_write_subspace_val(
+                &mut batch,
+                BlockHeight(10),
+                &key,
+                [2_u8],
+                true,
+            )
+            .unwrap();
+            db.batch_delete_subspace_val(
+                &mut batch,
+                BlockHeight(11),
+                &child_key,
+                true,
+            )
+            .unwrap();
+            db.exec_batch(batch.0).unwrap();
+
+            // Pruning the diffs doesn't prune the history
+            let mut batch = RocksDB::batch();
+            db.prune_below(&mut batch, BlockHeight(10)).unwrap();
+            db.exec_batch(batch.0).unwrap();
+
+            let read_history =
+                |key| db.read_key_history(key, BlockHeight(0), 10).unwrap();
+            let history = read_history(&key);
+            let child_history = read_history(&child_key);
+            assert!(read_history(&not_persisted_key).is_empty());
+            let last_change =
+                db.read_last_change_height(&key, BlockHeight(9)).unwrap();
+            if profile == DbProfile::Archive {
+                assert_eq!(history, vec![BlockHeight(1), BlockHeight(10)]);
+                assert_eq!(
+                    child_history,
+                    vec![BlockHeight(2), BlockHeight(11)]
+                );
+                assert_eq!(last_change, Some(BlockHeight(1)));
+                // The history is paginated
+                let page =
+                    db.read_key_history(&key, BlockHeight(2), 1).unwrap();
+                assert_eq!(page, vec![BlockHeight(10)]);
+                let page =
+                    db.read_key_history(&key, BlockHeight(0), 1).unwrap();
+                assert_eq!(page, vec![BlockHeight(1)]);
+                assert_eq!(
+                    db.read_last_change_height(&child_key, BlockHeight(1))
+                        .unwrap(),
+                    None
+                );
+            } else {
+                assert!(history.is_empty());
+                assert!(child_history.is_empty());
+                assert_eq!(last_change, None);
+            }
         }
     }
 
@@ -2299,6 +3047,7 @@ mod test {
             time,
             epoch,
             results: &results,
+            receipts: &[],
             conversion_state,
             pred_epochs: &pred_epochs,
             next_epoch_min_start_height,
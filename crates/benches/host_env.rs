@@ -3,9 +3,11 @@ use std::collections::{HashMap, HashSet};
 use criterion::{criterion_group, criterion_main, Criterion};
 use namada::core::types::account::AccountPublicKeysMap;
 use namada::core::types::address;
+use namada::core::types::internal::HostHashAlgorithm;
 use namada::ledger::storage::DB;
 use namada::token::{Amount, Transfer};
 use namada::tx::Signature;
+use namada::vm::host_env;
 use namada::vm::wasm::TxCache;
 use namada_apps::bench_utils::{
     BenchShell, TX_INIT_PROPOSAL_WASM, TX_REVEAL_PK_WASM, TX_TRANSFER_WASM,
@@ -108,6 +110,31 @@ fn compile_wasm(c: &mut Criterion) {
     group.finish();
 }
 
+// Benchmarks the native hashing host function for each of the supported
+// algorithms, to set the fixed and the per byte gas costs of hashing
+fn native_hash(c: &mut Criterion) {
+    let mut group = c.benchmark_group("native_hash");
+
+    for algorithm in [
+        HostHashAlgorithm::Sha256,
+        HostHashAlgorithm::Blake2b256,
+        HostHashAlgorithm::Keccak256,
+    ] {
+        for len in [0, 32, 1_024, 64 * 1_024] {
+            let data = vec![0xab_u8; len];
+            // Extract the throughput, together with the wall-time, so that we
+            // can than invert it to calculate the desired metric (time/byte)
+            group.throughput(criterion::Throughput::Bytes(len as u64));
+            group.bench_function(
+                format!("Algorithm: {algorithm:?}, size: {len}"),
+                |b| b.iter(|| host_env::native_hash(algorithm, &data)),
+            );
+        }
+    }
+
+    group.finish();
+}
+
 fn untrusted_wasm_validation(c: &mut Criterion) {
     let mut group = c.benchmark_group("untrusted_wasm_validation");
     let mut txs: HashMap<&str, Vec<u8>> = HashMap::default();
@@ -334,6 +361,7 @@ criterion_group!(
     host_env,
     tx_section_signature_validation,
     compile_wasm,
+    native_hash,
     untrusted_wasm_validation,
     write_log_read,
     storage_read,
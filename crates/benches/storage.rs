@@ -0,0 +1,185 @@
+//! Benchmarks of the storage layer against the DB backends
+//!
+//! The numbers of keys and the sizes of the values can be set with the
+//! `NAMADA_BENCH_STORAGE_KEYS` and `NAMADA_BENCH_STORAGE_VALUE_SIZES` env vars
+//! as comma-separated lists, e.g. `NAMADA_BENCH_STORAGE_KEYS=100,10000`.
+
+use criterion::{
+    criterion_group, criterion_main, BenchmarkId, Criterion, Throughput,
+};
+use namada::state::{DBIter, DB};
+use namada::types::storage::{BlockHeight, Key};
+use namada_apps::config::{DbBackend, RocksDBConfig};
+use namada_apps::node::ledger::storage::{PersistentDB, PersistentDBCache};
+use tempfile::TempDir;
+
+/// Env. var to set the numbers of keys written in a block
+const ENV_VAR_KEYS: &str = "NAMADA_BENCH_STORAGE_KEYS";
+/// Env. var to set the sizes of the values in bytes
+const ENV_VAR_VALUE_SIZES: &str = "NAMADA_BENCH_STORAGE_VALUE_SIZES";
+
+const DEFAULT_KEYS: [usize; 2] = [100, 1_000];
+const DEFAULT_VALUE_SIZES: [usize; 2] = [32, 1_024];
+
+/// The size of the RocksDB block cache
+const BLOCK_CACHE_BYTES: u64 = 64 * 1024 * 1024;
+
+const BACKENDS: [DbBackend; 2] = [DbBackend::Memory, DbBackend::RocksDB];
+
+/// Parse a comma-separated list of numbers from the env. var or use the
+/// defaults
+fn env_list(env_var: &str, defaults: &[usize]) -> Vec<usize> {
+    match std::env::var(env_var) {
+        Ok(list) => list
+            .split(',')
+            .map(|num| {
+                num.trim().parse().unwrap_or_else(|_| {
+                    panic!("Invalid number {num} in {env_var}")
+                })
+            })
+            .collect(),
+        Err(_) => defaults.to_vec(),
+    }
+}
+
+/// The combinations of the numbers of keys and the sizes of the values
+fn params() -> Vec<(usize, usize)> {
+    let value_sizes = env_list(ENV_VAR_VALUE_SIZES, &DEFAULT_VALUE_SIZES);
+    env_list(ENV_VAR_KEYS, &DEFAULT_KEYS)
+        .into_iter()
+        .flat_map(|keys| value_sizes.iter().map(move |size| (keys, *size)))
+        .collect()
+}
+
+/// Open a DB of the given backend in a temporary directory. The directory
+/// must outlive the DB.
+fn open_db(backend: DbBackend) -> (TempDir, PersistentDB) {
+    let dir = tempfile::tempdir().unwrap();
+    let cache = PersistentDBCache::new(
+        backend,
+        BLOCK_CACHE_BYTES,
+        RocksDBConfig::default(),
+    );
+    let db = PersistentDB::open(dir.path(), Some(&cache));
+    (dir, db)
+}
+
+/// The benchmarked keys, all under the same prefix
+fn keys(count: usize) -> Vec<Key> {
+    (0..count)
+        .map(|i| Key::parse(format!("bench/{i:010}")).unwrap())
+        .collect()
+}
+
+/// Write the key-vals of a block in a batch
+fn write_block(
+    db: &mut PersistentDB,
+    height: BlockHeight,
+    keys: &[Key],
+    value: &[u8],
+) {
+    let mut batch = PersistentDB::batch();
+    for key in keys {
+        db.batch_write_subspace_val(&mut batch, height, key, value, true)
+            .unwrap();
+    }
+    db.exec_batch(batch).unwrap();
+}
+
+fn bench_id(backend: DbBackend, keys: usize, value_size: usize) -> BenchmarkId {
+    BenchmarkId::new(
+        format!("{backend:?}"),
+        format!("{keys}_keys/{value_size}_bytes"),
+    )
+}
+
+// Benchmarks writing the key-vals of a block
+fn storage_write_block(c: &mut Criterion) {
+    let mut group = c.benchmark_group("storage_write_block");
+
+    for (key_count, value_size) in params() {
+        let keys = keys(key_count);
+        let value = vec![1_u8; value_size];
+        group.throughput(Throughput::Elements(key_count as u64));
+        for backend in BACKENDS {
+            let (_dir, mut db) = open_db(backend);
+            let mut height = BlockHeight::first();
+            group.bench_function(
+                bench_id(backend, key_count, value_size),
+                |b| {
+                    b.iter(|| {
+                        write_block(&mut db, height, &keys, &value);
+                        height = height.next_height();
+                    })
+                },
+            );
+        }
+    }
+
+    group.finish();
+}
+
+// Benchmarks reading every committed key
+fn storage_read(c: &mut Criterion) {
+    let mut group = c.benchmark_group("storage_read");
+
+    for (key_count, value_size) in params() {
+        let keys = keys(key_count);
+        let value = vec![1_u8; value_size];
+        group.throughput(Throughput::Elements(key_count as u64));
+        for backend in BACKENDS {
+            let (_dir, mut db) = open_db(backend);
+            write_block(&mut db, BlockHeight::first(), &keys, &value);
+            group.bench_function(
+                bench_id(backend, key_count, value_size),
+                |b| {
+                    b.iter(|| {
+                        for key in &keys {
+                            let value = db.read_subspace_val(key).unwrap();
+                            assert!(value.is_some());
+                        }
+                    })
+                },
+            );
+        }
+    }
+
+    group.finish();
+}
+
+// Benchmarks iterating over all the committed keys with a prefix
+fn storage_iter_prefix(c: &mut Criterion) {
+    let mut group = c.benchmark_group("storage_iter_prefix");
+    let prefix = Key::parse("bench").unwrap();
+
+    for (key_count, value_size) in params() {
+        let keys = keys(key_count);
+        let value = vec![1_u8; value_size];
+        group.throughput(Throughput::Elements(key_count as u64));
+        for backend in BACKENDS {
+            let (_dir, mut db) = open_db(backend);
+            write_block(&mut db, BlockHeight::first(), &keys, &value);
+            group.bench_function(
+                bench_id(backend, key_count, value_size),
+                |b| {
+                    b.iter(|| {
+                        assert_eq!(
+                            db.iter_prefix(Some(&prefix)).count(),
+                            key_count
+                        );
+                    })
+                },
+            );
+        }
+    }
+
+    group.finish();
+}
+
+criterion_group!(
+    storage,
+    storage_write_block,
+    storage_read,
+    storage_iter_prefix
+);
+criterion_main!(storage);
@@ -20,8 +20,8 @@ pub const HEX_HASH_LENGTH: usize = HASH_LENGTH * 2;
 #[allow(missing_docs)]
 #[derive(Error, Debug)]
 pub enum Error {
-    #[error("TEMPORARY error: {error}")]
-    Temporary { error: String },
+    #[error("Unexpected hash length {0}, expected {}", HASH_LENGTH)]
+    InvalidLength(usize),
     #[error("Failed trying to convert slice to a hash: {0}")]
     ConversionFailed(std::array::TryFromSliceError),
     #[error("Failed to convert string into a hash: {0}")]
@@ -77,13 +77,7 @@ impl TryFrom<&[u8]> for Hash {
 
     fn try_from(value: &[u8]) -> HashResult<Self> {
         if value.len() != HASH_LENGTH {
-            return Err(Error::Temporary {
-                error: format!(
-                    "Unexpected tx hash length {}, expected {}",
-                    value.len(),
-                    HASH_LENGTH
-                ),
-            });
+            return Err(Error::InvalidLength(value.len()));
         }
         let hash: [u8; HASH_LENGTH] =
             TryFrom::try_from(value).map_err(Error::ConversionFailed)?;
@@ -11,6 +11,17 @@ pub enum HostEnvResult {
     Fail = -1,
 }
 
+/// A hashing algorithm implemented natively by the host env.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HostHashAlgorithm {
+    /// SHA-256
+    Sha256 = 0,
+    /// Blake2b with a 32 bytes output
+    Blake2b256 = 1,
+    /// Keccak-256, as used by Ethereum
+    Keccak256 = 2,
+}
+
 /// Key-value pair represents data from account's subspace.
 /// It is used for prefix iterator's WASM host_env functions.
 #[derive(Clone, Debug, BorshSerialize, BorshDeserialize)]
@@ -38,6 +49,23 @@ impl HostEnvResult {
     }
 }
 
+impl HostHashAlgorithm {
+    /// Convert the algorithm to `u8`, which can be passed to the host
+    pub fn to_u8(self) -> u8 {
+        self as _
+    }
+
+    /// Get the algorithm from its `u8` representation, if it's known
+    pub fn from_u8(int: u8) -> Option<Self> {
+        match int {
+            0 => Some(Self::Sha256),
+            1 => Some(Self::Blake2b256),
+            2 => Some(Self::Keccak256),
+            _ => None,
+        }
+    }
+}
+
 impl From<bool> for HostEnvResult {
     fn from(success: bool) -> Self {
         if success { Self::Success } else { Self::Fail }
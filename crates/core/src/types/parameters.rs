@@ -54,6 +54,33 @@ pub struct Parameters {
     pub fee_unshielding_descriptions_limit: u64,
     /// Map of the cost per gas unit for every token allowed for fee payment
     pub minimum_gas_price: BTreeMap<Address, token::Amount>,
+    /// Rent in the native token charged on every new epoch for each byte
+    /// stored in an account's subspace
+    pub storage_rent_per_byte: token::Amount,
+}
+
+/// The storage rent charge in progress, stored under the parameters address.
+/// The accounts are charged in the order of their addresses over several
+/// blocks.
+#[derive(
+    Clone,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    BorshSerialize,
+    BorshDeserialize,
+    BorshSchema,
+)]
+pub struct StorageRentCursor {
+    /// The last charged account, if any
+    pub last_charged: Option<Address>,
+    /// The number of epochs of rent charged to the accounts after the last
+    /// charged one
+    pub epochs: u64,
+    /// The number of epochs that began while the charge was in progress. Their
+    /// rent is charged from every account once the charge is complete.
+    pub owed_epochs: u64,
 }
 
 /// Epoch duration. A new epoch begins as soon as both the `min_num_of_blocks`
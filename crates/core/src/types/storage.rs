@@ -1292,6 +1292,16 @@ pub struct PrefixValue {
     pub value: Vec<u8>,
 }
 
+/// A page of the values of a storage prefix iterator.
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize, BorshSchema)]
+pub struct PrefixValuesPage {
+    /// The values in this page
+    pub values: Vec<PrefixValue>,
+    /// The key of the last value in this page, to resume the iteration after
+    /// it, if there are more values
+    pub next_after: Option<Key>,
+}
+
 /// Container of all Ethereum event queues.
 #[derive(Default, Debug, BorshSerialize, BorshDeserialize)]
 pub struct EthEventsQueue {
@@ -1476,10 +1486,39 @@ pub struct IndexedTx {
 pub mod tests {
     use proptest::prelude::*;
 
+    use super::testing::{arb_block_height, arb_epoch, arb_key};
     use super::*;
     use crate::types::address::testing::arb_address;
 
     proptest! {
+        /// Tests that any key can be printed and parsed back to the same key.
+        #[test]
+        fn test_key_display_parse_round_trip(key in arb_key()) {
+            let parsed = Key::parse(key.to_string())
+                .expect("cannot parse the key");
+            assert_eq!(parsed, key);
+        }
+
+        /// Tests that keys, epochs and block heights are preserved by a Borsh
+        /// encoding round-trip.
+        #[test]
+        fn test_borsh_round_trip(
+            key in arb_key(),
+            epoch in arb_epoch(),
+            height in arb_block_height(),
+        ) {
+            let decoded = Key::try_from_slice(&key.serialize_to_vec())
+                .expect("cannot decode the key");
+            assert_eq!(decoded, key);
+            let decoded = Epoch::try_from_slice(&epoch.serialize_to_vec())
+                .expect("cannot decode the epoch");
+            assert_eq!(decoded, epoch);
+            let decoded =
+                BlockHeight::try_from_slice(&height.serialize_to_vec())
+                    .expect("cannot decode the block height");
+            assert_eq!(decoded, height);
+        }
+
         /// Tests that any key that doesn't contain reserved prefixes is valid.
         /// This test excludes key segments starting with `#` or `?`
         /// because they are reserved for `Address` or a validity predicate.
@@ -1917,6 +1956,13 @@ pub mod testing {
         }
     }
 
+    prop_compose! {
+        /// Generate an arbitrary block height
+        pub fn arb_block_height()(height: u64) -> BlockHeight {
+            BlockHeight(height)
+        }
+    }
+
     /// Generate an arbitrary [`Key`].
     pub fn arb_key() -> impl Strategy<Value = Key> {
         prop_oneof![
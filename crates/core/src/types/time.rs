@@ -174,7 +174,21 @@ impl DateTimeUtc {
 
     /// Returns the DateTimeUtc corresponding to one second in the future
     pub fn next_second(&self) -> Self {
-        *self + DurationSecs(0)
+        *self + DurationSecs(1)
+    }
+
+    /// Returns the time after the given duration, or `None` if it's out of
+    /// the supported range.
+    pub fn checked_add(self, duration: DurationSecs) -> Option<Self> {
+        let duration = Duration::from_std(duration.into()).ok()?;
+        self.0.checked_add_signed(duration).map(Self)
+    }
+
+    /// Returns the time before the given duration, or `None` if it's out of
+    /// the supported range.
+    pub fn checked_sub(self, duration: DurationSecs) -> Option<Self> {
+        let duration = Duration::from_std(duration.into()).ok()?;
+        self.0.checked_sub_signed(duration).map(Self)
     }
 }
 
@@ -190,12 +204,10 @@ impl Add<DurationSecs> for DateTimeUtc {
     type Output = DateTimeUtc;
 
     fn add(self, duration: DurationSecs) -> Self::Output {
-        let duration_std = std::time::Duration::from_secs(duration.0);
-        let duration_chrono = Duration::from_std(duration_std).expect(
+        self.checked_add(duration).expect(
             "Duration shouldn't be larger than the maximum value supported \
              for chrono::Duration",
-        );
-        (self.0 + duration_chrono).into()
+        )
     }
 }
 
@@ -342,3 +354,21 @@ impl From<DurationNanos> for crate::tendermint::Timeout {
         Self::from(std::time::Duration::from(val))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checked_duration_arithmetic() {
+        let time = DateTimeUtc::unix_epoch();
+        let later = time.checked_add(DurationSecs(60)).unwrap();
+        assert_eq!(later, DateTimeUtc::from_unix_timestamp(60).unwrap());
+        assert_eq!(later.checked_sub(DurationSecs(60)), Some(time));
+        assert_eq!(time.next_second(), time + DurationSecs(1));
+
+        // Durations out of the supported range don't panic
+        assert!(time.checked_add(DurationSecs(u64::MAX)).is_none());
+        assert!(time.checked_sub(DurationSecs(u64::MAX)).is_none());
+    }
+}
@@ -1069,6 +1069,60 @@ pub struct Transfer {
     pub shielded: Option<Hash>,
 }
 
+/// An approval of the amount of the owner's tokens that a spender is allowed
+/// to transfer
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    BorshSerialize,
+    BorshDeserialize,
+    BorshSchema,
+    Hash,
+    Eq,
+    PartialOrd,
+    Serialize,
+    Deserialize,
+)]
+pub struct Approve {
+    /// Owner of the tokens
+    pub owner: Address,
+    /// Spender allowed to transfer the owner's tokens
+    pub spender: Address,
+    /// Token's address
+    pub token: Address,
+    /// The allowed amount of tokens, replacing any previous allowance
+    pub amount: DenominatedAmount,
+}
+
+/// A token transfer by a spender, using up its allowance of the owner's
+/// tokens
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    BorshSerialize,
+    BorshDeserialize,
+    BorshSchema,
+    Hash,
+    Eq,
+    PartialOrd,
+    Serialize,
+    Deserialize,
+)]
+pub struct TransferFrom {
+    /// Spender transferring the owner's tokens
+    pub spender: Address,
+    /// Owner of the tokens
+    pub owner: Address,
+    /// Target address will receive the tokens
+    pub target: Address,
+    /// Token's address
+    pub token: Address,
+    /// The amount of tokens
+    pub amount: DenominatedAmount,
+}
+
 #[allow(missing_docs)]
 #[derive(Error, Debug)]
 pub enum AmountError {
@@ -1147,8 +1201,22 @@ pub mod testing {
 
 #[cfg(test)]
 mod tests {
+    use borsh_ext::BorshSerializeExt;
+    use proptest::prelude::*;
+
+    use super::testing::arb_amount;
     use super::*;
 
+    proptest! {
+        /// Tests that any amount is preserved by a Borsh encoding round-trip.
+        #[test]
+        fn test_amount_borsh_round_trip(amount in arb_amount()) {
+            let decoded = Amount::try_from_slice(&amount.serialize_to_vec())
+                .expect("cannot decode the amount");
+            assert_eq!(decoded, amount);
+        }
+    }
+
     #[test]
     fn test_token_display() {
         let max = Amount::from_uint(u64::MAX, 0).expect("Test failed");
@@ -45,6 +45,12 @@ pub const STORAGE_WRITE_GAS_PER_BYTE: u64 =
     MEMORY_ACCESS_GAS_PER_BYTE + 848 + STORAGE_OCCUPATION_GAS_PER_BYTE;
 /// The cost of verifying a single signature of a transaction
 pub const VERIFY_TX_SIG_GAS: u64 = 9_793;
+/// The fixed cost of hashing data with a native host function, from the
+/// `native_hash` bench of an empty input with the slowest algorithm
+pub const NATIVE_HASH_GAS: u64 = 1_000;
+/// The cost of hashing data with a native host function, per byte, from the
+/// throughput of the `native_hash` bench with the slowest algorithm
+pub const NATIVE_HASH_GAS_PER_BYTE: u64 = 8;
 /// The cost for requesting one more page in wasm (64KiB)
 pub const WASM_MEMORY_PAGE_GAS: u32 =
     MEMORY_ACCESS_GAS_PER_BYTE as u32 * 64 * 1_024;
@@ -325,6 +331,12 @@ impl VpGasMeter {
             current_gas: Gas::default(),
         }
     }
+
+    /// Get the gas consumed by this VP alone, excluding the gas consumed by
+    /// the transaction before it
+    pub fn get_vp_consumed_gas(&self) -> Gas {
+        self.current_gas
+    }
 }
 
 impl VpsGas {
@@ -417,6 +429,18 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_vp_consumed_gas() {
+        let tx_gas_meter = TxGasMeter {
+            tx_gas_limit: TX_GAS_LIMIT.into(),
+            transaction_gas: 1_000.into(),
+        };
+        let mut meter = VpGasMeter::new_from_tx_meter(&tx_gas_meter);
+        meter.consume(500).expect("cannot add the gas");
+        assert_eq!(meter.get_vp_consumed_gas(), 500.into());
+        assert_eq!(meter.get_tx_consumed_gas(), 1_000.into());
+    }
+
     #[test]
     fn test_vp_gas_limit() {
         let tx_gas_meter = TxGasMeter {
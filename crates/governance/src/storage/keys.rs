@@ -27,6 +27,7 @@ struct Keys {
     counter: &'static str,
     pending: &'static str,
     result: &'static str,
+    upgrade: &'static str,
 }
 
 /// Check if key is inside governance address space
@@ -247,6 +248,14 @@ pub fn is_max_proposal_period_key(key: &Key) -> bool {
              && max_proposal_period_param == Keys::VALUES.max_period)
 }
 
+/// Check if key is the scheduled upgrade key
+pub fn is_upgrade_key(key: &Key) -> bool {
+    matches!(&key.segments[..], [
+             DbKeySeg::AddressSeg(addr),
+             DbKeySeg::StringSeg(upgrade),
+         ] if addr == &ADDRESS && upgrade == Keys::VALUES.upgrade)
+}
+
 /// Check if key is a min grace epoch key
 pub fn is_commit_proposal_key(key: &Key) -> bool {
     matches!(&key.segments[..], [
@@ -278,6 +287,7 @@ pub fn is_parameter_key(key: &Key) -> bool {
         || is_min_proposal_voting_period_key(key)
         || is_max_proposal_period_key(key)
         || is_min_grace_epoch_key(key)
+        || is_upgrade_key(key)
 }
 
 /// Check if key is start epoch or end epoch key
@@ -320,6 +330,13 @@ pub fn get_max_proposal_period_key() -> Key {
         .expect("Cannot obtain a storage key")
 }
 
+/// Get the scheduled upgrade key
+pub fn get_upgrade_key() -> Key {
+    Key::from(ADDRESS.to_db_key())
+        .push(&Keys::VALUES.upgrade.to_owned())
+        .expect("Cannot obtain a storage key")
+}
+
 /// Get maximum proposal content key
 pub fn get_max_proposal_content_key() -> Key {
     Key::from(ADDRESS.to_db_key())
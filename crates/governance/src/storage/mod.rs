@@ -4,6 +4,8 @@
 pub mod keys;
 /// Proposal structures
 pub mod proposal;
+/// Upgrades of the node scheduled by governance
+pub mod upgrade;
 /// Vote structures
 pub mod vote;
 
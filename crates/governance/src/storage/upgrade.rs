@@ -0,0 +1,60 @@
+//! An upgrade of the node is scheduled by a governance proposal whose code
+//! writes an [`UpgradeSchedule`]. The nodes stop at the scheduled height
+//! unless they run a binary with the scheduled version hash. The new binary
+//! applies its state migrations at the upgrade height and clears the
+//! schedule.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use namada_core::types::hash::Hash;
+use namada_core::types::storage::BlockHeight;
+use namada_state::{StorageError, StorageRead, StorageResult, StorageWrite};
+
+use crate::storage::keys as governance_keys;
+
+/// An upgrade of the node scheduled by governance
+#[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct UpgradeSchedule {
+    /// The height of the first block processed by the new binary
+    pub height: BlockHeight,
+    /// The hash of the release version and of the protocol upgrade name of
+    /// the new binary
+    pub version_hash: Hash,
+}
+
+/// Schedule an upgrade of the node. Only the code of an accepted governance
+/// proposal is allowed to write the schedule. The upgrade height must be
+/// after the current block height, which the governance VP checks too.
+pub fn schedule_upgrade<S>(
+    storage: &mut S,
+    schedule: UpgradeSchedule,
+) -> StorageResult<()>
+where
+    S: StorageRead + StorageWrite,
+{
+    let current_height = storage.get_block_height()?;
+    if schedule.height <= current_height {
+        return Err(StorageError::new_alloc(format!(
+            "The upgrade height {} must be after the current block height {}",
+            schedule.height, current_height
+        )));
+    }
+    storage.write(&governance_keys::get_upgrade_key(), schedule)
+}
+
+/// Read the scheduled upgrade of the node, if any
+pub fn read_upgrade_schedule<S>(
+    storage: &S,
+) -> StorageResult<Option<UpgradeSchedule>>
+where
+    S: StorageRead,
+{
+    storage.read(&governance_keys::get_upgrade_key())
+}
+
+/// Clear the scheduled upgrade of the node, once it has been applied
+pub fn clear_upgrade_schedule<S>(storage: &mut S) -> StorageResult<()>
+where
+    S: StorageRead + StorageWrite,
+{
+    storage.delete(&governance_keys::get_upgrade_key())
+}
@@ -20,8 +20,8 @@ use namada_parameters::read_epoch_duration_parameter;
 use namada_state::wl_storage::{PrefixIter, WriteLogAndStorage};
 use namada_state::write_log::{self, WriteLog};
 use namada_state::{
-    self as storage, iter_prefix_post, DBIter, ResultExt, State, StorageError,
-    StorageHasher, StorageResult, StorageWrite, WlStorage, DB,
+    self as storage, DBIter, ResultExt, State, StorageError, StorageHasher,
+    StorageResult, StorageWrite, WlStorage, DB,
 };
 use namada_storage::StorageRead;
 use namada_trans_token as token;
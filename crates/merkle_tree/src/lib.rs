@@ -13,8 +13,10 @@ use arse_merkle_tree::{
 };
 use eth_bridge_pool::{BridgePoolProof, BridgePoolTree};
 use ics23::commitment_proof::Proof as Ics23Proof;
-use ics23::{CommitmentProof, ExistenceProof, NonExistenceProof};
-use ics23_specs::ibc_leaf_spec;
+use ics23::{
+    CommitmentProof, ExistenceProof, HostFunctionsManager, NonExistenceProof,
+};
+use ics23_specs::{ibc_leaf_spec, ibc_proof_specs, proof_specs};
 use namada_core::borsh::{BorshDeserialize, BorshSerialize, BorshSerializeExt};
 use namada_core::bytes::ByteBuf;
 use namada_core::types::address::{Address, InternalAddress};
@@ -103,6 +105,8 @@ pub enum Error {
     Ics23MultiLeaf,
     #[error("A Tendermint proof can only be constructed from an ICS23 proof.")]
     TendermintProof,
+    #[error("Invalid proof: {0}")]
+    InvalidProof(String),
 }
 
 /// Result for functions that may fail
@@ -814,6 +818,98 @@ impl From<Proof> for namada_core::tendermint::merkle::proof::ProofOps {
     }
 }
 
+impl TryFrom<namada_core::tendermint::merkle::proof::ProofOps> for Proof {
+    type Error = Error;
+
+    fn try_from(
+        proof: namada_core::tendermint::merkle::proof::ProofOps,
+    ) -> Result<Self> {
+        use prost::Message;
+
+        let [sub_proof_op, base_proof_op]: [_; 2] =
+            proof.ops.try_into().map_err(|ops: Vec<_>| {
+                Error::InvalidProof(format!(
+                    "Expected 2 proof ops, got {}",
+                    ops.len()
+                ))
+            })?;
+        let key = String::from_utf8(sub_proof_op.key)
+            .map_err(|e| Error::InvalidProof(e.to_string()))?;
+        let key = Key::parse(key).map_err(Error::InvalidKey)?;
+        let sub_proof = CommitmentProof::decode(sub_proof_op.data.as_slice())
+            .map_err(|e| Error::InvalidProof(e.to_string()))?;
+        let base_proof = CommitmentProof::decode(base_proof_op.data.as_slice())
+            .map_err(|e| Error::InvalidProof(e.to_string()))?;
+        Ok(Self {
+            key,
+            sub_proof,
+            base_proof,
+        })
+    }
+}
+
+impl Proof {
+    /// Verify the proof against the given root of the merkle tree. When a
+    /// value is given, the proof is checked for the existence of the key with
+    /// the value, otherwise for the non-existence of the key.
+    pub fn verify<H: StorageHasher>(
+        &self,
+        value: Option<&[u8]>,
+        root: &MerkleRoot,
+    ) -> bool {
+        let (store_type, sub_key) = match StoreType::sub_key(&self.key) {
+            Ok(res) => res,
+            Err(_) => return false,
+        };
+        let specs = if store_type == StoreType::Ibc {
+            ibc_proof_specs::<H>()
+        } else {
+            proof_specs::<H>()
+        };
+
+        // The sub-tree root is calculated from the sub proof and then checked
+        // against the base tree
+        let existence_proof = match &self.sub_proof.proof {
+            Some(Ics23Proof::Exist(ep)) => Some(ep),
+            Some(Ics23Proof::Nonexist(nep)) => {
+                nep.left.as_ref().or(nep.right.as_ref())
+            }
+            _ => None,
+        };
+        let sub_root = match existence_proof.map(|ep| {
+            ics23::calculate_existence_root::<HostFunctionsManager>(ep)
+        }) {
+            Some(Ok(sub_root)) => sub_root,
+            _ => return false,
+        };
+        let sub_key = sub_key.to_string();
+        let is_sub_proof_valid = match value {
+            Some(value) => ics23::verify_membership::<HostFunctionsManager>(
+                &self.sub_proof,
+                &specs[0],
+                &sub_root,
+                sub_key.as_bytes(),
+                value,
+            ),
+            None => ics23::verify_non_membership::<HostFunctionsManager>(
+                &self.sub_proof,
+                &specs[0],
+                &sub_root,
+                sub_key.as_bytes(),
+            ),
+        };
+
+        is_sub_proof_valid
+            && ics23::verify_membership::<HostFunctionsManager>(
+                &self.base_proof,
+                &specs[1],
+                &root.0.to_vec(),
+                store_type.to_string().as_bytes(),
+                &sub_root,
+            )
+    }
+}
+
 impl<'a, H: StorageHasher + Default> SubTreeRead for &'a Smt<H> {
     fn root(&self) -> MerkleRoot {
         Smt::<H>::root(self).into()
@@ -1003,12 +1099,10 @@ impl<'a> SubTreeWrite for &'a mut BridgePoolTree {
 
 #[cfg(test)]
 mod test {
-    use ics23::HostFunctionsManager;
     use namada_core::types::hash::Sha256Hasher;
     use namada_core::types::storage::KeySeg;
 
     use super::*;
-    use crate::ics23_specs::{ibc_proof_specs, proof_specs};
 
     #[test]
     fn test_crud_value() {
@@ -1095,6 +1189,55 @@ mod test {
         assert!(basetree_verification_res);
     }
 
+    #[test]
+    fn test_verify_proof() {
+        let mut tree = MerkleTree::<Sha256Hasher>::default();
+
+        let key_prefix: Key =
+            Address::Internal(InternalAddress::Ibc).to_db_key().into();
+        let ibc_key = key_prefix.push(&"test".to_string()).unwrap();
+        let ibc_non_key = key_prefix.push(&"test2".to_string()).unwrap();
+        let key_prefix: Key =
+            Address::Internal(InternalAddress::PoS).to_db_key().into();
+        let pos_key = key_prefix.push(&"test".to_string()).unwrap();
+
+        let ibc_val = [1u8; 8].to_vec();
+        tree.update(&ibc_key, ibc_val).unwrap();
+        let pos_val = [2u8; 8].to_vec();
+        tree.update(&pos_key, pos_val.clone()).unwrap();
+        let root = tree.root();
+
+        // existence proof
+        let proof = match tree
+            .get_sub_tree_existence_proof(
+                std::array::from_ref(&pos_key),
+                vec![&pos_val],
+            )
+            .unwrap()
+        {
+            MembershipProof::ICS23(proof) => proof,
+            _ => panic!("Test failed"),
+        };
+        let proof = tree.get_sub_tree_proof(&pos_key, proof).unwrap();
+        // the proof can be verified after being converted to `ProofOps`
+        let proof_ops: namada_core::tendermint::merkle::proof::ProofOps =
+            proof.into();
+        let proof = Proof::try_from(proof_ops).unwrap();
+        assert_eq!(proof.key, pos_key);
+        assert!(proof.verify::<Sha256Hasher>(Some(&pos_val[..]), &root));
+        assert!(!proof.verify::<Sha256Hasher>(Some(&[3u8; 8][..]), &root));
+        assert!(!proof.verify::<Sha256Hasher>(None, &root));
+        let wrong_root = MerkleRoot([0u8; 32]);
+        assert!(
+            !proof.verify::<Sha256Hasher>(Some(&pos_val[..]), &wrong_root)
+        );
+
+        // non-existence proof
+        let proof = tree.get_non_existence_proof(&ibc_non_key).unwrap();
+        assert!(proof.verify::<Sha256Hasher>(None, &root));
+        assert!(!proof.verify::<Sha256Hasher>(Some(&[1u8; 8][..]), &root));
+    }
+
     #[test]
     fn test_restore_tree() {
         let mut tree = MerkleTree::<Sha256Hasher>::default();
@@ -8,6 +8,7 @@ use borsh::BorshDeserialize;
 use namada_governance::storage::proposal::{
     AddRemove, PGFAction, ProposalType,
 };
+use namada_governance::storage::upgrade::UpgradeSchedule;
 use namada_governance::storage::{is_proposal_accepted, keys as gov_storage};
 use namada_governance::utils::is_valid_validator_voting_period;
 use namada_governance::ProposalVote;
@@ -116,6 +117,7 @@ where
                 (KeyType::PROPOSAL_COMMIT, _) => {
                     self.is_valid_proposal_commit()
                 }
+                (KeyType::UPGRADE, _) => self.is_valid_upgrade(tx_data),
                 (KeyType::PARAMETER, _) => self.is_valid_parameter(tx_data),
                 (KeyType::BALANCE, _) => self.is_valid_balance(&native_token),
                 (KeyType::UNKNOWN_GOVERNANCE, _) => Ok(false),
@@ -702,6 +704,33 @@ where
         }
     }
 
+    /// Validate an upgrade schedule written by an accepted proposal. The
+    /// upgrade height must be after the current block height, otherwise the
+    /// nodes would halt at the next block.
+    pub fn is_valid_upgrade(&self, tx: &Tx) -> Result<bool> {
+        if !self.is_valid_parameter(tx)? {
+            return Ok(false);
+        }
+        let schedule: Option<UpgradeSchedule> =
+            self.ctx.post().read(&gov_storage::get_upgrade_key())?;
+        match schedule {
+            Some(schedule) => {
+                let current_height = self.ctx.get_block_height()?;
+                let is_valid_height = schedule.height > current_height;
+                if !is_valid_height {
+                    tracing::info!(
+                        "The upgrade height {} must be after the current \
+                         block height {current_height}",
+                        schedule.height,
+                    );
+                }
+                Ok(is_valid_height)
+            }
+            // Cancelling a scheduled upgrade
+            None => Ok(true),
+        }
+    }
+
     /// Check if a vote is from a validator
     pub fn is_validator(
         &self,
@@ -805,6 +834,8 @@ enum KeyType {
     #[allow(non_camel_case_types)]
     AUTHOR,
     #[allow(non_camel_case_types)]
+    UPGRADE,
+    #[allow(non_camel_case_types)]
     PARAMETER,
     #[allow(non_camel_case_types)]
     UNKNOWN_GOVERNANCE,
@@ -836,6 +867,8 @@ impl KeyType {
             KeyType::AUTHOR
         } else if gov_storage::is_counter_key(key) {
             KeyType::COUNTER
+        } else if gov_storage::is_upgrade_key(key) {
+            KeyType::UPGRADE
         } else if gov_storage::is_parameter_key(key) {
             KeyType::PARAMETER
         } else if token::storage_key::is_balance_key(native_token, key)
@@ -367,6 +367,49 @@ mod test {
             .unwrap();
         assert!(has_balance_key);
 
+        // Write another balance and request the balances one per page
+        let other_owner = address::testing::established_address_3();
+        let other_balance_key =
+            token::storage_key::balance_key(&token_addr, &other_owner);
+        StorageWrite::write(
+            &mut client.wl_storage,
+            &other_balance_key,
+            balance,
+        )?;
+        client.wl_storage.commit_tx();
+        client.wl_storage.commit_block().unwrap();
+        let first_page = RPC
+            .shell()
+            .storage_prefix_page(
+                &client,
+                None,
+                None,
+                false,
+                &1,
+                &balance_prefix,
+            )
+            .await
+            .unwrap()
+            .data;
+        assert_eq!(first_page.values.len(), 1);
+        let after = first_page.next_after.expect("There must be another page");
+        let second_page = RPC
+            .shell()
+            .storage_prefix_page(
+                &client,
+                Some(after.serialize_to_vec()),
+                None,
+                false,
+                &1,
+                &balance_prefix,
+            )
+            .await
+            .unwrap()
+            .data;
+        assert_eq!(second_page.values.len(), 1);
+        assert!(second_page.next_after.is_none());
+        assert_ne!(first_page.values[0].key, second_page.values[0].key);
+
         Ok(())
     }
 }
@@ -2,6 +2,7 @@
 
 use std::collections::{BTreeSet, HashMap};
 
+use namada_gas::GasMetering;
 use namada_governance::is_proposal_accepted;
 use namada_token::storage_key::is_any_token_parameter_key;
 use namada_tx::Tx;
@@ -10,13 +11,14 @@ use thiserror::Error;
 
 use crate::ledger::native_vp::{self, Ctx, NativeVp};
 use crate::token::storage_key::{
-    is_any_minted_balance_key, is_any_minter_key, is_any_token_balance_key,
-    minter_key,
+    balance_key, is_any_allowance_key, is_any_minted_balance_key,
+    is_any_minter_key, is_any_token_balance_key, minter_key,
 };
 use crate::token::Amount;
 use crate::types::address::{Address, InternalAddress};
 use crate::types::storage::{Key, KeySeg};
 use crate::vm::WasmCacheAccess;
+use crate::{account, parameters};
 
 #[allow(missing_docs)]
 #[derive(Error, Debug)]
@@ -57,6 +59,12 @@ where
         let mut dec_changes: HashMap<Address, Amount> = HashMap::new();
         let mut inc_mints: HashMap<Address, Amount> = HashMap::new();
         let mut dec_mints: HashMap<Address, Amount> = HashMap::new();
+        // The sums of the decrements of the allowances and the spenders that
+        // decremented them, keyed by the token and the owner
+        let mut allowance_spends: HashMap<
+            (Address, Address),
+            (Amount, BTreeSet<Address>),
+        > = HashMap::new();
         for key in keys_changed {
             if let Some([token, _]) = is_any_token_balance_key(key) {
                 let pre: Amount = self.ctx.read_pre(key)?.unwrap_or_default();
@@ -128,6 +136,36 @@ where
                 }
             } else if is_any_token_parameter_key(key).is_some() {
                 return self.is_valid_parameter(tx_data);
+            } else if let Some([token, owner, spender]) =
+                is_any_allowance_key(key)
+            {
+                let pre: Amount = self.ctx.read_pre(key)?.unwrap_or_default();
+                let post: Amount = self.ctx.read_post(key)?.unwrap_or_default();
+                match pre.checked_sub(post) {
+                    Some(spent) => {
+                        let (total_spent, spenders) = allowance_spends
+                            .entry((token.clone(), owner.clone()))
+                            .or_default();
+                        *total_spent = total_spent
+                            .checked_add(spent)
+                            .ok_or_else(|| {
+                                Error::NativeVpError(
+                                    native_vp::Error::SimpleMessage(
+                                        "Overflowed in allowance check",
+                                    ),
+                                )
+                            })?;
+                        if !spent.is_zero() {
+                            spenders.insert(spender.clone());
+                        }
+                    }
+                    None => {
+                        // An allowance can only be increased by its owner
+                        if !self.is_signed_by(owner, verifiers)? {
+                            return Ok(false);
+                        }
+                    }
+                }
             } else if key.segments.get(0)
                 == Some(
                     &Address::Internal(InternalAddress::Multitoken).to_db_key(),
@@ -139,6 +177,14 @@ where
             }
         }
 
+        for ((token, owner), (spent, spenders)) in allowance_spends {
+            if !self.is_valid_allowance_spend(
+                &token, &owner, spent, &spenders, verifiers,
+            )? {
+                return Ok(false);
+            }
+        }
+
         let mut all_tokens = BTreeSet::new();
         all_tokens.extend(inc_changes.keys().cloned());
         all_tokens.extend(dec_changes.keys().cloned());
@@ -202,6 +248,69 @@ where
         }
     }
 
+    /// Return if the decrements of the owner's allowances of the token are
+    /// valid. The owner may decrease its allowances when it signs the tx.
+    /// Otherwise, they have to be spent: the debit of the owner's balance must
+    /// be equal to their total decrement and every spender has to sign the tx.
+    pub fn is_valid_allowance_spend(
+        &self,
+        token: &Address,
+        owner: &Address,
+        spent: Amount,
+        spenders: &BTreeSet<Address>,
+        verifiers: &BTreeSet<Address>,
+    ) -> Result<bool> {
+        if spent.is_zero() || self.is_signed_by(owner, verifiers)? {
+            return Ok(true);
+        }
+        let key = balance_key(token, owner);
+        let pre: Amount = self.ctx.read_pre(&key)?.unwrap_or_default();
+        let post: Amount = self.ctx.read_post(&key)?.unwrap_or_default();
+        if pre.checked_sub(post) != Some(spent) {
+            return Ok(false);
+        }
+        for spender in spenders {
+            if !self.is_signed_by(spender, verifiers)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Return if the address is a verifier and the tx carries enough valid
+    /// signatures of its account's keys
+    pub fn is_signed_by(
+        &self,
+        addr: &Address,
+        verifiers: &BTreeSet<Address>,
+    ) -> Result<bool> {
+        if !verifiers.contains(addr) {
+            return Ok(false);
+        }
+        let max_signatures =
+            parameters::max_signatures_per_transaction(&self.ctx.pre())?;
+        let public_keys_index_map =
+            account::public_keys_index_map(&self.ctx.pre(), addr)?;
+        let threshold = account::threshold(&self.ctx.pre(), addr)?.unwrap_or(1);
+        let mut gas_meter = self.ctx.gas_meter.borrow_mut();
+        match self.ctx.tx.verify_signatures(
+            &[self.ctx.tx.raw_header_hash()],
+            public_keys_index_map,
+            &Some(addr.clone()),
+            threshold,
+            max_signatures,
+            || gas_meter.consume(crate::gas::VERIFY_TX_SIG_GAS),
+        ) {
+            Ok(_) => Ok(true),
+            Err(namada_tx::VerifySigError::Gas(_)) => {
+                Err(Error::NativeVpError(native_vp::Error::SimpleMessage(
+                    "Gas limit exceeded in signature verification",
+                )))
+            }
+            Err(_) => Ok(false),
+        }
+    }
+
     /// Return if the parameter change was done via a governance proposal
     pub fn is_valid_parameter(&self, tx: &Tx) -> Result<bool> {
         match tx.data() {
@@ -230,11 +339,12 @@ mod tests {
     use crate::ledger::gas::VpGasMeter;
     use crate::ledger::ibc::storage::ibc_token;
     use crate::token::storage_key::{
-        balance_key, minted_balance_key, minter_key,
+        allowance_key, balance_key, minted_balance_key, minter_key,
     };
     use crate::token::Amount;
     use crate::types::address::{Address, InternalAddress};
     use crate::types::key::testing::keypair_1;
+    use crate::types::key::RefTo;
     use crate::types::storage::TxIndex;
     use crate::vm::wasm::compilation_cache::common::testing::cache as wasm_cache;
 
@@ -255,6 +365,125 @@ mod tests {
         tx
     }
 
+    /// Run the multitoken VP on the `dummy_tx` with the given changed keys and
+    /// verifiers and return if it accepts it
+    fn run_vp(
+        wl_storage: &TestWlStorage,
+        keys_changed: &BTreeSet<Key>,
+        verifiers: &BTreeSet<Address>,
+    ) -> bool {
+        let tx_index = TxIndex::default();
+        let tx = dummy_tx(wl_storage);
+        let gas_meter = VpGasMeter::new_from_tx_meter(
+            &TxGasMeter::new_from_sub_limit(u64::MAX.into()),
+        );
+        let (vp_wasm_cache, _vp_cache_dir) = wasm_cache();
+        let ctx = Ctx::new(
+            &ADDRESS,
+            &wl_storage.storage,
+            &wl_storage.write_log,
+            &tx,
+            &tx_index,
+            gas_meter,
+            keys_changed,
+            verifiers,
+            vp_wasm_cache,
+        );
+
+        let vp = MultitokenVp { ctx };
+        vp.validate_tx(&tx, keys_changed, verifiers)
+            .expect("validation failed")
+    }
+
+    /// Register the public key that signs the `dummy_tx` for the account
+    fn init_signer(wl_storage: &mut TestWlStorage, addr: &Address) {
+        crate::account::init_account_storage(
+            wl_storage,
+            addr,
+            &[keypair_1().ref_to()],
+            1,
+        )
+        .expect("init account failed");
+        wl_storage.commit_block().expect("commit failed");
+    }
+
+    /// Set up an allowance spend of the owner's tokens by the spender, who
+    /// debits the owner's balance by `debit` and decrements the allowance to
+    /// `allowance`, and return if the multitoken VP accepts it
+    fn validate_allowance_spend(
+        spender_signs: bool,
+        debit: u64,
+        allowance: u64,
+    ) -> bool {
+        let mut wl_storage = TestWlStorage::default();
+        let mut keys_changed = BTreeSet::new();
+
+        let owner = established_address_1();
+        let spender = established_address_2();
+        if spender_signs {
+            init_signer(&mut wl_storage, &spender);
+        }
+        let owner_key = balance_key(&nam(), &owner);
+        let allowance_key = allowance_key(&nam(), &owner, &spender);
+        wl_storage
+            .storage
+            .write(&owner_key, Amount::native_whole(100).serialize_to_vec())
+            .expect("write failed");
+        wl_storage
+            .storage
+            .write(&allowance_key, Amount::native_whole(50).serialize_to_vec())
+            .expect("write failed");
+
+        wl_storage
+            .write_log
+            .write(
+                &owner_key,
+                Amount::native_whole(100 - debit).serialize_to_vec(),
+            )
+            .expect("write failed");
+        keys_changed.insert(owner_key);
+        wl_storage
+            .write_log
+            .write(
+                &allowance_key,
+                Amount::native_whole(allowance).serialize_to_vec(),
+            )
+            .expect("write failed");
+        keys_changed.insert(allowance_key);
+        let spender_key = balance_key(&nam(), &spender);
+        wl_storage
+            .write_log
+            .write(&spender_key, Amount::native_whole(debit).serialize_to_vec())
+            .expect("write failed");
+        keys_changed.insert(spender_key);
+
+        let verifiers = BTreeSet::from([owner, spender]);
+        run_vp(&wl_storage, &keys_changed, &verifiers)
+    }
+
+    /// Set up an approval of an allowance of the owner's tokens for the
+    /// spender, who signs the tx, and return if the multitoken VP accepts it
+    fn validate_allowance_approval(owner_signs: bool) -> bool {
+        let mut wl_storage = TestWlStorage::default();
+        let mut keys_changed = BTreeSet::new();
+
+        let owner = established_address_1();
+        let spender = established_address_2();
+        init_signer(&mut wl_storage, &spender);
+        if owner_signs {
+            init_signer(&mut wl_storage, &owner);
+        }
+        let allowance_key = allowance_key(&nam(), &owner, &spender);
+        wl_storage
+            .write_log
+            .write(&allowance_key, Amount::native_whole(50).serialize_to_vec())
+            .expect("write failed");
+        keys_changed.insert(allowance_key);
+
+        let verifiers = BTreeSet::from([owner, spender]);
+        run_vp(&wl_storage, &keys_changed, &verifiers)
+    }
+
     #[test]
     fn test_valid_transfer() {
         let mut wl_storage = TestWlStorage::default();
@@ -311,6 +540,29 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_valid_transfer_from_allowance() {
+        // the spender transfers 10 of the owner's tokens to itself
+        assert!(validate_allowance_spend(true, 10, 40));
+    }
+
+    #[test]
+    fn test_invalid_transfer_from_allowance() {
+        // the spender transfers 10 of the owner's tokens to itself, but only
+        // decreases the allowance by 5
+        assert!(!validate_allowance_spend(true, 10, 45));
+        // the spender decreases the allowance by more than it transfers
+        assert!(!validate_allowance_spend(true, 10, 30));
+        // the spender doesn't sign the transfer
+        assert!(!validate_allowance_spend(false, 10, 40));
+    }
+
+    #[test]
+    fn test_allowance_approval() {
+        assert!(validate_allowance_approval(true));
+        assert!(!validate_allowance_approval(false));
+    }
+
     #[test]
     fn test_invalid_transfer() {
         let mut wl_storage = TestWlStorage::default();
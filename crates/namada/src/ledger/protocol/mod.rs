@@ -843,9 +843,14 @@ where
     H: 'static + StorageHasher + Sync,
     CA: 'static + WasmCacheAccess + Sync,
 {
+    // The VPs run in other threads, so the tx span has to be passed on
+    let tx_span = tracing::Span::current();
     let vps_result = verifiers
         .par_iter()
         .try_fold(VpsResult::default, |mut result, addr| {
+            let _vp_span =
+                tracing::debug_span!(parent: &tx_span, "vp", address = %addr)
+                    .entered();
             let mut gas_meter = VpGasMeter::new_from_tx_meter(tx_gas_meter);
             let accept = match &addr {
                 Address::Implicit(_) | Address::Established(_) => {
@@ -1103,6 +1108,9 @@ where
                 },
             }
 
+            result
+                .vps_gas
+                .insert(addr.clone(), gas_meter.get_vp_consumed_gas());
             result
                 .gas_used
                 .set(gas_meter)
@@ -1131,6 +1139,8 @@ fn merge_vp_results(
     errors.append(&mut b.errors);
     let invalid_sig = a.invalid_sig || b.invalid_sig;
     let mut gas_used = a.gas_used;
+    let mut vps_gas = a.vps_gas;
+    vps_gas.append(&mut b.vps_gas);
 
     gas_used
         .merge(b.gas_used, tx_gas_meter)
@@ -1140,6 +1150,7 @@ fn merge_vp_results(
         accepted_vps,
         rejected_vps,
         gas_used,
+        vps_gas,
         errors,
         invalid_sig,
     })
@@ -43,6 +43,8 @@ pub enum RuntimeError {
     InvalidCodeHash,
     #[error("No value found in result buffer")]
     NoValueInResultBuffer,
+    #[error("Unknown hash algorithm: {0}")]
+    UnknownHashAlgorithm(u8),
 }
 
 /// VP environment function result
@@ -438,6 +440,87 @@ where
     Ok(iter)
 }
 
+/// Storage prefix iterator for prior state (before tx execution), in reverse
+/// order of storage keys. It will try to get an iterator from the storage.
+pub fn rev_iter_prefix_pre<'a, DB, H>(
+    gas_meter: &mut VpGasMeter,
+    write_log: &'a WriteLog,
+    storage: &'a State<DB, H>,
+    prefix: &Key,
+    sentinel: &mut VpSentinel,
+) -> EnvResult<namada_state::PrefixIter<'a, DB>>
+where
+    DB: namada_state::DB + for<'iter> namada_state::DBIter<'iter>,
+    H: StorageHasher,
+{
+    let (iter, gas) =
+        namada_state::rev_iter_prefix_pre(write_log, storage, prefix);
+    add_gas(gas_meter, gas, sentinel)?;
+    Ok(iter)
+}
+
+/// Storage prefix iterator for posterior state (after tx execution), in
+/// reverse order of storage keys. It will try to get an iterator from the
+/// storage.
+pub fn rev_iter_prefix_post<'a, DB, H>(
+    gas_meter: &mut VpGasMeter,
+    write_log: &'a WriteLog,
+    storage: &'a State<DB, H>,
+    prefix: &Key,
+    sentinel: &mut VpSentinel,
+) -> EnvResult<namada_state::PrefixIter<'a, DB>>
+where
+    DB: namada_state::DB + for<'iter> namada_state::DBIter<'iter>,
+    H: StorageHasher,
+{
+    let (iter, gas) =
+        namada_state::rev_iter_prefix_post(write_log, storage, prefix);
+    add_gas(gas_meter, gas, sentinel)?;
+    Ok(iter)
+}
+
+/// Storage range iterator for prior state (before tx execution), over the keys
+/// within `[start, end)` ordered by storage keys. It will try to get an
+/// iterator from the storage.
+pub fn iter_range_pre<'a, DB, H>(
+    gas_meter: &mut VpGasMeter,
+    write_log: &'a WriteLog,
+    storage: &'a State<DB, H>,
+    start: &Key,
+    end: &Key,
+    sentinel: &mut VpSentinel,
+) -> EnvResult<namada_state::PrefixIter<'a, DB>>
+where
+    DB: namada_state::DB + for<'iter> namada_state::DBIter<'iter>,
+    H: StorageHasher,
+{
+    let (iter, gas) =
+        namada_state::iter_range_pre(write_log, storage, start, end);
+    add_gas(gas_meter, gas, sentinel)?;
+    Ok(iter)
+}
+
+/// Storage range iterator for posterior state (after tx execution), over the
+/// keys within `[start, end)` ordered by storage keys. It will try to get an
+/// iterator from the storage.
+pub fn iter_range_post<'a, DB, H>(
+    gas_meter: &mut VpGasMeter,
+    write_log: &'a WriteLog,
+    storage: &'a State<DB, H>,
+    start: &Key,
+    end: &Key,
+    sentinel: &mut VpSentinel,
+) -> EnvResult<namada_state::PrefixIter<'a, DB>>
+where
+    DB: namada_state::DB + for<'iter> namada_state::DBIter<'iter>,
+    H: StorageHasher,
+{
+    let (iter, gas) =
+        namada_state::iter_range_post(write_log, storage, start, end);
+    add_gas(gas_meter, gas, sentinel)?;
+    Ok(iter)
+}
+
 /// Get the next item in a storage prefix iterator (pre or post).
 pub fn iter_next<DB>(
     gas_meter: &mut VpGasMeter,
@@ -8,12 +8,12 @@ use borsh::BorshDeserialize;
 use borsh_ext::BorshSerializeExt;
 use masp_primitives::transaction::Transaction;
 use namada_core::types::address::ESTABLISHED_ADDRESS_BYTES_LEN;
-use namada_core::types::internal::KeyVal;
+use namada_core::types::internal::{HostHashAlgorithm, KeyVal};
 use namada_core::types::storage::{Epochs, TX_INDEX_LENGTH};
 use namada_core::types::validity_predicate::VpSentinel;
 use namada_gas::{
     self as gas, GasMetering, TxGasMeter, VpGasMeter,
-    MEMORY_ACCESS_GAS_PER_BYTE,
+    MEMORY_ACCESS_GAS_PER_BYTE, NATIVE_HASH_GAS, NATIVE_HASH_GAS_PER_BYTE,
 };
 use namada_state::write_log::{self, WriteLog};
 use namada_state::{self, ResultExt, State, StorageError, StorageHasher};
@@ -80,6 +80,8 @@ pub enum TxRuntimeError {
     NoValueInResultBuffer,
     #[error("VP code is not allowed in allowlist parameter.")]
     DisallowedVp,
+    #[error("Unknown hash algorithm: {0}")]
+    UnknownHashAlgorithm(u8),
 }
 
 /// Result of a tx host env fn call
@@ -733,6 +735,84 @@ where
     Ok(iterators.insert(iter).id())
 }
 
+/// Storage prefix iterator function exposed to the wasm VM Tx environment.
+/// It will try to get an iterator from the storage and return the corresponding
+/// ID of the iterator, in reverse order of storage keys.
+pub fn tx_rev_iter_prefix<MEM, DB, H, CA>(
+    env: &TxVmEnv<MEM, DB, H, CA>,
+    prefix_ptr: u64,
+    prefix_len: u64,
+) -> TxResult<u64>
+where
+    MEM: VmMemory,
+    DB: 'static + namada_state::DB + for<'iter> namada_state::DBIter<'iter>,
+    H: StorageHasher,
+    CA: WasmCacheAccess,
+{
+    let (prefix, gas) = env
+        .memory
+        .read_string(prefix_ptr, prefix_len as _)
+        .map_err(|e| TxRuntimeError::MemoryError(Box::new(e)))?;
+    tx_charge_gas(env, gas)?;
+
+    tracing::debug!("tx_rev_iter_prefix {}", prefix);
+
+    let prefix =
+        Key::parse(prefix).map_err(TxRuntimeError::StorageDataError)?;
+
+    let write_log = unsafe { env.ctx.write_log.get() };
+    let storage = unsafe { env.ctx.storage.get() };
+    let (iter, gas) =
+        namada_state::rev_iter_prefix_post(write_log, storage, &prefix);
+    tx_charge_gas(env, gas)?;
+
+    let iterators = unsafe { env.ctx.iterators.get() };
+    Ok(iterators.insert(iter).id())
+}
+
+/// Storage range iterator function exposed to the wasm VM Tx environment.
+/// It will try to get an iterator over the keys within `[start, end)` from the
+/// storage and return the corresponding ID of the iterator, ordered by storage
+/// keys.
+pub fn tx_iter_range<MEM, DB, H, CA>(
+    env: &TxVmEnv<MEM, DB, H, CA>,
+    start_ptr: u64,
+    start_len: u64,
+    end_ptr: u64,
+    end_len: u64,
+) -> TxResult<u64>
+where
+    MEM: VmMemory,
+    DB: 'static + namada_state::DB + for<'iter> namada_state::DBIter<'iter>,
+    H: StorageHasher,
+    CA: WasmCacheAccess,
+{
+    let (start, gas) = env
+        .memory
+        .read_string(start_ptr, start_len as _)
+        .map_err(|e| TxRuntimeError::MemoryError(Box::new(e)))?;
+    tx_charge_gas(env, gas)?;
+    let (end, gas) = env
+        .memory
+        .read_string(end_ptr, end_len as _)
+        .map_err(|e| TxRuntimeError::MemoryError(Box::new(e)))?;
+    tx_charge_gas(env, gas)?;
+
+    tracing::debug!("tx_iter_range {}..{}", start, end);
+
+    let start = Key::parse(start).map_err(TxRuntimeError::StorageDataError)?;
+    let end = Key::parse(end).map_err(TxRuntimeError::StorageDataError)?;
+
+    let write_log = unsafe { env.ctx.write_log.get() };
+    let storage = unsafe { env.ctx.storage.get() };
+    let (iter, gas) =
+        namada_state::iter_range_post(write_log, storage, &start, &end);
+    tx_charge_gas(env, gas)?;
+
+    let iterators = unsafe { env.ctx.iterators.get() };
+    Ok(iterators.insert(iter).id())
+}
+
 /// Storage prefix iterator next function exposed to the wasm VM Tx environment.
 /// It will try to read from the write log first and if no entry found then from
 /// the storage.
@@ -1368,6 +1448,180 @@ where
     Ok(iterators.insert(iter).id())
 }
 
+/// Storage prefix iterator function for prior state (before tx execution)
+/// exposed to the wasm VM VP environment. It will try to get an iterator from
+/// the storage and return the corresponding ID of the iterator, in reverse
+/// order of storage keys.
+pub fn vp_rev_iter_prefix_pre<MEM, DB, H, EVAL, CA>(
+    env: &VpVmEnv<MEM, DB, H, EVAL, CA>,
+    prefix_ptr: u64,
+    prefix_len: u64,
+) -> vp_host_fns::EnvResult<u64>
+where
+    MEM: VmMemory,
+    DB: 'static + namada_state::DB + for<'iter> namada_state::DBIter<'iter>,
+    H: StorageHasher,
+    EVAL: VpEvaluator,
+    CA: WasmCacheAccess,
+{
+    let (prefix, gas) = env
+        .memory
+        .read_string(prefix_ptr, prefix_len as _)
+        .map_err(|e| vp_host_fns::RuntimeError::MemoryError(Box::new(e)))?;
+    let gas_meter = unsafe { env.ctx.gas_meter.get() };
+    let sentinel = unsafe { env.ctx.sentinel.get() };
+    vp_host_fns::add_gas(gas_meter, gas, sentinel)?;
+
+    tracing::debug!("vp_rev_iter_prefix_pre {}", prefix);
+
+    let prefix = Key::parse(prefix)
+        .map_err(vp_host_fns::RuntimeError::StorageDataError)?;
+
+    let write_log = unsafe { env.ctx.write_log.get() };
+    let storage = unsafe { env.ctx.storage.get() };
+    let iter = vp_host_fns::rev_iter_prefix_pre(
+        gas_meter, write_log, storage, &prefix, sentinel,
+    )?;
+
+    let iterators = unsafe { env.ctx.iterators.get() };
+    Ok(iterators.insert(iter).id())
+}
+
+/// Storage prefix iterator function for posterior state (after tx execution)
+/// exposed to the wasm VM VP environment. It will try to get an iterator from
+/// the storage and return the corresponding ID of the iterator, in reverse
+/// order of storage keys.
+pub fn vp_rev_iter_prefix_post<MEM, DB, H, EVAL, CA>(
+    env: &VpVmEnv<MEM, DB, H, EVAL, CA>,
+    prefix_ptr: u64,
+    prefix_len: u64,
+) -> vp_host_fns::EnvResult<u64>
+where
+    MEM: VmMemory,
+    DB: 'static + namada_state::DB + for<'iter> namada_state::DBIter<'iter>,
+    H: StorageHasher,
+    EVAL: VpEvaluator,
+    CA: WasmCacheAccess,
+{
+    let (prefix, gas) = env
+        .memory
+        .read_string(prefix_ptr, prefix_len as _)
+        .map_err(|e| vp_host_fns::RuntimeError::MemoryError(Box::new(e)))?;
+    let gas_meter = unsafe { env.ctx.gas_meter.get() };
+    let sentinel = unsafe { env.ctx.sentinel.get() };
+    vp_host_fns::add_gas(gas_meter, gas, sentinel)?;
+
+    tracing::debug!("vp_rev_iter_prefix_post {}", prefix);
+
+    let prefix = Key::parse(prefix)
+        .map_err(vp_host_fns::RuntimeError::StorageDataError)?;
+
+    let write_log = unsafe { env.ctx.write_log.get() };
+    let storage = unsafe { env.ctx.storage.get() };
+    let iter = vp_host_fns::rev_iter_prefix_post(
+        gas_meter, write_log, storage, &prefix, sentinel,
+    )?;
+
+    let iterators = unsafe { env.ctx.iterators.get() };
+    Ok(iterators.insert(iter).id())
+}
+
+/// Storage range iterator function for prior state (before tx execution)
+/// exposed to the wasm VM VP environment. It will try to get an iterator over
+/// the keys within `[start, end)` from the storage and return the
+/// corresponding ID of the iterator, ordered by storage keys.
+pub fn vp_iter_range_pre<MEM, DB, H, EVAL, CA>(
+    env: &VpVmEnv<MEM, DB, H, EVAL, CA>,
+    start_ptr: u64,
+    start_len: u64,
+    end_ptr: u64,
+    end_len: u64,
+) -> vp_host_fns::EnvResult<u64>
+where
+    MEM: VmMemory,
+    DB: 'static + namada_state::DB + for<'iter> namada_state::DBIter<'iter>,
+    H: StorageHasher,
+    EVAL: VpEvaluator,
+    CA: WasmCacheAccess,
+{
+    let gas_meter = unsafe { env.ctx.gas_meter.get() };
+    let sentinel = unsafe { env.ctx.sentinel.get() };
+    let (start, gas) = env
+        .memory
+        .read_string(start_ptr, start_len as _)
+        .map_err(|e| vp_host_fns::RuntimeError::MemoryError(Box::new(e)))?;
+    vp_host_fns::add_gas(gas_meter, gas, sentinel)?;
+    let (end, gas) = env
+        .memory
+        .read_string(end_ptr, end_len as _)
+        .map_err(|e| vp_host_fns::RuntimeError::MemoryError(Box::new(e)))?;
+    vp_host_fns::add_gas(gas_meter, gas, sentinel)?;
+
+    tracing::debug!("vp_iter_range_pre {}..{}", start, end);
+
+    let start = Key::parse(start)
+        .map_err(vp_host_fns::RuntimeError::StorageDataError)?;
+    let end =
+        Key::parse(end).map_err(vp_host_fns::RuntimeError::StorageDataError)?;
+
+    let write_log = unsafe { env.ctx.write_log.get() };
+    let storage = unsafe { env.ctx.storage.get() };
+    let iter = vp_host_fns::iter_range_pre(
+        gas_meter, write_log, storage, &start, &end, sentinel,
+    )?;
+
+    let iterators = unsafe { env.ctx.iterators.get() };
+    Ok(iterators.insert(iter).id())
+}
+
+/// Storage range iterator function for posterior state (after tx execution)
+/// exposed to the wasm VM VP environment. It will try to get an iterator over
+/// the keys within `[start, end)` from the storage and return the
+/// corresponding ID of the iterator, ordered by storage keys.
+pub fn vp_iter_range_post<MEM, DB, H, EVAL, CA>(
+    env: &VpVmEnv<MEM, DB, H, EVAL, CA>,
+    start_ptr: u64,
+    start_len: u64,
+    end_ptr: u64,
+    end_len: u64,
+) -> vp_host_fns::EnvResult<u64>
+where
+    MEM: VmMemory,
+    DB: 'static + namada_state::DB + for<'iter> namada_state::DBIter<'iter>,
+    H: StorageHasher,
+    EVAL: VpEvaluator,
+    CA: WasmCacheAccess,
+{
+    let gas_meter = unsafe { env.ctx.gas_meter.get() };
+    let sentinel = unsafe { env.ctx.sentinel.get() };
+    let (start, gas) = env
+        .memory
+        .read_string(start_ptr, start_len as _)
+        .map_err(|e| vp_host_fns::RuntimeError::MemoryError(Box::new(e)))?;
+    vp_host_fns::add_gas(gas_meter, gas, sentinel)?;
+    let (end, gas) = env
+        .memory
+        .read_string(end_ptr, end_len as _)
+        .map_err(|e| vp_host_fns::RuntimeError::MemoryError(Box::new(e)))?;
+    vp_host_fns::add_gas(gas_meter, gas, sentinel)?;
+
+    tracing::debug!("vp_iter_range_post {}..{}", start, end);
+
+    let start = Key::parse(start)
+        .map_err(vp_host_fns::RuntimeError::StorageDataError)?;
+    let end =
+        Key::parse(end).map_err(vp_host_fns::RuntimeError::StorageDataError)?;
+
+    let write_log = unsafe { env.ctx.write_log.get() };
+    let storage = unsafe { env.ctx.storage.get() };
+    let iter = vp_host_fns::iter_range_post(
+        gas_meter, write_log, storage, &start, &end, sentinel,
+    )?;
+
+    let iterators = unsafe { env.ctx.iterators.get() };
+    Ok(iterators.insert(iter).id())
+}
+
 /// Storage prefix iterator for prior or posterior state function
 /// exposed to the wasm VM VP environment.
 ///
@@ -2028,6 +2282,101 @@ where
     }
 }
 
+/// Hash the given data with a native implementation of the hashing algorithm
+/// and write the 32 bytes digest to the result pointer. This function is
+/// exposed to the wasm VM Tx environment.
+pub fn tx_hash_bytes<MEM, DB, H, CA>(
+    env: &TxVmEnv<MEM, DB, H, CA>,
+    algorithm: u8,
+    data_ptr: u64,
+    data_len: u64,
+    result_ptr: u64,
+) -> TxResult<()>
+where
+    MEM: VmMemory,
+    DB: namada_state::DB + for<'iter> namada_state::DBIter<'iter>,
+    H: StorageHasher,
+    CA: WasmCacheAccess,
+{
+    let algorithm = HostHashAlgorithm::from_u8(algorithm)
+        .ok_or(TxRuntimeError::UnknownHashAlgorithm(algorithm))?;
+    // Charge for the hashing before doing any work on the data
+    tx_charge_gas(env, native_hash_gas(data_len))?;
+    let (data, gas) = env
+        .memory
+        .read_bytes(data_ptr, data_len as _)
+        .map_err(|e| TxRuntimeError::MemoryError(Box::new(e)))?;
+    tx_charge_gas(env, gas)?;
+
+    let digest = native_hash(algorithm, &data);
+    let gas = env
+        .memory
+        .write_bytes(result_ptr, digest)
+        .map_err(|e| TxRuntimeError::MemoryError(Box::new(e)))?;
+    tx_charge_gas(env, gas)
+}
+
+/// Hash the given data with a native implementation of the hashing algorithm
+/// and write the 32 bytes digest to the result pointer. This function is
+/// exposed to the wasm VM VP environment.
+pub fn vp_hash_bytes<MEM, DB, H, EVAL, CA>(
+    env: &VpVmEnv<MEM, DB, H, EVAL, CA>,
+    algorithm: u8,
+    data_ptr: u64,
+    data_len: u64,
+    result_ptr: u64,
+) -> vp_host_fns::EnvResult<()>
+where
+    MEM: VmMemory,
+    DB: namada_state::DB + for<'iter> namada_state::DBIter<'iter>,
+    H: StorageHasher,
+    EVAL: VpEvaluator,
+    CA: WasmCacheAccess,
+{
+    let algorithm = HostHashAlgorithm::from_u8(algorithm)
+        .ok_or(vp_host_fns::RuntimeError::UnknownHashAlgorithm(algorithm))?;
+    let gas_meter = unsafe { env.ctx.gas_meter.get() };
+    let sentinel = unsafe { env.ctx.sentinel.get() };
+    // Charge for the hashing before doing any work on the data
+    vp_host_fns::add_gas(gas_meter, native_hash_gas(data_len), sentinel)?;
+    let (data, gas) = env
+        .memory
+        .read_bytes(data_ptr, data_len as _)
+        .map_err(|e| vp_host_fns::RuntimeError::MemoryError(Box::new(e)))?;
+    vp_host_fns::add_gas(gas_meter, gas, sentinel)?;
+
+    let digest = native_hash(algorithm, &data);
+    let gas = env
+        .memory
+        .write_bytes(result_ptr, digest)
+        .map_err(|e| vp_host_fns::RuntimeError::MemoryError(Box::new(e)))?;
+    vp_host_fns::add_gas(gas_meter, gas, sentinel)
+}
+
+/// The gas cost of hashing data of the given length with a native host
+/// function
+pub fn native_hash_gas(data_len: u64) -> u64 {
+    NATIVE_HASH_GAS
+        .saturating_add(data_len.saturating_mul(NATIVE_HASH_GAS_PER_BYTE))
+}
+
+/// Hash the data with the given algorithm and return the 32 bytes digest
+pub fn native_hash(algorithm: HostHashAlgorithm, data: &[u8]) -> [u8; 32] {
+    match algorithm {
+        HostHashAlgorithm::Sha256 => Hash::sha256(data).0,
+        HostHashAlgorithm::Blake2b256 => {
+            let mut digest = [0u8; 32];
+            let mut hasher = blake2b_rs::Blake2bBuilder::new(32).build();
+            hasher.update(data);
+            hasher.finalize(&mut digest);
+            digest
+        }
+        HostHashAlgorithm::Keccak256 => {
+            namada_core::types::keccak::keccak_hash(data).0
+        }
+    }
+}
+
 /// Log a string from exposed to the wasm VM Tx environment. The message will be
 /// printed at the [`tracing::Level::INFO`]. This function is for development
 /// only.
@@ -10,6 +10,7 @@ use std::fs;
 use std::marker::PhantomData;
 use std::num::NonZeroUsize;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, RwLock};
 use std::thread::sleep;
 use std::time::Duration;
@@ -33,6 +34,10 @@ pub struct Cache<N: CacheName, A: WasmCacheAccess> {
     progress: Arc<RwLock<HashMap<Hash, Compilation>>>,
     /// In-memory LRU cache of compiled modules
     in_memory: Arc<RwLock<MemoryCache>>,
+    /// The number of fetches that found a compiled module
+    hits: Arc<AtomicU64>,
+    /// The number of fetches that didn't find a compiled module
+    misses: Arc<AtomicU64>,
     /// The cache's name
     name: PhantomData<N>,
     /// Cache access level
@@ -87,6 +92,8 @@ impl<N: CacheName, A: WasmCacheAccess> Cache<N, A> {
             dir,
             progress: Default::default(),
             in_memory,
+            hits: Default::default(),
+            misses: Default::default(),
             name: Default::default(),
             access: Default::default(),
         }
@@ -100,11 +107,29 @@ impl<N: CacheName, A: WasmCacheAccess> Cache<N, A> {
         &mut self,
         code_hash: &Hash,
     ) -> Result<Option<(Module, Store)>, wasm::run::Error> {
-        if A::is_read_write() {
+        let res = if A::is_read_write() {
             self.get(code_hash)
         } else {
             self.peek(code_hash)
+        };
+        if let Ok(found) = &res {
+            let counter = if found.is_some() {
+                &self.hits
+            } else {
+                &self.misses
+            };
+            counter.fetch_add(1, Ordering::Relaxed);
         }
+        res
+    }
+
+    /// Get the number of fetches that found and that didn't find a compiled
+    /// module, in this order
+    pub fn get_hits_and_misses(&self) -> (u64, u64) {
+        (
+            self.hits.load(Ordering::Relaxed),
+            self.misses.load(Ordering::Relaxed),
+        )
     }
 
     /// Get the current number of items in the cache
@@ -460,6 +485,8 @@ impl<N: CacheName, A: WasmCacheAccess> Cache<N, A> {
             dir: self.dir.clone(),
             progress: self.progress.clone(),
             in_memory: self.in_memory.clone(),
+            hits: self.hits.clone(),
+            misses: self.misses.clone(),
             name: Default::default(),
             access: Default::default(),
         }
@@ -989,6 +1016,29 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_fetch_hits_and_misses() {
+        let vp_always_true = load_wasm(TestWasms::VpAlwaysTrue.path());
+        let (mut cache, _) = testing::cache::<TestCache>();
+
+        // The module hasn't been compiled yet
+        let fetched = cache.fetch(&vp_always_true.hash).unwrap();
+        assert_matches!(fetched, None);
+        assert_eq!(cache.get_hits_and_misses(), (0, 1));
+
+        // Pre-compile it and fetch it to wait for the compilation to finish
+        cache.pre_compile(&vp_always_true.code);
+        let fetched = cache.fetch(&vp_always_true.hash).unwrap();
+        assert_matches!(fetched, Some(_));
+        assert_eq!(cache.get_hits_and_misses(), (1, 1));
+
+        // The counters are shared with read-only handles
+        let mut read_only = cache.read_only();
+        let fetched = read_only.fetch(&vp_always_true.hash).unwrap();
+        assert_matches!(fetched, Some(_));
+        assert_eq!(cache.get_hits_and_misses(), (2, 1));
+    }
+
     #[test]
     fn test_pre_compile_invalid_wasm() {
         // Some random bytes
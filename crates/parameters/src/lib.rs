@@ -64,6 +64,7 @@ where
         minimum_gas_price,
         fee_unshielding_gas_limit,
         fee_unshielding_descriptions_limit,
+        storage_rent_per_byte,
     } = parameters;
 
     // write max tx bytes parameter
@@ -147,6 +148,9 @@ where
     let gas_cost_key = storage::get_gas_cost_key();
     storage.write(&gas_cost_key, minimum_gas_price)?;
 
+    let storage_rent_per_byte_key = storage::get_storage_rent_per_byte_key();
+    storage.write(&storage_rent_per_byte_key, storage_rent_per_byte)?;
+
     Ok(())
 }
 
@@ -161,6 +165,18 @@ where
     storage.read(&key)
 }
 
+/// Read the storage rent per byte parameter. It defaults to zero when it's
+/// not in storage, e.g. on a chain started before the parameter was added.
+pub fn read_storage_rent_per_byte<S>(
+    storage: &S,
+) -> namada_storage::Result<token::Amount>
+where
+    S: StorageRead,
+{
+    let key = storage::get_storage_rent_per_byte_key();
+    Ok(storage.read(&key)?.unwrap_or_default())
+}
+
 /// Update the max_expected_time_per_block parameter in storage. Returns the
 /// parameters and gas cost.
 pub fn update_max_expected_time_per_block_parameter<S>(
@@ -437,6 +453,9 @@ where
         .ok_or(ReadError::ParametersMissing)
         .into_storage_result()?;
 
+    // read storage rent per byte
+    let storage_rent_per_byte = read_storage_rent_per_byte(storage)?;
+
     Ok(Parameters {
         max_tx_bytes,
         epoch_duration,
@@ -453,6 +472,7 @@ where
         minimum_gas_price,
         fee_unshielding_gas_limit,
         fee_unshielding_descriptions_limit,
+        storage_rent_per_byte,
     })
 }
 
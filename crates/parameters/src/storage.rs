@@ -45,6 +45,7 @@ struct Keys {
     fee_unshielding_gas_limit: &'static str,
     fee_unshielding_descriptions_limit: &'static str,
     max_signatures_per_transaction: &'static str,
+    storage_rent_per_byte: &'static str,
 }
 
 /// Returns if the key is a parameter key.
@@ -190,6 +191,53 @@ pub fn get_max_signatures_per_transaction_key() -> Key {
     get_max_signatures_per_transaction_key_at_addr(ADDRESS)
 }
 
+/// Storage key used for the storage rent per byte parameter
+pub fn get_storage_rent_per_byte_key() -> Key {
+    get_storage_rent_per_byte_key_at_addr(ADDRESS)
+}
+
+/// Sub-key of the account storage sizes. These aren't protocol parameters,
+/// they're kept up to date by the protocol, so that no tx may write them.
+const ACCOUNT_STORAGE_SIZE: &str = "account_storage_size";
+
+/// Sub-key of the storage rent charge in progress
+const STORAGE_RENT_CURSOR: &str = "storage_rent_cursor";
+
+/// Storage key prefix of the account storage sizes
+pub fn get_account_storage_size_prefix() -> Key {
+    Key::from(ADDRESS.to_db_key())
+        .push(&ACCOUNT_STORAGE_SIZE.to_owned())
+        .expect("Cannot obtain a storage key")
+}
+
+/// Storage key of the total size in bytes of the values stored in the
+/// subspace of the given account
+pub fn get_account_storage_size_key(owner: &Address) -> Key {
+    get_account_storage_size_prefix()
+        .push(owner)
+        .expect("Cannot obtain a storage key")
+}
+
+/// Returns the owner of the account if the key is an account storage size
+/// key.
+pub fn is_account_storage_size_key(key: &Key) -> Option<&Address> {
+    match &key.segments[..] {
+        [DbKeySeg::AddressSeg(addr), segment, DbKeySeg::AddressSeg(owner)]
+            if addr == &ADDRESS && segment.raw() == ACCOUNT_STORAGE_SIZE =>
+        {
+            Some(owner)
+        }
+        _ => None,
+    }
+}
+
+/// Storage key of the storage rent charge in progress, if any
+pub fn get_storage_rent_cursor_key() -> Key {
+    Key::from(ADDRESS.to_db_key())
+        .push(&STORAGE_RENT_CURSOR.to_owned())
+        .expect("Cannot obtain a storage key")
+}
+
 /// Helper function to retrieve the `max_block_gas` protocol parameter from
 /// storage
 pub fn get_max_block_gas(
@@ -11,7 +11,7 @@ use namada_core::types::ethereum_events::EthAddress;
 use namada_core::types::keccak::KeccakHash;
 use namada_core::types::key::{common, SchemeType};
 use namada_core::types::masp::PaymentAddress;
-use namada_core::types::storage::Epoch;
+use namada_core::types::storage::{BlockHeight, Epoch};
 use namada_core::types::time::DateTimeUtc;
 use namada_core::types::{storage, token};
 use namada_governance::cli::onchain::{
@@ -23,6 +23,7 @@ use serde::{Deserialize, Serialize};
 use zeroize::Zeroizing;
 
 use crate::eth_bridge::bridge_pool;
+use crate::events::EventType;
 use crate::ibc::core::host::types::identifiers::{ChannelId, PortId};
 use crate::signing::SigningTxData;
 use crate::{rpc, tx, Namada};
@@ -1267,6 +1268,17 @@ pub struct QueryAccount<C: NamadaTypes = SdkTypes> {
     pub owner: C::Address,
 }
 
+/// Watch the events of an address
+#[derive(Clone, Debug)]
+pub struct WatchAddress<C: NamadaTypes = SdkTypes> {
+    /// Common query args
+    pub query: Query<C>,
+    /// The address whose events are watched
+    pub address: C::Address,
+    /// Only watch the events of the given type
+    pub event_type: Option<EventType>,
+}
+
 /// Query token balance(s)
 #[derive(Clone, Debug)]
 pub struct QueryBalance<C: NamadaTypes = SdkTypes> {
@@ -1871,6 +1883,21 @@ pub struct QueryFindValidator<C: NamadaTypes = SdkTypes> {
 pub struct QueryRawBytes<C: NamadaTypes = SdkTypes> {
     /// The storage key to query
     pub storage_key: storage::Key,
+    /// The block height at which to query, defaults to the last block
+    pub height: Option<BlockHeight>,
+    /// Common query args
+    pub query: Query<C>,
+}
+
+/// Query the value of given storage key, decoded as the given type
+#[derive(Clone, Debug)]
+pub struct QueryDecode<C: NamadaTypes = SdkTypes> {
+    /// The storage key to query
+    pub storage_key: storage::Key,
+    /// The name of the type to decode the value as
+    pub value_type: String,
+    /// The block height at which to query, defaults to the last block
+    pub height: Option<BlockHeight>,
     /// Common query args
     pub query: Query<C>,
 }
@@ -1890,6 +1917,9 @@ pub struct Tx<C: NamadaTypes = SdkTypes> {
     pub force: bool,
     /// Do not wait for the transaction to be added to the blockchain
     pub broadcast_only: bool,
+    /// Wait for the results of the transaction with a subscription to the
+    /// events of the node instead of polling the node
+    pub wait: bool,
     /// The address of the ledger node as host:port
     pub ledger_address: C::ConfigRpcTendermintAddress,
     /// If any new account is initialized by the tx, use the given alias to
@@ -1968,6 +1998,11 @@ pub trait TxBuilder<C: NamadaTypes>: Sized {
             ..x
         })
     }
+    /// Wait for the results of the transaction with a subscription to the
+    /// events of the node instead of polling the node
+    fn wait(self, wait: bool) -> Self {
+        self.tx(|x| Tx { wait, ..x })
+    }
     /// The address of the ledger node as host:port
     fn ledger_address(self, ledger_address: C::TendermintAddress) -> Self {
         self.tx(|x| Tx {
@@ -75,6 +75,9 @@ pub enum EventError {
     /// Missing value in attributes.
     #[error("Attributes missing value: {0}")]
     MissingValue(String),
+    /// The subscription to the events of a node failed
+    #[error("The subscription to the node's events failed: {0}")]
+    Subscription(String),
 }
 
 /// Errors that deal with querying some kind of data
@@ -92,6 +95,9 @@ pub enum QueryError {
     /// Wasm querying failure
     #[error("Wasm code path {0} does not exist on chain")]
     Wasm(String),
+    /// The proof of the queried value couldn't be verified
+    #[error("Invalid proof of the queried value: {0}")]
+    InvalidProof(String),
     /// The queried node is outdated, and is in the process of
     /// synchronizing with the network.
     #[error("Node is still catching up with the network")]
@@ -3,10 +3,14 @@
 //! The log can only hold `N` events at a time, where `N` is a configurable
 //! parameter. If the log is holding `N` events, and a new event is logged,
 //! old events are pruned.
+//!
+//! The logged events are also published to the local subscribers of the log,
+//! if any.
 
 use std::default::Default;
 
 use circular_queue::CircularQueue;
+use tokio::sync::broadcast;
 
 use crate::events::Event;
 
@@ -20,6 +24,10 @@ pub struct Params {
     /// If the number of events in the log exceeds this value, the log
     /// will be pruned.
     pub max_log_events: usize,
+    /// The number of events that are buffered for the local subscribers.
+    ///
+    /// A subscriber that falls behind by more events misses the oldest ones.
+    pub subscription_capacity: usize,
 }
 
 impl Default for Params {
@@ -27,6 +35,7 @@ impl Default for Params {
         // TODO: tune the default params
         Self {
             max_log_events: 50000,
+            subscription_capacity: 1024,
         }
     }
 }
@@ -36,6 +45,7 @@ impl Default for Params {
 #[derive(Debug)]
 pub struct EventLog {
     queue: CircularQueue<Event>,
+    subscribers: broadcast::Sender<Event>,
 }
 
 impl Default for EventLog {
@@ -47,11 +57,18 @@ impl Default for EventLog {
 impl EventLog {
     /// Return a new event log.
     pub fn new(params: Params) -> Self {
+        let (subscribers, _) = broadcast::channel(params.subscription_capacity);
         Self {
             queue: CircularQueue::with_capacity(params.max_log_events),
+            subscribers,
         }
     }
 
+    /// Subscribe to the events logged from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.subscribers.subscribe()
+    }
+
     /// Log a new batch of events into the event log.
     pub fn log_events<E>(&mut self, events: E)
     where
@@ -59,6 +76,10 @@ impl EventLog {
     {
         let mut num_entries = 0;
         for event in events.into_iter() {
+            if self.subscribers.receiver_count() > 0 {
+                // Sending only fails when all the receivers were dropped
+                let _ = self.subscribers.send(event.clone());
+            }
             self.queue.push(event);
             num_entries += 1;
         }
@@ -164,6 +185,7 @@ mod tests {
 
         let mut log = EventLog::new(Params {
             max_log_events: LOG_CAP,
+            ..Default::default()
         });
 
         // completely fill the log with events
@@ -201,4 +223,26 @@ mod tests {
             assert_eq!(events[0], event);
         }
     }
+
+    /// Test that the subscribers receive the events logged after they
+    /// subscribed.
+    #[test]
+    fn test_log_subscribe() {
+        let mut log = EventLog::new(Params::default());
+        let events = mock_tx_events(HASH);
+
+        // no subscribers yet
+        log.log_events(events.clone());
+
+        let mut subscriber = log.subscribe();
+        log.log_events(Some(events[1].clone()));
+
+        assert_eq!(subscriber.try_recv().unwrap(), events[1]);
+        assert!(subscriber.try_recv().is_err());
+
+        // a dropped subscriber doesn't prevent logging
+        drop(subscriber);
+        log.log_events(events);
+        assert_eq!(log.iter().count(), 5);
+    }
 }
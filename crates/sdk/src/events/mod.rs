@@ -1,5 +1,7 @@
 //! Logic to do with events emitted by the ledger.
 pub mod log;
+#[cfg(feature = "websocket-client")]
+pub mod subscription;
 
 use std::collections::HashMap;
 use std::convert::TryFrom;
@@ -8,8 +10,10 @@ use std::ops::{Index, IndexMut};
 use std::str::FromStr;
 
 use borsh::{BorshDeserialize, BorshSerialize};
+use namada_core::types::address::Address;
 use namada_core::types::ethereum_structs::{BpTransferStatus, EthBridgeEvent};
 use namada_core::types::ibc::IbcEvent;
+use namada_core::types::storage::Epoch;
 use namada_tx::data::TxType;
 use serde_json::Value;
 
@@ -86,6 +90,12 @@ pub enum EventType {
     PgfPayment,
     /// Ethereum Bridge event
     EthereumBridge,
+    /// An account was initialized by an applied transaction
+    InitAccount,
+    /// A token balance was changed by an applied transaction
+    BalanceChange,
+    /// A new epoch has started
+    NewEpoch,
 }
 
 impl Display for EventType {
@@ -97,6 +107,9 @@ impl Display for EventType {
             EventType::Proposal => write!(f, "proposal"),
             EventType::PgfPayment => write!(f, "pgf_payment"),
             EventType::EthereumBridge => write!(f, "ethereum_bridge"),
+            EventType::InitAccount => write!(f, "init_account"),
+            EventType::BalanceChange => write!(f, "balance_change"),
+            EventType::NewEpoch => write!(f, "new_epoch"),
         }?;
         Ok(())
     }
@@ -118,6 +131,9 @@ impl FromStr for EventType {
                 Ok(EventType::Ibc("write_acknowledgement".to_string()))
             }
             "ethereum_bridge" => Ok(EventType::EthereumBridge),
+            "init_account" => Ok(EventType::InitAccount),
+            "balance_change" => Ok(EventType::BalanceChange),
+            "new_epoch" => Ok(EventType::NewEpoch),
             _ => Err(EventError::InvalidEventType),
         }
     }
@@ -166,6 +182,56 @@ impl Event {
         event
     }
 
+    /// Creates a new event for an account initialized by the applied
+    /// transaction with the given hash
+    pub fn new_init_account_event(
+        tx_hash: &str,
+        height: u64,
+        address: &Address,
+    ) -> Self {
+        let mut event = Event {
+            event_type: EventType::InitAccount,
+            level: EventLevel::Tx,
+            attributes: HashMap::new(),
+        };
+        event["hash"] = tx_hash.to_string();
+        event["height"] = height.to_string();
+        event["address"] = address.to_string();
+        event
+    }
+
+    /// Creates a new event for a change of the owner's balance of the token
+    /// by the applied transaction with the given hash
+    pub fn new_balance_change_event(
+        tx_hash: &str,
+        height: u64,
+        token: &Address,
+        owner: &Address,
+    ) -> Self {
+        let mut event = Event {
+            event_type: EventType::BalanceChange,
+            level: EventLevel::Tx,
+            attributes: HashMap::new(),
+        };
+        event["hash"] = tx_hash.to_string();
+        event["height"] = height.to_string();
+        event["token"] = token.to_string();
+        event["owner"] = owner.to_string();
+        event
+    }
+
+    /// Creates a new event for the start of the epoch at the given height
+    pub fn new_epoch_event(epoch: Epoch, height: u64) -> Self {
+        let mut event = Event {
+            event_type: EventType::NewEpoch,
+            level: EventLevel::Block,
+            attributes: HashMap::new(),
+        };
+        event["epoch"] = epoch.to_string();
+        event["height"] = height.to_string();
+        event
+    }
+
     /// Check if the events keys contains a given string
     pub fn contains_key(&self, key: &str) -> bool {
         self.attributes.contains_key(key)
@@ -223,6 +289,33 @@ impl From<Event> for crate::tendermint_proto::v0_37::abci::Event {
     }
 }
 
+/// Convert an event received from tendermint into our custom event. The
+/// events with a tx hash are considered to be tx level events.
+impl TryFrom<&crate::tendermint::abci::Event> for Event {
+    type Error = EventError;
+
+    fn try_from(
+        event: &crate::tendermint::abci::Event,
+    ) -> Result<Self, Self::Error> {
+        let event_type = EventType::from_str(&event.kind)?;
+        let attributes: HashMap<String, String> = event
+            .attributes
+            .iter()
+            .map(|attr| (attr.key.clone(), attr.value.clone()))
+            .collect();
+        let level = if attributes.contains_key("hash") {
+            EventLevel::Tx
+        } else {
+            EventLevel::Block
+        };
+        Ok(Self {
+            event_type,
+            level,
+            attributes,
+        })
+    }
+}
+
 /// A thin wrapper around a HashMap for parsing event JSONs
 /// returned in tendermint subscription responses.
 #[derive(Debug)]
@@ -0,0 +1,216 @@
+//! Subscription to the events emitted by a node, over a WebSocket connection
+//! to its CometBFT RPC. The clients are notified of the events of every new
+//! block as soon as it's committed, instead of polling the node for them.
+
+use std::collections::VecDeque;
+
+use futures::StreamExt;
+use namada_core::types::address::Address;
+use namada_core::types::storage::BlockHeight;
+use tendermint_config::net::Address as TendermintAddress;
+
+use crate::control_flow::time;
+use crate::error::{Error, EventError};
+use crate::events::{Event, EventType};
+use crate::tendermint_rpc::event::EventData;
+use crate::tendermint_rpc::query::EventType as TmEventType;
+use crate::tendermint_rpc::{
+    Subscription, SubscriptionClient, WebSocketClient,
+};
+
+/// A filter of the events received from a subscription
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EventFilter {
+    /// The events of the tx with the given hash
+    TxHash(String),
+    /// The events with an attribute set to the given address
+    Address(Address),
+    /// The events of the given type
+    EventType(EventType),
+}
+
+impl EventFilter {
+    /// Check if the event passes this filter
+    pub fn matches(&self, event: &Event) -> bool {
+        match self {
+            EventFilter::TxHash(hash) => event
+                .get("hash")
+                .map(|event_hash| event_hash.eq_ignore_ascii_case(hash))
+                .unwrap_or_default(),
+            EventFilter::Address(address) => {
+                let address = address.to_string();
+                event.attributes.values().any(|value| *value == address)
+            }
+            EventFilter::EventType(event_type) => {
+                event.event_type == *event_type
+            }
+        }
+    }
+}
+
+/// Check if the event passes all the given filters
+pub fn matches_all(filters: &[EventFilter], event: &Event) -> bool {
+    filters.iter().all(|filter| filter.matches(event))
+}
+
+/// A subscription to the events of the new blocks of a node
+pub struct EventSubscription {
+    client: WebSocketClient,
+    subscription: Subscription,
+    /// The events received but not yet returned, with their block height
+    pending: VecDeque<(BlockHeight, Event)>,
+}
+
+impl EventSubscription {
+    /// Connect to the WebSocket endpoint of the node at the given address
+    /// and subscribe to its new blocks
+    pub async fn connect(address: &TendermintAddress) -> Result<Self, Error> {
+        let (client, driver) = WebSocketClient::new(address.clone())
+            .await
+            .map_err(subscription_error)?;
+        tokio::spawn(async move {
+            if let Err(err) = driver.run().await {
+                tracing::debug!(%err, "The WebSocket client driver failed");
+            }
+        });
+        let subscription = client
+            .subscribe(TmEventType::NewBlock.into())
+            .await
+            .map_err(subscription_error)?;
+        Ok(Self {
+            client,
+            subscription,
+            pending: VecDeque::new(),
+        })
+    }
+
+    /// Wait for the next new block and return its height and the events
+    /// emitted in it. Returns `None` once the subscription is closed.
+    pub async fn next_block(
+        &mut self,
+    ) -> Result<Option<(BlockHeight, Vec<Event>)>, Error> {
+        while let Some(event) = self.subscription.next().await {
+            let event = event.map_err(subscription_error)?;
+            if let EventData::NewBlock {
+                block,
+                result_end_block,
+                ..
+            } = event.data
+            {
+                let height = block
+                    .as_ref()
+                    .map(|block| BlockHeight(block.header.height.value()))
+                    .unwrap_or_default();
+                // The events of Namada that can't be parsed are skipped
+                let events = result_end_block
+                    .map(|result| result.events)
+                    .unwrap_or_default()
+                    .iter()
+                    .filter_map(|event| Event::try_from(event).ok())
+                    .collect();
+                return Ok(Some((height, events)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Wait for the next event passing all the given filters and return it
+    /// with the height of its block. Returns `None` once the subscription is
+    /// closed.
+    pub async fn next_event(
+        &mut self,
+        filters: &[EventFilter],
+    ) -> Result<Option<(BlockHeight, Event)>, Error> {
+        loop {
+            while let Some((height, event)) = self.pending.pop_front() {
+                if matches_all(filters, &event) {
+                    return Ok(Some((height, event)));
+                }
+            }
+            match self.next_block().await? {
+                Some((height, events)) => self
+                    .pending
+                    .extend(events.into_iter().map(|event| (height, event))),
+                None => return Ok(None),
+            }
+        }
+    }
+
+    /// Wait for the next event passing all the given filters until the
+    /// deadline. Returns `None` if the deadline is exceeded or the
+    /// subscription is closed.
+    pub async fn wait_for_event(
+        &mut self,
+        filters: &[EventFilter],
+        deadline: time::Instant,
+    ) -> Result<Option<(BlockHeight, Event)>, Error> {
+        tokio::time::timeout_at(deadline, self.next_event(filters))
+            .await
+            .unwrap_or(Ok(None))
+    }
+
+    /// Close the subscription and the connection to the node
+    pub fn close(self) {
+        if let Err(err) = self.client.close() {
+            tracing::debug!(%err, "Cannot close the WebSocket client");
+        }
+    }
+}
+
+fn subscription_error(err: crate::tendermint_rpc::Error) -> Error {
+    Error::Event(EventError::Subscription(err.to_string()))
+}
+
+#[cfg(test)]
+mod test_subscription {
+    use std::collections::HashMap;
+
+    use namada_core::types::address::testing::{
+        established_address_1, established_address_2,
+    };
+
+    use super::*;
+    use crate::events::EventLevel;
+
+    fn event(event_type: EventType, attributes: &[(&str, String)]) -> Event {
+        Event {
+            event_type,
+            level: EventLevel::Tx,
+            attributes: attributes
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.clone()))
+                .collect::<HashMap<_, _>>(),
+        }
+    }
+
+    /// Test filtering the events by tx hash, address and event type
+    #[test]
+    fn test_event_filters() {
+        let owner = established_address_1();
+        let applied = event(EventType::Applied, &[("hash", "AB12".into())]);
+        let balance = event(
+            EventType::BalanceChange,
+            &[("hash", "AB12".into()), ("owner", owner.to_string())],
+        );
+
+        let by_hash = EventFilter::TxHash("ab12".to_string());
+        assert!(by_hash.matches(&applied));
+        assert!(by_hash.matches(&balance));
+        assert!(!EventFilter::TxHash("CD34".to_string()).matches(&applied));
+
+        let by_address = EventFilter::Address(owner);
+        assert!(!by_address.matches(&applied));
+        assert!(by_address.matches(&balance));
+        assert!(
+            !EventFilter::Address(established_address_2()).matches(&balance)
+        );
+
+        let by_type = EventFilter::EventType(EventType::Applied);
+        assert!(by_type.matches(&applied));
+        assert!(!by_type.matches(&balance));
+
+        assert!(matches_all(&[by_hash.clone(), by_address], &balance));
+        assert!(!matches_all(&[by_hash, by_type], &balance));
+        assert!(matches_all(&[], &balance));
+    }
+}
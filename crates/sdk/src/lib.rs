@@ -127,6 +127,7 @@ pub trait Namada: Sized + MaybeSync + MaybeSend {
             output_folder: None,
             force: false,
             broadcast_only: false,
+            wait: false,
             ledger_address: tendermint_config::net::Address::from_str(
                 "127.0.0.1:26657",
             )
@@ -642,6 +643,7 @@ where
                 output_folder: None,
                 force: false,
                 broadcast_only: false,
+                wait: false,
                 ledger_address: tendermint_config::net::Address::from_str(
                     "127.0.0.1:26657",
                 )
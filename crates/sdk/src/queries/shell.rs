@@ -13,12 +13,14 @@ use namada_core::types::address::Address;
 use namada_core::types::dec::Dec;
 use namada_core::types::hash::Hash;
 use namada_core::types::storage::{
-    self, BlockHeight, BlockResults, Epoch, KeySeg, PrefixValue,
+    self, BlockHeight, BlockResults, Epoch, Header, KeySeg, PrefixValue,
+    PrefixValuesPage,
 };
 use namada_core::types::token::{Denomination, MaspDigitPos};
 use namada_core::types::uint::Uint;
-use namada_state::{DBIter, LastBlock, StorageHasher, DB};
+use namada_state::{DBIter, KeyDiff, LastBlock, StorageHasher, DB};
 use namada_storage::{self, ResultExt, StorageRead};
+use namada_tx::data::TxReceipt;
 #[cfg(any(test, feature = "async-client"))]
 use namada_tx::data::TxResult;
 
@@ -72,6 +74,11 @@ router! {SHELL,
     ( "value" / [storage_key: storage::Key] )
         -> Vec<u8> = (with_options storage_value),
 
+    // Raw storage access - read value at the given height (or the last
+    // committed height when 0)
+    ( "value_at" / [height: BlockHeight] / [storage_key: storage::Key] )
+        -> Vec<u8> = (with_options storage_value_at),
+
     // Dry run a transaction
     ( "dry_run_tx" ) -> TxResult = (with_options dry_run_tx),
 
@@ -79,6 +86,11 @@ router! {SHELL,
     ( "prefix" / [storage_key: storage::Key] )
         -> Vec<PrefixValue> = (with_options storage_prefix),
 
+    // Raw storage access - a page of at most `limit` values of a prefix
+    // iterator, resuming after the key given in the request's data, if any
+    ( "prefix_page" / [limit: u64] / [storage_key: storage::Key] )
+        -> PrefixValuesPage = (with_options storage_prefix_page),
+
     // Raw storage access - is given storage key present?
     ( "has_key" / [storage_key: storage::Key] )
         -> bool = storage_has_key,
@@ -95,12 +107,32 @@ router! {SHELL,
     // Block results access - read bit-vec
     ( "results" ) -> Vec<BlockResults> = read_results,
 
+    // Diffs of the storage keys changed in the block at the given height
+    ( "diffs" / [height: BlockHeight] ) -> Vec<KeyDiff> = read_diffs,
+
+    // Header of the committed block at the given height
+    ( "block_header" / [height: BlockHeight] ) -> Option<Header> = block_header,
+
+    // Up to `limit` heights at or above `from_height` at which the storage
+    // key was changed, indexed by archive nodes
+    ( "history" / [from_height: BlockHeight] / [limit: u64]
+        / [storage_key: storage::Key] )
+        -> Vec<BlockHeight> = key_history,
+
+    // The last height at or below the given height (or the last committed
+    // height when 0) at which the storage key was changed
+    ( "last_change" / [height: BlockHeight] / [storage_key: storage::Key] )
+        -> Option<BlockHeight> = last_change_height,
+
     // was the transaction accepted?
     ( "accepted" / [tx_hash: Hash] ) -> Option<Event> = accepted,
 
     // was the transaction applied?
     ( "applied" / [tx_hash: Hash] ) -> Option<Event> = applied,
 
+    // Receipt of the transaction applied in a committed block
+    ( "tx_receipt" / [tx_hash: Hash] ) -> Option<TxReceipt> = tx_receipt,
+
     // Query account subspace
     ( "account" / [owner: Address] ) -> Option<Account> = account,
 
@@ -333,6 +365,75 @@ where
     Ok(ctx.wl_storage.storage.block.pred_epochs.get_epoch(height))
 }
 
+/// Query to read the diffs of the storage keys changed in a block
+fn read_diffs<D, H, V, T>(
+    ctx: RequestCtx<'_, D, H, V, T>,
+    height: BlockHeight,
+) -> namada_storage::Result<Vec<KeyDiff>>
+where
+    D: 'static + DB + for<'iter> DBIter<'iter> + Sync,
+    H: 'static + StorageHasher + Sync,
+{
+    ctx.wl_storage
+        .storage
+        .read_diffs(height)
+        .into_storage_result()
+}
+
+/// Query to read the header of a committed block
+fn block_header<D, H, V, T>(
+    ctx: RequestCtx<'_, D, H, V, T>,
+    height: BlockHeight,
+) -> namada_storage::Result<Option<Header>>
+where
+    D: 'static + DB + for<'iter> DBIter<'iter> + Sync,
+    H: 'static + StorageHasher + Sync,
+{
+    ctx.wl_storage
+        .storage
+        .db
+        .read_block_header(height)
+        .into_storage_result()
+}
+
+/// Query to read a page of the heights at which the storage key was changed
+fn key_history<D, H, V, T>(
+    ctx: RequestCtx<'_, D, H, V, T>,
+    from_height: BlockHeight,
+    limit: u64,
+    storage_key: storage::Key,
+) -> namada_storage::Result<Vec<BlockHeight>>
+where
+    D: 'static + DB + for<'iter> DBIter<'iter> + Sync,
+    H: 'static + StorageHasher + Sync,
+{
+    if limit == 0 {
+        return Err(namada_storage::Error::new_const(
+            "The limit of a history page must be greater than 0",
+        ));
+    }
+    ctx.wl_storage
+        .storage
+        .key_history(&storage_key, from_height, limit as usize)
+        .into_storage_result()
+}
+
+/// Query to find the last height at which the storage key was changed
+fn last_change_height<D, H, V, T>(
+    ctx: RequestCtx<'_, D, H, V, T>,
+    height: BlockHeight,
+    storage_key: storage::Key,
+) -> namada_storage::Result<Option<BlockHeight>>
+where
+    D: 'static + DB + for<'iter> DBIter<'iter> + Sync,
+    H: 'static + StorageHasher + Sync,
+{
+    ctx.wl_storage
+        .storage
+        .last_change_height(&storage_key, height)
+        .into_storage_result()
+}
+
 fn last_block<D, H, V, T>(
     ctx: RequestCtx<'_, D, H, V, T>,
 ) -> namada_storage::Result<Option<LastBlock>>
@@ -352,13 +453,43 @@ fn storage_value<D, H, V, T>(
     request: &RequestQuery,
     storage_key: storage::Key,
 ) -> namada_storage::Result<EncodedResponseQuery>
+where
+    D: 'static + DB + for<'iter> DBIter<'iter> + Sync,
+    H: 'static + StorageHasher + Sync,
+{
+    let height = request.height.into();
+    read_storage_value(ctx, request, height, storage_key)
+}
+
+/// Query to read a value at the height in the path instead of the height of
+/// the request
+fn storage_value_at<D, H, V, T>(
+    ctx: RequestCtx<'_, D, H, V, T>,
+    request: &RequestQuery,
+    height: BlockHeight,
+    storage_key: storage::Key,
+) -> namada_storage::Result<EncodedResponseQuery>
+where
+    D: 'static + DB + for<'iter> DBIter<'iter> + Sync,
+    H: 'static + StorageHasher + Sync,
+{
+    read_storage_value(ctx, request, height, storage_key)
+}
+
+/// Read a value at the given height (or the last committed height when 0),
+/// with a proof if requested
+fn read_storage_value<D, H, V, T>(
+    ctx: RequestCtx<'_, D, H, V, T>,
+    request: &RequestQuery,
+    height: BlockHeight,
+    storage_key: storage::Key,
+) -> namada_storage::Result<EncodedResponseQuery>
 where
     D: 'static + DB + for<'iter> DBIter<'iter> + Sync,
     H: 'static + StorageHasher + Sync,
 {
     let last_committed_height = ctx.wl_storage.storage.get_last_block_height();
     let queried_height = {
-        let height: BlockHeight = request.height.into();
         let is_last_height_query = height.0 == 0;
 
         if hints::likely(is_last_height_query) {
@@ -443,6 +574,100 @@ where
         })
         .collect();
     let data = data?;
+    let proof = prefix_values_proof(&ctx, request, &data)?;
+    let data = data.serialize_to_vec();
+    Ok(EncodedResponseQuery {
+        data,
+        proof,
+        ..Default::default()
+    })
+}
+
+/// The maximum number of values in a page of a prefix iterator. A greater
+/// requested limit is lowered to this one.
+const MAX_PREFIX_PAGE_LIMIT: u64 = 1000;
+
+fn storage_prefix_page<D, H, V, T>(
+    ctx: RequestCtx<'_, D, H, V, T>,
+    request: &RequestQuery,
+    limit: u64,
+    storage_key: storage::Key,
+) -> namada_storage::Result<EncodedResponseQuery>
+where
+    D: 'static + DB + for<'iter> DBIter<'iter> + Sync,
+    H: 'static + StorageHasher + Sync,
+{
+    require_latest_height(&ctx, request)?;
+    if limit == 0 {
+        return Err(namada_storage::Error::new_const(
+            "The limit of a prefix page must be greater than 0",
+        ));
+    }
+    let limit = limit.min(MAX_PREFIX_PAGE_LIMIT) as usize;
+    let after = if request.data.is_empty() {
+        None
+    } else {
+        let after = storage::Key::try_from_slice(&request.data)
+            .into_storage_result()?;
+        if after.split_prefix(&storage_key).is_none() {
+            return Err(namada_storage::Error::new_const(
+                "The key to resume a prefix page after must have the prefix",
+            ));
+        }
+        Some(after)
+    };
+
+    // The iteration is resumed from the given key rather than from the start
+    // of the prefix. The keys with the prefix are all ordered before the
+    // prefix with `'0'` appended to it, as `'/'` < `'0'`.
+    let start = after.as_ref().unwrap_or(&storage_key);
+    let mut end = storage_key.clone();
+    if let Some(last) = end.segments.last_mut() {
+        *last = storage::DbKeySeg::StringSeg(format!("{}0", last.raw()));
+    }
+    let (iter, _gas) = namada_state::iter_range_post(
+        &ctx.wl_storage.write_log,
+        &ctx.wl_storage.storage,
+        start,
+        &end,
+    );
+    let mut iter = iter.filter_map(|(key, value, _gas)| {
+        let key = match storage::Key::parse(key).into_storage_result() {
+            Ok(key) => key,
+            Err(err) => return Some(Err(err)),
+        };
+        // Skip the key that the page is resumed after and the keys that only
+        // share the prefix's string
+        (key.split_prefix(&storage_key).is_some()
+            && Some(&key) != after.as_ref())
+        .then_some(Ok(PrefixValue { key, value }))
+    });
+    let values: namada_storage::Result<Vec<PrefixValue>> =
+        iter.by_ref().take(limit).collect();
+    let values = values?;
+    let next_after = match iter.next().transpose()? {
+        Some(_) => values.last().map(|value| value.key.clone()),
+        None => None,
+    };
+    let proof = prefix_values_proof(&ctx, request, &values)?;
+    let data = PrefixValuesPage { values, next_after }.serialize_to_vec();
+    Ok(EncodedResponseQuery {
+        data,
+        proof,
+        ..Default::default()
+    })
+}
+
+/// Build the proof of existence of the given prefix values, if requested.
+fn prefix_values_proof<D, H, V, T>(
+    ctx: &RequestCtx<'_, D, H, V, T>,
+    request: &RequestQuery,
+    values: &[PrefixValue],
+) -> namada_storage::Result<Option<ProofOps>>
+where
+    D: 'static + DB + for<'iter> DBIter<'iter> + Sync,
+    H: 'static + StorageHasher + Sync,
+{
     let proof = if request.prove {
         let queried_height = {
             let last_committed_height =
@@ -458,7 +683,7 @@ where
             }
         };
         let mut ops = vec![];
-        for PrefixValue { key, value } in &data {
+        for PrefixValue { key, value } in values {
             let mut proof = ctx
                 .wl_storage
                 .storage
@@ -472,12 +697,7 @@ where
     } else {
         None
     };
-    let data = data.serialize_to_vec();
-    Ok(EncodedResponseQuery {
-        data,
-        proof,
-        ..Default::default()
-    })
+    Ok(proof)
 }
 
 fn storage_has_key<D, H, V, T>(
@@ -526,6 +746,20 @@ where
         .cloned())
 }
 
+fn tx_receipt<D, H, V, T>(
+    ctx: RequestCtx<'_, D, H, V, T>,
+    tx_hash: Hash,
+) -> namada_storage::Result<Option<TxReceipt>>
+where
+    D: 'static + DB + for<'iter> DBIter<'iter> + Sync,
+    H: 'static + StorageHasher + Sync,
+{
+    ctx.wl_storage
+        .storage
+        .get_tx_receipt(&tx_hash)
+        .into_storage_result()
+}
+
 fn ibc_client_update<D, H, V, T>(
     ctx: RequestCtx<'_, D, H, V, T>,
     client_id: ClientId,
@@ -616,6 +850,8 @@ where
 #[cfg(test)]
 mod test {
     use namada_core::types::address;
+    use namada_core::types::hash::Hash;
+    use namada_core::types::storage::BlockHeight;
     use namada_token::storage_key::balance_key;
 
     use crate::queries::RPC;
@@ -628,6 +864,10 @@ mod test {
         let token_addr = address::testing::established_address_1();
         let owner = address::testing::established_address_2();
         let key = balance_key(&token_addr, &owner);
+        let height = BlockHeight(10);
+        let path = RPC.shell().storage_value_at_path(&height, &key);
+        assert_eq!(format!("/shell/value_at/{}/{}", height, key), path);
+
         let path = RPC.shell().storage_value_path(&key);
         assert_eq!(format!("/shell/value/{}", key), path);
 
@@ -637,7 +877,26 @@ mod test {
         let path = RPC.shell().storage_prefix_path(&key);
         assert_eq!(format!("/shell/prefix/{}", key), path);
 
+        let path = RPC.shell().storage_prefix_page_path(&20, &key);
+        assert_eq!(format!("/shell/prefix_page/20/{}", key), path);
+
         let path = RPC.shell().storage_has_key_path(&key);
         assert_eq!(format!("/shell/has_key/{}", key), path);
+
+        let path = RPC.shell().read_diffs_path(&height);
+        assert_eq!(format!("/shell/diffs/{}", height), path);
+
+        let path = RPC.shell().block_header_path(&height);
+        assert_eq!(format!("/shell/block_header/{}", height), path);
+
+        let path = RPC.shell().key_history_path(&height, &20, &key);
+        assert_eq!(format!("/shell/history/{}/20/{}", height, key), path);
+
+        let path = RPC.shell().last_change_height_path(&height, &key);
+        assert_eq!(format!("/shell/last_change/{}/{}", height, key), path);
+
+        let tx_hash = Hash::sha256(b"tx");
+        let path = RPC.shell().tx_receipt_path(&tx_hash);
+        assert_eq!(format!("/shell/tx_receipt/{}", tx_hash), path);
     }
 }
@@ -881,7 +881,7 @@ mod test_ethbridge_router {
         client
             .wl_storage
             .storage
-            .commit_block(MockDBWriteBatch)
+            .commit_block(MockDBWriteBatch::default())
             .expect("Test failed");
 
         // check the response
@@ -941,7 +941,7 @@ mod test_ethbridge_router {
         client
             .wl_storage
             .storage
-            .commit_block(MockDBWriteBatch)
+            .commit_block(MockDBWriteBatch::default())
             .expect("Test failed");
 
         // check the response
@@ -994,7 +994,7 @@ mod test_ethbridge_router {
         client
             .wl_storage
             .storage
-            .commit_block(MockDBWriteBatch)
+            .commit_block(MockDBWriteBatch::default())
             .expect("Test failed");
 
         // check the response
@@ -1035,7 +1035,7 @@ mod test_ethbridge_router {
         client
             .wl_storage
             .storage
-            .commit_block(MockDBWriteBatch)
+            .commit_block(MockDBWriteBatch::default())
             .expect("Test failed");
 
         // check the response
@@ -1301,7 +1301,7 @@ mod test_ethbridge_router {
         client
             .wl_storage
             .storage
-            .commit_block(MockDBWriteBatch)
+            .commit_block(MockDBWriteBatch::default())
             .expect("Test failed");
         client.wl_storage.storage.block.height += 1;
 
@@ -1323,7 +1323,7 @@ mod test_ethbridge_router {
         client
             .wl_storage
             .storage
-            .commit_block(MockDBWriteBatch)
+            .commit_block(MockDBWriteBatch::default())
             .expect("Test failed");
         client.wl_storage.storage.block.height += 1;
 
@@ -1474,7 +1474,7 @@ mod test_ethbridge_router {
         client
             .wl_storage
             .storage
-            .commit_block(MockDBWriteBatch)
+            .commit_block(MockDBWriteBatch::default())
             .expect("Test failed");
         client.wl_storage.storage.block.height += 1;
 
@@ -1490,7 +1490,7 @@ mod test_ethbridge_router {
         client
             .wl_storage
             .storage
-            .commit_block(MockDBWriteBatch)
+            .commit_block(MockDBWriteBatch::default())
             .expect("Test failed");
         client.wl_storage.storage.block.height += 1;
         let resp = RPC
@@ -1734,7 +1734,7 @@ mod test_ethbridge_router {
             client
                 .wl_storage
                 .storage
-                .commit_block(MockDBWriteBatch)
+                .commit_block(MockDBWriteBatch::default())
                 .expect("Test failed");
         }
 
@@ -5,7 +5,7 @@ use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use borsh::{BorshDeserialize, BorshSchema, BorshSerialize};
 use namada_core::types::address::Address;
 use namada_core::types::key::common;
-use namada_core::types::storage::Epoch;
+use namada_core::types::storage::{BlockHeight, Epoch};
 use namada_core::types::token;
 use namada_proof_of_stake::parameters::PosParams;
 use namada_proof_of_stake::queries::{
@@ -32,7 +32,7 @@ use namada_proof_of_stake::types::{
 use namada_proof_of_stake::{self, bond_amount, query_reward_tokens};
 use namada_state::{DBIter, StorageHasher, DB};
 use namada_storage::collections::lazy_map;
-use namada_storage::OptionExt;
+use namada_storage::{OptionExt, ResultExt};
 
 use crate::queries::types::RequestCtx;
 
@@ -123,6 +123,9 @@ router! {POS,
     ( "validator_by_tm_addr" / [tm_addr: String] )
         -> Option<Address> = validator_by_tm_addr,
 
+    ( "block_proposer" / [height: BlockHeight] )
+        -> Option<Address> = block_proposer,
+
     ( "consensus_keys" ) -> BTreeSet<common::PublicKey> = consensus_key_set,
 
     ( "has_bonds" / [source: Address] )
@@ -657,6 +660,28 @@ where
     )
 }
 
+/// The address of the validator that proposed the block at the given height,
+/// as recorded by the PoS at that height. Fails if the state at the height has
+/// been pruned.
+fn block_proposer<D, H, V, T>(
+    ctx: RequestCtx<'_, D, H, V, T>,
+    height: BlockHeight,
+) -> namada_storage::Result<Option<Address>>
+where
+    D: 'static + DB + for<'iter> DBIter<'iter> + Sync,
+    H: 'static + StorageHasher + Sync,
+{
+    let key = namada_proof_of_stake::storage_key::last_block_proposer_key();
+    let (value, _gas) = ctx
+        .wl_storage
+        .storage
+        .read_with_height(&key, height)
+        .into_storage_result()?;
+    value
+        .map(|bytes| Address::try_from_slice(&bytes).into_storage_result())
+        .transpose()
+}
+
 /// Native validator address by looking up the Tendermint address
 fn consensus_key_set<D, H, V, T>(
     ctx: RequestCtx<'_, D, H, V, T>,
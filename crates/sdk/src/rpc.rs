@@ -6,6 +6,7 @@ use std::ops::ControlFlow;
 use std::str::FromStr;
 
 use borsh::BorshDeserialize;
+use borsh_ext::BorshSerializeExt;
 use masp_primitives::asset_type::AssetType;
 use masp_primitives::merkle_tree::MerklePath;
 use masp_primitives::sapling::Node;
@@ -14,7 +15,7 @@ use namada_core::types::address::{Address, InternalAddress};
 use namada_core::types::hash::Hash;
 use namada_core::types::key::common;
 use namada_core::types::storage::{
-    BlockHeight, BlockResults, Epoch, Key, PrefixValue,
+    BlockHeight, BlockResults, Epoch, Header, Key, PrefixValue,
 };
 use namada_core::types::token::{
     Amount, DenominatedAmount, Denomination, MaspDigitPos,
@@ -34,8 +35,9 @@ use namada_proof_of_stake::parameters::PosParams;
 use namada_proof_of_stake::types::{
     BondsAndUnbondsDetails, CommissionPair, ValidatorMetaData, ValidatorState,
 };
-use namada_state::LastBlock;
-use namada_tx::data::{ResultCode, TxResult};
+use namada_state::merkle_tree::{MerkleRoot, Proof};
+use namada_state::{KeyDiff, LastBlock, StorageHasher};
+use namada_tx::data::{ResultCode, TxReceipt, TxResult};
 use serde::Serialize;
 
 use crate::args::InputAmount;
@@ -170,6 +172,72 @@ pub async fn query_results<C: crate::queries::Client + Sync>(
     convert_response::<C, _>(RPC.shell().read_results(client).await)
 }
 
+/// Query the diffs of the storage keys changed in the block at the given
+/// height
+pub async fn query_block_diffs<C: crate::queries::Client + Sync>(
+    client: &C,
+    height: BlockHeight,
+) -> Result<Vec<KeyDiff>, error::Error> {
+    convert_response::<C, _>(RPC.shell().read_diffs(client, &height).await)
+}
+
+/// Query the header of the committed block at the given height
+pub async fn query_block_header<C: crate::queries::Client + Sync>(
+    client: &C,
+    height: BlockHeight,
+) -> Result<Option<Header>, error::Error> {
+    convert_response::<C, _>(RPC.shell().block_header(client, &height).await)
+}
+
+/// Query the receipt of the transaction with the given hash, which confirms
+/// its inclusion in a committed block and its effects
+pub async fn query_tx_receipt<C: crate::queries::Client + Sync>(
+    client: &C,
+    tx_hash: Hash,
+) -> Result<Option<TxReceipt>, error::Error> {
+    convert_response::<C, _>(RPC.shell().tx_receipt(client, &tx_hash).await)
+}
+
+/// Query the address of the validator that proposed the block at the given
+/// height
+pub async fn query_block_proposer<C: crate::queries::Client + Sync>(
+    client: &C,
+    height: BlockHeight,
+) -> Result<Option<Address>, error::Error> {
+    convert_response::<C, _>(
+        RPC.vp().pos().block_proposer(client, &height).await,
+    )
+}
+
+/// Query up to `limit` heights at or above `from_height` at which the value
+/// of the storage key was changed. The next page starts after the last
+/// returned height. The heights are only indexed by archive nodes.
+pub async fn query_key_history<C: crate::queries::Client + Sync>(
+    client: &C,
+    key: &storage::Key,
+    from_height: BlockHeight,
+    limit: u64,
+) -> Result<Vec<BlockHeight>, error::Error> {
+    convert_response::<C, _>(
+        RPC.shell()
+            .key_history(client, &from_height, &limit, key)
+            .await,
+    )
+}
+
+/// Query the last height at or below the given height (or the last committed
+/// height when 0) at which the value of the storage key was changed. The
+/// heights are only indexed by archive nodes.
+pub async fn query_last_change_height<C: crate::queries::Client + Sync>(
+    client: &C,
+    key: &storage::Key,
+    height: BlockHeight,
+) -> Result<Option<BlockHeight>, error::Error> {
+    convert_response::<C, _>(
+        RPC.shell().last_change_height(client, &height, key).await,
+    )
+}
+
 /// Query token amount of owner.
 pub async fn get_token_balance<C: crate::queries::Client + Sync>(
     client: &C,
@@ -411,6 +479,82 @@ pub async fn query_storage_value_bytes<C: crate::queries::Client + Sync>(
     })
 }
 
+/// Query a storage value with a proof, verify the proof and decode the value
+/// with [`BorshDeserialize`]. Returns `None` if the proof shows that the key
+/// is not present.
+///
+/// The proof is verified against the app hash agreed on by the consensus,
+/// which is in the header of the block after the queried height. Without a
+/// height, the value is queried at the height before the last committed
+/// block. The proof is verified with the hasher `H` of the chain's Merkle
+/// tree.
+pub async fn query_storage_value_verified<C, T, H>(
+    client: &C,
+    key: &storage::Key,
+    height: Option<BlockHeight>,
+) -> Result<Option<T>, Error>
+where
+    T: BorshDeserialize,
+    C: crate::queries::Client + Sync,
+    H: StorageHasher,
+{
+    let invalid_proof =
+        |msg: String| Error::from(QueryError::InvalidProof(msg));
+    let height = match height {
+        Some(height) => height,
+        None => query_block(client)
+            .await?
+            .filter(|last_block| last_block.height > BlockHeight::first())
+            .map(|last_block| last_block.height.prev_height())
+            .ok_or_else(|| {
+                invalid_proof(
+                    "There is no block to verify the proof against".to_string(),
+                )
+            })?,
+    };
+
+    let (value, proof) =
+        query_storage_value_bytes(client, key, Some(height), true).await?;
+    let proof = proof.ok_or_else(|| {
+        invalid_proof(format!("No proof returned for the key {key}"))
+    })?;
+    let proof =
+        Proof::try_from(proof).map_err(|err| invalid_proof(err.to_string()))?;
+    if &proof.key != key {
+        return Err(invalid_proof(format!(
+            "The proof is for the key {}, expected {key}",
+            proof.key
+        )));
+    }
+
+    let next_height = Height::try_from(height.next_height().0)
+        .map_err(|err| invalid_proof(err.to_string()))?;
+    let app_hash = client
+        .block(next_height)
+        .await
+        .map_err(|err| Error::from(QueryError::NoResponse(err.to_string())))?
+        .block
+        .header
+        .app_hash;
+    let root = MerkleRoot(app_hash.as_bytes().try_into().map_err(|_| {
+        invalid_proof(format!("Unexpected app hash {app_hash}"))
+    })?);
+    if !proof.verify::<H>(value.as_deref(), &root) {
+        return Err(invalid_proof(format!(
+            "The proof of the key {key} at height {height} doesn't match the \
+             app hash {app_hash}"
+        )));
+    }
+
+    value
+        .map(|bytes| {
+            T::try_from_slice(&bytes[..]).map_err(|err| {
+                Error::from(EncodingError::Decoding(err.to_string()))
+            })
+        })
+        .transpose()
+}
+
 /// Query a range of storage values with a matching prefix and decode them with
 /// [`BorshDeserialize`]. Returns an iterator of the storage keys paired with
 /// their associated values.
@@ -448,6 +592,40 @@ where
     })
 }
 
+/// Query a page of at most `limit` storage values with a matching prefix,
+/// resuming after the `after` key returned with the previous page, if any,
+/// and decode them with [`BorshDeserialize`]. Returns the storage keys paired
+/// with their associated values and the key to resume the next page after, if
+/// there are more values. The node may return fewer values than the `limit`.
+pub async fn query_storage_prefix_page<C: crate::queries::Client + Sync, T>(
+    client: &C,
+    key: &storage::Key,
+    after: Option<&storage::Key>,
+    limit: u64,
+) -> Result<(Vec<(storage::Key, T)>, Option<storage::Key>), Error>
+where
+    T: BorshDeserialize,
+{
+    let data = after.map(|after| after.serialize_to_vec());
+    let page = convert_response::<C, _>(
+        RPC.shell()
+            .storage_prefix_page(client, data, None, false, &limit, key)
+            .await,
+    )?
+    .data;
+    let values = page
+        .values
+        .into_iter()
+        .map(|PrefixValue { key, value }| {
+            let value = T::try_from_slice(&value[..]).map_err(|err| {
+                Error::from(EncodingError::Decoding(err.to_string()))
+            })?;
+            Ok((key, value))
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+    Ok((values, page.next_after))
+}
+
 /// Query to check if the given storage key exists.
 pub async fn query_has_storage_key<C: crate::queries::Client + Sync>(
     client: &C,
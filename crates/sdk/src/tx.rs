@@ -88,6 +88,10 @@ pub const TX_REVEAL_PK: &str = "tx_reveal_pk.wasm";
 pub const TX_UPDATE_ACCOUNT_WASM: &str = "tx_update_account.wasm";
 /// Transfer transaction WASM path
 pub const TX_TRANSFER_WASM: &str = "tx_transfer.wasm";
+/// Approve an allowance transaction WASM path
+pub const TX_APPROVE_WASM: &str = "tx_approve.wasm";
+/// Transfer from an allowance transaction WASM path
+pub const TX_TRANSFER_FROM_WASM: &str = "tx_transfer_from.wasm";
 /// IBC transaction WASM path
 pub const TX_IBC_WASM: &str = "tx_ibc.wasm";
 /// User validity predicate WASM path
@@ -236,7 +240,13 @@ pub async fn process_tx(
                 .await
                 .map(ProcessTxResponse::Broadcast)
         } else {
-            match submit_tx(context, to_broadcast).await {
+            let resp = if args.wait {
+                submit_tx_and_watch(context, &args.ledger_address, to_broadcast)
+                    .await
+            } else {
+                submit_tx(context, to_broadcast).await
+            };
+            match resp {
                 Ok(resp) => {
                     if let InnerTxResult::Success(result) =
                         resp.inner_tx_result()
@@ -425,6 +435,134 @@ pub async fn submit_tx(
     response
 }
 
+/// Submit transaction and wait for result with a subscription to the events
+/// of the node at the given address, instead of polling the node.
+#[cfg(feature = "websocket-client")]
+pub async fn submit_tx_and_watch(
+    context: &impl Namada,
+    ledger_address: &tendermint_config::net::Address,
+    to_broadcast: TxBroadcastData,
+) -> Result<TxResponse> {
+    use crate::events::subscription::EventSubscription;
+
+    let (wrapper_hash, decrypted_hash) = match &to_broadcast {
+        TxBroadcastData::Live {
+            wrapper_hash,
+            decrypted_hash,
+            ..
+        } => Ok((wrapper_hash.clone(), decrypted_hash.clone())),
+        TxBroadcastData::DryRun(tx) => {
+            Err(TxSubmitError::ExpectLiveRun(tx.clone()))
+        }
+    }?;
+
+    // Subscribe before the broadcast to not miss the events of the tx
+    let mut subscription = EventSubscription::connect(ledger_address).await?;
+    broadcast_tx(context, &to_broadcast).await?;
+
+    let deadline = time::Instant::now()
+        + time::Duration::from_secs(
+            DEFAULT_NAMADA_EVENTS_MAX_WAIT_TIME_SECONDS,
+        );
+
+    tracing::debug!(
+        transaction = ?to_broadcast,
+        ?deadline,
+        "Watching the transaction events",
+    );
+
+    let response = async {
+        let wrapper_query = rpc::TxEventQuery::Accepted(wrapper_hash.as_str());
+        let event =
+            watch_tx_event(context, &mut subscription, wrapper_query, deadline)
+                .await?;
+        let wrapper_resp = TxResponse::from_event(event);
+
+        if display_wrapper_resp_and_get_result(context, &wrapper_resp) {
+            display_line!(
+                context.io(),
+                "Waiting for inner transaction result..."
+            );
+            let decrypted_query =
+                rpc::TxEventQuery::Applied(decrypted_hash.as_str());
+            let event = watch_tx_event(
+                context,
+                &mut subscription,
+                decrypted_query,
+                deadline,
+            )
+            .await?;
+            let inner_resp = TxResponse::from_event(event);
+
+            display_inner_resp(context, &inner_resp);
+            Ok(inner_resp)
+        } else {
+            Ok(wrapper_resp)
+        }
+    }
+    .await;
+
+    subscription.close();
+    response
+}
+
+/// Without the WebSocket client, the tx results are waited for by polling
+/// the node.
+#[cfg(not(feature = "websocket-client"))]
+pub async fn submit_tx_and_watch(
+    context: &impl Namada,
+    _ledger_address: &tendermint_config::net::Address,
+    to_broadcast: TxBroadcastData,
+) -> Result<TxResponse> {
+    edisplay_line!(
+        context.io(),
+        "The subscription to the node's events is not supported by this \
+         client, polling the node for the transaction results instead."
+    );
+    submit_tx(context, to_broadcast).await
+}
+
+/// Wait for the event of the given tx query from the subscription
+#[cfg(feature = "websocket-client")]
+async fn watch_tx_event(
+    context: &impl Namada,
+    subscription: &mut crate::events::subscription::EventSubscription,
+    query: rpc::TxEventQuery<'_>,
+    deadline: time::Instant,
+) -> Result<crate::events::Event> {
+    use crate::events::subscription::EventFilter;
+    use crate::events::EventType;
+
+    let event_type = match query {
+        rpc::TxEventQuery::Accepted(_) => EventType::Accepted,
+        rpc::TxEventQuery::Applied(_) => EventType::Applied,
+    };
+    let filters = [
+        EventFilter::EventType(event_type),
+        EventFilter::TxHash(query.tx_hash().to_string()),
+    ];
+    match subscription.wait_for_event(&filters, deadline).await? {
+        Some((_height, event)) => {
+            tracing::debug!(?event, "Found tx event");
+            Ok(event)
+        }
+        None => {
+            edisplay_line!(
+                context.io(),
+                "Transaction events deadline of {deadline:?} exceeded"
+            );
+            Err(match query {
+                rpc::TxEventQuery::Accepted(_) => {
+                    Error::Tx(TxSubmitError::AcceptTimeout)
+                }
+                rpc::TxEventQuery::Applied(_) => {
+                    Error::Tx(TxSubmitError::AppliedTimeout)
+                }
+            })
+        }
+    }
+}
+
 /// Display a result of a wrapper tx.
 /// Returns true if the wrapper tx was successful.
 pub fn display_wrapper_resp_and_get_result(
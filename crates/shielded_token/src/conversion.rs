@@ -574,6 +574,7 @@ mod tests {
             fee_unshielding_gas_limit: 0,
             fee_unshielding_descriptions_limit: 0,
             minimum_gas_price: Default::default(),
+            storage_rent_per_byte: Default::default(),
         };
 
         // Initialize the state
@@ -1,10 +1,14 @@
 //! Ledger's state storage with key-value backed store and a merkle tree
 
+pub mod read_cache;
+pub mod snapshot;
+pub mod wal;
 pub mod wl_storage;
 pub mod write_log;
 
 use core::fmt::Debug;
 use std::cmp::Ordering;
+use std::collections::BTreeMap;
 use std::format;
 
 use namada_core::borsh::{BorshDeserialize, BorshSerialize, BorshSerializeExt};
@@ -23,7 +27,7 @@ pub use namada_core::types::storage::{
 };
 use namada_core::types::time::DateTimeUtc;
 pub use namada_core::types::token::ConversionState;
-use namada_core::types::{encode, ethereum_structs, storage};
+use namada_core::types::{decode, encode, ethereum_structs, storage};
 use namada_gas::{
     MEMORY_ACCESS_GAS_PER_BYTE, STORAGE_ACCESS_GAS_PER_BYTE,
     STORAGE_WRITE_GAS_PER_BYTE,
@@ -35,10 +39,19 @@ pub use namada_merkle_tree::{
 use namada_merkle_tree::{Error as MerkleTreeError, MerkleRoot};
 use namada_parameters::{self, EpochDuration, Parameters};
 pub use namada_storage::{Error as StorageError, Result as StorageResult, *};
+use namada_tx::data::TxReceipt;
+use read_cache::ReadCache;
+use snapshot::{
+    write_chunks, SnapshotBlock, SnapshotChunk, SnapshotMetadata,
+    SNAPSHOT_FORMAT,
+};
 use thiserror::Error;
 use tx_queue::{ExpiredTxsQueue, TxQueue};
+use wal::WriteAheadLog;
 pub use wl_storage::{
-    iter_prefix_post, iter_prefix_pre, PrefixIter, TempWlStorage, WlStorage,
+    iter_prefix_post, iter_prefix_pre, iter_range_post, iter_range_pre,
+    rev_iter_prefix_post, rev_iter_prefix_pre, PrefixIter, TempWlStorage,
+    WlStorage,
 };
 
 /// A result of a function that may fail
@@ -104,6 +117,17 @@ where
     pub eth_events_queue: EthEventsQueue,
     /// How many block heights in the past can the storage be queried
     pub storage_read_past_height_limit: Option<u64>,
+    /// When set, the diffs of blocks older than the given number of last
+    /// blocks are pruned on every new epoch
+    pub retain_last_n_blocks: Option<u64>,
+    /// When set, a checkpoint is saved on every new epoch and only the given
+    /// number of the most recent checkpoints is kept
+    pub checkpoints_to_keep: Option<u64>,
+    /// When set, the subspace values read from the DB are cached in memory
+    pub read_cache: Option<ReadCache>,
+    /// When set, the data of every block is recorded in this write-ahead
+    /// log before it's committed, so that it can be replayed
+    pub wal: Option<WriteAheadLog>,
     /// Static merkle tree storage key filter
     pub merkle_tree_key_filter: fn(&storage::Key) -> bool,
 }
@@ -119,6 +143,17 @@ pub struct LastBlock {
     pub time: DateTimeUtc,
 }
 
+/// The change of a subspace key-val in a block
+#[derive(Clone, Debug, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct KeyDiff {
+    /// The changed key
+    pub key: Key,
+    /// The value before the block, `None` if the key didn't exist
+    pub old_value: Option<Vec<u8>>,
+    /// The value after the block, `None` if the key was deleted
+    pub new_value: Option<Vec<u8>>,
+}
+
 /// The block storage data
 #[derive(Debug)]
 pub struct BlockStorage<H: StorageHasher> {
@@ -140,6 +175,9 @@ pub struct BlockStorage<H: StorageHasher> {
     pub epoch: Epoch,
     /// Results of applying transactions
     pub results: BlockResults,
+    /// Receipts of the transactions applied in the block, written to the DB
+    /// when the block is committed
+    pub receipts: Vec<TxReceipt>,
     /// Predecessor block epochs
     pub pred_epochs: Epochs,
 }
@@ -151,8 +189,8 @@ pub fn merklize_all_keys(_key: &storage::Key) -> bool {
 #[allow(missing_docs)]
 #[derive(Error, Debug)]
 pub enum Error {
-    #[error("TEMPORARY error: {error}")]
-    Temporary { error: String },
+    #[error("The block at the height {height} hasn't been committed yet")]
+    BlockNotCommitted { height: BlockHeight },
     #[error("Found an unknown key: {key}")]
     UnknownKey { key: String },
     #[error("Storage key error {0}")]
@@ -167,8 +205,43 @@ pub enum Error {
     BorshCodingError(std::io::Error),
     #[error("Merkle tree at the height {height} is not stored")]
     NoMerkleTree { height: BlockHeight },
+    #[error(
+        "The state at the height {height} has been pruned, the oldest \
+         readable height is {oldest_height}"
+    )]
+    PrunedHeight {
+        height: BlockHeight,
+        oldest_height: BlockHeight,
+    },
+    #[error(
+        "The diffs at the height {height} have been pruned, the oldest \
+         retained height is {oldest_height}"
+    )]
+    PrunedDiffs {
+        height: BlockHeight,
+        oldest_height: BlockHeight,
+    },
     #[error("Code hash error: {0}")]
     InvalidCodeHash(HashError),
+    #[error("Write-ahead log error: {0}")]
+    WalError(std::io::Error),
+    #[error(
+        "A snapshot can only be taken at the last committed height \
+         {last_height}, requested height {height}"
+    )]
+    SnapshotHeight {
+        height: BlockHeight,
+        last_height: BlockHeight,
+    },
+    #[error("Snapshot I/O error: {0}")]
+    SnapshotIo(std::io::Error),
+    #[error("Invalid snapshot: {0}")]
+    InvalidSnapshot(String),
+    #[error(
+        "Only the first block of an epoch can be checkpointed, the last \
+         committed block at height {height} is not"
+    )]
+    NoEpochBoundary { height: BlockHeight },
     #[error("DB error: {0}")]
     DbError(#[from] namada_storage::DbError),
 }
@@ -186,6 +259,23 @@ where
         cache: Option<&D::Cache>,
         storage_read_past_height_limit: Option<u64>,
         merkle_tree_key_filter: fn(&storage::Key) -> bool,
+    ) -> Self {
+        Self::open_with_db(
+            D::open(db_path, cache),
+            chain_id,
+            native_token,
+            storage_read_past_height_limit,
+            merkle_tree_key_filter,
+        )
+    }
+
+    /// Create a new instance of the storage with an already opened DB
+    pub fn open_with_db(
+        db: D,
+        chain_id: ChainId,
+        native_token: Address,
+        storage_read_past_height_limit: Option<u64>,
+        merkle_tree_key_filter: fn(&storage::Key) -> bool,
     ) -> Self {
         let block = BlockStorage {
             tree: MerkleTree::default(),
@@ -194,9 +284,10 @@ where
             epoch: Epoch::default(),
             pred_epochs: Epochs::default(),
             results: BlockResults::default(),
+            receipts: Vec::new(),
         };
         State::<D, H> {
-            db: D::open(db_path, cache),
+            db,
             chain_id,
             block,
             header: None,
@@ -216,6 +307,10 @@ where
             ethereum_height: None,
             eth_events_queue: EthEventsQueue::default(),
             storage_read_past_height_limit,
+            retain_last_n_blocks: None,
+            checkpoints_to_keep: None,
+            read_cache: None,
+            wal: None,
             merkle_tree_key_filter,
         }
     }
@@ -295,6 +390,8 @@ where
             }
         }
 
+        // The receipts are only written once, at the height of their block
+        let receipts = std::mem::take(&mut self.block.receipts);
         let state = BlockStateWrite {
             merkle_tree_stores: self.block.tree.stores(),
             header: self.header.as_ref(),
@@ -307,6 +404,7 @@ where
                 .time,
             epoch: self.block.epoch,
             results: &self.block.results,
+            receipts: &receipts,
             pred_epochs: &self.block.pred_epochs,
             next_epoch_min_start_height: self.next_epoch_min_start_height,
             next_epoch_min_start_time: self.next_epoch_min_start_time,
@@ -332,8 +430,25 @@ where
         if is_full_commit {
             // prune old merkle tree stores
             self.prune_merkle_tree_stores(&mut batch)?;
+            // prune the diffs of blocks that are no longer retained
+            if let Some(oldest_height) = self.get_oldest_retained_height() {
+                self.db.prune_below(&mut batch, oldest_height)?;
+            }
         }
+        self.clear_read_cache();
         self.db.exec_batch(batch)?;
+        let checkpoints_to_keep =
+            self.checkpoints_to_keep.filter(|_| is_full_commit);
+        if let Some(checkpoints_to_keep) = checkpoints_to_keep {
+            // The block is already committed, so a failed checkpoint doesn't
+            // fail the commit
+            if let Err(err) = self
+                .write_checkpoint()
+                .and_then(|_| self.prune_checkpoints(checkpoints_to_keep))
+            {
+                tracing::error!("Failed to save a checkpoint: {err}");
+            }
+        }
         Ok(())
     }
 
@@ -346,7 +461,7 @@ where
     /// gas cost.
     pub fn has_key(&self, key: &Key) -> Result<(bool, u64)> {
         Ok((
-            self.db.read_subspace_val(key)?.is_some(),
+            self.read_subspace_val(key)?.is_some(),
             key.len() as u64 * STORAGE_ACCESS_GAS_PER_BYTE,
         ))
     }
@@ -355,7 +470,7 @@ where
     pub fn read(&self, key: &Key) -> Result<(Option<Vec<u8>>, u64)> {
         tracing::debug!("storage read key {}", key);
 
-        match self.db.read_subspace_val(key)? {
+        match self.read_subspace_val(key)? {
             Some(v) => {
                 let gas =
                     (key.len() + v.len()) as u64 * STORAGE_ACCESS_GAS_PER_BYTE;
@@ -365,8 +480,24 @@ where
         }
     }
 
+    /// Returns a Borsh decoded value from the specified subspace and the gas
+    /// cost. Fails with [`Error::BorshCodingError`] if the value cannot be
+    /// decoded as `T`.
+    pub fn read_decoded<T: BorshDeserialize>(
+        &self,
+        key: &Key,
+    ) -> Result<(Option<T>, u64)> {
+        let (value, gas) = self.read(key)?;
+        let value = value
+            .map(|bytes| T::try_from_slice(&bytes))
+            .transpose()
+            .map_err(Error::BorshCodingError)?;
+        Ok((value, gas))
+    }
+
     /// Returns a value from the specified subspace at the given height (or the
-    /// last committed height when 0) and the gas cost.
+    /// last committed height when 0) and the gas cost. Returns an error if the
+    /// state at the given height has been pruned.
     pub fn read_with_height(
         &self,
         key: &Key,
@@ -376,6 +507,15 @@ where
         if height == BlockHeight(0) || height >= self.get_last_block_height() {
             self.read(key)
         } else {
+            // The values at past heights are read from the diffs
+            if let Some(oldest_height) = self.get_diffs_pruned_height()? {
+                if height < oldest_height {
+                    return Err(Error::PrunedHeight {
+                        height,
+                        oldest_height,
+                    });
+                }
+            }
             if !(self.merkle_tree_key_filter)(key) {
                 return Ok((None, 0));
             }
@@ -397,6 +537,44 @@ where
         }
     }
 
+    /// Returns up to `limit` committed heights at or above `from_height` at
+    /// which the value of the key was changed, in ascending order. The
+    /// heights are only indexed when the DB is opened with the archive
+    /// profile.
+    pub fn key_history(
+        &self,
+        key: &Key,
+        from_height: BlockHeight,
+        limit: usize,
+    ) -> Result<Vec<BlockHeight>> {
+        Ok(self.db.read_key_history(key, from_height, limit)?)
+    }
+
+    /// Returns the receipt of the committed transaction with the given hash,
+    /// if any
+    pub fn get_tx_receipt(&self, hash: &Hash) -> Result<Option<TxReceipt>> {
+        Ok(self.db.read_tx_receipt(hash)?)
+    }
+
+    /// Returns the last height at or below the given height (or the last
+    /// committed height when 0) at which the value of the key was changed.
+    /// The heights are only indexed when the DB is opened with the archive
+    /// profile.
+    pub fn last_change_height(
+        &self,
+        key: &Key,
+        height: BlockHeight,
+    ) -> Result<Option<BlockHeight>> {
+        // Only the committed heights are indexed, so the last one is found
+        // below the greatest height
+        let height = if height == BlockHeight(0) {
+            BlockHeight(u64::MAX)
+        } else {
+            height
+        };
+        Ok(self.db.read_last_change_height(key, height)?)
+    }
+
     /// WARNING: This only works for values that have been committed to DB.
     /// To be able to see values written or deleted, but not yet committed,
     /// use the `StorageWithWriteLog`.
@@ -412,11 +590,86 @@ where
         )
     }
 
+    /// WARNING: This only works for values that have been committed to DB.
+    /// To be able to see values written or deleted, but not yet committed,
+    /// use the `StorageWithWriteLog`.
+    ///
+    /// Returns a prefix iterator, in reverse order of storage keys, and the
+    /// gas cost.
+    pub fn rev_iter_prefix(
+        &self,
+        prefix: &Key,
+    ) -> (<D as DBIter<'_>>::PrefixIter, u64) {
+        (
+            self.db.rev_iter_prefix(Some(prefix)),
+            prefix.len() as u64 * STORAGE_ACCESS_GAS_PER_BYTE,
+        )
+    }
+
+    /// WARNING: This only works for values that have been committed to DB.
+    /// To be able to see values written or deleted, but not yet committed,
+    /// use the `StorageWithWriteLog`.
+    ///
+    /// Returns an iterator over the keys within the range `[start, end)`,
+    /// ordered by storage keys, and the gas cost.
+    pub fn iter_range(
+        &self,
+        start: &Key,
+        end: &Key,
+    ) -> (<D as DBIter<'_>>::PrefixIter, u64) {
+        (
+            self.db.iter_range(start, end),
+            (start.len() + end.len()) as u64 * STORAGE_ACCESS_GAS_PER_BYTE,
+        )
+    }
+
     /// Returns an iterator over the block results
     pub fn iter_results(&self) -> (<D as DBIter<'_>>::PrefixIter, u64) {
         (self.db.iter_results(), 0)
     }
 
+    /// Read the diffs of the subspace key-vals changed in the block at the
+    /// given height, ordered by the keys. The diffs of the keys that are not
+    /// merklized are only kept for the last block that changed them.
+    pub fn read_diffs(&self, height: BlockHeight) -> Result<Vec<KeyDiff>> {
+        if let Some(oldest_height) = self.get_diffs_pruned_height()? {
+            if height < oldest_height {
+                return Err(Error::PrunedDiffs {
+                    height,
+                    oldest_height,
+                });
+            }
+        }
+        let mut diffs: BTreeMap<String, (Option<Vec<u8>>, Option<Vec<u8>>)> =
+            BTreeMap::new();
+        for (key, old_value, _gas) in self.db.iter_old_diffs(height, None) {
+            diffs.entry(key).or_default().0 = Some(old_value);
+        }
+        for (key, new_value, _gas) in self.db.iter_new_diffs(height, None) {
+            diffs.entry(key).or_default().1 = Some(new_value);
+        }
+        diffs
+            .into_iter()
+            .map(|(key, (old_value, new_value))| {
+                Ok(KeyDiff {
+                    key: Key::parse(key).map_err(Error::KeyError)?,
+                    old_value,
+                    new_value,
+                })
+            })
+            .collect()
+    }
+
+    /// Write a Borsh encoded value to the specified subspace and returns the
+    /// gas cost and the size difference
+    pub fn write_encoded<T: BorshSerialize>(
+        &mut self,
+        key: &Key,
+        value: &T,
+    ) -> Result<(u64, i64)> {
+        self.write(key, value.serialize_to_vec())
+    }
+
     /// Write a value to the specified subspace and returns the gas cost and the
     /// size difference
     pub fn write(
@@ -444,6 +697,7 @@ where
 
         let len = value.len();
         let gas = (key.len() + len) as u64 * STORAGE_WRITE_GAS_PER_BYTE;
+        self.invalidate_cached(key);
         let size_diff = self.db.write_subspace_val(
             self.block.height,
             key,
@@ -464,6 +718,7 @@ where
             if is_key_merklized {
                 self.block.tree.delete(key)?;
             }
+            self.invalidate_cached(key);
             deleted_bytes_len = self.db.delete_subspace_val(
                 self.block.height,
                 key,
@@ -588,7 +843,7 @@ where
         let prefix = store_type.and_then(|st| st.provable_prefix());
         let mut tree = match store_type {
             Some(_) => MerkleTree::<H>::new_partial(stores),
-            None => MerkleTree::<H>::new(stores).expect("invalid stores"),
+            None => MerkleTree::<H>::new(stores)?,
         };
         // Restore the tree state with diffs
         let mut target_height = epoch_start_height;
@@ -697,6 +952,455 @@ where
         Ok(tree)
     }
 
+    /// Export a snapshot of the state at the given height for state sync. Only
+    /// the last committed height can be exported. The chunks of the snapshot
+    /// are written into the given writer.
+    pub fn export_snapshot(
+        &self,
+        height: BlockHeight,
+        mut writer: impl std::io::Write,
+    ) -> Result<SnapshotMetadata> {
+        let last_height = self.get_last_block_height();
+        let block = match self.db.read_last_block()? {
+            Some(block) if height == last_height => block,
+            _ => {
+                return Err(Error::SnapshotHeight {
+                    height,
+                    last_height,
+                });
+            }
+        };
+        // The tree in memory is only the committed one until the next block
+        // begins, otherwise it has to be restored from the DB
+        let restored_tree;
+        let tree = if self.block.height == height {
+            &self.block.tree
+        } else {
+            restored_tree = self.get_merkle_tree(height, None)?;
+            &restored_tree
+        };
+
+        let mut chunk_hashes = vec![];
+        let mut write_chunk = |chunk: SnapshotChunk| -> Result<()> {
+            let hash = snapshot::write_chunk(&mut writer, &chunk)
+                .map_err(Error::SnapshotIo)?;
+            chunk_hashes.push(hash);
+            Ok(())
+        };
+        write_chunk(SnapshotChunk::Block(Box::new(SnapshotBlock {
+            hash: block.hash,
+            height: block.height,
+            time: block.time,
+            epoch: block.epoch,
+            pred_epochs: block.pred_epochs,
+            results: block.results,
+            next_epoch_min_start_height: block.next_epoch_min_start_height,
+            next_epoch_min_start_time: block.next_epoch_min_start_time,
+            update_epoch_blocks_delay: block.update_epoch_blocks_delay,
+            address_gen: block.address_gen,
+            conversion_state: block.conversion_state,
+            tx_queue: block.tx_queue,
+            ethereum_height: block.ethereum_height,
+            eth_events_queue: block.eth_events_queue,
+        })))?;
+        let stores = tree.stores();
+        for store_type in StoreType::iter() {
+            write_chunk(SnapshotChunk::MerkleTreeStore {
+                store_type: *store_type,
+                root: *stores.root(store_type),
+                store: stores.store(store_type).encode(),
+            })?;
+        }
+        write_chunks(
+            self.db.iter_prefix(None).map(|(key, value, _gas)| {
+                Ok((key.len() + value.len(), (key, value)))
+            }),
+            SnapshotChunk::Subspace,
+            &mut write_chunk,
+        )?;
+        // Without the replay protection entries, the restored node would
+        // accept the txs that have already been applied
+        write_chunks(
+            self.db
+                .iter_all_replay_protection()
+                .map(|(key, _value, _gas)| Ok((key.len(), key))),
+            SnapshotChunk::ReplayProtection,
+            &mut write_chunk,
+        )?;
+        write_chunks(
+            (1..=height.0).filter_map(|height| {
+                let height = BlockHeight(height);
+                self.db
+                    .read_block_header(height)
+                    .map_err(Error::from)
+                    .transpose()
+                    .map(|header| {
+                        let header = header?;
+                        let size = header.serialize_to_vec().len();
+                        Ok((size, (height, header)))
+                    })
+            }),
+            SnapshotChunk::BlockHeaders,
+            &mut write_chunk,
+        )?;
+        write_chunks(
+            self.db.iter_results().map(|(key, value, _gas)| {
+                let height =
+                    BlockHeight::parse(key).map_err(Error::KeyError)?;
+                let size = value.len();
+                let results: BlockResults = decode(value)?;
+                Ok((size, (height, results)))
+            }),
+            SnapshotChunk::BlockResults,
+            &mut write_chunk,
+        )?;
+
+        Ok(SnapshotMetadata {
+            height,
+            format: SNAPSHOT_FORMAT,
+            chunk_hashes,
+        })
+    }
+
+    /// Import a snapshot of the state read from the given reader into an empty
+    /// storage. The Merkle tree of the snapshot must have the given root,
+    /// which is checked before any of the subspace is written. The block is
+    /// written last, so the state is only loaded on restart once the whole
+    /// snapshot has been imported.
+    pub fn import_snapshot(
+        &mut self,
+        mut reader: impl std::io::Read,
+        root: &MerkleRoot,
+    ) -> Result<()> {
+        if self.last_block.is_some() {
+            return Err(Error::InvalidSnapshot(
+                "The storage already has a committed block".to_string(),
+            ));
+        }
+        let mut read_chunk =
+            || snapshot::read_chunk(&mut reader).map_err(Error::SnapshotIo);
+
+        let block = match read_chunk()? {
+            Some(SnapshotChunk::Block(block)) => block,
+            _ => {
+                return Err(Error::InvalidSnapshot(
+                    "The block metadata is missing".to_string(),
+                ));
+            }
+        };
+        let mut stores = MerkleTreeStoresRead::default();
+        for store_type in StoreType::iter() {
+            match read_chunk()? {
+                Some(SnapshotChunk::MerkleTreeStore {
+                    store_type: chunk_store_type,
+                    root,
+                    store,
+                }) if chunk_store_type == *store_type => {
+                    stores.set_root(store_type, root);
+                    stores.set_store(store_type.decode_store(store)?);
+                }
+                _ => {
+                    return Err(Error::InvalidSnapshot(format!(
+                        "The {} Merkle tree store is missing",
+                        store_type
+                    )));
+                }
+            }
+        }
+        let tree = MerkleTree::<H>::new(stores)?;
+        if tree.root() != *root {
+            return Err(Error::InvalidSnapshot(format!(
+                "The Merkle root {} doesn't match the expected root {}",
+                tree.root(),
+                root
+            )));
+        }
+
+        self.clear_read_cache();
+        let imported = self
+            .import_subspace(&mut read_chunk, &tree, block.height)
+            .and_then(|next_chunk| {
+                self.import_block_history(
+                    next_chunk,
+                    &mut read_chunk,
+                    block.height,
+                )
+            });
+        if let Err(err) = imported {
+            // Remove the imported subspace and replay protection entries, so
+            // that the storage is left empty for another snapshot
+            self.clear_imported_state(block.height)?;
+            return Err(err);
+        }
+
+        let SnapshotBlock {
+            hash,
+            height,
+            time,
+            epoch,
+            pred_epochs,
+            results,
+            next_epoch_min_start_height,
+            next_epoch_min_start_time,
+            update_epoch_blocks_delay,
+            address_gen,
+            conversion_state,
+            tx_queue,
+            ethereum_height,
+            eth_events_queue,
+        } = *block;
+        self.block.tree = tree;
+        self.block.hash = hash.clone();
+        self.block.height = height;
+        self.block.epoch = epoch;
+        self.block.results = results;
+        self.block.pred_epochs = pred_epochs;
+        self.last_block = Some(LastBlock { height, hash, time });
+        self.last_epoch = epoch;
+        self.next_epoch_min_start_height = next_epoch_min_start_height;
+        self.next_epoch_min_start_time = next_epoch_min_start_time;
+        self.update_epoch_blocks_delay = update_epoch_blocks_delay;
+        self.address_gen = address_gen;
+        self.conversion_state = conversion_state;
+        self.tx_queue = tx_queue;
+        self.ethereum_height = ethereum_height;
+        self.eth_events_queue = eth_events_queue;
+
+        let mut batch = D::batch();
+        let state = BlockStateWrite {
+            merkle_tree_stores: self.block.tree.stores(),
+            header: None,
+            hash: &self.block.hash,
+            height,
+            time,
+            epoch,
+            results: &self.block.results,
+            receipts: &[],
+            pred_epochs: &self.block.pred_epochs,
+            next_epoch_min_start_height,
+            next_epoch_min_start_time,
+            update_epoch_blocks_delay,
+            address_gen: &self.address_gen,
+            conversion_state: &self.conversion_state,
+            tx_queue: &self.tx_queue,
+            ethereum_height: self.ethereum_height.as_ref(),
+            eth_events_queue: &self.eth_events_queue,
+        };
+        self.db.add_block_to_batch(state, &mut batch, true)?;
+        // The diffs of the snapshotted block and of the ones before it aren't
+        // in the snapshot, so the state can't be read at their heights
+        self.db.prune_below(&mut batch, height.next_height())?;
+        self.db.exec_batch(batch)?;
+        Ok(())
+    }
+
+    /// Import the subspace chunks of a snapshot, verifying them against its
+    /// Merkle tree. Every merklized key must be in the tree and the tree
+    /// rebuilt from the imported subspace must have the same root, so that
+    /// none of the values differ and none of the leaves are missing. Returns
+    /// the chunk that follows the subspace chunks, if any.
+    fn import_subspace(
+        &mut self,
+        read_chunk: &mut impl FnMut() -> Result<Option<SnapshotChunk>>,
+        tree: &MerkleTree<H>,
+        height: BlockHeight,
+    ) -> Result<Option<SnapshotChunk>> {
+        let mut rebuilt_tree = MerkleTree::<H>::default();
+        let next_chunk = loop {
+            let key_vals = match read_chunk()? {
+                Some(SnapshotChunk::Subspace(key_vals)) => key_vals,
+                next_chunk => break next_chunk,
+            };
+            let mut batch = D::batch();
+            for (key, value) in key_vals {
+                let key = Key::parse(key).map_err(Error::KeyError)?;
+                if (self.merkle_tree_key_filter)(&key) {
+                    if !tree.has_key(&key)? {
+                        return Err(Error::InvalidSnapshot(format!(
+                            "The key {key} isn't in the Merkle tree"
+                        )));
+                    }
+                    if is_pending_transfer_key(&key) {
+                        // The tree of the bridge pool stores the height of
+                        // the pending transfer, which isn't in the subspace
+                        rebuilt_tree.update(&key, tree.get(&key)?)?;
+                    } else {
+                        rebuilt_tree.update(&key, &value)?;
+                    }
+                }
+                self.db.batch_write_subspace_val(
+                    &mut batch, height, &key, value, false,
+                )?;
+            }
+            self.db.exec_batch(batch)?;
+        };
+        if rebuilt_tree.root() != tree.root() {
+            return Err(Error::InvalidSnapshot(
+                "The subspace doesn't match the Merkle tree".to_string(),
+            ));
+        }
+        Ok(next_chunk)
+    }
+
+    /// Import the replay protection entries and the headers and results of
+    /// the blocks of a snapshot, starting from the given chunk. They are all
+    /// written before the snapshotted block, so that the node never runs
+    /// without them.
+    fn import_block_history(
+        &mut self,
+        mut next_chunk: Option<SnapshotChunk>,
+        read_chunk: &mut impl FnMut() -> Result<Option<SnapshotChunk>>,
+        height: BlockHeight,
+    ) -> Result<()> {
+        let check_height = |block_height: BlockHeight| {
+            if block_height > height {
+                return Err(Error::InvalidSnapshot(format!(
+                    "The block at the height {block_height} is above the \
+                     snapshotted height {height}"
+                )));
+            }
+            Ok(())
+        };
+        while let Some(chunk) = next_chunk {
+            let mut batch = D::batch();
+            match chunk {
+                SnapshotChunk::ReplayProtection(keys) => {
+                    for key in keys {
+                        let key = Key::parse(key).map_err(Error::KeyError)?;
+                        self.db
+                            .write_replay_protection_entry(&mut batch, &key)?;
+                    }
+                }
+                SnapshotChunk::BlockHeaders(headers) => {
+                    for (block_height, header) in headers {
+                        check_height(block_height)?;
+                        self.db.write_block_header(
+                            &mut batch,
+                            block_height,
+                            &header,
+                        )?;
+                    }
+                }
+                SnapshotChunk::BlockResults(results) => {
+                    for (block_height, results) in results {
+                        check_height(block_height)?;
+                        self.db.write_block_results(
+                            &mut batch,
+                            block_height,
+                            &results,
+                        )?;
+                    }
+                }
+                _ => {
+                    return Err(Error::InvalidSnapshot(
+                        "Expected a replay protection or a block history chunk"
+                            .to_string(),
+                    ));
+                }
+            }
+            self.db.exec_batch(batch)?;
+            next_chunk = read_chunk()?;
+        }
+        Ok(())
+    }
+
+    /// Delete the subspace and the replay protection entries written by a
+    /// snapshot import that failed, in bounded batches. The headers and the
+    /// results of the blocks are overwritten by the next import.
+    fn clear_imported_state(&mut self, height: BlockHeight) -> Result<()> {
+        const KEYS_PER_BATCH: usize = 10_000;
+        loop {
+            let keys: Vec<String> = self
+                .db
+                .iter_all_replay_protection()
+                .take(KEYS_PER_BATCH)
+                .map(|(key, _value, _gas)| key)
+                .collect();
+            if keys.is_empty() {
+                break;
+            }
+            let mut batch = D::batch();
+            for key in keys {
+                let key = Key::parse(key).map_err(Error::KeyError)?;
+                self.db.delete_replay_protection_entry(&mut batch, &key)?;
+            }
+            self.db.exec_batch(batch)?;
+        }
+        loop {
+            let keys: Vec<String> = self
+                .db
+                .iter_prefix(None)
+                .take(KEYS_PER_BATCH)
+                .map(|(key, _value, _gas)| key)
+                .collect();
+            if keys.is_empty() {
+                return Ok(());
+            }
+            let mut batch = D::batch();
+            for key in keys {
+                let key = Key::parse(key).map_err(Error::KeyError)?;
+                self.db.batch_delete_subspace_val(
+                    &mut batch, height, &key, false,
+                )?;
+            }
+            self.db.exec_batch(batch)?;
+        }
+    }
+
+    /// Save a checkpoint of the storage tagged with the epoch of the last
+    /// committed block. Only the first block of an epoch can be checkpointed,
+    /// because its commit writes all the Merkle tree stores. Returns the epoch
+    /// of the checkpoint.
+    pub fn write_checkpoint(&self) -> Result<Epoch> {
+        let height = self.get_last_block_height();
+        if self.last_block.is_none()
+            || self.block.pred_epochs.get_epoch_start_height(height)
+                != Some(height)
+        {
+            return Err(Error::NoEpochBoundary { height });
+        }
+        self.db.write_checkpoint(self.last_epoch)?;
+        tracing::info!(
+            "Saved a checkpoint of the epoch {} at height {}",
+            self.last_epoch,
+            height
+        );
+        Ok(self.last_epoch)
+    }
+
+    /// Delete all but the given number of the most recent checkpoints
+    pub fn prune_checkpoints(&self, checkpoints_to_keep: u64) -> Result<()> {
+        let epochs = self.db.checkpoints()?;
+        let pruned = epochs.len().saturating_sub(
+            checkpoints_to_keep.try_into().unwrap_or(usize::MAX),
+        );
+        for epoch in &epochs[..pruned] {
+            self.db.delete_checkpoint(*epoch)?;
+        }
+        Ok(())
+    }
+
+    /// Roll the storage back to the checkpoint of the given epoch. The DB,
+    /// including the Merkle tree stores, is restored from the checkpoint and
+    /// the state of its block is reloaded. The checkpoints of later epochs
+    /// are kept.
+    pub fn rollback_to_checkpoint(&mut self, epoch: Epoch) -> Result<()> {
+        self.clear_read_cache();
+        self.db.restore_checkpoint(epoch)?;
+        // The logged block, if any, comes after the checkpoint
+        if let Some(wal) = self.wal.as_ref() {
+            wal.clear().map_err(Error::WalError)?;
+        }
+        self.header = None;
+        self.load_last_state()?;
+        tracing::info!(
+            "Rolled back to the checkpoint of the epoch {} at height {}",
+            epoch,
+            self.get_last_block_height()
+        );
+        Ok(())
+    }
+
     /// Get a Tendermint-compatible existence proof.
     ///
     /// Proofs from the Ethereum bridge pool are not
@@ -763,12 +1467,7 @@ where
         };
 
         if height > self.get_last_block_height() {
-            Err(Error::Temporary {
-                error: format!(
-                    "The block at the height {} hasn't committed yet",
-                    height,
-                ),
-            })
+            Err(Error::BlockNotCommitted { height })
         } else {
             let (store_type, _) = StoreType::sub_key(key)?;
             self.get_merkle_tree(height, Some(store_type))?
@@ -778,6 +1477,24 @@ where
         }
     }
 
+    /// Get the value of the given key at the given height (or the last
+    /// committed height when 0) together with a proof of its existence, or a
+    /// proof of its non-existence when the key is not found. The proof can be
+    /// verified against the block's merkle root with
+    /// [`namada_merkle_tree::Proof::verify`].
+    pub fn get_with_proof(
+        &self,
+        key: &Key,
+        height: BlockHeight,
+    ) -> Result<(Option<Vec<u8>>, ProofOps)> {
+        let (value, _gas) = self.read_with_height(key, height)?;
+        let proof = match &value {
+            Some(value) => self.get_existence_proof(key, value, height)?,
+            None => self.get_non_existence_proof(key, height)?,
+        };
+        Ok((value, proof))
+    }
+
     /// Get the current (yet to be committed) block epoch
     pub fn get_current_epoch(&self) -> (Epoch, u64) {
         (
@@ -891,6 +1608,7 @@ where
 
     /// Execute write batch.
     pub fn exec_batch(&mut self, batch: D::WriteBatch) -> Result<()> {
+        self.clear_read_cache();
         Ok(self.db.exec_batch(batch)?)
     }
 
@@ -917,6 +1635,7 @@ where
                 self.block.tree.update(key, value)?;
             }
         }
+        self.invalidate_cached(key);
         Ok(self.db.batch_write_subspace_val(
             batch,
             self.block.height,
@@ -939,6 +1658,7 @@ where
         if is_key_merklized {
             self.block.tree.delete(key)?;
         }
+        self.invalidate_cached(key);
         Ok(self.db.batch_delete_subspace_val(
             batch,
             self.block.height,
@@ -947,6 +1667,36 @@ where
         )?)
     }
 
+    /// Read the latest value of the subspace key through the read cache, if
+    /// it's enabled
+    fn read_subspace_val(&self, key: &Key) -> Result<Option<Vec<u8>>> {
+        match self.read_cache.as_ref() {
+            Some(cache) => {
+                if let Some(value) = cache.get(key) {
+                    return Ok(value);
+                }
+                let value = self.db.read_subspace_val(key)?;
+                cache.insert(key.clone(), value.clone());
+                Ok(value)
+            }
+            None => Ok(self.db.read_subspace_val(key)?),
+        }
+    }
+
+    /// Invalidate the cached value of the key, if any
+    fn invalidate_cached(&self, key: &Key) {
+        if let Some(cache) = self.read_cache.as_ref() {
+            cache.invalidate(key);
+        }
+    }
+
+    /// Clear the read cache, if it's enabled
+    fn clear_read_cache(&self) {
+        if let Some(cache) = self.read_cache.as_ref() {
+            cache.clear();
+        }
+    }
+
     // Prune merkle tree stores. Use after updating self.block.height in the
     // commit.
     fn prune_merkle_tree_stores(
@@ -1005,6 +1755,20 @@ where
             .unwrap_or_default()
     }
 
+    /// Get the oldest height whose diffs are retained, if the diffs are being
+    /// pruned. The diffs of the last block are always retained.
+    pub fn get_oldest_retained_height(&self) -> Option<BlockHeight> {
+        self.retain_last_n_blocks.map(|n| {
+            BlockHeight(self.get_last_block_height().0.saturating_sub(n))
+        })
+    }
+
+    /// Get the height below which the diffs have been pruned, if they have
+    /// ever been pruned. The values at the heights below it can't be read.
+    pub fn get_diffs_pruned_height(&self) -> Result<Option<BlockHeight>> {
+        Ok(self.db.read_diffs_pruned_height()?)
+    }
+
     /// Get the oldest epoch where we can read a value
     pub fn get_oldest_epoch(&self) -> Epoch {
         let oldest_height = match self.storage_read_past_height_limit {
@@ -1129,6 +1893,7 @@ pub mod testing {
                 epoch: Epoch::default(),
                 pred_epochs: Epochs::default(),
                 results: BlockResults::default(),
+                receipts: Vec::new(),
             };
             Self {
                 db: MockDB::default(),
@@ -1151,6 +1916,10 @@ pub mod testing {
                 ethereum_height: None,
                 eth_events_queue: EthEventsQueue::default(),
                 storage_read_past_height_limit: Some(1000),
+                retain_last_n_blocks: None,
+                checkpoints_to_keep: None,
+                read_cache: None,
+                wal: None,
                 merkle_tree_key_filter: merklize_all_keys,
             }
         }
@@ -1179,6 +1948,7 @@ mod tests {
     use proptest::prelude::*;
     use proptest::test_runner::Config;
 
+    use super::read_cache::ReadCacheStats;
     use super::testing::*;
     use super::*;
 
@@ -1266,6 +2036,7 @@ mod tests {
                 fee_unshielding_gas_limit: 20_000,
                 fee_unshielding_descriptions_limit: 15,
                 minimum_gas_price: BTreeMap::default(),
+                storage_rent_per_byte: token::Amount::zero(),
             };
             namada_parameters::init_storage(&parameters, &mut wl_storage).unwrap();
             // Initialize pred_epochs to the current height
@@ -1578,4 +2349,376 @@ mod tests {
             .unwrap();
         assert!(res2.is_none());
     }
+
+    #[test]
+    fn test_read_with_pruned_height() {
+        let mut storage = TestStorage::default();
+        storage.last_block = Some(LastBlock {
+            height: BlockHeight(25),
+            hash: BlockHash::default(),
+            time: DateTimeUtc::now(),
+        });
+        let key = test_key_1();
+
+        // Nothing has been pruned yet
+        let (res, _) = storage.read_with_height(&key, BlockHeight(1)).unwrap();
+        assert!(res.is_none());
+
+        // The state can still be read at the height at which the diffs have
+        // been pruned, but not below it
+        let mut batch = TestStorage::batch();
+        storage.db.prune_below(&mut batch, BlockHeight(15)).unwrap();
+        storage.db.exec_batch(batch).unwrap();
+        let (res, _) = storage.read_with_height(&key, BlockHeight(15)).unwrap();
+        assert!(res.is_none());
+        let res = storage.read_with_height(&key, BlockHeight(14));
+        assert!(matches!(
+            res,
+            Err(Error::PrunedHeight {
+                height: BlockHeight(14),
+                oldest_height: BlockHeight(15),
+            })
+        ));
+    }
+
+    #[test]
+    fn test_rollback_to_checkpoint() {
+        let mut wls = TestWlStorage::default();
+        let key = test_key_1();
+        let mut roots = BTreeMap::new();
+        // Epochs start at heights 1 and 4, the first blocks of the epochs are
+        // checkpointed
+        for height in 1..=6 {
+            let height = BlockHeight(height);
+            wls.storage
+                .begin_block(BlockHash::default(), height)
+                .unwrap();
+            if height == BlockHeight(4) {
+                wls.storage.block.epoch = wls.storage.block.epoch.next();
+            }
+            if height == BlockHeight(1) || height == BlockHeight(4) {
+                wls.storage.block.pred_epochs.new_epoch(height);
+            }
+            wls.write_log
+                .protocol_write(&key, height.0.serialize_to_vec())
+                .unwrap();
+            wls.commit_block().unwrap();
+
+            let res = wls.storage.write_checkpoint();
+            if height == BlockHeight(1) || height == BlockHeight(4) {
+                assert_eq!(res.unwrap(), wls.storage.last_epoch);
+                roots.insert(wls.storage.last_epoch, wls.storage.merkle_root());
+            } else {
+                assert!(matches!(res, Err(Error::NoEpochBoundary { .. })));
+            }
+        }
+        assert_eq!(
+            wls.storage.db.checkpoints().unwrap(),
+            vec![Epoch(0), Epoch(1)]
+        );
+
+        wls.storage.rollback_to_checkpoint(Epoch(1)).unwrap();
+        assert_eq!(wls.storage.get_last_block_height(), BlockHeight(4));
+        assert_eq!(wls.storage.last_epoch, Epoch(1));
+        assert!(wls.storage.merkle_root() == roots[&Epoch(1)]);
+        assert_eq!(wls.read::<u64>(&key).unwrap(), Some(4));
+
+        // An older checkpoint can still be restored after a rollback
+        wls.storage.rollback_to_checkpoint(Epoch(0)).unwrap();
+        assert_eq!(wls.storage.get_last_block_height(), BlockHeight(1));
+        assert_eq!(wls.storage.last_epoch, Epoch(0));
+        assert!(wls.storage.merkle_root() == roots[&Epoch(0)]);
+        assert_eq!(wls.read::<u64>(&key).unwrap(), Some(1));
+
+        // New blocks can be committed on top of the restored state
+        wls.storage
+            .begin_block(BlockHash::default(), BlockHeight(2))
+            .unwrap();
+        wls.write_log
+            .protocol_write(&key, 20_u64.serialize_to_vec())
+            .unwrap();
+        wls.commit_block().unwrap();
+        assert_eq!(wls.storage.get_last_block_height(), BlockHeight(2));
+        assert_eq!(wls.read::<u64>(&key).unwrap(), Some(20));
+
+        let res = wls.storage.rollback_to_checkpoint(Epoch(2));
+        assert!(matches!(
+            res,
+            Err(Error::DbError(DbError::NoCheckpoint { epoch: Epoch(2) }))
+        ));
+    }
+
+    #[test]
+    fn test_checkpoints_on_new_epoch() {
+        let mut wls = TestWlStorage::default();
+        wls.storage.checkpoints_to_keep = Some(2);
+        // Epochs start at heights 1, 3 and 5
+        for height in 1..=6 {
+            let height = BlockHeight(height);
+            wls.storage
+                .begin_block(BlockHash::default(), height)
+                .unwrap();
+            if height.0 % 2 == 1 {
+                if height != BlockHeight(1) {
+                    wls.storage.block.epoch = wls.storage.block.epoch.next();
+                }
+                wls.storage.block.pred_epochs.new_epoch(height);
+            }
+            wls.commit_block().unwrap();
+        }
+        // Only the checkpoints of the last two epochs are kept
+        assert_eq!(
+            wls.storage.db.checkpoints().unwrap(),
+            vec![Epoch(1), Epoch(2)]
+        );
+    }
+
+    #[test]
+    fn test_read_write_decoded() {
+        let mut storage = TestStorage::default();
+        storage
+            .begin_block(BlockHash::default(), BlockHeight(1))
+            .unwrap();
+        let key = test_key_1();
+
+        assert_eq!(storage.read_decoded::<u64>(&key).unwrap().0, None);
+
+        storage.write_encoded(&key, &42_u64).unwrap();
+        let (value, gas) = storage.read_decoded::<u64>(&key).unwrap();
+        assert_eq!(value, Some(42));
+        assert_eq!(gas, storage.read(&key).unwrap().1);
+
+        // A value that cannot be decoded as the requested type
+        storage.write(&key, [1]).unwrap();
+        assert_matches!(
+            storage.read_decoded::<u64>(&key),
+            Err(Error::BorshCodingError(_))
+        );
+    }
+
+    #[test]
+    fn test_read_cache() {
+        let mut storage = TestStorage {
+            read_cache: Some(ReadCache::new(1024)),
+            ..Default::default()
+        };
+        storage
+            .begin_block(BlockHash::default(), BlockHeight(1))
+            .unwrap();
+        let key = test_key_1();
+        let stats = |storage: &TestStorage| {
+            storage.read_cache.as_ref().unwrap().stats()
+        };
+
+        // A missing key is cached too
+        assert_eq!(storage.read(&key).unwrap().0, None);
+        assert!(!storage.has_key(&key).unwrap().0);
+        assert_eq!(stats(&storage), ReadCacheStats { hits: 1, misses: 1 });
+
+        // A write invalidates the cached value
+        storage.write(&key, [1]).unwrap();
+        let (value, gas) = storage.read(&key).unwrap();
+        assert_eq!(value, Some(vec![1]));
+        let (cached_value, cached_gas) = storage.read(&key).unwrap();
+        assert_eq!(cached_value, Some(vec![1]));
+        // The gas doesn't depend on the cache
+        assert_eq!(gas, cached_gas);
+        assert_eq!(stats(&storage), ReadCacheStats { hits: 2, misses: 2 });
+
+        // The deleted key is looked up before it's invalidated
+        storage.delete(&key).unwrap();
+        assert_eq!(storage.read(&key).unwrap().0, None);
+
+        // The cache is cleared on commit
+        storage.write(&key, [2]).unwrap();
+        assert_eq!(storage.read(&key).unwrap().0, Some(vec![2]));
+        storage.commit_block(TestStorage::batch()).unwrap();
+        assert_eq!(storage.read(&key).unwrap().0, Some(vec![2]));
+        assert_eq!(stats(&storage), ReadCacheStats { hits: 3, misses: 5 });
+    }
+
+    #[test]
+    fn test_read_diffs() {
+        let mut storage = TestStorage::default();
+        let key1 = test_key_1();
+        let key2 = test_key_2();
+
+        storage
+            .begin_block(BlockHash::default(), BlockHeight(1))
+            .unwrap();
+        storage.write(&key1, [1]).unwrap();
+        storage.write(&key2, [2]).unwrap();
+        storage.commit_block(TestStorage::batch()).unwrap();
+
+        storage
+            .begin_block(BlockHash::default(), BlockHeight(2))
+            .unwrap();
+        storage.write(&key1, [3]).unwrap();
+        storage.delete(&key2).unwrap();
+        storage.commit_block(TestStorage::batch()).unwrap();
+
+        assert_eq!(
+            storage.read_diffs(BlockHeight(1)).unwrap(),
+            vec![
+                KeyDiff {
+                    key: key1.clone(),
+                    old_value: None,
+                    new_value: Some(vec![1]),
+                },
+                KeyDiff {
+                    key: key2.clone(),
+                    old_value: None,
+                    new_value: Some(vec![2]),
+                },
+            ]
+        );
+        assert_eq!(
+            storage.read_diffs(BlockHeight(2)).unwrap(),
+            vec![
+                KeyDiff {
+                    key: key1,
+                    old_value: Some(vec![1]),
+                    new_value: Some(vec![3]),
+                },
+                KeyDiff {
+                    key: key2,
+                    old_value: Some(vec![2]),
+                    new_value: None,
+                },
+            ]
+        );
+        assert!(storage.read_diffs(BlockHeight(3)).unwrap().is_empty());
+
+        // The pruned diffs can't be read
+        let mut batch = TestStorage::batch();
+        storage.db.prune_below(&mut batch, BlockHeight(2)).unwrap();
+        storage.db.exec_batch(batch).unwrap();
+        assert!(matches!(
+            storage.read_diffs(BlockHeight(1)),
+            Err(Error::PrunedDiffs { .. })
+        ));
+    }
+
+    /// Test that pruning the MockDB removes the diffs below the height, but
+    /// keeps the history index
+    #[test]
+    fn test_prune_below() {
+        let mut storage = TestStorage::default();
+        let db = &mut storage.db;
+        let key = test_key_1();
+        // The encoded height segments of "5" and "10" contain letters
+        let heights = [1, 5, 10].map(BlockHeight);
+        for (i, height) in heights.iter().enumerate() {
+            db.write_subspace_val(*height, &key, [i as u8], true)
+                .unwrap();
+        }
+
+        // Nothing is pruned until the batch is executed
+        let mut batch = TestStorage::batch();
+        db.prune_below(&mut batch, BlockHeight(10)).unwrap();
+        assert_eq!(
+            db.read_diffs_val(&key, BlockHeight(1), false).unwrap(),
+            Some(vec![0_u8])
+        );
+        assert_eq!(db.read_diffs_pruned_height().unwrap(), None);
+        db.exec_batch(batch).unwrap();
+        assert_eq!(
+            db.read_diffs_pruned_height().unwrap(),
+            Some(BlockHeight(10))
+        );
+
+        for height in [BlockHeight(1), BlockHeight(5)] {
+            for is_old in [true, false] {
+                let diff = db.read_diffs_val(&key, height, is_old).unwrap();
+                assert!(diff.is_none());
+            }
+        }
+        assert_eq!(
+            db.read_diffs_val(&key, BlockHeight(10), true).unwrap(),
+            Some(vec![1_u8])
+        );
+        assert_eq!(
+            db.read_diffs_val(&key, BlockHeight(10), false).unwrap(),
+            Some(vec![2_u8])
+        );
+        assert_eq!(
+            db.read_key_history(&key, BlockHeight(0), 10).unwrap(),
+            heights.to_vec()
+        );
+    }
+
+    #[test]
+    fn test_key_history() {
+        let mut storage = TestStorage::default();
+        let key1 = test_key_1();
+        let key2 = test_key_2();
+
+        for height in 1..=3 {
+            storage
+                .begin_block(BlockHash::default(), BlockHeight(height))
+                .unwrap();
+            if height != 2 {
+                storage.write(&key1, [height as u8]).unwrap();
+            }
+            if height == 3 {
+                storage.delete(&key2).unwrap();
+            }
+            storage.commit_block(TestStorage::batch()).unwrap();
+        }
+
+        assert_eq!(
+            storage.key_history(&key1, BlockHeight(0), 10).unwrap(),
+            vec![BlockHeight(1), BlockHeight(3)]
+        );
+        assert_eq!(
+            storage.key_history(&key1, BlockHeight(2), 10).unwrap(),
+            vec![BlockHeight(3)]
+        );
+        assert_eq!(
+            storage.key_history(&key1, BlockHeight(0), 1).unwrap(),
+            vec![BlockHeight(1)]
+        );
+        let history = storage.key_history(&key2, BlockHeight(0), 10).unwrap();
+        assert!(history.is_empty());
+        assert_eq!(
+            storage.last_change_height(&key1, BlockHeight(2)).unwrap(),
+            Some(BlockHeight(1))
+        );
+        assert_eq!(
+            storage.last_change_height(&key1, BlockHeight(0)).unwrap(),
+            Some(BlockHeight(3))
+        );
+        assert_eq!(
+            storage.last_change_height(&key2, BlockHeight(0)).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_tx_receipts() {
+        let mut storage = TestStorage::default();
+        let hash1 = Hash::sha256(b"tx1");
+        let hash2 = Hash::sha256(b"tx2");
+
+        for (height, hash) in [(1, hash1), (2, hash2)] {
+            storage
+                .begin_block(BlockHash::default(), BlockHeight(height))
+                .unwrap();
+            storage.block.receipts.push(TxReceipt {
+                hash,
+                height: BlockHeight(height),
+                ..Default::default()
+            });
+            storage.commit_block(TestStorage::batch()).unwrap();
+            // The receipts are not carried over to the next block
+            assert!(storage.block.receipts.is_empty());
+        }
+
+        let receipt = storage.get_tx_receipt(&hash1).unwrap().unwrap();
+        assert_eq!(receipt.height, BlockHeight(1));
+        let receipt = storage.get_tx_receipt(&hash2).unwrap().unwrap();
+        assert_eq!(receipt.height, BlockHeight(2));
+        let unknown = Hash::sha256(b"tx3");
+        assert!(storage.get_tx_receipt(&unknown).unwrap().is_none());
+    }
 }
@@ -0,0 +1,144 @@
+//! In-memory LRU cache of the subspace values read from the DB. A cached
+//! value is invalidated when its key is written and the whole cache is cleared
+//! when a batch is executed, i.e. on every block commit.
+
+use std::collections::hash_map::RandomState;
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use clru::{CLruCache, CLruCacheConfig, WeightScale};
+use namada_core::types::storage::Key;
+
+/// A cached value, `None` if the key is not present in the DB
+type CachedValue = Option<Vec<u8>>;
+
+/// LRU cache of the values read from the DB
+type Cache = CLruCache<Key, CachedValue, RandomState, ReadCacheScale>;
+
+/// Configures the cache scale to weigh the cached entries by their size, so
+/// that the capacity of the cache is in bytes
+/// (CLruCache::len + CLruCache::weight <= CLruCache::capacity).
+#[derive(Debug)]
+struct ReadCacheScale;
+
+impl WeightScale<Key, CachedValue> for ReadCacheScale {
+    fn weight(&self, key: &Key, value: &CachedValue) -> usize {
+        key.len() + value.as_ref().map_or(0, Vec::len)
+    }
+}
+
+/// The number of lookups of the cache
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReadCacheStats {
+    /// Lookups served from the cache
+    pub hits: u64,
+    /// Lookups that had to read from the DB
+    pub misses: u64,
+}
+
+/// Cache of the subspace values read from the DB. Thread-safe.
+#[derive(Debug)]
+pub struct ReadCache {
+    cache: Mutex<Cache>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl ReadCache {
+    /// Create a cache with a given size limit.
+    ///
+    /// # Panics
+    /// The `max_bytes` must be non-zero.
+    pub fn new(max_bytes: usize) -> Self {
+        let cache = CLruCache::with_config(
+            CLruCacheConfig::new(NonZeroUsize::new(max_bytes).unwrap())
+                .with_scale(ReadCacheScale),
+        );
+        Self {
+            cache: Mutex::new(cache),
+            hits: AtomicU64::default(),
+            misses: AtomicU64::default(),
+        }
+    }
+
+    /// Look up the value of the given key. Returns `None` if the key is not
+    /// cached or `Some(None)` if it's cached as not present in the DB.
+    pub fn get(&self, key: &Key) -> Option<CachedValue> {
+        let value = self.cache.lock().unwrap().get(key).cloned();
+        let counter = if value.is_some() {
+            &self.hits
+        } else {
+            &self.misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        value
+    }
+
+    /// Cache the value read from the DB for the given key. A value larger
+    /// than the whole cache is not cached.
+    pub fn insert(&self, key: Key, value: CachedValue) {
+        // The only error is returned when the entry is too large to be cached
+        let _ = self.cache.lock().unwrap().put_with_weight(key, value);
+    }
+
+    /// Remove the cached value of the given key
+    pub fn invalidate(&self, key: &Key) {
+        self.cache.lock().unwrap().pop(key);
+    }
+
+    /// Remove all the cached values. The stats are kept.
+    pub fn clear(&self) {
+        self.cache.lock().unwrap().clear();
+    }
+
+    /// Get the number of cache hits and misses so far
+    pub fn stats(&self) -> ReadCacheStats {
+        ReadCacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Reset the number of cache hits and misses
+    pub fn reset_stats(&self) {
+        self.hits.store(0, Ordering::Relaxed);
+        self.misses.store(0, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_cache() {
+        let key = Key::parse("key").unwrap();
+        let cache = ReadCache::new(1024);
+        assert_eq!(cache.get(&key), None);
+
+        cache.insert(key.clone(), Some(vec![1]));
+        assert_eq!(cache.get(&key), Some(Some(vec![1])));
+        assert_eq!(cache.stats(), ReadCacheStats { hits: 1, misses: 1 });
+
+        // Missing keys are cached too
+        let missing = Key::parse("missing").unwrap();
+        cache.insert(missing.clone(), None);
+        assert_eq!(cache.get(&missing), Some(None));
+
+        cache.invalidate(&key);
+        assert_eq!(cache.get(&key), None);
+        assert_eq!(cache.get(&missing), Some(None));
+
+        cache.clear();
+        assert_eq!(cache.get(&missing), None);
+        assert_eq!(cache.stats(), ReadCacheStats { hits: 3, misses: 3 });
+
+        // Values larger than the cache are not cached
+        cache.insert(key.clone(), Some(vec![0; 2048]));
+        assert_eq!(cache.get(&key), None);
+
+        cache.reset_stats();
+        assert_eq!(cache.stats(), ReadCacheStats::default());
+    }
+}
@@ -0,0 +1,377 @@
+//! Snapshots of the state for state sync. A snapshot holds the full subspace
+//! and the Merkle tree stores of a committed block, together with the replay
+//! protection entries and the headers and results of the blocks up to and
+//! including it, split into chunks that can be fetched one by one, so that a
+//! new node can restore the state without replaying all the blocks.
+//!
+//! A snapshot is a sequence of chunks, each prefixed with its length as a
+//! little-endian `u32`. The first chunk is a [`SnapshotChunk::Block`], followed
+//! by a [`SnapshotChunk::MerkleTreeStore`] for every [`StoreType`], by the
+//! [`SnapshotChunk::Subspace`] chunks and then by the
+//! [`SnapshotChunk::ReplayProtection`], [`SnapshotChunk::BlockHeaders`] and
+//! [`SnapshotChunk::BlockResults`] chunks.
+
+use std::io::{self, Read, Write};
+
+use namada_core::borsh::{BorshDeserialize, BorshSerialize, BorshSerializeExt};
+use namada_core::types::address::EstablishedAddressGen;
+use namada_core::types::ethereum_structs;
+use namada_core::types::hash::Hash;
+use namada_core::types::storage::{
+    BlockHash, BlockHeight, BlockResults, Epoch, Epochs, EthEventsQueue, Header,
+};
+use namada_core::types::time::DateTimeUtc;
+use namada_core::types::token::ConversionState;
+use namada_merkle_tree::StoreType;
+use namada_storage::tx_queue::TxQueue;
+
+/// The version of the snapshot format
+pub const SNAPSHOT_FORMAT: u32 = 2;
+
+/// The size in bytes of the subspace data after which a chunk is closed. A
+/// chunk may still be larger when it holds a large value or a Merkle tree
+/// store.
+pub const SNAPSHOT_CHUNK_SIZE: usize = 8 * 1024 * 1024;
+
+/// A chunk of a snapshot
+#[derive(Debug, BorshSerialize, BorshDeserialize)]
+pub enum SnapshotChunk {
+    /// The metadata of the snapshotted block
+    Block(Box<SnapshotBlock>),
+    /// The backing store of a Merkle tree
+    MerkleTreeStore {
+        /// Type of the tree
+        store_type: StoreType,
+        /// Root of the tree
+        root: Hash,
+        /// The encoded store
+        store: Vec<u8>,
+    },
+    /// Subspace key-vals, ordered by the keys
+    Subspace(Vec<(String, Vec<u8>)>),
+    /// Replay protection keys, under both the `all` and the `last` subkeys
+    ReplayProtection(Vec<String>),
+    /// Headers of the blocks up to and including the snapshotted one
+    BlockHeaders(Vec<(BlockHeight, Header)>),
+    /// Results of the blocks up to and including the snapshotted one
+    BlockResults(Vec<(BlockHeight, BlockResults)>),
+}
+
+/// The metadata of a snapshotted block
+#[derive(Debug, BorshSerialize, BorshDeserialize)]
+pub struct SnapshotBlock {
+    /// Hash of the block
+    pub hash: BlockHash,
+    /// Height of the block
+    pub height: BlockHeight,
+    /// Time of the block
+    pub time: DateTimeUtc,
+    /// Epoch of the block
+    pub epoch: Epoch,
+    /// Predecessor block epochs
+    pub pred_epochs: Epochs,
+    /// Results of applying transactions
+    pub results: BlockResults,
+    /// Minimum block height at which the next epoch may start
+    pub next_epoch_min_start_height: BlockHeight,
+    /// Minimum block time at which the next epoch may start
+    pub next_epoch_min_start_time: DateTimeUtc,
+    /// Update epoch delay
+    pub update_epoch_blocks_delay: Option<u32>,
+    /// Established address generator
+    pub address_gen: EstablishedAddressGen,
+    /// The conversion state
+    pub conversion_state: ConversionState,
+    /// Wrapper txs to be decrypted in the next block proposal
+    pub tx_queue: TxQueue,
+    /// The latest block height on Ethereum processed, if the bridge is
+    /// enabled.
+    pub ethereum_height: Option<ethereum_structs::BlockHeight>,
+    /// The queue of Ethereum events to be processed in order.
+    pub eth_events_queue: EthEventsQueue,
+}
+
+/// The description of an exported snapshot
+#[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct SnapshotMetadata {
+    /// Height of the snapshotted block
+    pub height: BlockHeight,
+    /// Version of the snapshot format
+    pub format: u32,
+    /// Hashes of the chunks, in order
+    pub chunk_hashes: Vec<Hash>,
+}
+
+impl SnapshotMetadata {
+    /// The number of chunks of the snapshot
+    pub fn chunks(&self) -> u32 {
+        self.chunk_hashes
+            .len()
+            .try_into()
+            .expect("The number of chunks must fit in u32")
+    }
+
+    /// The hash of the snapshot, which commits to all of its chunks
+    pub fn hash(&self) -> Hash {
+        Hash::sha256(self.chunk_hashes.serialize_to_vec())
+    }
+}
+
+/// Split the sized items into chunks of about [`SNAPSHOT_CHUNK_SIZE`] bytes,
+/// built by `to_chunk`, and pass them in order to `write_chunk`
+pub fn write_chunks<T, E>(
+    items: impl Iterator<Item = Result<(usize, T), E>>,
+    to_chunk: impl Fn(Vec<T>) -> SnapshotChunk,
+    write_chunk: &mut impl FnMut(SnapshotChunk) -> Result<(), E>,
+) -> Result<(), E> {
+    let mut chunk_items = vec![];
+    let mut chunk_size = 0;
+    for item in items {
+        let (size, item) = item?;
+        chunk_size += size;
+        chunk_items.push(item);
+        if chunk_size >= SNAPSHOT_CHUNK_SIZE {
+            write_chunk(to_chunk(std::mem::take(&mut chunk_items)))?;
+            chunk_size = 0;
+        }
+    }
+    if !chunk_items.is_empty() {
+        write_chunk(to_chunk(chunk_items))?;
+    }
+    Ok(())
+}
+
+/// Encode the chunk and write it prefixed with its length. Returns the hash of
+/// the encoded chunk.
+pub fn write_chunk(
+    writer: &mut impl Write,
+    chunk: &SnapshotChunk,
+) -> io::Result<Hash> {
+    let bytes = chunk.serialize_to_vec();
+    write_raw_chunk(writer, &bytes)?;
+    Ok(Hash::sha256(&bytes))
+}
+
+/// Write the encoded chunk prefixed with its length
+pub fn write_raw_chunk(
+    writer: &mut impl Write,
+    bytes: &[u8],
+) -> io::Result<()> {
+    let len = u32::try_from(bytes.len()).map_err(|_| {
+        io::Error::new(io::ErrorKind::InvalidInput, "Snapshot chunk too large")
+    })?;
+    writer.write_all(&len.to_le_bytes())?;
+    writer.write_all(bytes)
+}
+
+/// Read the next encoded chunk, if any
+pub fn read_raw_chunk(reader: &mut impl Read) -> io::Result<Option<Vec<u8>>> {
+    let mut len = [0_u8; 4];
+    match reader.read_exact(&mut len) {
+        Ok(()) => {}
+        Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => {
+            return Ok(None);
+        }
+        Err(err) => return Err(err),
+    }
+    let mut bytes = vec![0_u8; u32::from_le_bytes(len) as usize];
+    reader.read_exact(&mut bytes)?;
+    Ok(Some(bytes))
+}
+
+/// Read and decode the next chunk, if any
+pub fn read_chunk(reader: &mut impl Read) -> io::Result<Option<SnapshotChunk>> {
+    read_raw_chunk(reader)?
+        .map(|bytes| SnapshotChunk::try_from_slice(&bytes))
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use namada_core::types::storage::Key;
+    use namada_storage::{DBIter, StorageRead, DB};
+
+    use super::*;
+    use crate::testing::{TestStorage, TestWlStorage};
+    use crate::Error;
+
+    /// Commit a block that writes the height into the given key
+    fn commit_block(s: &mut TestWlStorage, height: u64, key: &Key) {
+        s.storage
+            .begin_block(BlockHash::default(), BlockHeight(height))
+            .unwrap();
+        s.storage
+            .set_header(Header {
+                hash: Hash::default(),
+                time: DateTimeUtc::unix_epoch(),
+                next_validators_hash: Hash::default(),
+            })
+            .unwrap();
+        s.write_log
+            .protocol_write(key, height.serialize_to_vec())
+            .unwrap();
+        s.commit_block().unwrap();
+    }
+
+    /// Re-encode a snapshot with its subspace changed by the given function
+    fn tamper_subspace(
+        bytes: &[u8],
+        tamper: impl Fn(&mut Vec<(String, Vec<u8>)>),
+    ) -> Vec<u8> {
+        let mut reader = bytes;
+        let mut tampered = vec![];
+        while let Some(mut chunk) = read_chunk(&mut reader).unwrap() {
+            if let SnapshotChunk::Subspace(key_vals) = &mut chunk {
+                tamper(key_vals);
+            }
+            write_chunk(&mut tampered, &chunk).unwrap();
+        }
+        tampered
+    }
+
+    /// Test that a state restored from a snapshot matches the exported state
+    #[test]
+    fn test_snapshot_roundtrip() {
+        let key_1 = Key::parse("key_1").unwrap();
+        let key_2 = Key::parse("key_2").unwrap();
+        let mut s = TestWlStorage::default();
+        let tx_hash = Hash::sha256(b"tx");
+        s.write_log.write_tx_hash(tx_hash).unwrap();
+        commit_block(&mut s, 1, &key_1);
+        commit_block(&mut s, 2, &key_2);
+
+        let mut bytes = vec![];
+        let metadata = s
+            .storage
+            .export_snapshot(BlockHeight(2), &mut bytes)
+            .unwrap();
+        assert_eq!(metadata.height, BlockHeight(2));
+        assert_eq!(metadata.format, SNAPSHOT_FORMAT);
+        // The block, a chunk per Merkle tree store, the subspace, the replay
+        // protection entries and the headers and results of the blocks
+        assert_eq!(metadata.chunks() as usize, StoreType::iter().count() + 5);
+
+        // The chunks match the hashes in the metadata
+        let mut reader = bytes.as_slice();
+        let mut chunk_hashes = vec![];
+        while let Some(chunk) = read_raw_chunk(&mut reader).unwrap() {
+            chunk_hashes.push(Hash::sha256(chunk));
+        }
+        assert_eq!(chunk_hashes, metadata.chunk_hashes);
+
+        let root = s.storage.merkle_root();
+        let mut restored = TestWlStorage::default();
+        restored
+            .storage
+            .import_snapshot(bytes.as_slice(), &root)
+            .unwrap();
+        assert_eq!(restored.storage.get_last_block_height(), BlockHeight(2));
+        assert!(restored.storage.merkle_root() == root);
+        assert_eq!(restored.read::<u64>(&key_1).unwrap(), Some(1));
+        assert_eq!(restored.read::<u64>(&key_2).unwrap(), Some(2));
+        assert!(
+            restored.storage.has_replay_protection_entry(&tx_hash).unwrap()
+        );
+        for height in [BlockHeight(1), BlockHeight(2)] {
+            let header = |s: &TestWlStorage| {
+                s.storage
+                    .db
+                    .read_block_header(height)
+                    .unwrap()
+                    .map(|header| header.serialize_to_vec())
+            };
+            assert!(header(&s).is_some());
+            assert_eq!(header(&restored), header(&s));
+        }
+        assert_eq!(
+            restored.storage.db.iter_results().collect::<Vec<_>>(),
+            s.storage.db.iter_results().collect::<Vec<_>>(),
+        );
+        // The diffs of the restored blocks can't be read
+        assert_eq!(
+            restored.storage.get_diffs_pruned_height().unwrap(),
+            Some(BlockHeight(3))
+        );
+
+        // The imported state is loaded from the DB
+        let mut loaded = TestStorage {
+            db: std::mem::take(&mut restored.storage.db),
+            ..Default::default()
+        };
+        loaded.load_last_state().unwrap();
+        assert_eq!(loaded.get_last_block_height(), BlockHeight(2));
+        assert!(loaded.merkle_root() == root);
+
+        // A new block can be committed on top of the imported state
+        let key_3 = Key::parse("key_3").unwrap();
+        restored.storage = loaded;
+        commit_block(&mut restored, 3, &key_3);
+        commit_block(&mut s, 3, &key_3);
+        assert!(restored.storage.merkle_root() == s.storage.merkle_root());
+    }
+
+    /// Test that invalid snapshots are rejected
+    #[test]
+    fn test_snapshot_rejected() {
+        let key = Key::parse("key").unwrap();
+        let mut s = TestWlStorage::default();
+        commit_block(&mut s, 1, &key);
+
+        // Only the last committed height can be exported
+        let result = s.storage.export_snapshot(BlockHeight(2), vec![]);
+        assert!(matches!(result, Err(Error::SnapshotHeight { .. })));
+
+        let mut bytes = vec![];
+        s.storage
+            .export_snapshot(BlockHeight(1), &mut bytes)
+            .unwrap();
+
+        // The root must match
+        let mut other = TestWlStorage::default();
+        commit_block(&mut other, 1, &Key::parse("other").unwrap());
+        let mut restored = TestWlStorage::default();
+        let result = restored
+            .storage
+            .import_snapshot(bytes.as_slice(), &other.storage.merkle_root());
+        assert!(matches!(result, Err(Error::InvalidSnapshot(_))));
+        assert!(!restored.has_key(&key).unwrap());
+        assert_eq!(restored.storage.get_last_block_height(), BlockHeight(0));
+
+        // The storage must be empty
+        let result = other
+            .storage
+            .import_snapshot(bytes.as_slice(), &s.storage.merkle_root());
+        assert!(matches!(result, Err(Error::InvalidSnapshot(_))));
+
+        // The subspace must match the Merkle tree
+        let root = s.storage.merkle_root();
+        let key_str = key.to_string();
+        let tampered_snapshots = [
+            // A changed value
+            tamper_subspace(&bytes, |key_vals| {
+                for (key, value) in key_vals.iter_mut() {
+                    if *key == key_str {
+                        *value = 2_u64.serialize_to_vec();
+                    }
+                }
+            }),
+            // A missing leaf
+            tamper_subspace(&bytes, |key_vals| {
+                key_vals.retain(|(key, _value)| *key != key_str)
+            }),
+            // A key that isn't in the tree
+            tamper_subspace(&bytes, |key_vals| {
+                key_vals.push(("other".to_string(), vec![1]))
+            }),
+        ];
+        for tampered in tampered_snapshots {
+            let mut restored = TestWlStorage::default();
+            let result =
+                restored.storage.import_snapshot(tampered.as_slice(), &root);
+            assert!(matches!(result, Err(Error::InvalidSnapshot(_))));
+            // The imported subspace is removed
+            assert!(!restored.has_key(&key).unwrap());
+            assert!(restored.storage.last_block.is_none());
+        }
+    }
+}
@@ -0,0 +1,306 @@
+//! Write-ahead log (WAL) of the block being committed. The data of a block is
+//! recorded in the WAL before the block is persisted in the DB, so that if the
+//! node stops before the DB is flushed, the block can be replayed on restart.
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use namada_core::borsh::{BorshDeserialize, BorshSerialize, BorshSerializeExt};
+use namada_core::types::address::EstablishedAddressGen;
+use namada_core::types::ethereum_structs;
+use namada_core::types::storage::{
+    BlockHash, BlockHeight, BlockResults, Epoch, Epochs, EthEventsQueue, Header,
+};
+use namada_core::types::time::DateTimeUtc;
+use namada_core::types::token::ConversionState;
+use namada_storage::tx_queue::TxQueue;
+use namada_tx::data::TxReceipt;
+
+use crate::write_log::{BlockWriteLog, WriteLog};
+use crate::{DBIter, State, StorageHasher, DB};
+
+/// A write-ahead log persisted in a file. It only holds the last recorded
+/// block.
+#[derive(Debug, Clone)]
+pub struct WriteAheadLog {
+    path: PathBuf,
+}
+
+/// A block recorded in the [`WriteAheadLog`]
+#[derive(Debug, BorshDeserialize)]
+pub struct WalEntry {
+    /// Header of the block
+    pub header: Option<Header>,
+    /// Hash of the block
+    pub hash: BlockHash,
+    /// Height of the block
+    pub height: BlockHeight,
+    /// Epoch of the block
+    pub epoch: Epoch,
+    /// Predecessor block epochs
+    pub pred_epochs: Epochs,
+    /// Results of applying transactions
+    pub results: BlockResults,
+    /// Receipts of the applied transactions
+    pub receipts: Vec<TxReceipt>,
+    /// Minimum block height at which the next epoch may start
+    pub next_epoch_min_start_height: BlockHeight,
+    /// Minimum block time at which the next epoch may start
+    pub next_epoch_min_start_time: DateTimeUtc,
+    /// Update epoch delay
+    pub update_epoch_blocks_delay: Option<u32>,
+    /// Established address generator
+    pub address_gen: EstablishedAddressGen,
+    /// The conversion state
+    pub conversion_state: ConversionState,
+    /// Wrapper txs to be decrypted in the next block proposal
+    pub tx_queue: TxQueue,
+    /// The latest block height on Ethereum processed, if the bridge is
+    /// enabled.
+    pub ethereum_height: Option<ethereum_structs::BlockHeight>,
+    /// The queue of Ethereum events to be processed in order.
+    pub eth_events_queue: EthEventsQueue,
+    /// The block-level modifications of the write log
+    pub write_log: BlockWriteLog,
+}
+
+/// A borrowed [`WalEntry`] with the same encoding, to avoid cloning the state
+/// when it's being recorded.
+#[derive(BorshSerialize)]
+struct WalEntryRef<'a> {
+    header: &'a Option<Header>,
+    hash: &'a BlockHash,
+    height: BlockHeight,
+    epoch: Epoch,
+    pred_epochs: &'a Epochs,
+    results: &'a BlockResults,
+    receipts: &'a Vec<TxReceipt>,
+    next_epoch_min_start_height: BlockHeight,
+    next_epoch_min_start_time: DateTimeUtc,
+    update_epoch_blocks_delay: Option<u32>,
+    address_gen: &'a EstablishedAddressGen,
+    conversion_state: &'a ConversionState,
+    tx_queue: &'a TxQueue,
+    ethereum_height: Option<&'a ethereum_structs::BlockHeight>,
+    eth_events_queue: &'a EthEventsQueue,
+    write_log: BlockWriteLog,
+}
+
+impl WriteAheadLog {
+    /// Create a write-ahead log persisted in the given file
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Record the block that is about to be committed, replacing the
+    /// previously recorded block
+    pub fn write<D, H>(
+        &self,
+        state: &State<D, H>,
+        write_log: &WriteLog,
+    ) -> io::Result<()>
+    where
+        D: DB + for<'iter> DBIter<'iter>,
+        H: StorageHasher,
+    {
+        let entry = WalEntryRef {
+            header: &state.header,
+            hash: &state.block.hash,
+            height: state.block.height,
+            epoch: state.block.epoch,
+            pred_epochs: &state.block.pred_epochs,
+            results: &state.block.results,
+            receipts: &state.block.receipts,
+            next_epoch_min_start_height: state.next_epoch_min_start_height,
+            next_epoch_min_start_time: state.next_epoch_min_start_time,
+            update_epoch_blocks_delay: state.update_epoch_blocks_delay,
+            address_gen: &state.address_gen,
+            conversion_state: &state.conversion_state,
+            tx_queue: &state.tx_queue,
+            ethereum_height: state.ethereum_height.as_ref(),
+            eth_events_queue: &state.eth_events_queue,
+            write_log: write_log.block_write_log(),
+        }
+        .serialize_to_vec();
+
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        // Write into a temporary file first and then move it in place, so that
+        // a crash while writing cannot leave behind a corrupted entry
+        let tmp_path = self.path.with_extension("tmp");
+        let mut file = fs::File::create(&tmp_path)?;
+        file.write_all(&entry)?;
+        file.sync_all()?;
+        fs::rename(tmp_path, &self.path)?;
+        // Sync the directory too, otherwise the rename may be lost on a power
+        // loss
+        if let Some(dir) =
+            self.path.parent().filter(|dir| !dir.as_os_str().is_empty())
+        {
+            fs::File::open(dir)?.sync_all()?;
+        }
+        Ok(())
+    }
+
+    /// Read the last recorded block, if any
+    pub fn read(&self) -> io::Result<Option<WalEntry>> {
+        match fs::read(&self.path) {
+            Ok(bytes) => WalEntry::try_from_slice(&bytes).map(Some),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Remove the recorded block, if any
+    pub fn clear(&self) -> io::Result<()> {
+        match fs::remove_file(&self.path) {
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+            res => res,
+        }
+    }
+}
+
+impl WalEntry {
+    /// Restore the recorded block into the state and its write log, so that
+    /// the block can be committed again
+    pub fn restore<D, H>(
+        self,
+        state: &mut State<D, H>,
+        write_log: &mut WriteLog,
+    ) where
+        D: DB + for<'iter> DBIter<'iter>,
+        H: StorageHasher,
+    {
+        state.header = self.header;
+        state.block.hash = self.hash;
+        state.block.height = self.height;
+        state.block.epoch = self.epoch;
+        state.block.pred_epochs = self.pred_epochs;
+        state.block.results = self.results;
+        state.block.receipts = self.receipts;
+        state.next_epoch_min_start_height = self.next_epoch_min_start_height;
+        state.next_epoch_min_start_time = self.next_epoch_min_start_time;
+        state.update_epoch_blocks_delay = self.update_epoch_blocks_delay;
+        state.address_gen = self.address_gen;
+        state.conversion_state = self.conversion_state;
+        state.tx_queue = self.tx_queue;
+        state.ethereum_height = self.ethereum_height;
+        state.eth_events_queue = self.eth_events_queue;
+        write_log.restore_block_write_log(self.write_log);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use namada_core::types::address;
+    use namada_core::types::chain::ChainId;
+    use namada_core::types::hash::{Hash, Sha256Hasher};
+    use namada_core::types::storage::Key;
+    use namada_storage::faultydb::{Fault, FaultyDB, FaultyOp};
+    use namada_storage::mockdb::MockDB;
+    use namada_storage::StorageRead;
+
+    use super::*;
+    use crate::testing::TestWlStorage;
+    use crate::WlStorage;
+
+    type FaultyWlStorage = WlStorage<FaultyDB<MockDB>, Sha256Hasher>;
+
+    /// Commit a block that writes the height into the given key
+    fn commit_block<D>(
+        s: &mut WlStorage<D, Sha256Hasher>,
+        height: u64,
+        key: &Key,
+    ) -> namada_storage::Result<()>
+    where
+        D: 'static + DB + for<'iter> DBIter<'iter>,
+    {
+        s.storage
+            .begin_block(BlockHash::default(), BlockHeight(height))
+            .unwrap();
+        s.storage
+            .set_header(Header {
+                hash: Hash::default(),
+                time: DateTimeUtc::unix_epoch(),
+                next_validators_hash: Hash::default(),
+            })
+            .unwrap();
+        s.write_log
+            .protocol_write(key, height.serialize_to_vec())
+            .unwrap();
+        s.commit_block()
+    }
+
+    /// Test that a block whose flush failed is replayed from the WAL
+    #[test]
+    fn test_replay_after_failed_flush() {
+        let dir = tempfile::tempdir().unwrap();
+        let wal = WriteAheadLog::new(dir.path().join("block.wal"));
+        let key_1 = Key::parse("key_1").unwrap();
+        let key_2 = Key::parse("key_2").unwrap();
+
+        // Commit the blocks into a storage without failures for reference
+        let mut expected = TestWlStorage::default();
+        commit_block(&mut expected, 1, &key_1).unwrap();
+        commit_block(&mut expected, 2, &key_2).unwrap();
+
+        let storage = State::open(
+            "",
+            ChainId::default(),
+            address::nam(),
+            None,
+            None,
+            crate::merklize_all_keys,
+        );
+        let mut s = FaultyWlStorage::new(WriteLog::default(), storage);
+        s.storage.wal = Some(wal.clone());
+        commit_block(&mut s, 1, &key_1).unwrap();
+        // The DB as it was persisted before the second block. The mock DB
+        // doesn't revert the writes of a batch that failed to be executed.
+        let db = s.storage.db.db.read_only_view().unwrap();
+        // Fail to flush the second block
+        s.storage.db.inject(FaultyOp::Flush, Fault::AfterCalls(0));
+        assert!(commit_block(&mut s, 2, &key_2).is_err());
+
+        // Restart with the DB, which only has the first block
+        let mut s = TestWlStorage::default();
+        s.storage.db = db;
+        s.storage.wal = Some(wal);
+        s.storage.load_last_state().unwrap();
+        assert_eq!(s.storage.get_last_block_height(), BlockHeight(1));
+        assert!(!s.has_key(&key_2).unwrap());
+
+        // The second block is replayed
+        assert_eq!(s.replay_wal().unwrap(), Some(BlockHeight(2)));
+        assert_eq!(s.storage.get_last_block_height(), BlockHeight(2));
+        assert_eq!(s.read::<u64>(&key_2).unwrap(), Some(2));
+        assert_eq!(s.storage.merkle_root(), expected.storage.merkle_root());
+
+        // A block that's already been committed is not replayed again
+        assert_eq!(s.replay_wal().unwrap(), None);
+        assert_eq!(s.storage.get_last_block_height(), BlockHeight(2));
+    }
+
+    /// Test that a WAL entry that's not the next block is ignored
+    #[test]
+    fn test_replay_ignores_committed_block() {
+        let dir = tempfile::tempdir().unwrap();
+        let wal = WriteAheadLog::new(dir.path().join("block.wal"));
+        let key = Key::parse("key").unwrap();
+
+        let mut s = TestWlStorage::default();
+        s.storage.wal = Some(wal.clone());
+        commit_block(&mut s, 1, &key).unwrap();
+        assert!(wal.read().unwrap().is_some());
+        assert_eq!(s.replay_wal().unwrap(), None);
+
+        // Nothing is replayed without a recorded block
+        wal.clear().unwrap();
+        assert!(wal.read().unwrap().is_none());
+        assert_eq!(s.replay_wal().unwrap(), None);
+    }
+}
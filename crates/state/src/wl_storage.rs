@@ -1,16 +1,17 @@
 //! Storage with write log.
 
+use std::collections::{BTreeMap, BTreeSet};
 use std::iter::Peekable;
 
 use namada_core::types::address::Address;
-use namada_core::types::hash::{Hash, StorageHasher};
+use namada_core::types::hash::{Hash, StorageHasher, HASH_LENGTH};
 use namada_core::types::storage::{self, BlockHeight, Epochs};
 use namada_core::types::time::DateTimeUtc;
 use namada_parameters::EpochDuration;
 use namada_storage::{ResultExt, StorageRead, StorageWrite};
 
 use super::EPOCH_SWITCH_BLOCKS_DELAY;
-use crate::write_log::{self, WriteLog};
+use crate::write_log::{self, StorageModification, WriteLog};
 use crate::{DBIter, State, DB};
 
 /// Storage with write log that allows to implement prefix iterator that works
@@ -39,6 +40,9 @@ where
 {
     /// Write log
     pub write_log: WriteLog,
+    /// The write log of the uncommitted block that this storage speculates
+    /// on, if any. It's read after the `write_log` and before the `storage`.
+    pub block_write_log: Option<&'a WriteLog>,
     /// Storage provides access to DB
     pub storage: &'a State<D, H>,
 }
@@ -53,17 +57,50 @@ where
     pub fn new(storage: &'a State<D, H>) -> Self {
         Self {
             write_log: WriteLog::default(),
+            block_write_log: None,
             storage,
         }
     }
 
+    /// Create a temp storage with a speculative view of the state that also
+    /// includes the modifications of the given storage's write log that are
+    /// not committed to DB yet. The write log is borrowed, not copied, and
+    /// the changes to the temp storage don't affect it. Note that these
+    /// modifications are only visible through the storage traits and the
+    /// replay protection checks. The wasm host environment only reads the
+    /// temp `write_log`, so [`TempWlStorage::detach_block_write_log`] must be
+    /// called before running a wasm tx or VPs on this storage.
+    pub fn new_speculative(wl_storage: &'a WlStorage<D, H>) -> Self {
+        Self {
+            write_log: WriteLog::default(),
+            block_write_log: Some(&wl_storage.write_log),
+            storage: &wl_storage.storage,
+        }
+    }
+
+    /// Copy the modifications of the borrowed block write log, if any, under
+    /// the temp `write_log`. The wasm host environment then sees the same
+    /// state as the storage traits.
+    pub fn detach_block_write_log(&mut self) {
+        if let Some(block_write_log) = self.block_write_log.take() {
+            self.write_log = std::mem::take(&mut self.write_log)
+                .layered_over(block_write_log);
+        }
+    }
+
     /// Check if the given tx hash has already been processed
     pub fn has_replay_protection_entry(
         &self,
         hash: &Hash,
     ) -> Result<bool, super::Error> {
-        if let Some(present) = self.write_log.has_replay_protection_entry(hash)
-        {
+        let logged =
+            self.write_log
+                .has_replay_protection_entry(hash)
+                .or_else(|| {
+                    self.block_write_log
+                        .and_then(|log| log.has_replay_protection_entry(hash))
+                });
+        if let Some(present) = logged {
             return Ok(present);
         }
 
@@ -103,6 +140,24 @@ pub trait WriteLogAndStorage {
 
     /// Write the provided tx hash to storage.
     fn write_tx_hash(&mut self, hash: Hash) -> write_log::Result<()>;
+
+    /// Read the modification of the given key that's not committed to the
+    /// storage yet, if any
+    fn read_modification(
+        &self,
+        key: &storage::Key,
+    ) -> Option<&write_log::StorageModification> {
+        self.write_log().read(key).0
+    }
+
+    /// Iterate the modifications that are not committed to the storage yet,
+    /// whose storage key matches the given prefix
+    fn iter_modifications(
+        &self,
+        prefix: &storage::Key,
+    ) -> write_log::PrefixIter {
+        self.write_log().iter_prefix_post(prefix)
+    }
 }
 
 impl<D, H> WriteLogAndStorage for WlStorage<D, H>
@@ -161,6 +216,37 @@ where
     fn write_tx_hash(&mut self, hash: Hash) -> write_log::Result<()> {
         self.write_log.write_tx_hash(hash)
     }
+
+    fn read_modification(
+        &self,
+        key: &storage::Key,
+    ) -> Option<&write_log::StorageModification> {
+        self.write_log
+            .read(key)
+            .0
+            .or_else(|| self.block_write_log.and_then(|log| log.read(key).0))
+    }
+
+    fn iter_modifications(
+        &self,
+        prefix: &storage::Key,
+    ) -> write_log::PrefixIter {
+        let iter = self.write_log.iter_prefix_post(prefix);
+        match self.block_write_log {
+            Some(log) => {
+                // The modifications of this write log override the ones of
+                // the block
+                let mut matches: std::collections::BTreeMap<_, _> =
+                    log.iter_prefix_post(prefix).iter.collect();
+                matches.extend(iter.iter);
+                write_log::PrefixIter {
+                    iter: matches.into_iter(),
+                    reverse: false,
+                }
+            }
+            None => iter,
+        }
+    }
 }
 
 impl<D, H> WlStorage<D, H>
@@ -189,6 +275,13 @@ where
     /// Commit the current block's write log to the storage and commit the block
     /// to DB. Starts a new block write log.
     pub fn commit_block(&mut self) -> namada_storage::Result<()> {
+        // Record the block before anything gets written to the DB
+        if let Some(wal) = self.storage.wal.as_ref() {
+            wal.write(&self.storage, &self.write_log)
+                .map_err(crate::Error::WalError)
+                .into_storage_result()?;
+        }
+
         if self.storage.last_epoch != self.storage.block.epoch {
             self.storage
                 .update_epoch_in_merkle_tree()
@@ -202,6 +295,45 @@ where
         self.storage.commit_block(batch).into_storage_result()
     }
 
+    /// Commit the block recorded in the write-ahead log again, if it's the
+    /// block following the last committed block. This is the case when the
+    /// node stopped before the recorded block was persisted in the DB. Returns
+    /// the height of the replayed block, if any.
+    pub fn replay_wal(
+        &mut self,
+    ) -> namada_storage::Result<Option<BlockHeight>> {
+        let entry = match self.storage.wal.as_ref() {
+            Some(wal) => wal
+                .read()
+                .map_err(crate::Error::WalError)
+                .into_storage_result()?,
+            None => None,
+        };
+        let entry = match entry {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+        let next_height = self.storage.get_last_block_height().next_height();
+        if entry.height != next_height {
+            // The recorded block has already been committed, or it cannot be
+            // applied on top of the last committed block
+            if entry.height > next_height {
+                tracing::warn!(
+                    "Ignoring the block at height {} recorded in the \
+                     write-ahead log, the next block height is {}",
+                    entry.height,
+                    next_height
+                );
+            }
+            return Ok(None);
+        }
+        let height = entry.height;
+        tracing::info!("Replaying the block at height {height} from the WAL");
+        entry.restore(&mut self.storage, &mut self.write_log);
+        self.commit_block()?;
+        Ok(Some(height))
+    }
+
     /// Initialize a new epoch when the current epoch is finished. Returns
     /// `true` on a new epoch.
     pub fn update_epoch(
@@ -281,6 +413,100 @@ where
             })
             .unwrap_or(false)
     }
+
+    /// Write the sizes of the accounts' subspaces changed by the current
+    /// block through the write log, so that they're committed and merklized
+    /// with the rest of the block. The size of an emptied subspace is
+    /// deleted. It must be called after the block's last write to the
+    /// subspace of a non-internal account.
+    pub fn write_account_storage_sizes(
+        &mut self,
+    ) -> namada_storage::Result<()> {
+        let mut size_diffs: BTreeMap<Address, i64> = BTreeMap::new();
+        for key in self.write_log.get_block_keys() {
+            let Some(owner) = key_owner(key) else {
+                continue;
+            };
+            let len = match self.write_log.read(key).0 {
+                Some(StorageModification::Write { value }) => value.len(),
+                Some(StorageModification::InitAccount { .. }) => HASH_LENGTH,
+                Some(StorageModification::Delete) | None => 0,
+                // temporary value isn't persisted
+                Some(StorageModification::Temp { .. }) => continue,
+            };
+            let (prev_value, _gas) =
+                self.storage.read(key).into_storage_result()?;
+            let prev_len = prev_value.map(|value| value.len()).unwrap_or(0);
+            let diff = size_diffs.entry(owner.clone()).or_default();
+            *diff = diff.saturating_add(len as i64 - prev_len as i64);
+        }
+        for (owner, size_diff) in size_diffs {
+            if size_diff == 0 {
+                continue;
+            }
+            let key = namada_parameters::storage::get_account_storage_size_key(
+                &owner,
+            );
+            let size = self
+                .read::<u64>(&key)?
+                .unwrap_or_default()
+                .saturating_add_signed(size_diff);
+            if size == 0 {
+                self.delete(&key)?;
+            } else {
+                self.write(&key, size)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Seed the account storage sizes from the whole subspace in the DB, for
+    /// a chain on which they weren't tracked since genesis. The sizes are
+    /// written through the write log and the changes of the current block
+    /// are added to them by [`WlStorage::write_account_storage_sizes`].
+    pub fn seed_account_storage_sizes(&mut self) -> namada_storage::Result<()> {
+        let mut sizes: BTreeMap<Address, u64> = BTreeMap::new();
+        let mut stored: BTreeSet<Address> = BTreeSet::new();
+        for (key, value, _gas) in self.storage.db.iter_prefix(None) {
+            let key = storage::Key::parse(key).into_storage_result()?;
+            if let Some(owner) =
+                namada_parameters::storage::is_account_storage_size_key(&key)
+            {
+                stored.insert(owner.clone());
+            } else if let Some(owner) = key_owner(&key) {
+                *sizes.entry(owner.clone()).or_default() += value.len() as u64;
+            }
+        }
+        for owner in stored {
+            if !sizes.contains_key(&owner) {
+                let key =
+                    namada_parameters::storage::get_account_storage_size_key(
+                        &owner,
+                    );
+                self.delete(&key)?;
+            }
+        }
+        for (owner, size) in sizes {
+            let key = namada_parameters::storage::get_account_storage_size_key(
+                &owner,
+            );
+            self.write(&key, size)?;
+        }
+        Ok(())
+    }
+}
+
+/// The non-internal account whose subspace the key belongs to, if any. Only
+/// the storage sizes of these accounts are tracked.
+fn key_owner(key: &storage::Key) -> Option<&Address> {
+    match key.first() {
+        Some(storage::DbKeySeg::AddressSeg(owner))
+            if !matches!(owner, Address::Internal(_)) =>
+        {
+            Some(owner)
+        }
+        _ => None,
+    }
 }
 
 /// Prefix iterator for [`WlStorage`].
@@ -293,6 +519,8 @@ where
     pub storage_iter: Peekable<<D as DBIter<'iter>>::PrefixIter>,
     /// Peekable write log iterator
     pub write_log_iter: Peekable<write_log::PrefixIter>,
+    /// Whether both iterators are in reverse order of the storage keys
+    pub reverse: bool,
 }
 
 /// Iterate write-log storage items prior to a tx execution, matching the
@@ -315,6 +543,7 @@ where
         PrefixIter {
             storage_iter,
             write_log_iter,
+            reverse: false,
         },
         prefix.len() as u64 * namada_gas::STORAGE_ACCESS_GAS_PER_BYTE,
     )
@@ -340,11 +569,110 @@ where
         PrefixIter {
             storage_iter,
             write_log_iter,
+            reverse: false,
+        },
+        prefix.len() as u64 * namada_gas::STORAGE_ACCESS_GAS_PER_BYTE,
+    )
+}
+
+/// Iterate write-log storage items prior to a tx execution, matching the
+/// given prefix, in reverse order of the storage keys. Returns the iterator
+/// and gas cost.
+pub fn rev_iter_prefix_pre<'iter, D, H>(
+    write_log: &'iter WriteLog,
+    storage: &'iter State<D, H>,
+    prefix: &storage::Key,
+) -> (PrefixIter<'iter, D>, u64)
+where
+    D: DB + for<'iter_> DBIter<'iter_>,
+    H: StorageHasher,
+{
+    let storage_iter = storage.db.rev_iter_prefix(Some(prefix)).peekable();
+    let write_log_iter = write_log.rev_iter_prefix_pre(prefix).peekable();
+    (
+        PrefixIter {
+            storage_iter,
+            write_log_iter,
+            reverse: true,
+        },
+        prefix.len() as u64 * namada_gas::STORAGE_ACCESS_GAS_PER_BYTE,
+    )
+}
+
+/// Iterate write-log storage items posterior to a tx execution, matching the
+/// given prefix, in reverse order of the storage keys. Returns the iterator
+/// and gas cost.
+pub fn rev_iter_prefix_post<'iter, D, H>(
+    write_log: &'iter WriteLog,
+    storage: &'iter State<D, H>,
+    prefix: &storage::Key,
+) -> (PrefixIter<'iter, D>, u64)
+where
+    D: DB + for<'iter_> DBIter<'iter_>,
+    H: StorageHasher,
+{
+    let storage_iter = storage.db.rev_iter_prefix(Some(prefix)).peekable();
+    let write_log_iter = write_log.rev_iter_prefix_post(prefix).peekable();
+    (
+        PrefixIter {
+            storage_iter,
+            write_log_iter,
+            reverse: true,
         },
         prefix.len() as u64 * namada_gas::STORAGE_ACCESS_GAS_PER_BYTE,
     )
 }
 
+/// Iterate write-log storage items prior to a tx execution, whose keys are
+/// within the range `[start, end)`. Returns the iterator and gas cost.
+pub fn iter_range_pre<'iter, D, H>(
+    write_log: &'iter WriteLog,
+    storage: &'iter State<D, H>,
+    start: &storage::Key,
+    end: &storage::Key,
+) -> (PrefixIter<'iter, D>, u64)
+where
+    D: DB + for<'iter_> DBIter<'iter_>,
+    H: StorageHasher,
+{
+    let storage_iter = storage.db.iter_range(start, end).peekable();
+    let write_log_iter = write_log.iter_range_pre(start, end).peekable();
+    (
+        PrefixIter {
+            storage_iter,
+            write_log_iter,
+            reverse: false,
+        },
+        (start.len() + end.len()) as u64
+            * namada_gas::STORAGE_ACCESS_GAS_PER_BYTE,
+    )
+}
+
+/// Iterate write-log storage items posterior to a tx execution, whose keys
+/// are within the range `[start, end)`. Returns the iterator and gas cost.
+pub fn iter_range_post<'iter, D, H>(
+    write_log: &'iter WriteLog,
+    storage: &'iter State<D, H>,
+    start: &storage::Key,
+    end: &storage::Key,
+) -> (PrefixIter<'iter, D>, u64)
+where
+    D: DB + for<'iter_> DBIter<'iter_>,
+    H: StorageHasher,
+{
+    let storage_iter = storage.db.iter_range(start, end).peekable();
+    let write_log_iter = write_log.iter_range_post(start, end).peekable();
+    (
+        PrefixIter {
+            storage_iter,
+            write_log_iter,
+            reverse: false,
+        },
+        (start.len() + end.len()) as u64
+            * namada_gas::STORAGE_ACCESS_GAS_PER_BYTE,
+    )
+}
+
 impl<'iter, D> Iterator for PrefixIter<'iter, D>
 where
     D: DB + DBIter<'iter>,
@@ -372,7 +700,14 @@ where
                         what = Next::ReturnStorage;
                     }
                     (Some((storage_key, _, _)), Some((wl_key, _))) => {
-                        if wl_key <= storage_key {
+                        // The write log key comes first if it's ordered
+                        // before the storage key in the iteration direction
+                        let wl_first = if self.reverse {
+                            wl_key >= storage_key
+                        } else {
+                            wl_key <= storage_key
+                        };
+                        if wl_first {
                             what = Next::ReturnWl {
                                 advance_storage: wl_key == storage_key,
                             };
@@ -434,7 +769,7 @@ macro_rules! impl_storage_traits {
                 key: &storage::Key,
             ) -> namada_storage::Result<Option<Vec<u8>>> {
                 // try to read from the write log first
-                let (log_val, _gas) = self.write_log().read(key);
+                let log_val = self.read_modification(key);
                 match log_val {
                     Some(write_log::StorageModification::Write { ref value }) => {
                         Ok(Some(value.clone()))
@@ -458,7 +793,7 @@ macro_rules! impl_storage_traits {
 
             fn has_key(&self, key: &storage::Key) -> namada_storage::Result<bool> {
                 // try to read from the write log first
-                let (log_val, _gas) = self.write_log().read(key);
+                let log_val = self.read_modification(key);
                 match log_val {
                     Some(&write_log::StorageModification::Write { .. })
                     | Some(&write_log::StorageModification::InitAccount { .. })
@@ -478,9 +813,14 @@ macro_rules! impl_storage_traits {
                 &'iter self,
                 prefix: &storage::Key,
             ) -> namada_storage::Result<Self::PrefixIter<'iter>> {
-                let (iter, _gas) =
-                    iter_prefix_post(self.write_log(), self.storage(), prefix);
-                Ok(iter)
+                let storage_iter =
+                    self.storage().db.iter_prefix(Some(prefix)).peekable();
+                let write_log_iter = self.iter_modifications(prefix).peekable();
+                Ok(PrefixIter {
+                    storage_iter,
+                    write_log_iter,
+                    reverse: false,
+                })
             }
 
             fn iter_next<'iter>(
@@ -577,8 +917,12 @@ mod tests {
     use std::collections::BTreeMap;
 
     use namada_core::borsh::{BorshDeserialize, BorshSerializeExt};
-    use namada_core::types::address::InternalAddress;
-    use namada_core::types::storage::DbKeySeg;
+    use namada_core::types::address::{self, InternalAddress};
+    use namada_core::types::chain::ChainId;
+    use namada_core::types::hash::Sha256Hasher;
+    use namada_core::types::storage::{BlockHash, DbKeySeg, KeySeg};
+    use namada_storage::faultydb::{Fault, FaultyDB, FaultyOp};
+    use namada_storage::mockdb::MockDB;
     use proptest::prelude::*;
     use proptest::test_runner::Config;
     // Use `RUST_LOG=info` (or another tracing level) and `--nocapture` to
@@ -700,6 +1044,296 @@ mod tests {
         }
         dbg!(keys_to_string(&expected_post), keys_to_string(&read_post));
         itertools::assert_equal(expected_post, read_post);
+
+        // The reverse iterators must yield the same items in reverse order
+        let prefix = storage::Key::default();
+        let (iter_pre, _gas) =
+            iter_prefix_pre(&s.write_log, &s.storage, &prefix);
+        let (rev_iter_pre, _gas) =
+            rev_iter_prefix_pre(&s.write_log, &s.storage, &prefix);
+        let mut read_pre: Vec<_> = iter_pre.collect();
+        read_pre.reverse();
+        itertools::assert_equal(read_pre, rev_iter_pre);
+
+        let (iter_post, _gas) =
+            iter_prefix_post(&s.write_log, &s.storage, &prefix);
+        let (rev_iter_post, _gas) =
+            rev_iter_prefix_post(&s.write_log, &s.storage, &prefix);
+        let mut read_post: Vec<_> = iter_post.collect();
+        read_post.reverse();
+        itertools::assert_equal(read_post, rev_iter_post);
+    }
+
+    #[test]
+    fn test_iter_range() {
+        let mut s = TestWlStorage::default();
+        let key = |seg: &str| storage::Key::parse(seg).unwrap();
+
+        // "a", "c" and "e" are in storage, "b" is written and "c" deleted at
+        // the block level and "d" is written by the current tx
+        for seg in ["a", "c", "e"] {
+            s.storage.write(&key(seg), 0_i8.serialize_to_vec()).unwrap();
+        }
+        s.write_log
+            .protocol_write(&key("b"), 0_i8.serialize_to_vec())
+            .unwrap();
+        s.write_log.protocol_delete(&key("c")).unwrap();
+        s.write_log
+            .write(&key("d"), 0_i8.serialize_to_vec())
+            .unwrap();
+
+        // The end of the range is exclusive
+        let (iter_pre, _gas) =
+            iter_range_pre(&s.write_log, &s.storage, &key("a"), &key("e"));
+        let read_pre: Vec<String> =
+            iter_pre.map(|(key, _val, _gas)| key).collect();
+        assert_eq!(read_pre, vec!["a", "b"]);
+
+        let (iter_post, _gas) =
+            iter_range_post(&s.write_log, &s.storage, &key("a"), &key("e"));
+        let read_post: Vec<String> =
+            iter_post.map(|(key, _val, _gas)| key).collect();
+        assert_eq!(read_post, vec!["a", "b", "d"]);
+    }
+
+    type FaultyWlStorage = WlStorage<FaultyDB<MockDB>, Sha256Hasher>;
+
+    fn faulty_wl_storage() -> FaultyWlStorage {
+        let storage = State::open(
+            "",
+            ChainId::default(),
+            address::nam(),
+            None,
+            None,
+            crate::merklize_all_keys,
+        );
+        WlStorage::new(WriteLog::default(), storage)
+    }
+
+    /// Test that a failure to persist a block is propagated from
+    /// `commit_block`
+    #[test]
+    fn test_commit_block_db_failures() {
+        let mut s = faulty_wl_storage();
+        let key = storage::Key::parse("key").unwrap();
+        s.storage
+            .begin_block(BlockHash::default(), BlockHeight(1))
+            .unwrap();
+        s.write_log
+            .protocol_write(&key, 1_u64.serialize_to_vec())
+            .unwrap();
+
+        s.storage
+            .db
+            .inject(FaultyOp::WriteBlock, Fault::AfterCalls(0));
+        assert!(s.commit_block().is_err());
+        assert_eq!(s.storage.db.calls(FaultyOp::Flush), 0);
+
+        s.storage.db.clear_faults();
+        s.storage.db.inject(FaultyOp::Flush, Fault::AfterCalls(0));
+        assert!(s.commit_block().is_err());
+        assert_eq!(s.storage.db.calls(FaultyOp::WriteBlock), 1);
+
+        s.storage.db.clear_faults();
+        s.commit_block().unwrap();
+    }
+
+    /// Test that the reads fail after the given number of calls or for the
+    /// given key
+    #[test]
+    fn test_read_db_failures() {
+        let mut s = faulty_wl_storage();
+        let key_1 = storage::Key::parse("key_1").unwrap();
+        let key_2 = storage::Key::parse("key_2").unwrap();
+        s.storage.write(&key_1, 1_u64.serialize_to_vec()).unwrap();
+        s.storage.write(&key_2, 2_u64.serialize_to_vec()).unwrap();
+
+        s.storage
+            .db
+            .inject(FaultyOp::Read, Fault::OnKey(key_2.clone()));
+        assert_eq!(s.read::<u64>(&key_1).unwrap(), Some(1));
+        assert!(s.read::<u64>(&key_2).is_err());
+
+        s.storage.db.clear_faults();
+        s.storage.db.inject(FaultyOp::Read, Fault::AfterCalls(1));
+        assert_eq!(s.read::<u64>(&key_2).unwrap(), Some(2));
+        assert!(s.read::<u64>(&key_1).is_err());
+        assert!(s.read::<u64>(&key_2).is_err());
+    }
+
+    /// Test that a speculative temp storage sees the uncommitted block-level
+    /// modifications without changing them
+    #[test]
+    fn test_speculative_temp_storage() {
+        let mut s = TestWlStorage::default();
+        let key = storage::Key::parse("key").unwrap();
+        let hash = Hash::sha256(b"tx");
+        s.write(&key, 1_u64).unwrap();
+        s.write_tx_hash(hash).unwrap();
+
+        let temp = TempWlStorage::new(&s.storage);
+        assert_eq!(temp.read::<u64>(&key).unwrap(), None);
+        assert!(!temp.has_replay_protection_entry(&hash).unwrap());
+
+        let mut speculative = TempWlStorage::new_speculative(&s);
+        assert_eq!(speculative.read::<u64>(&key).unwrap(), Some(1));
+        assert!(speculative.has_replay_protection_entry(&hash).unwrap());
+
+        speculative.write(&key, 2_u64).unwrap();
+        assert_eq!(speculative.read::<u64>(&key).unwrap(), Some(2));
+        assert_eq!(s.read::<u64>(&key).unwrap(), Some(1));
+
+        // The iteration merges both write logs, the temp one taking precedence
+        let other_key = storage::Key::parse("other_key").unwrap();
+        speculative.write(&other_key, 3_u64).unwrap();
+        let read = namada_storage::iter_prefix_bytes(
+            &speculative,
+            &storage::Key::default(),
+        )
+        .unwrap()
+        .map(|item| item.unwrap().0)
+        .collect::<Vec<_>>();
+        assert_eq!(read, vec![key.clone(), other_key]);
+        speculative.delete(&key).unwrap();
+        assert!(!speculative.has_key(&key).unwrap());
+        assert!(s.has_key(&key).unwrap());
+    }
+
+    /// Test that the write log of a detached speculative temp storage, which
+    /// is what the wasm host environment reads, gives the same view as the
+    /// storage traits
+    #[test]
+    fn test_detach_speculative_temp_storage() {
+        let mut s = TestWlStorage::default();
+        let key = storage::Key::parse("key").unwrap();
+        let other_key = storage::Key::parse("other_key").unwrap();
+        let hash = Hash::sha256(b"tx");
+        s.write(&key, 1_u64).unwrap();
+        s.write(&other_key, 1_u64).unwrap();
+        s.write_tx_hash(hash).unwrap();
+
+        let mut speculative = TempWlStorage::new_speculative(&s);
+        speculative.write(&other_key, 2_u64).unwrap();
+        assert!(speculative.write_log.read(&key).0.is_none());
+
+        speculative.detach_block_write_log();
+        assert!(speculative.block_write_log.is_none());
+        let expected = [(&key, 1_u64), (&other_key, 2_u64)];
+        for (key, val) in expected {
+            assert_eq!(speculative.read::<u64>(key).unwrap(), Some(val));
+            match speculative.write_log.read(key).0 {
+                Some(write_log::StorageModification::Write { value }) => {
+                    assert_eq!(value, &val.serialize_to_vec())
+                }
+                modification => panic!("Unexpected {modification:?}"),
+            }
+        }
+        assert!(speculative.has_replay_protection_entry(&hash).unwrap());
+        assert_eq!(
+            speculative.write_log.has_replay_protection_entry(&hash),
+            Some(true)
+        );
+
+        // The original write log is not modified
+        speculative.write(&key, 3_u64).unwrap();
+        assert_eq!(s.read::<u64>(&key).unwrap(), Some(1));
+        assert_eq!(s.read::<u64>(&other_key).unwrap(), Some(1));
+    }
+
+    /// Test that the account storage sizes are written through the write log
+    #[test]
+    fn test_account_storage_sizes() {
+        let mut s = TestWlStorage::default();
+        let owner = address::testing::established_address_1();
+        let other = address::testing::established_address_2();
+        let key = |seg: &str| {
+            storage::Key::from(owner.to_db_key())
+                .push(&seg.to_owned())
+                .unwrap()
+        };
+        let size = |s: &TestWlStorage, owner: &Address| {
+            let key =
+                namada_parameters::storage::get_account_storage_size_key(owner);
+            s.read::<u64>(&key).unwrap()
+        };
+
+        s.storage
+            .begin_block(BlockHash::default(), BlockHeight(1))
+            .unwrap();
+        s.write_bytes(&key("a"), [0; 10]).unwrap();
+        s.write_bytes(&key("b"), [0; 5]).unwrap();
+        // The internal addresses' sizes aren't tracked
+        let param_key =
+            namada_parameters::storage::get_tx_allowlist_storage_key();
+        s.write_bytes(&param_key, [0; 4]).unwrap();
+        s.write_account_storage_sizes().unwrap();
+        assert_eq!(size(&s, &owner), Some(15));
+        assert_eq!(size(&s, &namada_parameters::ADDRESS), None);
+        s.commit_block().unwrap();
+        assert_eq!(size(&s, &owner), Some(15));
+
+        // The differences with the committed values are added to the sizes
+        s.storage
+            .begin_block(BlockHash::default(), BlockHeight(2))
+            .unwrap();
+        s.write_bytes(&key("a"), [0; 3]).unwrap();
+        s.delete(&key("b")).unwrap();
+        s.write_bytes(&key("c"), [0; 7]).unwrap();
+        s.write_account_storage_sizes().unwrap();
+        s.commit_block().unwrap();
+        assert_eq!(size(&s, &owner), Some(10));
+
+        // The size of an emptied subspace is deleted
+        s.storage
+            .begin_block(BlockHash::default(), BlockHeight(3))
+            .unwrap();
+        s.delete(&key("a")).unwrap();
+        s.delete(&key("c")).unwrap();
+        s.write_account_storage_sizes().unwrap();
+        s.commit_block().unwrap();
+        assert_eq!(size(&s, &owner), None);
+
+        // The sizes of the values written without tracking them, as on a
+        // chain that didn't track them since genesis, are seeded from the DB
+        s.storage
+            .begin_block(BlockHash::default(), BlockHeight(4))
+            .unwrap();
+        s.storage.write(&key("a"), [0; 6]).unwrap();
+        let other_key = storage::Key::from(other.to_db_key())
+            .push(&"a".to_owned())
+            .unwrap();
+        s.storage.write(&other_key, [0; 2]).unwrap();
+        assert_eq!(size(&s, &owner), None);
+        s.seed_account_storage_sizes().unwrap();
+        s.write_bytes(&key("b"), [0; 1]).unwrap();
+        s.write_account_storage_sizes().unwrap();
+        assert_eq!(size(&s, &owner), Some(7));
+        assert_eq!(size(&s, &other), Some(2));
+        s.commit_block().unwrap();
+
+        // Seeding the tracked sizes doesn't change them
+        s.storage
+            .begin_block(BlockHash::default(), BlockHeight(5))
+            .unwrap();
+        s.seed_account_storage_sizes().unwrap();
+        assert_eq!(size(&s, &owner), Some(7));
+        assert_eq!(size(&s, &other), Some(2));
+    }
+
+    /// Test that an iterator panics on an injected failure
+    #[test]
+    #[should_panic(expected = "Injected Iter failure")]
+    fn test_iter_db_failure() {
+        let mut s = faulty_wl_storage();
+        let prefix = storage::Key::parse("prefix").unwrap();
+        for seg in ["a", "b"] {
+            let key = prefix.push(&seg.to_string()).unwrap();
+            s.storage.write(&key, 0_u64.serialize_to_vec()).unwrap();
+        }
+
+        s.storage.db.inject(FaultyOp::Iter, Fault::AfterCalls(1));
+        let (iter, _gas) = s.storage.iter_prefix(&prefix);
+        for _ in iter {}
     }
 
     fn apply_to_wl_storage(s: &mut TestWlStorage, kvs: &[KeyVal<i8>]) {
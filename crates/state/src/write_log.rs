@@ -4,6 +4,7 @@
 use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 
 use itertools::Itertools;
+use namada_core::borsh::{BorshDeserialize, BorshSerialize, BorshSerializeExt};
 use namada_core::ledger::replay_protection;
 use namada_core::types::address::{
     Address, EstablishedAddressGen, InternalAddress,
@@ -38,13 +39,15 @@ pub enum Error {
     WriteTempAfterDelete,
     #[error("Replay protection key: {0}")]
     ReplayProtection(String),
+    #[error("Borsh (de)-serialization error: {0}")]
+    BorshCodingError(std::io::Error),
 }
 
 /// Result for functions that may fail
 pub type Result<T> = std::result::Result<T, Error>;
 
 /// A storage modification
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize)]
 pub enum StorageModification {
     /// Write a new value
     Write {
@@ -68,7 +71,7 @@ pub enum StorageModification {
     },
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
 /// A replay protection storage modification
 enum ReProtStorageModification {
     /// Write an entry
@@ -85,8 +88,10 @@ pub struct WriteLog {
     /// The generator of established addresses
     address_gen: Option<EstablishedAddressGen>,
     /// All the storage modification accepted by validity predicates are stored
-    /// in block write-log, before being committed to the storage
-    block_write_log: HashMap<storage::Key, StorageModification>,
+    /// in block write-log, before being committed to the storage. The
+    /// modifications are ordered by their keys, so that they're committed in
+    /// a deterministic order.
+    block_write_log: BTreeMap<storage::Key, StorageModification>,
     /// The storage modifications for the current transaction
     tx_write_log: HashMap<storage::Key, StorageModification>,
     /// A precommit bucket for the `tx_write_log`. This is useful for
@@ -102,7 +107,16 @@ pub struct WriteLog {
     ibc_events: BTreeSet<IbcEvent>,
     /// Storage modifications for the replay protection storage, always
     /// committed regardless of the result of the transaction
-    replay_protection: HashMap<Hash, ReProtStorageModification>,
+    replay_protection: BTreeMap<Hash, ReProtStorageModification>,
+}
+
+/// The block-level modifications of a [`WriteLog`], that are recorded in the
+/// write-ahead log before they're committed.
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+pub struct BlockWriteLog {
+    address_gen: Option<EstablishedAddressGen>,
+    block_write_log: BTreeMap<storage::Key, StorageModification>,
+    replay_protection: BTreeMap<Hash, ReProtStorageModification>,
 }
 
 /// Write log prefix iterator
@@ -111,13 +125,19 @@ pub struct PrefixIter {
     /// The concrete iterator for modifications sorted by storage keys
     pub iter:
         std::collections::btree_map::IntoIter<String, StorageModification>,
+    /// Iterate in reverse order of the storage keys
+    pub reverse: bool,
 }
 
 impl Iterator for PrefixIter {
     type Item = (String, StorageModification);
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.iter.next()
+        if self.reverse {
+            self.iter.next_back()
+        } else {
+            self.iter.next()
+        }
     }
 }
 
@@ -125,11 +145,11 @@ impl Default for WriteLog {
     fn default() -> Self {
         Self {
             address_gen: None,
-            block_write_log: HashMap::with_capacity(100_000),
+            block_write_log: BTreeMap::new(),
             tx_write_log: HashMap::with_capacity(100),
             tx_precommit_write_log: HashMap::with_capacity(100),
             ibc_events: BTreeSet::new(),
-            replay_protection: HashMap::with_capacity(1_000),
+            replay_protection: BTreeMap::new(),
         }
     }
 }
@@ -172,6 +192,34 @@ impl WriteLog {
         }
     }
 
+    /// Read a Borsh decoded value at the given key and return the value and
+    /// the gas cost. Returns [`None`] if the key is not present in the write
+    /// log and `Some(None)` if it has been deleted. Fails with
+    /// [`Error::BorshCodingError`] if the value cannot be decoded as `T`.
+    #[allow(clippy::type_complexity)]
+    pub fn read_decoded<T: BorshDeserialize>(
+        &self,
+        key: &storage::Key,
+    ) -> Result<(Option<Option<T>>, u64)> {
+        let (modification, gas) = self.read(key);
+        let value = match modification {
+            Some(StorageModification::Write { value })
+            | Some(StorageModification::Temp { value }) => {
+                Some(Some(value.as_slice()))
+            }
+            Some(StorageModification::InitAccount { vp_code_hash }) => {
+                Some(Some(vp_code_hash.as_ref()))
+            }
+            Some(StorageModification::Delete) => Some(None),
+            None => None,
+        };
+        let value = value
+            .map(|value| value.map(T::try_from_slice).transpose())
+            .transpose()
+            .map_err(Error::BorshCodingError)?;
+        Ok((value, gas))
+    }
+
     /// Read a value before the latest tx execution at the given key and return
     /// the value and the gas cost, returns [`None`] if the key is not present
     /// in the write log
@@ -234,6 +282,16 @@ impl WriteLog {
         Ok((gas as u64 * STORAGE_WRITE_GAS_PER_BYTE, size_diff))
     }
 
+    /// Write a key and a Borsh encoded value and return the gas cost and the
+    /// size difference. Fails in the same cases as [`WriteLog::write`].
+    pub fn write_encoded<T: BorshSerialize>(
+        &mut self,
+        key: &storage::Key,
+        value: &T,
+    ) -> Result<(u64, i64)> {
+        self.write(key, value.serialize_to_vec())
+    }
+
     /// Write a key and a value.
     /// Fails with [`Error::UpdateVpOfNewAccount`] when attempting to update a
     /// validity predicate of a new account that's not yet committed to storage.
@@ -401,6 +459,13 @@ impl WriteLog {
             .collect()
     }
 
+    /// Get the storage keys changed in the current block, i.e. by the
+    /// committed transactions and the protocol. The keys of the current
+    /// transaction are not included.
+    pub fn get_block_keys(&self) -> impl Iterator<Item = &storage::Key> {
+        self.block_write_log.keys()
+    }
+
     /// Get the storage keys changed in the current transaction (left) and
     /// the addresses of accounts initialized in the current transaction
     /// (right). The first vector excludes keys of validity predicates of
@@ -493,6 +558,53 @@ impl WriteLog {
         self.tx_write_log.clear();
     }
 
+    /// Get a copy of the block-level modifications that are going to be
+    /// committed with the current block.
+    pub fn block_write_log(&self) -> BlockWriteLog {
+        BlockWriteLog {
+            address_gen: self.address_gen.clone(),
+            block_write_log: self.block_write_log.clone(),
+            replay_protection: self.replay_protection.clone(),
+        }
+    }
+
+    /// Replace the block-level modifications with the given ones, e.g. to
+    /// commit a block recorded in the write-ahead log again. Any modifications
+    /// of the current transaction are dropped.
+    pub fn restore_block_write_log(&mut self, log: BlockWriteLog) {
+        let BlockWriteLog {
+            address_gen,
+            block_write_log,
+            replay_protection,
+        } = log;
+        self.address_gen = address_gen;
+        self.block_write_log = block_write_log;
+        self.replay_protection = replay_protection;
+        self.tx_write_log.clear();
+        self.tx_precommit_write_log.clear();
+    }
+
+    /// Put the modifications of this write log on top of a copy of the given
+    /// write log, so that both can be read through a single write log. The
+    /// modifications of the current transaction of the given write log are
+    /// treated as committed to its block.
+    pub fn layered_over(self, below: &WriteLog) -> Self {
+        let mut block_write_log = below.block_write_log.clone();
+        block_write_log.extend(below.tx_precommit_write_log.clone());
+        block_write_log.extend(below.tx_write_log.clone());
+        block_write_log.extend(self.block_write_log);
+        let mut replay_protection = below.replay_protection.clone();
+        replay_protection.extend(self.replay_protection);
+        Self {
+            address_gen: self.address_gen.or_else(|| below.address_gen.clone()),
+            block_write_log,
+            tx_write_log: self.tx_write_log,
+            tx_precommit_write_log: self.tx_precommit_write_log,
+            ibc_events: self.ibc_events,
+            replay_protection,
+        }
+    }
+
     /// Commit the current block's write log to the storage. Starts a new block
     /// write log.
     pub fn commit_block<D, H>(
@@ -639,7 +751,19 @@ impl WriteLog {
         }
 
         let iter = matches.into_iter();
-        PrefixIter { iter }
+        PrefixIter {
+            iter,
+            reverse: false,
+        }
+    }
+
+    /// Iterate modifications prior to the current transaction, whose storage
+    /// key matches the given prefix, in reverse order of their storage key.
+    pub fn rev_iter_prefix_pre(&self, prefix: &storage::Key) -> PrefixIter {
+        PrefixIter {
+            reverse: true,
+            ..self.iter_prefix_pre(prefix)
+        }
     }
 
     /// Iterate modifications posterior of the current tx, whose storage key
@@ -659,7 +783,69 @@ impl WriteLog {
         }
 
         let iter = matches.into_iter();
-        PrefixIter { iter }
+        PrefixIter {
+            iter,
+            reverse: false,
+        }
+    }
+
+    /// Iterate modifications posterior of the current tx, whose storage key
+    /// matches the given prefix, in reverse order of their storage key.
+    pub fn rev_iter_prefix_post(&self, prefix: &storage::Key) -> PrefixIter {
+        PrefixIter {
+            reverse: true,
+            ..self.iter_prefix_post(prefix)
+        }
+    }
+
+    /// Iterate modifications prior to the current transaction, whose storage
+    /// key is within the range `[start, end)`, sorted by their storage key.
+    pub fn iter_range_pre(
+        &self,
+        start: &storage::Key,
+        end: &storage::Key,
+    ) -> PrefixIter {
+        let (start, end) = (start.to_string(), end.to_string());
+        let mut matches = BTreeMap::new();
+
+        for (key, modification) in &self.block_write_log {
+            let key = key.to_string();
+            if start <= key && key < end {
+                matches.insert(key, modification.clone());
+            }
+        }
+
+        let iter = matches.into_iter();
+        PrefixIter {
+            iter,
+            reverse: false,
+        }
+    }
+
+    /// Iterate modifications posterior of the current tx, whose storage key
+    /// is within the range `[start, end)`, sorted by their storage key.
+    pub fn iter_range_post(
+        &self,
+        start: &storage::Key,
+        end: &storage::Key,
+    ) -> PrefixIter {
+        let (start, end) = (start.to_string(), end.to_string());
+        let mut matches = BTreeMap::new();
+
+        for (key, modification) in
+            self.block_write_log.iter().chain(self.tx_write_log.iter())
+        {
+            let key = key.to_string();
+            if start <= key && key < end {
+                matches.insert(key, modification.clone());
+            }
+        }
+
+        let iter = matches.into_iter();
+        PrefixIter {
+            iter,
+            reverse: false,
+        }
     }
 
     /// Check if the given tx hash has already been processed. Returns `None` if
@@ -733,11 +919,38 @@ mod tests {
     use assert_matches::assert_matches;
     use namada_core::types::hash::Hash;
     use namada_core::types::{address, storage};
+    use namada_storage::db_key::DbKey;
     use pretty_assertions::assert_eq;
     use proptest::prelude::*;
 
     use super::*;
 
+    #[test]
+    fn test_read_write_decoded() {
+        let mut write_log = WriteLog::default();
+        let key =
+            storage::Key::parse("key").expect("cannot parse the key string");
+
+        let (value, gas) = write_log.read_decoded::<u64>(&key).unwrap();
+        assert!(value.is_none());
+        assert_eq!(gas, (key.len() as u64) * MEMORY_ACCESS_GAS_PER_BYTE);
+
+        write_log.write_encoded(&key, &42_u64).unwrap();
+        let (value, _gas) = write_log.read_decoded::<u64>(&key).unwrap();
+        assert_eq!(value, Some(Some(42)));
+
+        write_log.delete(&key).unwrap();
+        let (value, _gas) = write_log.read_decoded::<u64>(&key).unwrap();
+        assert_eq!(value, Some(None));
+
+        // A value that cannot be decoded as the requested type
+        write_log.write(&key, vec![1]).unwrap();
+        assert_matches!(
+            write_log.read_decoded::<u64>(&key),
+            Err(Error::BorshCodingError(_))
+        );
+    }
+
     #[test]
     fn test_crud_value() {
         let mut write_log = WriteLog::default();
@@ -955,6 +1168,68 @@ mod tests {
         assert_eq!(value, None);
     }
 
+    /// Test that the order of the writes in a block doesn't change the
+    /// committed DB contents
+    #[test]
+    fn test_commit_order() {
+        let keys: Vec<storage::Key> = (0..10)
+            .map(|i| storage::Key::parse(format!("key{i}")).unwrap())
+            .collect();
+        let hashes: Vec<Hash> = (0..10)
+            .map(|i| Hash::sha256(format!("tx{i}").as_bytes()))
+            .collect();
+
+        let commit = |reverse: bool| {
+            let mut storage = crate::testing::TestStorage::default();
+            let mut write_log = WriteLog::default();
+            let mut batch = crate::testing::TestStorage::batch();
+            let indices: Vec<usize> = if reverse {
+                (0..10).rev().collect()
+            } else {
+                (0..10).collect()
+            };
+            for i in indices {
+                write_log.write(&keys[i], vec![i as u8]).unwrap();
+                write_log.write_tx_hash(hashes[i]).unwrap();
+            }
+            write_log.delete(&keys[5]).unwrap();
+            write_log.commit_tx();
+
+            // The modifications are ordered by their keys
+            let written_keys: Vec<_> =
+                write_log.block_write_log.keys().cloned().collect();
+            assert_eq!(written_keys, keys);
+
+            write_log
+                .commit_block(&mut storage, &mut batch)
+                .expect("commit failed");
+            (storage, batch)
+        };
+        let (storage, batch) = commit(false);
+        let (reversed_storage, reversed_batch) = commit(true);
+
+        // The subspace writes reach the batch in the order of their keys,
+        // followed by the replay protection entries in the order of their
+        // hashes
+        fn db_key(key: DbKey<'_>) -> String {
+            format!("{}/{}", key.namespace(), key.key())
+        }
+        let mut sorted_hashes = hashes.clone();
+        sorted_hashes.sort();
+        let expected: Vec<String> = keys
+            .iter()
+            .map(|key| db_key(DbKey::Subspace(key)))
+            .chain(sorted_hashes.iter().map(|hash| {
+                db_key(DbKey::ReplayProtection(&replay_protection::last_key(
+                    hash,
+                )))
+            }))
+            .collect();
+        assert_eq!(batch.applied_keys(), expected);
+        assert_eq!(reversed_batch.applied_keys(), expected);
+        assert_eq!(storage.merkle_root(), reversed_storage.merkle_root());
+    }
+
     #[test]
     fn test_replay_protection_commit() {
         let mut storage = crate::testing::TestStorage::default();
@@ -13,6 +13,7 @@ use namada_merkle_tree::{
     Error as MerkleTreeError, MerkleTreeStoresRead, MerkleTreeStoresWrite,
     StoreType,
 };
+use namada_tx::data::TxReceipt;
 use thiserror::Error;
 
 use crate::tx_queue::TxQueue;
@@ -20,14 +21,22 @@ use crate::tx_queue::TxQueue;
 #[allow(missing_docs)]
 #[derive(Error, Debug)]
 pub enum Error {
-    #[error("TEMPORARY error: {error}")]
-    Temporary { error: String },
+    #[error("Missing the {component} of the block at height {height}")]
+    MissingBlockComponent {
+        height: BlockHeight,
+        component: &'static str,
+    },
     #[error("Found an unknown key: {key}")]
     UnknownKey { key: String },
     #[error("Storage key error {0}")]
     KeyError(namada_core::types::storage::Error),
     #[error("Coding error: {0}")]
     CodingError(#[from] namada_core::types::DecodeError),
+    #[error("Failed to decode the value at key {key}: {error}")]
+    DecodeFailed {
+        key: String,
+        error: namada_core::types::DecodeError,
+    },
     #[error("Merkle tree error: {0}")]
     MerkleTreeError(#[from] MerkleTreeError),
     #[error("DB error: {0}")]
@@ -38,6 +47,8 @@ pub enum Error {
     NoMerkleTree { height: BlockHeight },
     #[error("Code hash error: {0}")]
     InvalidCodeHash(HashError),
+    #[error("No checkpoint found for the epoch {epoch}")]
+    NoCheckpoint { epoch: Epoch },
 }
 
 /// A result of a function that may fail
@@ -104,6 +115,8 @@ pub struct BlockStateWrite<'a> {
     pub address_gen: &'a EstablishedAddressGen,
     /// Results of applying transactions
     pub results: &'a BlockResults,
+    /// Receipts of the applied transactions
+    pub receipts: &'a [TxReceipt],
     /// The conversion state
     pub conversion_state: &'a ConversionState,
     /// Wrapper txs to be decrypted in the next block proposal
@@ -115,8 +128,9 @@ pub struct BlockStateWrite<'a> {
     pub eth_events_queue: &'a EthEventsQueue,
 }
 
-/// A database backend.
-pub trait DB: Debug {
+/// A database backend. It can be moved to another thread, e.g. to export a
+/// snapshot of a read-only view in the background.
+pub trait DB: Debug + Send {
     /// A DB's cache
     type Cache;
     /// A handle for batch writes
@@ -159,6 +173,9 @@ pub trait DB: Debug {
     /// Check if the given replay protection entry exists
     fn has_replay_protection_entry(&self, hash: &Hash) -> Result<bool>;
 
+    /// Read the receipt of the applied transaction with the given hash
+    fn read_tx_receipt(&self, hash: &Hash) -> Result<Option<TxReceipt>>;
+
     /// Read the latest value for account subspace key from the DB
     fn read_subspace_val(&self, key: &Key) -> Result<Option<Vec<u8>>>;
 
@@ -240,6 +257,49 @@ pub trait DB: Debug {
         pruned_epoch: Epoch,
     ) -> Result<()>;
 
+    /// Prune the diffs of the subspace key-vals written below the given
+    /// height and record the height. Historical values can no longer be read
+    /// at the pruned heights.
+    fn prune_below(
+        &mut self,
+        batch: &mut Self::WriteBatch,
+        height: BlockHeight,
+    ) -> Result<()>;
+
+    /// Read the height below which the diffs have been pruned, if they have
+    /// ever been pruned
+    fn read_diffs_pruned_height(&self) -> Result<Option<BlockHeight>>;
+
+    /// Read up to `limit` heights at or above `from_height` at which the
+    /// value of the account subspace key was changed, in ascending order. The
+    /// heights are only indexed by archive nodes and only for the keys whose
+    /// diffs are persisted.
+    fn read_key_history(
+        &self,
+        key: &Key,
+        from_height: BlockHeight,
+        limit: usize,
+    ) -> Result<Vec<BlockHeight>>;
+
+    /// Read the last height at or below the given height at which the value
+    /// of the account subspace key was changed, from the same index as
+    /// [`DB::read_key_history`].
+    fn read_last_change_height(
+        &self,
+        key: &Key,
+        height: BlockHeight,
+    ) -> Result<Option<BlockHeight>>;
+
+    /// Read the version of the DB format, if it was written
+    fn read_db_version(&self) -> Result<Option<u64>>;
+
+    /// Write the version of the DB format
+    fn write_db_version(
+        &mut self,
+        batch: &mut Self::WriteBatch,
+        version: u64,
+    ) -> Result<()>;
+
     /// Read the signed nonce of Bridge Pool
     fn read_bridge_pool_signed_nonce(
         &self,
@@ -260,6 +320,45 @@ pub trait DB: Debug {
         batch: &mut Self::WriteBatch,
         key: &Key,
     ) -> Result<()>;
+
+    /// Write the header of the block at the given height, e.g. of a block
+    /// restored from a snapshot
+    fn write_block_header(
+        &mut self,
+        batch: &mut Self::WriteBatch,
+        height: BlockHeight,
+        header: &Header,
+    ) -> Result<()>;
+
+    /// Write the results of the block at the given height, e.g. of a block
+    /// restored from a snapshot
+    fn write_block_results(
+        &mut self,
+        batch: &mut Self::WriteBatch,
+        height: BlockHeight,
+        results: &BlockResults,
+    ) -> Result<()>;
+
+    /// Save a checkpoint of the whole DB tagged with the given epoch. An
+    /// existing checkpoint of the same epoch is replaced.
+    fn write_checkpoint(&self, epoch: Epoch) -> Result<()>;
+
+    /// Restore the whole DB from the checkpoint of the given epoch. The
+    /// checkpoint is kept, so it can be restored again.
+    fn restore_checkpoint(&mut self, epoch: Epoch) -> Result<()>;
+
+    /// Delete the checkpoint of the given epoch, if any
+    fn delete_checkpoint(&self, epoch: Epoch) -> Result<()>;
+
+    /// The epochs of the saved checkpoints in ascending order
+    fn checkpoints(&self) -> Result<Vec<Epoch>>;
+
+    /// Open a read-only view of the DB at its current state, which doesn't
+    /// see any of the later writes. The view must stay readable while the DB
+    /// keeps being written to.
+    fn read_only_view(&self) -> Result<Self>
+    where
+        Self: Sized;
 }
 
 /// A database prefix iterator.
@@ -275,6 +374,22 @@ pub trait DBIter<'iter> {
     /// ordered by the storage keys.
     fn iter_prefix(&'iter self, prefix: Option<&Key>) -> Self::PrefixIter;
 
+    /// WARNING: This only works for values that have been committed to DB.
+    /// To be able to see values written or deleted, but not yet committed,
+    /// use the `StorageWithWriteLog`.
+    ///
+    /// Read account subspace key value pairs with the given prefix from the DB,
+    /// in reverse order of the storage keys.
+    fn rev_iter_prefix(&'iter self, prefix: Option<&Key>) -> Self::PrefixIter;
+
+    /// WARNING: This only works for values that have been committed to DB.
+    /// To be able to see values written or deleted, but not yet committed,
+    /// use the `StorageWithWriteLog`.
+    ///
+    /// Read account subspace key value pairs whose keys are within the range
+    /// `[start, end)`, ordered by storage keys.
+    fn iter_range(&'iter self, start: &Key, end: &Key) -> Self::PrefixIter;
+
     /// Read results subspace key value pairs from the DB
     fn iter_results(&'iter self) -> Self::PrefixIter;
 
@@ -294,6 +409,10 @@ pub trait DBIter<'iter> {
 
     /// Read replay protection storage from the last block
     fn iter_replay_protection(&'iter self) -> Self::PrefixIter;
+
+    /// Read the whole replay protection storage, with the keys under both the
+    /// `all` and the `last` subkeys
+    fn iter_all_replay_protection(&'iter self) -> Self::PrefixIter;
 }
 
 /// Atomic batch write.
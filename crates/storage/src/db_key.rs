@@ -0,0 +1,424 @@
+//! Typed keys of the DB. Every key belongs to a [`Namespace`], which is a
+//! column family in the persistent DB and a key prefix in the
+//! [`crate::mockdb::MockDB`]. The DB implementations build their keys only
+//! from a [`DbKey`], so they never have to parse key segments to find out
+//! what a key holds.
+
+use std::fmt;
+
+use namada_core::types::hash::Hash;
+use namada_core::types::storage::{BlockHeight, Epoch, Key, KeySeg};
+use namada_merkle_tree::{base_tree_key_prefix, subtree_key_prefix, StoreType};
+
+/// The prefix of the diffs of the values before a block's changes
+const OLD_DIFF_PREFIX: &str = "old";
+/// The prefix of the diffs of the values after a block's changes
+const NEW_DIFF_PREFIX: &str = "new";
+/// The prefix of the predecessor values of the ledger state
+const PRED_PREFIX: &str = "pred";
+/// The prefix of the block results
+const RESULTS_PREFIX: &str = "results";
+/// The prefix of the receipts of the applied txs
+const RECEIPTS_PREFIX: &str = "receipts";
+/// The prefix of the index of the heights of the applied txs' receipts
+const RECEIPT_HEIGHTS_PREFIX: &str = "receipt_heights";
+/// The prefix of the index of the heights at which the keys changed
+const HISTORY_PREFIX: &str = "history";
+
+/// The namespaces of the DB
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Namespace {
+    /// Accounts sub-spaces
+    Subspace,
+    /// Diffs in the accounts sub-spaces' key-vals
+    Diffs,
+    /// The latest ledger state
+    State,
+    /// The blocks' data
+    Block,
+    /// Hashes of the processed txs
+    ReplayProtection,
+}
+
+impl Namespace {
+    /// All the namespaces
+    pub const ALL: [Namespace; 5] = [
+        Namespace::Subspace,
+        Namespace::Diffs,
+        Namespace::State,
+        Namespace::Block,
+        Namespace::ReplayProtection,
+    ];
+
+    /// The name of the namespace
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Namespace::Subspace => "subspace",
+            Namespace::Diffs => "diffs",
+            Namespace::State => "state",
+            Namespace::Block => "block",
+            Namespace::ReplayProtection => "replay_protection",
+        }
+    }
+}
+
+impl fmt::Display for Namespace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// The values of the latest ledger state
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StateKey {
+    /// The last committed block height
+    Height,
+    /// Minimum block height from which the next epoch can start
+    NextEpochMinStartHeight,
+    /// Minimum block time from which the next epoch can start
+    NextEpochMinStartTime,
+    /// The delay of the epoch update in blocks
+    UpdateEpochBlocksDelay,
+    /// MASP conversion state
+    ConversionState,
+    /// Txs to be decrypted in the next block
+    TxQueue,
+    /// The height of the last Ethereum block processed by the oracle
+    EthereumHeight,
+    /// A queue of confirmed Ethereum events to be processed in order
+    EthEventsQueue,
+    /// The version of the DB format, see [`crate::migrations`]
+    DbVersion,
+    /// The height below which the diffs have been pruned
+    DiffsPrunedHeight,
+}
+
+impl StateKey {
+    /// The name of the key
+    pub fn name(&self) -> &'static str {
+        match self {
+            StateKey::Height => "height",
+            StateKey::NextEpochMinStartHeight => "next_epoch_min_start_height",
+            StateKey::NextEpochMinStartTime => "next_epoch_min_start_time",
+            StateKey::UpdateEpochBlocksDelay => "update_epoch_blocks_delay",
+            StateKey::ConversionState => "conversion_state",
+            StateKey::TxQueue => "tx_queue",
+            StateKey::EthereumHeight => "ethereum_height",
+            StateKey::EthEventsQueue => "eth_events_queue",
+            StateKey::DbVersion => "db_version",
+            StateKey::DiffsPrunedHeight => "diffs_pruned_height",
+        }
+    }
+}
+
+/// The data of a block
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BlockField {
+    /// Block header
+    Header,
+    /// Block hash
+    Hash,
+    /// Block time
+    Time,
+    /// Block epoch
+    Epoch,
+    /// Predecessor block epochs
+    PredEpochs,
+    /// Established address generator
+    AddressGen,
+}
+
+impl BlockField {
+    /// The name of the field
+    pub fn name(&self) -> &'static str {
+        match self {
+            BlockField::Header => "header",
+            BlockField::Hash => "hash",
+            BlockField::Time => "time",
+            BlockField::Epoch => "epoch",
+            BlockField::PredEpochs => "pred_epochs",
+            BlockField::AddressGen => "address_gen",
+        }
+    }
+}
+
+/// The parts of a persisted Merkle tree
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TreeField {
+    /// Root hash of the tree
+    Root,
+    /// The tree's store
+    Store,
+}
+
+impl TreeField {
+    /// The name of the field
+    pub fn name(&self) -> &'static str {
+        match self {
+            TreeField::Root => "root",
+            TreeField::Store => "store",
+        }
+    }
+}
+
+/// A typed key of the DB
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DbKey<'a> {
+    /// A key-val of an account sub-space
+    Subspace(&'a Key),
+    /// The value of an account sub-space key before (`is_old`) or after the
+    /// changes of the block at the height
+    Diff {
+        /// Height of the block that changed the key
+        height: BlockHeight,
+        /// Is it the value before the change
+        is_old: bool,
+        /// The changed key
+        key: &'a Key,
+    },
+    /// An entry of the index of the heights at which an account sub-space key
+    /// changed, kept by the archive nodes
+    History {
+        /// The changed key
+        key: &'a Key,
+        /// Height of the block that changed the key
+        height: BlockHeight,
+    },
+    /// A value of the latest ledger state
+    State(StateKey),
+    /// The predecessor of a value of the latest ledger state, kept for
+    /// rollback
+    PredState(StateKey),
+    /// The data of the block at the height
+    Block {
+        /// Height of the block
+        height: BlockHeight,
+        /// The data
+        field: BlockField,
+    },
+    /// The results of the block at the height
+    Results(BlockHeight),
+    /// The receipt of a tx applied in the block at the height
+    TxReceipt {
+        /// Height of the block
+        height: BlockHeight,
+        /// Hash of the tx
+        hash: &'a Hash,
+    },
+    /// The height of the block with the receipt of a tx
+    TxReceiptHeight(&'a Hash),
+    /// A part of the base Merkle tree of the block at the height
+    BaseTree {
+        /// Height of the block
+        height: BlockHeight,
+        /// The part of the tree
+        field: TreeField,
+    },
+    /// A part of the Merkle subtree written at the start of the epoch
+    Subtree {
+        /// Type of the subtree
+        store_type: &'a StoreType,
+        /// The epoch at which the subtree was written
+        epoch: Epoch,
+        /// The part of the tree
+        field: TreeField,
+    },
+    /// A replay protection entry
+    ReplayProtection(&'a Key),
+}
+
+impl<'a> DbKey<'a> {
+    /// The key of a part of the Merkle tree of the given type. The base tree
+    /// is stored at every height, while the subtrees are stored only at the
+    /// start of every epoch.
+    pub fn merkle_tree(
+        store_type: &'a StoreType,
+        epoch: Epoch,
+        height: BlockHeight,
+        field: TreeField,
+    ) -> Self {
+        if *store_type == StoreType::Base {
+            Self::BaseTree { height, field }
+        } else {
+            Self::Subtree {
+                store_type,
+                epoch,
+                field,
+            }
+        }
+    }
+
+    /// The namespace of the key
+    pub fn namespace(&self) -> Namespace {
+        match self {
+            DbKey::Subspace(_) => Namespace::Subspace,
+            DbKey::Diff { .. } | DbKey::History { .. } => Namespace::Diffs,
+            DbKey::State(_) | DbKey::PredState(_) => Namespace::State,
+            DbKey::Block { .. }
+            | DbKey::Results(_)
+            | DbKey::TxReceipt { .. }
+            | DbKey::TxReceiptHeight(_)
+            | DbKey::BaseTree { .. }
+            | DbKey::Subtree { .. } => Namespace::Block,
+            DbKey::ReplayProtection(_) => Namespace::ReplayProtection,
+        }
+    }
+
+    /// The key within its namespace
+    pub fn key(&self) -> String {
+        match self {
+            DbKey::Subspace(key) | DbKey::ReplayProtection(key) => {
+                key.to_string()
+            }
+            DbKey::Diff {
+                height,
+                is_old,
+                key,
+            } => diffs_prefix(*height, *is_old).join(key).to_string(),
+            DbKey::History { key, height } => {
+                history_prefix(key).with_segment(*height).to_string()
+            }
+            DbKey::State(state_key) => state_key.name().to_owned(),
+            DbKey::PredState(state_key) => {
+                format!("{PRED_PREFIX}/{}", state_key.name())
+            }
+            DbKey::Block { height, field } => {
+                format!("{}/{}", height.raw(), field.name())
+            }
+            DbKey::Results(height) => {
+                format!("{}{}", results_prefix(), height.raw())
+            }
+            DbKey::TxReceipt { height, hash } => {
+                format!("{}{hash}", receipts_prefix(*height))
+            }
+            DbKey::TxReceiptHeight(hash) => {
+                format!("{RECEIPT_HEIGHTS_PREFIX}/{hash}")
+            }
+            DbKey::BaseTree { height, field } => base_tree_key_prefix(*height)
+                .with_segment(field.name().to_owned())
+                .to_string(),
+            DbKey::Subtree {
+                store_type,
+                epoch,
+                field,
+            } => subtree_key_prefix(store_type, *epoch)
+                .with_segment(field.name().to_owned())
+                .to_string(),
+        }
+    }
+}
+
+/// The prefix of the diffs of the block at the height in the
+/// [`Namespace::Diffs`]
+pub fn diffs_prefix(height: BlockHeight, is_old: bool) -> Key {
+    let old_new_seg = if is_old {
+        OLD_DIFF_PREFIX
+    } else {
+        NEW_DIFF_PREFIX
+    };
+    Key::from(height.to_db_key()).with_segment(old_new_seg.to_owned())
+}
+
+/// The prefix of the index of the heights at which the key changed in the
+/// [`Namespace::Diffs`]. The key is hashed, so that the prefix only matches
+/// the entries of the key itself and not the ones of its children.
+pub fn history_prefix(key: &Key) -> Key {
+    let key_hash = Hash::sha256(key.to_string().as_bytes());
+    Key::from(HISTORY_PREFIX.to_owned().to_db_key())
+        .with_segment(key_hash.to_string())
+}
+
+/// The prefix of all the block results in the [`Namespace::Block`]
+pub fn results_prefix() -> String {
+    format!("{RESULTS_PREFIX}/")
+}
+
+/// The prefix of the receipts of the txs applied in the block at the height in
+/// the [`Namespace::Block`]
+pub fn receipts_prefix(height: BlockHeight) -> String {
+    format!("{RECEIPTS_PREFIX}/{}/", height.raw())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Test that the typed keys match the layout of the persisted DB
+    #[test]
+    fn test_db_keys() {
+        let key = Key::parse("key").unwrap();
+        let height = BlockHeight(10);
+        let raw_height = height.raw();
+        assert_eq!(DbKey::Subspace(&key).key(), "key");
+        assert_eq!(
+            DbKey::Diff {
+                height,
+                is_old: true,
+                key: &key
+            }
+            .key(),
+            format!("{raw_height}/old/key")
+        );
+        assert_eq!(
+            DbKey::History { key: &key, height }.key(),
+            format!("history/{}/{raw_height}", Hash::sha256(b"key"))
+        );
+        assert_eq!(DbKey::State(StateKey::Height).key(), "height");
+        assert_eq!(DbKey::PredState(StateKey::TxQueue).key(), "pred/tx_queue");
+        assert_eq!(
+            DbKey::Block {
+                height,
+                field: BlockField::PredEpochs
+            }
+            .key(),
+            format!("{raw_height}/pred_epochs")
+        );
+        assert_eq!(
+            DbKey::Results(height).key(),
+            format!("results/{raw_height}")
+        );
+        let hash = Hash::sha256(b"tx");
+        assert_eq!(
+            DbKey::TxReceipt {
+                height,
+                hash: &hash
+            }
+            .key(),
+            format!("receipts/{raw_height}/{hash}")
+        );
+        assert_eq!(
+            DbKey::TxReceiptHeight(&hash).key(),
+            format!("receipt_heights/{hash}")
+        );
+        assert_eq!(
+            DbKey::merkle_tree(
+                &StoreType::Base,
+                Epoch(1),
+                height,
+                TreeField::Root
+            )
+            .key(),
+            format!("{raw_height}/tree/base/root")
+        );
+        assert_eq!(
+            DbKey::merkle_tree(
+                &StoreType::Account,
+                Epoch(1),
+                height,
+                TreeField::Store
+            )
+            .key(),
+            format!("{}/tree/account/store", Epoch(1).raw())
+        );
+        assert_eq!(
+            DbKey::merkle_tree(
+                &StoreType::Ibc,
+                Epoch(1),
+                height,
+                TreeField::Root
+            )
+            .namespace(),
+            Namespace::Block
+        );
+    }
+}
@@ -1,6 +1,7 @@
 //! Storage API error type, extensible with custom user errors and static string
 //! messages.
 
+use namada_core::types::storage;
 use thiserror::Error;
 
 #[allow(missing_docs)]
@@ -12,6 +13,11 @@ pub enum Error {
     Custom(CustomError),
     #[error("{0}: {1}")]
     CustomWithMessage(&'static str, CustomError),
+    #[error("Failed to decode the value of the storage key {key}: {error}")]
+    Decoding {
+        key: storage::Key,
+        error: std::io::Error,
+    },
 }
 
 /// Result of a storage API call.
@@ -56,6 +62,15 @@ impl Error {
         Self::SimpleMessage(msg)
     }
 
+    /// Create an [`enum@Error`] for a value at the given key that couldn't be
+    /// Borsh decoded.
+    pub fn decoding(key: &storage::Key, error: std::io::Error) -> Self {
+        Self::Decoding {
+            key: key.clone(),
+            error,
+        }
+    }
+
     /// Wrap another [`std::error::Error`] with a static message.
     pub fn wrap<E>(msg: &'static str, error: E) -> Self
     where
@@ -0,0 +1,453 @@
+//! DB decorator with fault injection for testing
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Mutex;
+
+use namada_core::types::ethereum_events;
+use namada_core::types::hash::Hash;
+use namada_core::types::storage::{
+    BlockHeight, BlockResults, Epoch, Header, Key,
+};
+use namada_merkle_tree::{MerkleTreeStoresRead, StoreType};
+use namada_tx::data::TxReceipt;
+
+use crate::db::{BlockStateRead, BlockStateWrite, DBIter, Error, Result, DB};
+
+/// The DB operations into which a fault can be injected
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FaultyOp {
+    /// Persisting the written data, i.e. `DB::flush` and `DB::exec_batch`
+    Flush,
+    /// Writing a block's metadata with `DB::add_block_to_batch`
+    WriteBlock,
+    /// Reading a subspace value, i.e. `DB::read_subspace_val`,
+    /// `DB::read_subspace_val_with_height` and `DB::read_diffs_val`
+    Read,
+    /// Advancing any of the `DBIter` iterators
+    Iter,
+}
+
+/// The condition under which an injected fault triggers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Fault {
+    /// Fail every call of the operation after the given number of successful
+    /// calls
+    AfterCalls(u64),
+    /// Fail the calls of the operation that access the given key
+    OnKey(Key),
+}
+
+/// A DB that delegates to the wrapped DB, but fails the operations into which
+/// a fault has been injected. Because the iterators cannot return an error,
+/// an iteration failure panics, the same way as the iterators of the
+/// persistent DB do.
+#[derive(Debug, Default)]
+pub struct FaultyDB<D> {
+    /// The wrapped DB
+    pub db: D,
+    // The faults are wrapped in `Mutex` to allow injecting them and
+    // counting the calls from methods that require immutable self ref, also
+    // when the DB is shared across threads for parallelized VP runs.
+    faults: Mutex<Faults>,
+}
+
+/// The injected faults and the number of calls made of each operation
+#[derive(Debug, Default)]
+struct Faults {
+    faults: HashMap<FaultyOp, Vec<Fault>>,
+    calls: HashMap<FaultyOp, u64>,
+}
+
+impl<D> FaultyDB<D> {
+    /// Wrap the given DB with no faults injected
+    pub fn new(db: D) -> Self {
+        Self {
+            db,
+            faults: Default::default(),
+        }
+    }
+
+    /// Inject a fault into the given operation
+    pub fn inject(&self, op: FaultyOp, fault: Fault) {
+        self.faults
+            .lock()
+            .unwrap()
+            .faults
+            .entry(op)
+            .or_default()
+            .push(fault);
+    }
+
+    /// Remove all the injected faults and reset the calls counters
+    pub fn clear_faults(&self) {
+        *self.faults.lock().unwrap() = Faults::default();
+    }
+
+    /// The number of calls of the given operation made so far
+    pub fn calls(&self, op: FaultyOp) -> u64 {
+        self.faults
+            .lock()
+            .unwrap()
+            .calls
+            .get(&op)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    fn check(&self, op: FaultyOp, key: Option<&str>) -> Result<()> {
+        self.faults.lock().unwrap().check(op, key)
+    }
+
+    fn wrap_iter<I>(&self, iter: I) -> FaultyIterator<'_, I> {
+        FaultyIterator {
+            faults: &self.faults,
+            iter,
+        }
+    }
+}
+
+impl Faults {
+    /// Count a call of the operation and fail it, if any of its faults
+    /// triggers
+    fn check(&mut self, op: FaultyOp, key: Option<&str>) -> Result<()> {
+        let count = self.calls.entry(op).or_default();
+        let calls = *count;
+        *count += 1;
+        let triggered = self.faults.get(&op).map_or(false, |faults| {
+            faults.iter().any(|fault| match fault {
+                Fault::AfterCalls(n) => calls >= *n,
+                Fault::OnKey(fault_key) => {
+                    key == Some(fault_key.to_string().as_str())
+                }
+            })
+        });
+        if triggered {
+            Err(Error::DBError(format!("Injected {op:?} failure")))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl<D: DB> DB for FaultyDB<D> {
+    type Cache = D::Cache;
+    type WriteBatch = D::WriteBatch;
+
+    fn open(db_path: impl AsRef<Path>, cache: Option<&Self::Cache>) -> Self {
+        Self::new(D::open(db_path, cache))
+    }
+
+    fn flush(&self, wait: bool) -> Result<()> {
+        self.check(FaultyOp::Flush, None)?;
+        self.db.flush(wait)
+    }
+
+    fn read_last_block(&self) -> Result<Option<BlockStateRead>> {
+        self.db.read_last_block()
+    }
+
+    fn add_block_to_batch(
+        &self,
+        state: BlockStateWrite,
+        batch: &mut Self::WriteBatch,
+        is_full_commit: bool,
+    ) -> Result<()> {
+        self.check(FaultyOp::WriteBlock, None)?;
+        self.db.add_block_to_batch(state, batch, is_full_commit)
+    }
+
+    fn read_block_header(&self, height: BlockHeight) -> Result<Option<Header>> {
+        self.db.read_block_header(height)
+    }
+
+    fn read_merkle_tree_stores(
+        &self,
+        epoch: Epoch,
+        base_height: BlockHeight,
+        store_type: Option<StoreType>,
+    ) -> Result<Option<MerkleTreeStoresRead>> {
+        self.db
+            .read_merkle_tree_stores(epoch, base_height, store_type)
+    }
+
+    fn has_replay_protection_entry(&self, hash: &Hash) -> Result<bool> {
+        self.db.has_replay_protection_entry(hash)
+    }
+
+    fn read_tx_receipt(&self, hash: &Hash) -> Result<Option<TxReceipt>> {
+        self.db.read_tx_receipt(hash)
+    }
+
+    fn read_subspace_val(&self, key: &Key) -> Result<Option<Vec<u8>>> {
+        self.check(FaultyOp::Read, Some(&key.to_string()))?;
+        self.db.read_subspace_val(key)
+    }
+
+    fn read_subspace_val_with_height(
+        &self,
+        key: &Key,
+        height: BlockHeight,
+        last_height: BlockHeight,
+    ) -> Result<Option<Vec<u8>>> {
+        self.check(FaultyOp::Read, Some(&key.to_string()))?;
+        self.db
+            .read_subspace_val_with_height(key, height, last_height)
+    }
+
+    fn read_diffs_val(
+        &self,
+        key: &Key,
+        height: BlockHeight,
+        is_old: bool,
+    ) -> Result<Option<Vec<u8>>> {
+        self.check(FaultyOp::Read, Some(&key.to_string()))?;
+        self.db.read_diffs_val(key, height, is_old)
+    }
+
+    fn write_subspace_val(
+        &mut self,
+        height: BlockHeight,
+        key: &Key,
+        value: impl AsRef<[u8]>,
+        persist_diffs: bool,
+    ) -> Result<i64> {
+        self.db
+            .write_subspace_val(height, key, value, persist_diffs)
+    }
+
+    fn delete_subspace_val(
+        &mut self,
+        height: BlockHeight,
+        key: &Key,
+        persist_diffs: bool,
+    ) -> Result<i64> {
+        self.db.delete_subspace_val(height, key, persist_diffs)
+    }
+
+    fn batch() -> Self::WriteBatch {
+        D::batch()
+    }
+
+    fn exec_batch(&mut self, batch: Self::WriteBatch) -> Result<()> {
+        self.check(FaultyOp::Flush, None)?;
+        self.db.exec_batch(batch)
+    }
+
+    fn batch_write_subspace_val(
+        &self,
+        batch: &mut Self::WriteBatch,
+        height: BlockHeight,
+        key: &Key,
+        value: impl AsRef<[u8]>,
+        persist_diffs: bool,
+    ) -> Result<i64> {
+        self.db.batch_write_subspace_val(
+            batch,
+            height,
+            key,
+            value,
+            persist_diffs,
+        )
+    }
+
+    fn batch_delete_subspace_val(
+        &self,
+        batch: &mut Self::WriteBatch,
+        height: BlockHeight,
+        key: &Key,
+        persist_diffs: bool,
+    ) -> Result<i64> {
+        self.db
+            .batch_delete_subspace_val(batch, height, key, persist_diffs)
+    }
+
+    fn prune_merkle_tree_store(
+        &mut self,
+        batch: &mut Self::WriteBatch,
+        store_type: &StoreType,
+        pruned_epoch: Epoch,
+    ) -> Result<()> {
+        self.db
+            .prune_merkle_tree_store(batch, store_type, pruned_epoch)
+    }
+
+    fn prune_below(
+        &mut self,
+        batch: &mut Self::WriteBatch,
+        height: BlockHeight,
+    ) -> Result<()> {
+        self.db.prune_below(batch, height)
+    }
+
+    fn read_diffs_pruned_height(&self) -> Result<Option<BlockHeight>> {
+        self.db.read_diffs_pruned_height()
+    }
+
+    fn read_key_history(
+        &self,
+        key: &Key,
+        from_height: BlockHeight,
+        limit: usize,
+    ) -> Result<Vec<BlockHeight>> {
+        self.db.read_key_history(key, from_height, limit)
+    }
+
+    fn read_last_change_height(
+        &self,
+        key: &Key,
+        height: BlockHeight,
+    ) -> Result<Option<BlockHeight>> {
+        self.db.read_last_change_height(key, height)
+    }
+
+    fn read_db_version(&self) -> Result<Option<u64>> {
+        self.db.read_db_version()
+    }
+
+    fn write_db_version(
+        &mut self,
+        batch: &mut Self::WriteBatch,
+        version: u64,
+    ) -> Result<()> {
+        self.db.write_db_version(batch, version)
+    }
+
+    fn read_bridge_pool_signed_nonce(
+        &self,
+        height: BlockHeight,
+        last_height: BlockHeight,
+    ) -> Result<Option<ethereum_events::Uint>> {
+        self.db.read_bridge_pool_signed_nonce(height, last_height)
+    }
+
+    fn write_replay_protection_entry(
+        &mut self,
+        batch: &mut Self::WriteBatch,
+        key: &Key,
+    ) -> Result<()> {
+        self.db.write_replay_protection_entry(batch, key)
+    }
+
+    fn delete_replay_protection_entry(
+        &mut self,
+        batch: &mut Self::WriteBatch,
+        key: &Key,
+    ) -> Result<()> {
+        self.db.delete_replay_protection_entry(batch, key)
+    }
+
+    fn write_block_header(
+        &mut self,
+        batch: &mut Self::WriteBatch,
+        height: BlockHeight,
+        header: &Header,
+    ) -> Result<()> {
+        self.db.write_block_header(batch, height, header)
+    }
+
+    fn write_block_results(
+        &mut self,
+        batch: &mut Self::WriteBatch,
+        height: BlockHeight,
+        results: &BlockResults,
+    ) -> Result<()> {
+        self.db.write_block_results(batch, height, results)
+    }
+
+    fn write_checkpoint(&self, epoch: Epoch) -> Result<()> {
+        self.db.write_checkpoint(epoch)
+    }
+
+    fn restore_checkpoint(&mut self, epoch: Epoch) -> Result<()> {
+        self.db.restore_checkpoint(epoch)
+    }
+
+    fn delete_checkpoint(&self, epoch: Epoch) -> Result<()> {
+        self.db.delete_checkpoint(epoch)
+    }
+
+    fn checkpoints(&self) -> Result<Vec<Epoch>> {
+        self.db.checkpoints()
+    }
+
+    fn read_only_view(&self) -> Result<Self> {
+        self.db.read_only_view().map(Self::new)
+    }
+}
+
+impl<'iter, D> DBIter<'iter> for FaultyDB<D>
+where
+    D: DB + DBIter<'iter>,
+{
+    type PrefixIter = FaultyIterator<'iter, D::PrefixIter>;
+
+    fn iter_prefix(&'iter self, prefix: Option<&Key>) -> Self::PrefixIter {
+        self.wrap_iter(self.db.iter_prefix(prefix))
+    }
+
+    fn rev_iter_prefix(&'iter self, prefix: Option<&Key>) -> Self::PrefixIter {
+        self.wrap_iter(self.db.rev_iter_prefix(prefix))
+    }
+
+    fn iter_range(&'iter self, start: &Key, end: &Key) -> Self::PrefixIter {
+        self.wrap_iter(self.db.iter_range(start, end))
+    }
+
+    fn iter_results(&'iter self) -> Self::PrefixIter {
+        self.wrap_iter(self.db.iter_results())
+    }
+
+    fn iter_old_diffs(
+        &'iter self,
+        height: BlockHeight,
+        prefix: Option<&'iter Key>,
+    ) -> Self::PrefixIter {
+        self.wrap_iter(self.db.iter_old_diffs(height, prefix))
+    }
+
+    fn iter_new_diffs(
+        &'iter self,
+        height: BlockHeight,
+        prefix: Option<&'iter Key>,
+    ) -> Self::PrefixIter {
+        self.wrap_iter(self.db.iter_new_diffs(height, prefix))
+    }
+
+    fn iter_replay_protection(&'iter self) -> Self::PrefixIter {
+        self.wrap_iter(self.db.iter_replay_protection())
+    }
+
+    fn iter_all_replay_protection(&'iter self) -> Self::PrefixIter {
+        self.wrap_iter(self.db.iter_all_replay_protection())
+    }
+}
+
+/// An iterator of a [`FaultyDB`] that panics when a fault injected into
+/// [`FaultyOp::Iter`] triggers. Every yielded item counts as a call and the
+/// [`Fault::OnKey`] faults are matched against the yielded keys.
+#[derive(Debug)]
+pub struct FaultyIterator<'iter, I> {
+    faults: &'iter Mutex<Faults>,
+    iter: I,
+}
+
+impl<'iter, I> Iterator for FaultyIterator<'iter, I>
+where
+    I: Iterator<Item = (String, Vec<u8>, u64)>,
+{
+    type Item = (String, Vec<u8>, u64);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.iter.next()?;
+        if let Err(err) = self
+            .faults
+            .lock()
+            .unwrap()
+            .check(FaultyOp::Iter, Some(&item.0))
+        {
+            panic!("Prefix iterator shouldn't fail: {err}");
+        }
+        Some(item)
+    }
+}
@@ -3,7 +3,11 @@
 
 pub mod collections;
 mod db;
+pub mod db_key;
 mod error;
+#[cfg(any(test, feature = "testing"))]
+pub mod faultydb;
+pub mod migrations;
 pub mod mockdb;
 pub mod tx_queue;
 pub mod types;
@@ -46,7 +50,8 @@ pub trait StorageRead {
         let bytes = self.read_bytes(key)?;
         match bytes {
             Some(bytes) => {
-                let val = T::try_from_slice(&bytes).into_storage_result()?;
+                let val = T::try_from_slice(&bytes)
+                    .map_err(|err| Error::decoding(key, err))?;
                 Ok(Some(val))
             }
             None => Ok(None),
@@ -222,7 +227,9 @@ where
                         return Some(Err(err));
                     }
                 };
-                let val = match T::try_from_slice(&val).into_storage_result() {
+                let val = match T::try_from_slice(&val)
+                    .map_err(|err| Error::decoding(&key, err))
+                {
                     Ok(val) => val,
                     Err(err) => {
                         // Propagate val encoding errors into Iterator's Item
@@ -279,15 +286,16 @@ where
                     if !filter(&key) {
                         continue;
                     }
-                    let val =
-                        match T::try_from_slice(&val).into_storage_result() {
-                            Ok(val) => val,
-                            Err(err) => {
-                                // Propagate val encoding errors into Iterator's
-                                // Item
-                                return Some(Err(err));
-                            }
-                        };
+                    let val = match T::try_from_slice(&val)
+                        .map_err(|err| Error::decoding(&key, err))
+                    {
+                        Ok(val) => val,
+                        Err(err) => {
+                            // Propagate val encoding errors into Iterator's
+                            // Item
+                            return Some(Err(err));
+                        }
+                    };
                     return Some(Ok((key, val)));
                 }
                 Ok(None) => return None,
@@ -0,0 +1,232 @@
+//! Migrations of the on-disk format of the DB.
+//!
+//! The version of the DB format is stored in the DB. When a node is upgraded
+//! to a version that changes the format, the [`Migration`]s with a higher
+//! version than the stored one are applied in order on startup. A DB written
+//! before the version was introduced has the version `0`.
+
+use crate::db::{Error, Result, DB};
+
+/// A migration of the DB format from the previous version
+pub struct Migration<D: DB> {
+    /// The version of the DB format after the migration
+    pub version: u64,
+    /// A short description of the migration for the logs
+    pub description: &'static str,
+    /// Add the changes of the migration to the batch. The batch is executed
+    /// together with the write of the new version.
+    pub migrate: fn(&D, &mut D::WriteBatch) -> Result<()>,
+}
+
+/// All the migrations of the DB format, in ascending order of their versions.
+/// The version of the last migration is the current version of the format.
+pub fn all<D: DB>() -> Vec<Migration<D>> {
+    vec![]
+}
+
+/// The current version of the DB format of the given migrations
+pub fn current_version<D: DB>(migrations: &[Migration<D>]) -> u64 {
+    migrations
+        .last()
+        .map(|migration| migration.version)
+        .unwrap_or_default()
+}
+
+/// Write the current version of the DB format into a new DB, which is created
+/// in that format and has nothing to migrate. The version of a DB that
+/// already holds a block is left as is.
+pub fn init_version<D: DB>(
+    db: &mut D,
+    migrations: &[Migration<D>],
+) -> Result<()> {
+    if db.read_db_version()?.is_some() || db.read_last_block()?.is_some() {
+        return Ok(());
+    }
+    let mut batch = D::batch();
+    db.write_db_version(&mut batch, current_version(migrations))?;
+    db.exec_batch(batch)
+}
+
+/// Apply the migrations with a higher version than the version stored in the
+/// DB, in ascending order. Each migration is executed in a separate batch
+/// with the write of its version, so an interrupted migration can be
+/// resumed. With `dry_run`, the DB is not changed. Returns the versions of
+/// the applied (or, with `dry_run`, pending) migrations.
+pub fn migrate<D: DB>(
+    db: &mut D,
+    migrations: &[Migration<D>],
+    dry_run: bool,
+) -> Result<Vec<u64>> {
+    if let Some(pair) = migrations
+        .windows(2)
+        .find(|pair| pair[0].version >= pair[1].version)
+    {
+        return Err(Error::DBError(format!(
+            "The DB migrations must be in ascending order of their versions, \
+             found version {} after {}",
+            pair[1].version, pair[0].version
+        )));
+    }
+
+    let stored_version = db.read_db_version()?.unwrap_or_default();
+    let version = current_version(migrations);
+    if stored_version > version {
+        return Err(Error::DBError(format!(
+            "The DB format version {stored_version} is newer than the \
+             supported version {version}"
+        )));
+    }
+
+    let mut migrated = vec![];
+    for migration in migrations
+        .iter()
+        .filter(|migration| migration.version > stored_version)
+    {
+        if dry_run {
+            tracing::info!(
+                "The DB migration to version {} would be applied: {}",
+                migration.version,
+                migration.description
+            );
+        } else {
+            tracing::info!(
+                "Applying the DB migration to version {}: {}",
+                migration.version,
+                migration.description
+            );
+            let mut batch = D::batch();
+            (migration.migrate)(db, &mut batch)?;
+            db.write_db_version(&mut batch, migration.version)?;
+            db.exec_batch(batch)?;
+        }
+        migrated.push(migration.version);
+    }
+    Ok(migrated)
+}
+
+#[cfg(test)]
+mod tests {
+    use namada_core::types::storage::{BlockHeight, Key, KeySeg};
+
+    use super::*;
+    use crate::mockdb::MockDB;
+    use crate::DBIter;
+
+    const OLD_PREFIX: &str = "old";
+    const NEW_PREFIX: &str = "new";
+
+    /// A migration that moves the keys under the old prefix to the new one
+    fn move_keys(
+        db: &MockDB,
+        batch: &mut <MockDB as DB>::WriteBatch,
+    ) -> Result<()> {
+        let old_prefix = Key::parse(OLD_PREFIX).unwrap();
+        let old_keys: Vec<_> = db
+            .iter_prefix(Some(&old_prefix))
+            .map(|(key, value, _gas)| (Key::parse(key).unwrap(), value))
+            .collect();
+        for (key, value) in old_keys {
+            let mut new_key = key.clone();
+            new_key.segments[0] = NEW_PREFIX.to_owned().to_db_key();
+            db.batch_write_subspace_val(
+                batch,
+                BlockHeight::first(),
+                &new_key,
+                value,
+                false,
+            )?;
+            db.batch_delete_subspace_val(
+                batch,
+                BlockHeight::first(),
+                &key,
+                false,
+            )?;
+        }
+        Ok(())
+    }
+
+    fn no_op(
+        _db: &MockDB,
+        _batch: &mut <MockDB as DB>::WriteBatch,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    fn migrations() -> Vec<Migration<MockDB>> {
+        vec![
+            Migration {
+                version: 1,
+                description: "Move the keys",
+                migrate: move_keys,
+            },
+            Migration {
+                version: 2,
+                description: "Nothing to do",
+                migrate: no_op,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_migrate() {
+        let mut db = MockDB::default();
+        let old_key = Key::parse(format!("{OLD_PREFIX}/key")).unwrap();
+        let new_key = Key::parse(format!("{NEW_PREFIX}/key")).unwrap();
+        db.write_subspace_val(BlockHeight::first(), &old_key, [1_u8], false)
+            .unwrap();
+
+        // A dry run only lists the pending migrations
+        assert_eq!(migrate(&mut db, &migrations(), true).unwrap(), vec![1, 2]);
+        assert_eq!(db.read_db_version().unwrap(), None);
+        assert_eq!(db.read_subspace_val(&old_key).unwrap(), Some(vec![1]));
+
+        assert_eq!(migrate(&mut db, &migrations(), false).unwrap(), vec![1, 2]);
+        assert_eq!(db.read_db_version().unwrap(), Some(2));
+        assert_eq!(db.read_subspace_val(&old_key).unwrap(), None);
+        assert_eq!(db.read_subspace_val(&new_key).unwrap(), Some(vec![1]));
+
+        // The applied migrations are not applied again
+        assert!(migrate(&mut db, &migrations(), false).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_migrate_from_stored_version() {
+        let mut db = MockDB::default();
+        let mut batch = MockDB::batch();
+        db.write_db_version(&mut batch, 1).unwrap();
+        db.exec_batch(batch).unwrap();
+        assert_eq!(migrate(&mut db, &migrations(), false).unwrap(), vec![2]);
+
+        // A DB written by a newer version of the node can't be used
+        let mut batch = MockDB::batch();
+        db.write_db_version(&mut batch, 3).unwrap();
+        db.exec_batch(batch).unwrap();
+        assert!(migrate(&mut db, &migrations(), false).is_err());
+    }
+
+    #[test]
+    fn test_init_version() {
+        // A new DB gets the current version and has nothing to migrate
+        let mut db = MockDB::default();
+        init_version(&mut db, &migrations()).unwrap();
+        assert_eq!(db.read_db_version().unwrap(), Some(2));
+        assert!(migrate(&mut db, &migrations(), false).unwrap().is_empty());
+
+        // A stored version is not overwritten
+        let mut db = MockDB::default();
+        let mut batch = MockDB::batch();
+        db.write_db_version(&mut batch, 1).unwrap();
+        db.exec_batch(batch).unwrap();
+        init_version(&mut db, &migrations()).unwrap();
+        assert_eq!(db.read_db_version().unwrap(), Some(1));
+    }
+
+    #[test]
+    fn test_migrations_order() {
+        let mut db = MockDB::default();
+        let mut migrations = migrations();
+        migrations.reverse();
+        assert!(migrate(&mut db, &migrations, false).is_err());
+        assert_eq!(db.read_db_version().unwrap(), None);
+    }
+}
@@ -2,54 +2,98 @@
 
 use std::cell::RefCell;
 use std::collections::{btree_map, BTreeMap};
-use std::ops::Bound::{Excluded, Included};
+use std::ops::Bound::{Excluded, Included, Unbounded};
 use std::path::Path;
-use std::str::FromStr;
 
 use itertools::Either;
 use namada_core::borsh::{BorshDeserialize, BorshSerializeExt};
 use namada_core::ledger::replay_protection;
 use namada_core::types;
+use namada_core::types::ethereum_events;
 use namada_core::types::hash::Hash;
 use namada_core::types::storage::{
-    BlockHeight, BlockResults, Epoch, EthEventsQueue, Header, Key, KeySeg,
-    KEY_SEGMENT_SEPARATOR,
-};
-use namada_core::types::time::DateTimeUtc;
-use namada_core::types::token::ConversionState;
-use namada_core::types::{ethereum_events, ethereum_structs};
-use namada_merkle_tree::{
-    base_tree_key_prefix, subtree_key_prefix, MerkleTreeStoresRead, StoreType,
+    BlockHeight, BlockResults, Epoch, Header, Key, KeySeg,
 };
+use namada_merkle_tree::{MerkleTreeStoresRead, StoreType};
+use namada_tx::data::TxReceipt;
 
 use crate::db::{
     BlockStateRead, BlockStateWrite, DBIter, DBWriteBatch, Error, Result, DB,
 };
-use crate::tx_queue::TxQueue;
+use crate::db_key::{
+    diffs_prefix, history_prefix, results_prefix, BlockField, DbKey, Namespace,
+    StateKey, TreeField,
+};
 use crate::types::{KVBytes, PrefixIterator};
 
-const SUBSPACE_CF: &str = "subspace";
-
-const OLD_DIFF_PREFIX: &str = "old";
-const NEW_DIFF_PREFIX: &str = "new";
-
 /// An in-memory DB for testing.
 #[derive(Debug, Default)]
 pub struct MockDB(
     // The state is wrapped in `RefCell` to allow modifying it directly from
     // batch write method (which requires immutable self ref).
     RefCell<BTreeMap<String, Vec<u8>>>,
+    // The checkpoints of the state by their epochs
+    RefCell<BTreeMap<Epoch, BTreeMap<String, Vec<u8>>>>,
 );
 
+impl MockDB {
+    /// The key of the typed key in the map, prefixed with its namespace
+    fn db_key(key: DbKey) -> String {
+        format!("{}/{}", key.namespace(), key.key())
+    }
+
+    fn read_value(&self, key: DbKey) -> Option<Vec<u8>> {
+        self.0.borrow().get(&Self::db_key(key)).cloned()
+    }
+
+    fn read_decoded<T: BorshDeserialize>(
+        &self,
+        key: DbKey,
+    ) -> Result<Option<T>> {
+        self.read_value(key)
+            .map(|bytes| {
+                types::decode(bytes).map_err(|error| Error::DecodeFailed {
+                    key: format!("{}/{}", key.namespace(), key.key()),
+                    error,
+                })
+            })
+            .transpose()
+    }
+
+    fn insert(&self, key: DbKey, value: Vec<u8>) -> Option<Vec<u8>> {
+        self.0.borrow_mut().insert(Self::db_key(key), value)
+    }
+
+    fn remove(&self, key: DbKey) -> Option<Vec<u8>> {
+        self.0.borrow_mut().remove(&Self::db_key(key))
+    }
+}
+
 // The `MockDB` is not `Sync`, but we're sharing it across threads for reading
 // only (for parallelized VP runs). In a different context, this may not be
 // safe.
 unsafe impl Sync for MockDB {}
 
-/// An in-memory write batch is not needed as it just updates values in memory.
-/// It's here to satisfy the storage interface.
+/// An in-memory write batch. Most of the batch writes update the values in
+/// memory directly, only the writes and deletes added to this batch are
+/// deferred until it's executed.
 #[derive(Debug, Default)]
-pub struct MockDBWriteBatch;
+pub struct MockDBWriteBatch {
+    /// The keys to write (with `Some` value) or delete (with `None`)
+    ops: Vec<(String, Option<Vec<u8>>)>,
+    /// The keys of the subspace and replay protection writes and deletes
+    /// that were applied directly, in order
+    applied: Vec<String>,
+}
+
+impl MockDBWriteBatch {
+    /// The keys of the subspace and replay protection writes and deletes
+    /// that were applied directly, in the order in which they reached this
+    /// batch. Each key is prefixed with its namespace, e.g. `subspace/key`.
+    pub fn applied_keys(&self) -> &[String] {
+        &self.applied
+    }
+}
 
 impl DB for MockDB {
     /// There is no cache for MockDB
@@ -66,186 +110,98 @@ impl DB for MockDB {
 
     fn read_last_block(&self) -> Result<Option<BlockStateRead>> {
         // Block height
-        let height: BlockHeight = match self.0.borrow().get("height") {
-            Some(bytes) => types::decode(bytes).map_err(Error::CodingError)?,
-            None => return Ok(None),
-        };
-        // Block results
-        let results_path = format!("results/{}", height.raw());
-        let results: BlockResults =
-            match self.0.borrow().get(results_path.as_str()) {
-                Some(bytes) => {
-                    types::decode(bytes).map_err(Error::CodingError)?
-                }
-                None => return Ok(None),
-            };
-
-        // Epoch start height and time
-        let next_epoch_min_start_height: BlockHeight =
-            match self.0.borrow().get("next_epoch_min_start_height") {
-                Some(bytes) => {
-                    types::decode(bytes).map_err(Error::CodingError)?
-                }
-                None => return Ok(None),
-            };
-        let next_epoch_min_start_time: DateTimeUtc =
-            match self.0.borrow().get("next_epoch_min_start_time") {
-                Some(bytes) => {
-                    types::decode(bytes).map_err(Error::CodingError)?
-                }
+        let height: BlockHeight =
+            match self.read_decoded(DbKey::State(StateKey::Height))? {
+                Some(height) => height,
                 None => return Ok(None),
             };
-        let update_epoch_blocks_delay: Option<u32> =
-            match self.0.borrow().get("update_epoch_blocks_delay") {
-                Some(bytes) => {
-                    types::decode(bytes).map_err(Error::CodingError)?
-                }
-                None => return Ok(None),
-            };
-        let conversion_state: ConversionState =
-            match self.0.borrow().get("conversion_state") {
-                Some(bytes) => {
-                    types::decode(bytes).map_err(Error::CodingError)?
-                }
-                None => return Ok(None),
-            };
-        let tx_queue: TxQueue = match self.0.borrow().get("tx_queue") {
-            Some(bytes) => types::decode(bytes).map_err(Error::CodingError)?,
-            None => return Ok(None),
-        };
-
-        let ethereum_height: Option<ethereum_structs::BlockHeight> =
-            match self.0.borrow().get("ethereum_height") {
-                Some(bytes) => {
-                    types::decode(bytes).map_err(Error::CodingError)?
-                }
-                None => return Ok(None),
-            };
-
-        let eth_events_queue: EthEventsQueue =
-            match self.0.borrow().get("ethereum_height") {
-                Some(bytes) => {
-                    types::decode(bytes).map_err(Error::CodingError)?
-                }
+        // Block results
+        let results: BlockResults =
+            match self.read_decoded(DbKey::Results(height))? {
+                Some(results) => results,
                 None => return Ok(None),
             };
 
-        // Load data at the height
-        let prefix = format!("{}/", height.raw());
-        let upper_prefix = format!("{}/", height.next_height().raw());
-        let mut merkle_tree_stores = MerkleTreeStoresRead::default();
-        let mut hash = None;
-        let mut time = None;
-        let mut epoch: Option<Epoch> = None;
-        let mut pred_epochs = None;
-        let mut address_gen = None;
-        for (path, bytes) in self
-            .0
-            .borrow()
-            .range((Included(prefix), Excluded(upper_prefix)))
-        {
-            let segments: Vec<&str> =
-                path.split(KEY_SEGMENT_SEPARATOR).collect();
-            match segments.get(1) {
-                Some(prefix) => match *prefix {
-                    "tree" => match segments.get(2) {
-                        Some(s) => {
-                            let st = StoreType::from_str(s)?;
-                            match segments.get(3) {
-                                Some(&"root") => merkle_tree_stores.set_root(
-                                    &st,
-                                    types::decode(bytes)
-                                        .map_err(Error::CodingError)?,
-                                ),
-                                Some(&"store") => merkle_tree_stores
-                                    .set_store(st.decode_store(bytes)?),
-                                _ => unknown_key_error(path)?,
-                            }
-                        }
-                        None => unknown_key_error(path)?,
-                    },
-                    "header" => {
-                        // the block header doesn't have to be restored
-                    }
-                    "hash" => {
-                        hash = Some(
-                            types::decode(bytes).map_err(Error::CodingError)?,
-                        )
-                    }
-                    "time" => {
-                        time = Some(
-                            types::decode(bytes).map_err(Error::CodingError)?,
-                        )
-                    }
-                    "epoch" => {
-                        epoch = Some(
-                            types::decode(bytes).map_err(Error::CodingError)?,
-                        )
-                    }
-                    "pred_epochs" => {
-                        pred_epochs = Some(
-                            types::decode(bytes).map_err(Error::CodingError)?,
-                        )
-                    }
-                    "address_gen" => {
-                        address_gen = Some(
-                            types::decode(bytes).map_err(Error::CodingError)?,
-                        );
-                    }
-                    _ => unknown_key_error(path)?,
-                },
-                None => unknown_key_error(path)?,
-            }
-        }
-        // Restore subtrees of Merkle tree
-        if let Some(epoch) = epoch {
-            for st in StoreType::iter_subtrees() {
-                let prefix_key = subtree_key_prefix(st, epoch);
-                let root_key =
-                    prefix_key.clone().with_segment("root".to_owned());
-                if let Some(bytes) = self.0.borrow().get(&root_key.to_string())
-                {
-                    merkle_tree_stores.set_root(
-                        st,
-                        types::decode(bytes).map_err(Error::CodingError)?,
-                    );
-                }
-                let store_key = prefix_key.with_segment("store".to_owned());
-                if let Some(bytes) = self.0.borrow().get(&store_key.to_string())
-                {
-                    merkle_tree_stores.set_store(st.decode_store(bytes)?);
-                }
-            }
-        }
-        match (hash, time, epoch, pred_epochs, address_gen) {
+        // The latest ledger state
+        let (
+            next_epoch_min_start_height,
+            next_epoch_min_start_time,
+            update_epoch_blocks_delay,
+            conversion_state,
+            tx_queue,
+            ethereum_height,
+            eth_events_queue,
+        ) = match (
+            self.read_decoded(DbKey::State(StateKey::NextEpochMinStartHeight))?,
+            self.read_decoded(DbKey::State(StateKey::NextEpochMinStartTime))?,
+            self.read_decoded(DbKey::State(StateKey::UpdateEpochBlocksDelay))?,
+            self.read_decoded(DbKey::State(StateKey::ConversionState))?,
+            self.read_decoded(DbKey::State(StateKey::TxQueue))?,
+            self.read_decoded(DbKey::State(StateKey::EthereumHeight))?,
+            self.read_decoded(DbKey::State(StateKey::EthEventsQueue))?,
+        ) {
             (
-                Some(hash),
-                Some(time),
-                Some(epoch),
-                Some(pred_epochs),
-                Some(address_gen),
-            ) => Ok(Some(BlockStateRead {
-                merkle_tree_stores,
-                hash,
-                height,
-                time,
-                epoch,
-                pred_epochs,
+                Some(next_epoch_min_start_height),
+                Some(next_epoch_min_start_time),
+                Some(update_epoch_blocks_delay),
+                Some(conversion_state),
+                Some(tx_queue),
+                Some(ethereum_height),
+                Some(eth_events_queue),
+            ) => (
                 next_epoch_min_start_height,
                 next_epoch_min_start_time,
                 update_epoch_blocks_delay,
-                address_gen,
-                results,
                 conversion_state,
                 tx_queue,
                 ethereum_height,
                 eth_events_queue,
-            })),
-            _ => Err(Error::Temporary {
-                error: "Essential data couldn't be read from the DB"
-                    .to_string(),
-            }),
-        }
+            ),
+            _ => return Ok(None),
+        };
+
+        // Load data at the height
+        let block_key = |field| DbKey::Block { height, field };
+        let missing =
+            |component| Error::MissingBlockComponent { height, component };
+        let hash = self
+            .read_decoded(block_key(BlockField::Hash))?
+            .ok_or_else(|| missing("hash"))?;
+        let time = self
+            .read_decoded(block_key(BlockField::Time))?
+            .ok_or_else(|| missing("time"))?;
+        let epoch: Epoch = self
+            .read_decoded(block_key(BlockField::Epoch))?
+            .ok_or_else(|| missing("epoch"))?;
+        let pred_epochs = self
+            .read_decoded(block_key(BlockField::PredEpochs))?
+            .ok_or_else(|| missing("predecessor epochs"))?;
+        let address_gen = self
+            .read_decoded(block_key(BlockField::AddressGen))?
+            .ok_or_else(|| missing("address generator"))?;
+
+        // The base tree is written at every height and the subtrees
+        // at the start of the epoch
+        let merkle_tree_stores = self
+            .read_merkle_tree_stores(epoch, height, None)?
+            .unwrap_or_default();
+        Ok(Some(BlockStateRead {
+            merkle_tree_stores,
+            hash,
+            height,
+            time,
+            epoch,
+            pred_epochs,
+            next_epoch_min_start_height,
+            next_epoch_min_start_time,
+            update_epoch_blocks_delay,
+            address_gen,
+            results,
+            conversion_state,
+            tx_queue,
+            ethereum_height,
+            eth_events_queue,
+        }))
     }
 
     fn add_block_to_batch(
@@ -267,6 +223,7 @@ impl DB for MockDB {
             update_epoch_blocks_delay,
             address_gen,
             results,
+            receipts,
             conversion_state,
             ethereum_height,
             eth_events_queue,
@@ -274,132 +231,86 @@ impl DB for MockDB {
         }: BlockStateWrite = state;
 
         // Epoch start height and time
-        self.0.borrow_mut().insert(
-            "next_epoch_min_start_height".into(),
+        self.insert(
+            DbKey::State(StateKey::NextEpochMinStartHeight),
             types::encode(&next_epoch_min_start_height),
         );
-        self.0.borrow_mut().insert(
-            "next_epoch_min_start_time".into(),
+        self.insert(
+            DbKey::State(StateKey::NextEpochMinStartTime),
             types::encode(&next_epoch_min_start_time),
         );
-        self.0.borrow_mut().insert(
-            "update_epoch_blocks_delay".into(),
+        self.insert(
+            DbKey::State(StateKey::UpdateEpochBlocksDelay),
             types::encode(&update_epoch_blocks_delay),
         );
-        self.0
-            .borrow_mut()
-            .insert("ethereum_height".into(), types::encode(&ethereum_height));
-        self.0.borrow_mut().insert(
-            "eth_events_queue".into(),
+        self.insert(
+            DbKey::State(StateKey::EthereumHeight),
+            types::encode(&ethereum_height),
+        );
+        self.insert(
+            DbKey::State(StateKey::EthEventsQueue),
             types::encode(&eth_events_queue),
         );
-        self.0
-            .borrow_mut()
-            .insert("tx_queue".into(), types::encode(&tx_queue));
-        self.0
-            .borrow_mut()
-            .insert("conversion_state".into(), types::encode(conversion_state));
-
-        let prefix_key = Key::from(height.to_db_key());
+        self.insert(DbKey::State(StateKey::TxQueue), types::encode(&tx_queue));
+        self.insert(
+            DbKey::State(StateKey::ConversionState),
+            types::encode(conversion_state),
+        );
+
         // Merkle tree
-        {
-            for st in StoreType::iter() {
-                if *st == StoreType::Base || is_full_commit {
-                    let key_prefix = if *st == StoreType::Base {
-                        base_tree_key_prefix(height)
-                    } else {
-                        subtree_key_prefix(st, epoch)
-                    };
-                    let root_key =
-                        key_prefix.clone().with_segment("root".to_owned());
-                    self.0.borrow_mut().insert(
-                        root_key.to_string(),
-                        types::encode(merkle_tree_stores.root(st)),
-                    );
-                    let store_key = key_prefix.with_segment("store".to_owned());
-                    self.0.borrow_mut().insert(
-                        store_key.to_string(),
-                        merkle_tree_stores.store(st).encode(),
-                    );
-                }
+        for st in StoreType::iter() {
+            if *st == StoreType::Base || is_full_commit {
+                self.insert(
+                    DbKey::merkle_tree(st, epoch, height, TreeField::Root),
+                    types::encode(merkle_tree_stores.root(st)),
+                );
+                self.insert(
+                    DbKey::merkle_tree(st, epoch, height, TreeField::Store),
+                    merkle_tree_stores.store(st).encode(),
+                );
             }
         }
+        let block_key = |field| DbKey::Block { height, field };
         // Block header
-        {
-            if let Some(h) = header {
-                let key = prefix_key
-                    .push(&"header".to_owned())
-                    .map_err(Error::KeyError)?;
-                self.0
-                    .borrow_mut()
-                    .insert(key.to_string(), h.serialize_to_vec());
-            }
+        if let Some(h) = header {
+            self.insert(block_key(BlockField::Header), h.serialize_to_vec());
         }
         // Block hash
-        {
-            let key = prefix_key
-                .push(&"hash".to_owned())
-                .map_err(Error::KeyError)?;
-            self.0
-                .borrow_mut()
-                .insert(key.to_string(), types::encode(&hash));
-        }
+        self.insert(block_key(BlockField::Hash), types::encode(&hash));
         // Block time
-        {
-            let key = prefix_key
-                .push(&"time".to_owned())
-                .map_err(Error::KeyError)?;
-            self.0
-                .borrow_mut()
-                .insert(key.to_string(), types::encode(&time));
-        }
+        self.insert(block_key(BlockField::Time), types::encode(&time));
         // Block epoch
-        {
-            let key = prefix_key
-                .push(&"epoch".to_owned())
-                .map_err(Error::KeyError)?;
-            self.0
-                .borrow_mut()
-                .insert(key.to_string(), types::encode(&epoch));
-        }
+        self.insert(block_key(BlockField::Epoch), types::encode(&epoch));
         // Predecessor block epochs
-        {
-            let key = prefix_key
-                .push(&"pred_epochs".to_owned())
-                .map_err(Error::KeyError)?;
-            self.0
-                .borrow_mut()
-                .insert(key.to_string(), types::encode(&pred_epochs));
-        }
+        self.insert(
+            block_key(BlockField::PredEpochs),
+            types::encode(&pred_epochs),
+        );
         // Address gen
-        {
-            let key = prefix_key
-                .push(&"address_gen".to_owned())
-                .map_err(Error::KeyError)?;
-            let value = &address_gen;
-            self.0
-                .borrow_mut()
-                .insert(key.to_string(), types::encode(value));
-        }
-        self.0
-            .borrow_mut()
-            .insert("height".to_owned(), types::encode(&height));
+        self.insert(
+            block_key(BlockField::AddressGen),
+            types::encode(&address_gen),
+        );
+        self.insert(DbKey::State(StateKey::Height), types::encode(&height));
         // Block results
-        {
-            let results_path = format!("results/{}", height.raw());
-            self.0
-                .borrow_mut()
-                .insert(results_path, types::encode(&results));
+        self.insert(DbKey::Results(height), types::encode(&results));
+        // Receipts of the applied txs
+        for receipt in receipts {
+            let hash = &receipt.hash;
+            self.insert(
+                DbKey::TxReceipt { height, hash },
+                types::encode(receipt),
+            );
+            self.insert(DbKey::TxReceiptHeight(hash), types::encode(&height));
         }
         Ok(())
     }
 
     fn read_block_header(&self, height: BlockHeight) -> Result<Option<Header>> {
-        let prefix_key = Key::from(height.to_db_key());
-        let key = prefix_key
-            .push(&"header".to_owned())
-            .map_err(Error::KeyError)?;
-        let value = self.0.borrow().get(&key.to_string()).cloned();
+        let value = self.read_value(DbKey::Block {
+            height,
+            field: BlockField::Header,
+        });
         match value {
             Some(v) => Ok(Some(
                 BorshDeserialize::try_from_slice(&v[..])
@@ -421,24 +332,16 @@ impl DB for MockDB {
             .map(|st| Either::Left(std::iter::once(st)))
             .unwrap_or_else(|| Either::Right(StoreType::iter()));
         for st in store_types {
-            let key_prefix = if *st == StoreType::Base {
-                base_tree_key_prefix(base_height)
-            } else {
-                subtree_key_prefix(st, epoch)
-            };
-            let root_key = key_prefix.clone().with_segment("root".to_owned());
-            let bytes = self.0.borrow().get(&root_key.to_string()).cloned();
-            match bytes {
-                Some(b) => {
-                    let root = types::decode(b).map_err(Error::CodingError)?;
-                    merkle_tree_stores.set_root(st, root);
-                }
+            let root_key =
+                DbKey::merkle_tree(st, epoch, base_height, TreeField::Root);
+            match self.read_decoded(root_key)? {
+                Some(root) => merkle_tree_stores.set_root(st, root),
                 None => return Ok(None),
             }
 
-            let store_key = key_prefix.with_segment("store".to_owned());
-            let bytes = self.0.borrow().get(&store_key.to_string()).cloned();
-            match bytes {
+            let store_key =
+                DbKey::merkle_tree(st, epoch, base_height, TreeField::Store);
+            match self.read_value(store_key) {
                 Some(b) => {
                     merkle_tree_stores.set_store(st.decode_store(b)?);
                 }
@@ -449,14 +352,11 @@ impl DB for MockDB {
     }
 
     fn has_replay_protection_entry(&self, hash: &Hash) -> Result<bool> {
-        let prefix_key =
-            Key::parse("replay_protection").map_err(Error::KeyError)?;
         for subkey in [
             replay_protection::last_key(hash),
             replay_protection::all_key(hash),
         ] {
-            let key = prefix_key.join(&subkey);
-            if self.0.borrow().contains_key(&key.to_string()) {
+            if self.read_value(DbKey::ReplayProtection(&subkey)).is_some() {
                 return Ok(true);
             }
         }
@@ -464,29 +364,30 @@ impl DB for MockDB {
         Ok(false)
     }
 
+    fn read_tx_receipt(&self, hash: &Hash) -> Result<Option<TxReceipt>> {
+        match self.read_decoded(DbKey::TxReceiptHeight(hash))? {
+            Some(height) => {
+                self.read_decoded(DbKey::TxReceipt { height, hash })
+            }
+            None => Ok(None),
+        }
+    }
+
     fn read_diffs_val(
         &self,
         key: &Key,
         height: BlockHeight,
         is_old: bool,
     ) -> Result<Option<Vec<u8>>> {
-        let old_new_seg = if is_old {
-            OLD_DIFF_PREFIX
-        } else {
-            NEW_DIFF_PREFIX
-        };
-
-        let prefix = Key::from(height.to_db_key())
-            .push(&old_new_seg.to_string().to_db_key())
-            .map_err(Error::KeyError)?
-            .join(key);
-
-        Ok(self.0.borrow().get(&prefix.to_string()).cloned())
+        Ok(self.read_value(DbKey::Diff {
+            height,
+            is_old,
+            key,
+        }))
     }
 
     fn read_subspace_val(&self, key: &Key) -> Result<Option<Vec<u8>>> {
-        let key = Key::parse(SUBSPACE_CF).map_err(Error::KeyError)?.join(key);
-        Ok(self.0.borrow().get(&key.to_string()).cloned())
+        Ok(self.read_value(DbKey::Subspace(key)))
     }
 
     fn read_subspace_val_with_height(
@@ -511,7 +412,7 @@ impl DB for MockDB {
     ) -> Result<i64> {
         // batch_write are directly committed
         self.batch_write_subspace_val(
-            &mut MockDBWriteBatch,
+            &mut MockDBWriteBatch::default(),
             height,
             key,
             value,
@@ -527,7 +428,7 @@ impl DB for MockDB {
     ) -> Result<i64> {
         // batch_delete are directly committed
         self.batch_delete_subspace_val(
-            &mut MockDBWriteBatch,
+            &mut MockDBWriteBatch::default(),
             height,
             key,
             persist_diffs,
@@ -535,71 +436,69 @@ impl DB for MockDB {
     }
 
     fn batch() -> Self::WriteBatch {
-        MockDBWriteBatch
+        MockDBWriteBatch::default()
     }
 
-    fn exec_batch(&mut self, _batch: Self::WriteBatch) -> Result<()> {
-        // Nothing to do - in MockDB, batch writes are committed directly from
+    fn exec_batch(&mut self, batch: Self::WriteBatch) -> Result<()> {
+        // In MockDB, most of the batch writes are committed directly from
         // `batch_write_subspace_val` and `batch_delete_subspace_val`.
+        let mut db = self.0.borrow_mut();
+        for (key, value) in batch.ops {
+            match value {
+                Some(value) => db.insert(key, value),
+                None => db.remove(&key),
+            };
+        }
         Ok(())
     }
 
     fn batch_write_subspace_val(
         &self,
-        _batch: &mut Self::WriteBatch,
+        batch: &mut Self::WriteBatch,
         height: BlockHeight,
         key: &Key,
         value: impl AsRef<[u8]>,
         persist_diffs: bool,
     ) -> Result<i64> {
+        batch.applied.push(Self::db_key(DbKey::Subspace(key)));
         let value = value.as_ref();
-        let subspace_key =
-            Key::parse(SUBSPACE_CF).map_err(Error::KeyError)?.join(key);
         let current_len = value.len() as i64;
-        let diff_prefix = Key::from(height.to_db_key());
         let mut db = self.0.borrow_mut();
+        let diff_key = |is_old| {
+            Self::db_key(DbKey::Diff {
+                height,
+                is_old,
+                key,
+            })
+        };
 
         // Diffs
-        let size_diff =
-            match db.insert(subspace_key.to_string(), value.to_owned()) {
-                Some(prev_value) => {
-                    let old_key = diff_prefix
-                        .push(&OLD_DIFF_PREFIX.to_string().to_db_key())
-                        .unwrap()
-                        .join(key);
-                    db.insert(old_key.to_string(), prev_value.clone());
-                    let new_key = diff_prefix
-                        .push(&NEW_DIFF_PREFIX.to_string().to_db_key())
-                        .unwrap()
-                        .join(key);
-                    db.insert(new_key.to_string(), value.to_owned());
-                    current_len - prev_value.len() as i64
-                }
-                None => {
-                    let new_key = diff_prefix
-                        .push(&NEW_DIFF_PREFIX.to_string().to_db_key())
-                        .unwrap()
-                        .join(key);
-                    db.insert(new_key.to_string(), value.to_owned());
-                    current_len
-                }
-            };
+        let size_diff = match db
+            .insert(Self::db_key(DbKey::Subspace(key)), value.to_owned())
+        {
+            Some(prev_value) => {
+                db.insert(diff_key(true), prev_value.clone());
+                db.insert(diff_key(false), value.to_owned());
+                current_len - prev_value.len() as i64
+            }
+            None => {
+                db.insert(diff_key(false), value.to_owned());
+                current_len
+            }
+        };
 
-        if !persist_diffs {
+        if persist_diffs {
+            // The MockDB always keeps the history index
+            db.insert(Self::db_key(DbKey::History { key, height }), vec![]);
+        } else {
             if let Some(pruned_height) = height.0.checked_sub(1) {
-                let pruned_key_prefix = Key::from(pruned_height.to_db_key());
-                let old_val_key = pruned_key_prefix
-                    .push(&NEW_DIFF_PREFIX.to_string().to_db_key())
-                    .unwrap()
-                    .join(key)
-                    .to_string();
-                db.remove(&old_val_key);
-                let new_val_key = pruned_key_prefix
-                    .push(&NEW_DIFF_PREFIX.to_string().to_db_key())
-                    .unwrap()
-                    .join(key)
-                    .to_string();
-                db.remove(&new_val_key);
+                for is_old in [true, false] {
+                    db.remove(&Self::db_key(DbKey::Diff {
+                        height: BlockHeight(pruned_height),
+                        is_old,
+                        key,
+                    }));
+                }
             }
         }
 
@@ -608,40 +507,39 @@ impl DB for MockDB {
 
     fn batch_delete_subspace_val(
         &self,
-        _batch: &mut Self::WriteBatch,
+        batch: &mut Self::WriteBatch,
         height: BlockHeight,
         key: &Key,
         persist_diffs: bool,
     ) -> Result<i64> {
-        let subspace_key =
-            Key::parse(SUBSPACE_CF).map_err(Error::KeyError)?.join(key);
-        let diff_prefix = Key::from(height.to_db_key());
+        batch.applied.push(Self::db_key(DbKey::Subspace(key)));
         let mut db = self.0.borrow_mut();
 
-        let size_diff = match db.remove(&subspace_key.to_string()) {
+        let size_diff = match db.remove(&Self::db_key(DbKey::Subspace(key))) {
             Some(value) => {
-                let old_key = diff_prefix
-                    .push(&OLD_DIFF_PREFIX.to_string().to_db_key())
-                    .unwrap()
-                    .join(key);
-                db.insert(old_key.to_string(), value.clone());
-
-                if !persist_diffs {
+                db.insert(
+                    Self::db_key(DbKey::Diff {
+                        height,
+                        is_old: true,
+                        key,
+                    }),
+                    value.clone(),
+                );
+
+                if persist_diffs {
+                    db.insert(
+                        Self::db_key(DbKey::History { key, height }),
+                        vec![],
+                    );
+                } else {
                     if let Some(pruned_height) = height.0.checked_sub(1) {
-                        let pruned_key_prefix =
-                            Key::from(pruned_height.to_db_key());
-                        let old_val_key = pruned_key_prefix
-                            .push(&NEW_DIFF_PREFIX.to_string().to_db_key())
-                            .unwrap()
-                            .join(key)
-                            .to_string();
-                        db.remove(&old_val_key);
-                        let new_val_key = pruned_key_prefix
-                            .push(&NEW_DIFF_PREFIX.to_string().to_db_key())
-                            .unwrap()
-                            .join(key)
-                            .to_string();
-                        db.remove(&new_val_key);
+                        for is_old in [true, false] {
+                            db.remove(&Self::db_key(DbKey::Diff {
+                                height: BlockHeight(pruned_height),
+                                is_old,
+                                key,
+                            }));
+                        }
                     }
                 }
                 value.len() as i64
@@ -658,15 +556,91 @@ impl DB for MockDB {
         store_type: &StoreType,
         epoch: Epoch,
     ) -> Result<()> {
-        let prefix_key = subtree_key_prefix(store_type, epoch);
-        let root_key = prefix_key
-            .push(&"root".to_owned())
-            .map_err(Error::KeyError)?;
-        self.0.borrow_mut().remove(&root_key.to_string());
-        let store_key = prefix_key
-            .push(&"store".to_owned())
-            .map_err(Error::KeyError)?;
-        self.0.borrow_mut().remove(&store_key.to_string());
+        for field in [TreeField::Root, TreeField::Store] {
+            self.remove(DbKey::Subtree {
+                store_type,
+                epoch,
+                field,
+            });
+        }
+        Ok(())
+    }
+
+    fn prune_below(
+        &mut self,
+        batch: &mut Self::WriteBatch,
+        height: BlockHeight,
+    ) -> Result<()> {
+        // The diffs of every height are stored under the height's prefix
+        let diffs_prefix = format!("{}/", Namespace::Diffs);
+        let pruned_upper_bound = format!("{diffs_prefix}{}", height.raw());
+        let db = self.0.borrow();
+        let pruned_keys = db
+            .range::<String, _>((
+                Included(&diffs_prefix),
+                Excluded(&pruned_upper_bound),
+            ))
+            .map(|(key, _)| (key.clone(), None));
+        batch.ops.extend(pruned_keys);
+        batch.ops.push((
+            Self::db_key(DbKey::State(StateKey::DiffsPrunedHeight)),
+            Some(types::encode(&height)),
+        ));
+        Ok(())
+    }
+
+    fn read_diffs_pruned_height(&self) -> Result<Option<BlockHeight>> {
+        self.read_decoded(DbKey::State(StateKey::DiffsPrunedHeight))
+    }
+
+    fn read_key_history(
+        &self,
+        key: &Key,
+        from_height: BlockHeight,
+        limit: usize,
+    ) -> Result<Vec<BlockHeight>> {
+        let prefix = format!("{}/{}/", Namespace::Diffs, history_prefix(key));
+        let from = Self::db_key(DbKey::History {
+            key,
+            height: from_height,
+        });
+        let db = self.0.borrow();
+        db.range::<String, _>((Included(&from), Unbounded))
+            .map_while(|(db_key, _)| db_key.strip_prefix(&prefix))
+            .take(limit)
+            .map(|height| {
+                BlockHeight::parse(height.to_owned()).map_err(Error::KeyError)
+            })
+            .collect()
+    }
+
+    fn read_last_change_height(
+        &self,
+        key: &Key,
+        height: BlockHeight,
+    ) -> Result<Option<BlockHeight>> {
+        let prefix = format!("{}/{}/", Namespace::Diffs, history_prefix(key));
+        let to = Self::db_key(DbKey::History { key, height });
+        let db = self.0.borrow();
+        db.range::<String, _>((Unbounded, Included(&to)))
+            .next_back()
+            .and_then(|(db_key, _)| db_key.strip_prefix(&prefix))
+            .map(|height| {
+                BlockHeight::parse(height.to_owned()).map_err(Error::KeyError)
+            })
+            .transpose()
+    }
+
+    fn read_db_version(&self) -> Result<Option<u64>> {
+        self.read_decoded(DbKey::State(StateKey::DbVersion))
+    }
+
+    fn write_db_version(
+        &mut self,
+        _batch: &mut Self::WriteBatch,
+        version: u64,
+    ) -> Result<()> {
+        self.insert(DbKey::State(StateKey::DbVersion), types::encode(&version));
         Ok(())
     }
 
@@ -680,14 +654,13 @@ impl DB for MockDB {
 
     fn write_replay_protection_entry(
         &mut self,
-        _batch: &mut Self::WriteBatch,
+        batch: &mut Self::WriteBatch,
         key: &Key,
     ) -> Result<()> {
-        let key = Key::parse("replay_protection")
-            .map_err(Error::KeyError)?
-            .join(key);
-
-        match self.0.borrow_mut().insert(key.to_string(), vec![]) {
+        batch
+            .applied
+            .push(Self::db_key(DbKey::ReplayProtection(key)));
+        match self.insert(DbKey::ReplayProtection(key), vec![]) {
             Some(_) => Err(Error::DBError(format!(
                 "Replay protection key {key} already in storage"
             ))),
@@ -697,47 +670,127 @@ impl DB for MockDB {
 
     fn delete_replay_protection_entry(
         &mut self,
-        _batch: &mut Self::WriteBatch,
+        batch: &mut Self::WriteBatch,
         key: &Key,
     ) -> Result<()> {
-        let key = Key::parse("replay_protection")
-            .map_err(Error::KeyError)?
-            .join(key);
+        batch
+            .applied
+            .push(Self::db_key(DbKey::ReplayProtection(key)));
+        self.remove(DbKey::ReplayProtection(key));
 
-        self.0.borrow_mut().remove(&key.to_string());
+        Ok(())
+    }
 
+    fn write_block_header(
+        &mut self,
+        _batch: &mut Self::WriteBatch,
+        height: BlockHeight,
+        header: &Header,
+    ) -> Result<()> {
+        self.insert(
+            DbKey::Block {
+                height,
+                field: BlockField::Header,
+            },
+            header.serialize_to_vec(),
+        );
+        Ok(())
+    }
+
+    fn write_block_results(
+        &mut self,
+        _batch: &mut Self::WriteBatch,
+        height: BlockHeight,
+        results: &BlockResults,
+    ) -> Result<()> {
+        self.insert(DbKey::Results(height), types::encode(results));
+        Ok(())
+    }
+
+    fn write_checkpoint(&self, epoch: Epoch) -> Result<()> {
+        self.1.borrow_mut().insert(epoch, self.0.borrow().clone());
+        Ok(())
+    }
+
+    fn restore_checkpoint(&mut self, epoch: Epoch) -> Result<()> {
+        let checkpoint = self
+            .1
+            .borrow()
+            .get(&epoch)
+            .cloned()
+            .ok_or(Error::NoCheckpoint { epoch })?;
+        *self.0.borrow_mut() = checkpoint;
         Ok(())
     }
+
+    fn delete_checkpoint(&self, epoch: Epoch) -> Result<()> {
+        self.1.borrow_mut().remove(&epoch);
+        Ok(())
+    }
+
+    fn checkpoints(&self) -> Result<Vec<Epoch>> {
+        Ok(self.1.borrow().keys().copied().collect())
+    }
+
+    fn read_only_view(&self) -> Result<Self> {
+        // The view is a copy of the state
+        Ok(Self(
+            RefCell::new(self.0.borrow().clone()),
+            RefCell::default(),
+        ))
+    }
 }
 
 impl<'iter> DBIter<'iter> for MockDB {
     type PrefixIter = MockPrefixIterator;
 
     fn iter_prefix(&'iter self, prefix: Option<&Key>) -> MockPrefixIterator {
-        let stripped_prefix = "subspace/".to_owned();
-        let prefix = format!(
-            "{}{}",
+        iter_subspace_prefix(self, prefix, false)
+    }
+
+    fn rev_iter_prefix(
+        &'iter self,
+        prefix: Option<&Key>,
+    ) -> MockPrefixIterator {
+        iter_subspace_prefix(self, prefix, true)
+    }
+
+    fn iter_range(&'iter self, start: &Key, end: &Key) -> MockPrefixIterator {
+        let stripped_prefix = format!("{}/", Namespace::Subspace);
+        let start = format!("{stripped_prefix}{start}");
+        let end = format!("{stripped_prefix}{end}");
+        let range: BTreeMap<String, Vec<u8>> = if start < end {
+            self.0
+                .borrow()
+                .range((Included(start), Excluded(end)))
+                .map(|(key, val)| (key.clone(), val.clone()))
+                .collect()
+        } else {
+            BTreeMap::new()
+        };
+        MockPrefixIterator::new(
+            MockIterator {
+                prefix: stripped_prefix.clone(),
+                iter: range.into_iter(),
+                reverse: false,
+            },
             stripped_prefix,
-            match prefix {
-                Some(prefix) => {
-                    if prefix == &Key::default() {
-                        prefix.to_string()
-                    } else {
-                        format!("{prefix}/")
-                    }
-                }
-                None => "".to_string(),
-            }
-        );
-        let iter = self.0.borrow().clone().into_iter();
-        MockPrefixIterator::new(MockIterator { prefix, iter }, stripped_prefix)
+        )
     }
 
     fn iter_results(&'iter self) -> MockPrefixIterator {
-        let stripped_prefix = "results/".to_owned();
-        let prefix = "results".to_owned();
+        let stripped_prefix =
+            format!("{}/{}", Namespace::Block, results_prefix());
+        let prefix = stripped_prefix.clone();
         let iter = self.0.borrow().clone().into_iter();
-        MockPrefixIterator::new(MockIterator { prefix, iter }, stripped_prefix)
+        MockPrefixIterator::new(
+            MockIterator {
+                prefix,
+                iter,
+                reverse: false,
+            },
+            stripped_prefix,
+        )
     }
 
     fn iter_old_diffs(
@@ -747,7 +800,8 @@ impl<'iter> DBIter<'iter> for MockDB {
     ) -> MockPrefixIterator {
         // Returns an empty iterator since Mock DB can read only the latest
         // value for now
-        let stripped_prefix = format!("{}/old/", height.0.raw());
+        let stripped_prefix =
+            format!("{}/{}/", Namespace::Diffs, diffs_prefix(height, true));
         let prefix = prefix
             .map(|k| {
                 if k == &Key::default() {
@@ -758,7 +812,14 @@ impl<'iter> DBIter<'iter> for MockDB {
             })
             .unwrap_or("".to_string());
         let iter = self.0.borrow().clone().into_iter();
-        MockPrefixIterator::new(MockIterator { prefix, iter }, stripped_prefix)
+        MockPrefixIterator::new(
+            MockIterator {
+                prefix,
+                iter,
+                reverse: false,
+            },
+            stripped_prefix,
+        )
     }
 
     fn iter_new_diffs(
@@ -768,7 +829,8 @@ impl<'iter> DBIter<'iter> for MockDB {
     ) -> MockPrefixIterator {
         // Returns an empty iterator since Mock DB can read only the latest
         // value for now
-        let stripped_prefix = format!("{}/new/", height.0.raw());
+        let stripped_prefix =
+            format!("{}/{}/", Namespace::Diffs, diffs_prefix(height, false));
         let prefix = prefix
             .map(|k| {
                 if k == &Key::default() {
@@ -779,15 +841,46 @@ impl<'iter> DBIter<'iter> for MockDB {
             })
             .unwrap_or("".to_string());
         let iter = self.0.borrow().clone().into_iter();
-        MockPrefixIterator::new(MockIterator { prefix, iter }, stripped_prefix)
+        MockPrefixIterator::new(
+            MockIterator {
+                prefix,
+                iter,
+                reverse: false,
+            },
+            stripped_prefix,
+        )
     }
 
     fn iter_replay_protection(&'iter self) -> Self::PrefixIter {
-        let stripped_prefix =
-            format!("replay_protection/{}/", replay_protection::last_prefix());
+        let stripped_prefix = format!(
+            "{}/{}/",
+            Namespace::ReplayProtection,
+            replay_protection::last_prefix()
+        );
+        let prefix = stripped_prefix.clone();
+        let iter = self.0.borrow().clone().into_iter();
+        MockPrefixIterator::new(
+            MockIterator {
+                prefix,
+                iter,
+                reverse: false,
+            },
+            stripped_prefix,
+        )
+    }
+
+    fn iter_all_replay_protection(&'iter self) -> Self::PrefixIter {
+        let stripped_prefix = format!("{}/", Namespace::ReplayProtection);
         let prefix = stripped_prefix.clone();
         let iter = self.0.borrow().clone().into_iter();
-        MockPrefixIterator::new(MockIterator { prefix, iter }, stripped_prefix)
+        MockPrefixIterator::new(
+            MockIterator {
+                prefix,
+                iter,
+                reverse: false,
+            },
+            stripped_prefix,
+        )
     }
 }
 
@@ -797,6 +890,8 @@ pub struct MockIterator {
     prefix: String,
     /// The concrete iterator
     pub iter: btree_map::IntoIter<String, Vec<u8>>,
+    /// Iterate in reverse key order
+    reverse: bool,
 }
 
 /// A prefix iterator for the [`MockDB`].
@@ -806,7 +901,12 @@ impl Iterator for MockIterator {
     type Item = Result<KVBytes>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        for (key, val) in &mut self.iter {
+        loop {
+            let (key, val) = if self.reverse {
+                self.iter.next_back()?
+            } else {
+                self.iter.next()?
+            };
             if key.starts_with(&self.prefix) {
                 return Some(Ok((
                     Box::from(key.as_bytes()),
@@ -814,7 +914,6 @@ impl Iterator for MockIterator {
                 )));
             }
         }
-        None
     }
 }
 
@@ -844,8 +943,34 @@ impl Iterator for PrefixIterator<MockIterator> {
 
 impl DBWriteBatch for MockDBWriteBatch {}
 
-fn unknown_key_error(key: &str) -> Result<()> {
-    Err(Error::UnknownKey {
-        key: key.to_owned(),
-    })
+/// Iterate the subspace key-vals with the given prefix.
+fn iter_subspace_prefix(
+    db: &MockDB,
+    prefix: Option<&Key>,
+    reverse: bool,
+) -> MockPrefixIterator {
+    let stripped_prefix = format!("{}/", Namespace::Subspace);
+    let prefix = format!(
+        "{}{}",
+        stripped_prefix,
+        match prefix {
+            Some(prefix) => {
+                if prefix == &Key::default() {
+                    prefix.to_string()
+                } else {
+                    format!("{prefix}/")
+                }
+            }
+            None => "".to_string(),
+        }
+    );
+    let iter = db.0.borrow().clone().into_iter();
+    MockPrefixIterator::new(
+        MockIterator {
+            prefix,
+            iter,
+            reverse,
+        },
+        stripped_prefix,
+    )
 }
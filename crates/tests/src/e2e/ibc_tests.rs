@@ -470,6 +470,9 @@ fn setup_two_single_node_nets(
     set_port(rpc_addr, ANOTHER_RPC);
     let p2p_addr = &mut config.ledger.cometbft.p2p.laddr;
     set_port(p2p_addr, ANOTHER_P2P);
+    // the genesis files of chain b were modified after its chain ID was
+    // derived
+    config.ledger.shell.allow_chain_id_mismatch = true;
     config
         .write(&genesis_b_dir, &test_b.net.chain_id, true)
         .map_err(|e| {
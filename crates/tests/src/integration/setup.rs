@@ -181,22 +181,24 @@ fn create_node(
         shell_handlers,
         controller,
     } = mock_services(services_cfg);
+    let shell = Shell::new(
+        config::Ledger::new(
+            global_args.base_dir,
+            chain_id.clone(),
+            TendermintMode::Validator,
+        ),
+        global_args
+            .wasm_dir
+            .expect("Wasm path not provided to integration test setup."),
+        shell_handlers.tx_broadcaster,
+        shell_handlers.eth_oracle_channels,
+        None,
+        50 * 1024 * 1024, // 50 kiB
+        50 * 1024 * 1024, // 50 kiB
+    )
+    .expect("Cannot create the shell");
     let node = MockNode {
-        shell: Arc::new(Mutex::new(Shell::new(
-            config::Ledger::new(
-                global_args.base_dir,
-                chain_id.clone(),
-                TendermintMode::Validator,
-            ),
-            global_args
-                .wasm_dir
-                .expect("Wasm path not provided to integration test setup."),
-            shell_handlers.tx_broadcaster,
-            shell_handlers.eth_oracle_channels,
-            None,
-            50 * 1024 * 1024, // 50 kiB
-            50 * 1024 * 1024, // 50 kiB
-        ))),
+        shell: Arc::new(Mutex::new(shell)),
         test_dir: ManuallyDrop::new(test_dir),
         keep_temp,
         services: Arc::new(services),
@@ -185,6 +185,73 @@ mod tests {
         itertools::assert_equal(iter, expected);
     }
 
+    #[test]
+    fn test_tx_rev_iter_prefix() {
+        // The environment must be initialized first
+        tx_host_env::init();
+
+        let prefix = storage::Key::parse("prefix").unwrap();
+        // We'll write sub-key in some random order to check prefix iter's order
+        let sub_keys = [2_i32, 1, i32::MAX, -1, 260, -2, i32::MIN, 5, 0];
+
+        // Write the values directly into the storage first
+        tx_host_env::with(|env| {
+            for i in sub_keys.iter() {
+                let key = prefix.push(i).unwrap();
+                env.wl_storage.write(&key, i).unwrap();
+            }
+        });
+
+        // Then try to iterate over their prefix in reverse
+        let mut iter = tx::ctx().rev_iter_prefix(&prefix).unwrap();
+        let mut keys = vec![];
+        while let Some((key, _val)) = tx::ctx().iter_next(&mut iter).unwrap() {
+            keys.push(storage::Key::parse(key).unwrap());
+        }
+
+        // The order has to be reverse sorted by sub-key value
+        let expected = sub_keys
+            .iter()
+            .sorted()
+            .rev()
+            .map(|i| prefix.push(i).unwrap());
+        itertools::assert_equal(keys, expected);
+    }
+
+    #[test]
+    fn test_tx_iter_range() {
+        // The environment must be initialized first
+        tx_host_env::init();
+
+        let prefix = storage::Key::parse("prefix").unwrap();
+        let sub_keys = [2_i32, 1, i32::MAX, -1, 260, -2, i32::MIN, 5, 0];
+
+        // Write the values directly into the storage first
+        tx_host_env::with(|env| {
+            for i in sub_keys.iter() {
+                let key = prefix.push(i).unwrap();
+                env.wl_storage.write(&key, i).unwrap();
+            }
+        });
+
+        // Iterate over the sub-keys within `[-1, 5)`
+        let start = prefix.push(&-1_i32).unwrap();
+        let end = prefix.push(&5_i32).unwrap();
+        let mut iter = tx::ctx().iter_range(&start, &end).unwrap();
+        let mut keys = vec![];
+        while let Some((key, _val)) = tx::ctx().iter_next(&mut iter).unwrap() {
+            keys.push(storage::Key::parse(key).unwrap());
+        }
+
+        // The order has to be sorted by sub-key value
+        let expected = sub_keys
+            .iter()
+            .filter(|i| (-1..5).contains(*i))
+            .sorted()
+            .map(|i| prefix.push(i).unwrap());
+        itertools::assert_equal(keys, expected);
+    }
+
     #[test]
     fn test_tx_insert_verifier() {
         // The environment must be initialized first
@@ -651,6 +718,47 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_tx_vp_hash_bytes() {
+        use namada::types::internal::HostHashAlgorithm;
+        use namada::types::keccak::keccak_hash;
+
+        // The environments must be initialized first
+        tx_host_env::init();
+        vp_host_env::init();
+
+        let data = b"namada";
+        let sha256_digest = Hash::sha256(data).0;
+        let keccak_digest = keccak_hash(data).0;
+        // Blake2b-256 of an empty input
+        let blake2b_empty_digest: [u8; 32] = [
+            0x0e, 0x57, 0x51, 0xc0, 0x26, 0xe5, 0x43, 0xb2, 0xe8, 0xab, 0x2e,
+            0xb0, 0x60, 0x99, 0xda, 0xa1, 0xd1, 0xe5, 0xdf, 0x47, 0x77, 0x8f,
+            0x77, 0x87, 0xfa, 0xab, 0x45, 0xcd, 0xf1, 0x2f, 0xe3, 0xa8,
+        ];
+
+        for (hash_bytes, name) in [
+            (namada_tx_prelude::hash_bytes as fn(_, &[u8]) -> _, "tx"),
+            (namada_vp_prelude::hash_bytes, "vp"),
+        ] {
+            assert_eq!(
+                hash_bytes(HostHashAlgorithm::Sha256, data),
+                sha256_digest,
+                "{name} SHA-256"
+            );
+            assert_eq!(
+                hash_bytes(HostHashAlgorithm::Keccak256, data),
+                keccak_digest,
+                "{name} Keccak-256"
+            );
+            assert_eq!(
+                hash_bytes(HostHashAlgorithm::Blake2b256, &[]),
+                blake2b_empty_digest,
+                "{name} Blake2b-256"
+            );
+        }
+    }
+
     #[test]
     fn test_vp_eval() {
         // The environment must be initialized first
@@ -481,6 +481,13 @@ mod native_tx_host_env {
     ));
     native_host_fn!(tx_delete(key_ptr: u64, key_len: u64));
     native_host_fn!(tx_iter_prefix(prefix_ptr: u64, prefix_len: u64) -> u64);
+    native_host_fn!(tx_rev_iter_prefix(prefix_ptr: u64, prefix_len: u64) -> u64);
+    native_host_fn!(tx_iter_range(
+        start_ptr: u64,
+        start_len: u64,
+        end_ptr: u64,
+        end_len: u64
+    ) -> u64);
     native_host_fn!(tx_iter_next(iter_id: u64) -> i64);
     native_host_fn!(tx_insert_verifier(addr_ptr: u64, addr_len: u64));
     native_host_fn!(tx_update_validity_predicate(
@@ -520,6 +527,12 @@ mod native_tx_host_env {
         max_signatures_ptr: u64,
         max_signatures_len: u64,
     ) -> i64);
+    native_host_fn!(tx_hash_bytes(
+        algorithm: u8,
+        data_ptr: u64,
+        data_len: u64,
+        result_ptr: u64,
+    ));
 }
 
 #[cfg(test)]
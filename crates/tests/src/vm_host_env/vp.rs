@@ -352,6 +352,20 @@ mod native_vp_host_env {
     native_host_fn!(vp_has_key_post(key_ptr: u64, key_len: u64) -> i64);
     native_host_fn!(vp_iter_prefix_pre(prefix_ptr: u64, prefix_len: u64) -> u64);
     native_host_fn!(vp_iter_prefix_post(prefix_ptr: u64, prefix_len: u64) -> u64);
+    native_host_fn!(vp_rev_iter_prefix_pre(prefix_ptr: u64, prefix_len: u64) -> u64);
+    native_host_fn!(vp_rev_iter_prefix_post(prefix_ptr: u64, prefix_len: u64) -> u64);
+    native_host_fn!(vp_iter_range_pre(
+        start_ptr: u64,
+        start_len: u64,
+        end_ptr: u64,
+        end_len: u64
+    ) -> u64);
+    native_host_fn!(vp_iter_range_post(
+        start_ptr: u64,
+        start_len: u64,
+        end_ptr: u64,
+        end_len: u64
+    ) -> u64);
     native_host_fn!(vp_iter_next(iter_id: u64) -> i64);
     native_host_fn!(vp_get_chain_id(result_ptr: u64));
     native_host_fn!(vp_get_block_height() -> u64);
@@ -380,5 +394,11 @@ mod native_vp_host_env {
         max_signatures_ptr: u64,
         max_signatures_len: u64,
     ) -> i64);
+    native_host_fn!(vp_hash_bytes(
+        algorithm: u8,
+        data_ptr: u64,
+        data_len: u64,
+        result_ptr: u64,
+    ));
     native_host_fn!(vp_charge_gas(used_gas: u64));
 }
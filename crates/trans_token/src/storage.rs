@@ -133,6 +133,69 @@ where
     }
 }
 
+/// Read the amount of the `owner`'s `token` that the `spender` is allowed to
+/// transfer.
+pub fn read_allowance<S>(
+    storage: &S,
+    token: &Address,
+    owner: &Address,
+    spender: &Address,
+) -> storage::Result<token::Amount>
+where
+    S: StorageRead,
+{
+    let key = allowance_key(token, owner, spender);
+    let allowance = storage.read::<token::Amount>(&key)?.unwrap_or_default();
+    Ok(allowance)
+}
+
+/// Allow the `spender` to transfer up to the `amount` of the `owner`'s
+/// `token`, replacing any previous allowance. In transactions, this must be
+/// signed by the owner.
+pub fn approve<S>(
+    storage: &mut S,
+    token: &Address,
+    owner: &Address,
+    spender: &Address,
+    amount: token::Amount,
+) -> storage::Result<()>
+where
+    S: StorageRead + StorageWrite,
+{
+    let key = allowance_key(token, owner, spender);
+    if amount.is_zero() {
+        storage.delete(&key)
+    } else {
+        storage.write(&key, amount)
+    }
+}
+
+/// Transfer `token` from `owner` to `dest` on behalf of the `spender`, using
+/// up the `spender`'s allowance. Returns an `Err` if the allowance or the
+/// `owner`'s balance is insufficient. In transactions, this must be signed by
+/// the spender.
+pub fn transfer_from<S>(
+    storage: &mut S,
+    token: &Address,
+    spender: &Address,
+    owner: &Address,
+    dest: &Address,
+    amount: token::Amount,
+) -> storage::Result<()>
+where
+    S: StorageRead + StorageWrite,
+{
+    if amount.is_zero() {
+        return Ok(());
+    }
+    let allowance = read_allowance(storage, token, owner, spender)?;
+    let new_allowance = allowance
+        .checked_sub(amount)
+        .ok_or_else(|| storage::Error::new_const("Insufficient allowance"))?;
+    approve(storage, token, owner, spender, new_allowance)?;
+    transfer(storage, token, owner, dest, amount)
+}
+
 /// Credit tokens to an account, to be used only by protocol. In transactions,
 /// this would get rejected by the default `vp_token`.
 pub fn credit_tokens<S>(
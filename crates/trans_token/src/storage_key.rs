@@ -13,6 +13,8 @@ pub const MINTER_STORAGE_KEY: &str = "minter";
 pub const MINTED_STORAGE_KEY: &str = "minted";
 /// Key segment for token parameters
 pub const PARAMETERS_STORAGE_KEY: &str = "parameters";
+/// Key segment for allowances
+pub const ALLOWANCE_STORAGE_KEY: &str = "allowance";
 
 /// Gets the key for the given token address, error with the given
 /// message to expect if the key is not in the address
@@ -73,6 +75,26 @@ pub fn minted_balance_key(token_addr: &Address) -> storage::Key {
         .expect("Cannot obtain a storage key")
 }
 
+/// Obtain a storage key for the amount of the owner's token that the spender is
+/// allowed to transfer.
+pub fn allowance_key(
+    token_addr: &Address,
+    owner: &Address,
+    spender: &Address,
+) -> storage::Key {
+    storage::Key::from(
+        Address::Internal(InternalAddress::Multitoken).to_db_key(),
+    )
+    .push(&token_addr.to_db_key())
+    .expect("Cannot obtain a storage key")
+    .push(&ALLOWANCE_STORAGE_KEY.to_owned())
+    .expect("Cannot obtain a storage key")
+    .push(&owner.to_db_key())
+    .expect("Cannot obtain a storage key")
+    .push(&spender.to_db_key())
+    .expect("Cannot obtain a storage key")
+}
+
 /// Check if the given storage key is a balance key for the given token. If it
 /// is, return the owner. For minted balances, use
 /// [`is_any_minted_balance_key()`].
@@ -132,6 +154,25 @@ pub fn is_any_token_balance_key(key: &storage::Key) -> Option<[&Address; 2]> {
     }
 }
 
+/// Check if the given storage key is an allowance key for an unspecified token.
+/// If it is, return the token, owner and spender addresses.
+pub fn is_any_allowance_key(key: &storage::Key) -> Option<[&Address; 3]> {
+    match &key.segments[..] {
+        [
+            DbKeySeg::AddressSeg(addr),
+            DbKeySeg::AddressSeg(token),
+            DbKeySeg::StringSeg(allowance),
+            DbKeySeg::AddressSeg(owner),
+            DbKeySeg::AddressSeg(spender),
+        ] if *addr == Address::Internal(InternalAddress::Multitoken)
+            && allowance == ALLOWANCE_STORAGE_KEY =>
+        {
+            Some([token, owner, spender])
+        }
+        _ => None,
+    }
+}
+
 /// Obtain a storage key denomination of a token.
 pub fn denom_key(token_addr: &Address) -> storage::Key {
     storage::Key::from(token_addr.to_db_key())
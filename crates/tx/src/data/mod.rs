@@ -13,7 +13,7 @@ pub mod protocol;
 /// wrapper txs with encrypted payloads
 pub mod wrapper;
 
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt::{self, Display};
 use std::str::FromStr;
 
@@ -194,6 +194,37 @@ impl TxResult {
     }
 }
 
+/// The receipt of a transaction applied in a committed block, kept in storage
+/// for the clients to confirm its inclusion and effects
+#[derive(
+    Clone,
+    Debug,
+    Default,
+    BorshSerialize,
+    BorshDeserialize,
+    Serialize,
+    Deserialize,
+)]
+pub struct TxReceipt {
+    /// The hash of the transaction, as in its events
+    pub hash: Hash,
+    /// The height of the block in which the transaction was applied
+    pub height: storage::BlockHeight,
+    /// The index of the transaction in the block
+    pub index: storage::TxIndex,
+    /// The result of applying the transaction
+    pub result: TxResult,
+    /// The error that the transaction failed with, if it couldn't be applied
+    pub error: Option<String>,
+}
+
+impl TxReceipt {
+    /// Check if the tx has been applied and accepted by all the VPs
+    pub fn is_accepted(&self) -> bool {
+        self.error.is_none() && self.result.is_accepted()
+    }
+}
+
 /// Result of checking a transaction with validity predicates
 // TODO derive BorshSchema after <https://github.com/near/borsh-rs/issues/82>
 #[derive(
@@ -212,6 +243,9 @@ pub struct VpsResult {
     pub rejected_vps: BTreeSet<Address>,
     /// The total gas used by all the VPs
     pub gas_used: VpsGas,
+    /// The gas used by each of the VPs
+    #[serde(default)]
+    pub vps_gas: BTreeMap<Address, Gas>,
     /// Errors occurred in any of the VPs, if any
     pub errors: Vec<(Address, String)>,
     /// Sentinel to signal an invalid transaction signature
@@ -251,9 +285,15 @@ impl fmt::Display for VpsResult {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
             f,
-            "{}{}{}",
+            "{}{}{}{}",
             iterable_to_string("Accepted", self.accepted_vps.iter()),
             iterable_to_string("Rejected", self.rejected_vps.iter()),
+            iterable_to_string(
+                "Gas used",
+                self.vps_gas
+                    .iter()
+                    .map(|(addr, gas)| format!("{} in {}", gas, addr))
+            ),
             iterable_to_string(
                 "Errors",
                 self.errors
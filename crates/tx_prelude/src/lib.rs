@@ -26,6 +26,7 @@ pub use namada_core::types::address::Address;
 use namada_core::types::chain::CHAIN_ID_LENGTH;
 pub use namada_core::types::ethereum_events::EthAddress;
 use namada_core::types::internal::HostEnvResult;
+pub use namada_core::types::internal::HostHashAlgorithm;
 use namada_core::types::key::common;
 use namada_core::types::storage::TxIndex;
 pub use namada_core::types::storage::{
@@ -53,6 +54,21 @@ pub fn log_string<T: AsRef<str>>(msg: T) {
     }
 }
 
+/// Hash the given bytes using the host's native implementation of the
+/// hashing algorithm, which is cheaper than hashing them in wasm.
+pub fn hash_bytes(algorithm: HostHashAlgorithm, bytes: &[u8]) -> [u8; 32] {
+    let mut digest = [0u8; 32];
+    unsafe {
+        namada_tx_hash_bytes(
+            algorithm.to_u8(),
+            bytes.as_ptr() as _,
+            bytes.len() as _,
+            digest.as_mut_ptr() as _,
+        );
+    }
+    digest
+}
+
 /// Format and log a string in a debug build.
 ///
 /// In WASM target debug build, the message will be printed at the
@@ -103,6 +119,40 @@ impl Ctx {
     pub const unsafe fn new() -> Self {
         Self(())
     }
+
+    /// Storage prefix iterator, in reverse order of storage keys. The
+    /// iterator is advanced with [`StorageRead::iter_next`].
+    pub fn rev_iter_prefix(
+        &self,
+        prefix: &storage::Key,
+    ) -> EnvResult<KeyValIterator<(String, Vec<u8>)>> {
+        let prefix = prefix.to_string();
+        let iter_id = unsafe {
+            namada_tx_rev_iter_prefix(prefix.as_ptr() as _, prefix.len() as _)
+        };
+        Ok(KeyValIterator(iter_id, PhantomData))
+    }
+
+    /// Storage range iterator over the keys within `[start, end)`, ordered by
+    /// storage keys. The iterator is advanced with
+    /// [`StorageRead::iter_next`].
+    pub fn iter_range(
+        &self,
+        start: &storage::Key,
+        end: &storage::Key,
+    ) -> EnvResult<KeyValIterator<(String, Vec<u8>)>> {
+        let start = start.to_string();
+        let end = end.to_string();
+        let iter_id = unsafe {
+            namada_tx_iter_range(
+                start.as_ptr() as _,
+                start.len() as _,
+                end.as_ptr() as _,
+                end.len() as _,
+            )
+        };
+        Ok(KeyValIterator(iter_id, PhantomData))
+    }
 }
 
 /// Result of `TxEnv`, `namada_storage::StorageRead` or
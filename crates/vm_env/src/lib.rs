@@ -51,6 +51,22 @@ pub mod tx {
         // keys.
         pub fn namada_tx_iter_prefix(prefix_ptr: u64, prefix_len: u64) -> u64;
 
+        // Get an ID of a data iterator with key prefix, in reverse order of
+        // storage keys.
+        pub fn namada_tx_rev_iter_prefix(
+            prefix_ptr: u64,
+            prefix_len: u64,
+        ) -> u64;
+
+        // Get an ID of a data iterator over the keys within `[start, end)`,
+        // ordered by storage keys.
+        pub fn namada_tx_iter_range(
+            start_ptr: u64,
+            start_len: u64,
+            end_ptr: u64,
+            end_len: u64,
+        ) -> u64;
+
         // Returns the size of the value (can be 0), or -1 if there's no next
         // value. If a value is found, it will be placed in the read
         // cache, because we cannot allocate a buffer for it before we know
@@ -136,6 +152,14 @@ pub mod tx {
             max_signatures_len: u64,
         ) -> i64;
 
+        // Hash the data with a native implementation of the hashing algorithm
+        pub fn namada_tx_hash_bytes(
+            algorithm: u8,
+            data_ptr: u64,
+            data_len: u64,
+            result_ptr: u64,
+        );
+
         /// Update the masp note commitment tree with the new notes
         pub fn namada_tx_update_masp_note_commitment_tree(
             transaction_ptr: u64,
@@ -193,6 +217,38 @@ pub mod vp {
             prefix_len: u64,
         ) -> u64;
 
+        // Get an ID of a data iterator with key prefix in prior state, in
+        // reverse order of storage keys.
+        pub fn namada_vp_rev_iter_prefix_pre(
+            prefix_ptr: u64,
+            prefix_len: u64,
+        ) -> u64;
+
+        // Get an ID of a data iterator with key prefix in posterior state, in
+        // reverse order of storage keys.
+        pub fn namada_vp_rev_iter_prefix_post(
+            prefix_ptr: u64,
+            prefix_len: u64,
+        ) -> u64;
+
+        // Get an ID of a data iterator over the keys within `[start, end)` in
+        // prior state, ordered by storage keys.
+        pub fn namada_vp_iter_range_pre(
+            start_ptr: u64,
+            start_len: u64,
+            end_ptr: u64,
+            end_len: u64,
+        ) -> u64;
+
+        // Get an ID of a data iterator over the keys within `[start, end)` in
+        // posterior state, ordered by storage keys.
+        pub fn namada_vp_iter_range_post(
+            start_ptr: u64,
+            start_len: u64,
+            end_ptr: u64,
+            end_len: u64,
+        ) -> u64;
+
         // Read variable-length iterator's next value when we don't know the
         // size up-front, returns the size of the value (can be 0), or
         // -1 if the key is not present. If a value is found, it will be
@@ -249,6 +305,14 @@ pub mod vp {
             max_signatures_len: u64,
         ) -> i64;
 
+        // Hash the data with a native implementation of the hashing algorithm
+        pub fn namada_vp_hash_bytes(
+            algorithm: u8,
+            data_ptr: u64,
+            data_len: u64,
+            result_ptr: u64,
+        );
+
         pub fn namada_vp_eval(
             vp_code_hash_ptr: u64,
             vp_code_hash_len: u64,
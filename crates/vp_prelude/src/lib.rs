@@ -25,6 +25,7 @@ pub use namada_core::types::address::Address;
 use namada_core::types::chain::CHAIN_ID_LENGTH;
 use namada_core::types::hash::{Hash, HASH_LENGTH};
 use namada_core::types::internal::HostEnvResult;
+pub use namada_core::types::internal::HostHashAlgorithm;
 use namada_core::types::storage::{
     BlockHash, BlockHeight, Epoch, Epochs, Header, TxIndex, BLOCK_HASH_LENGTH,
 };
@@ -33,7 +34,8 @@ pub use namada_governance::pgf::storage as pgf_storage;
 pub use namada_governance::storage as gov_storage;
 pub use namada_macros::validity_predicate;
 pub use namada_storage::{
-    iter_prefix, iter_prefix_bytes, Error, OptionExt, ResultExt, StorageRead,
+    collections, iter_prefix, iter_prefix_bytes, Error, OptionExt, ResultExt,
+    StorageRead,
 };
 pub use namada_tx::{Section, Tx};
 use namada_vm_env::vp::*;
@@ -50,6 +52,21 @@ pub fn sha256(bytes: &[u8]) -> Hash {
     Hash(*digest.as_ref())
 }
 
+/// Hash the given bytes using the host's native implementation of the
+/// hashing algorithm, which is cheaper than hashing them in wasm.
+pub fn hash_bytes(algorithm: HostHashAlgorithm, bytes: &[u8]) -> [u8; 32] {
+    let mut digest = [0u8; 32];
+    unsafe {
+        namada_vp_hash_bytes(
+            algorithm.to_u8(),
+            bytes.as_ptr() as _,
+            bytes.len() as _,
+            digest.as_mut_ptr() as _,
+        );
+    }
+    digest
+}
+
 /// Log a string. The message will be printed at the `tracing::Level::Info`.
 pub fn log_string<T: AsRef<str>>(msg: T) {
     let msg = msg.as_ref();
@@ -175,6 +192,72 @@ pub struct CtxPostStorageRead<'a> {
     _ctx: &'a Ctx,
 }
 
+impl CtxPreStorageRead<'_> {
+    /// Storage prefix iterator for prior state (before tx execution), in
+    /// reverse order of storage keys. The iterator is advanced with
+    /// [`StorageRead::iter_next`].
+    pub fn rev_iter_prefix(
+        &self,
+        prefix: &storage::Key,
+    ) -> EnvResult<KeyValIterator<(String, Vec<u8>)>> {
+        rev_iter_prefix_pre_impl(prefix)
+    }
+
+    /// Storage range iterator for prior state (before tx execution), over the
+    /// keys within `[start, end)` ordered by storage keys. The iterator is
+    /// advanced with [`StorageRead::iter_next`].
+    pub fn iter_range(
+        &self,
+        start: &storage::Key,
+        end: &storage::Key,
+    ) -> EnvResult<KeyValIterator<(String, Vec<u8>)>> {
+        let start = start.to_string();
+        let end = end.to_string();
+        let iter_id = unsafe {
+            namada_vp_iter_range_pre(
+                start.as_ptr() as _,
+                start.len() as _,
+                end.as_ptr() as _,
+                end.len() as _,
+            )
+        };
+        Ok(KeyValIterator(iter_id, PhantomData))
+    }
+}
+
+impl CtxPostStorageRead<'_> {
+    /// Storage prefix iterator for posterior state (after tx execution), in
+    /// reverse order of storage keys. The iterator is advanced with
+    /// [`StorageRead::iter_next`].
+    pub fn rev_iter_prefix(
+        &self,
+        prefix: &storage::Key,
+    ) -> EnvResult<KeyValIterator<(String, Vec<u8>)>> {
+        rev_iter_prefix_post_impl(prefix)
+    }
+
+    /// Storage range iterator for posterior state (after tx execution), over
+    /// the keys within `[start, end)` ordered by storage keys. The iterator is
+    /// advanced with [`StorageRead::iter_next`].
+    pub fn iter_range(
+        &self,
+        start: &storage::Key,
+        end: &storage::Key,
+    ) -> EnvResult<KeyValIterator<(String, Vec<u8>)>> {
+        let start = start.to_string();
+        let end = end.to_string();
+        let iter_id = unsafe {
+            namada_vp_iter_range_post(
+                start.as_ptr() as _,
+                start.len() as _,
+                end.as_ptr() as _,
+                end.len() as _,
+            )
+        };
+        Ok(KeyValIterator(iter_id, PhantomData))
+    }
+}
+
 /// Result of `VpEnv` or `namada_storage::StorageRead` method call
 pub type EnvResult<T> = Result<T, Error>;
 
@@ -498,6 +581,26 @@ fn iter_prefix_post_impl(
     Ok(KeyValIterator(iter_id, PhantomData))
 }
 
+fn rev_iter_prefix_pre_impl(
+    prefix: &storage::Key,
+) -> Result<KeyValIterator<(String, Vec<u8>)>, Error> {
+    let prefix = prefix.to_string();
+    let iter_id = unsafe {
+        namada_vp_rev_iter_prefix_pre(prefix.as_ptr() as _, prefix.len() as _)
+    };
+    Ok(KeyValIterator(iter_id, PhantomData))
+}
+
+fn rev_iter_prefix_post_impl(
+    prefix: &storage::Key,
+) -> Result<KeyValIterator<(String, Vec<u8>)>, Error> {
+    let prefix = prefix.to_string();
+    let iter_id = unsafe {
+        namada_vp_rev_iter_prefix_post(prefix.as_ptr() as _, prefix.len() as _)
+    };
+    Ok(KeyValIterator(iter_id, PhantomData))
+}
+
 fn get_chain_id() -> Result<String, Error> {
     let result = Vec::with_capacity(CHAIN_ID_LENGTH);
     unsafe {
@@ -0,0 +1,15 @@
+//! Fuzz the decoding of bech32m encoded addresses.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use namada_core::types::address::Address;
+
+fuzz_target!(|data: &str| {
+    if let Ok(address) = Address::decode(data) {
+        // A decoded address must encode back to a decodable string
+        let decoded =
+            Address::decode(address.encode()).expect("cannot decode address");
+        assert_eq!(address, decoded);
+    }
+});
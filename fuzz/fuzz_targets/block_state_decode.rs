@@ -0,0 +1,21 @@
+//! Fuzz the Borsh decoding of the block state fields persisted in the DB.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use namada_core::types::address::EstablishedAddressGen;
+use namada_core::types::decode;
+use namada_core::types::storage::{
+    BlockHash, BlockHeight, BlockResults, Epoch, Epochs,
+};
+use namada_core::types::time::DateTimeUtc;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = decode::<BlockHeight>(data);
+    let _ = decode::<BlockHash>(data);
+    let _ = decode::<BlockResults>(data);
+    let _ = decode::<DateTimeUtc>(data);
+    let _ = decode::<Epoch>(data);
+    let _ = decode::<Epochs>(data);
+    let _ = decode::<EstablishedAddressGen>(data);
+});
@@ -0,0 +1,14 @@
+//! Fuzz the parsing of storage keys, e.g. from RPC queries.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use namada_core::types::storage::Key;
+
+fuzz_target!(|data: &str| {
+    if let Ok(key) = Key::parse(data) {
+        // A parsed key must be printable and parse back to itself
+        let reparsed = Key::parse(key.to_string()).expect("cannot reparse key");
+        assert_eq!(key, reparsed);
+    }
+});
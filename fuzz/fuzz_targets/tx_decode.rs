@@ -0,0 +1,10 @@
+//! Fuzz the decoding of transactions received from the network.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use namada_tx::Tx;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = Tx::try_from(data);
+});
@@ -1,3 +1,5 @@
+#[cfg(feature = "tx_approve")]
+pub mod tx_approve;
 #[cfg(feature = "tx_become_validator")]
 pub mod tx_become_validator;
 #[cfg(feature = "tx_bond")]
@@ -30,6 +32,8 @@ pub mod tx_resign_steward;
 pub mod tx_reveal_pk;
 #[cfg(feature = "tx_transfer")]
 pub mod tx_transfer;
+#[cfg(feature = "tx_transfer_from")]
+pub mod tx_transfer_from;
 #[cfg(feature = "tx_unbond")]
 pub mod tx_unbond;
 #[cfg(feature = "tx_unjail_validator")]
@@ -0,0 +1,26 @@
+//! A tx to allow a spender to transfer some of the owner's tokens.
+//! This tx uses `token::Approve` wrapped inside `SignedTxData`
+//! as its input as declared in `namada` crate.
+
+use namada_tx_prelude::*;
+
+#[transaction(gas = 1222239)] // TODO: needs to be benchmarked
+fn apply_tx(ctx: &mut Ctx, tx_data: Tx) -> TxResult {
+    let signed = tx_data;
+    let data = signed.data().ok_or_err_msg("Missing data").map_err(|err| {
+        ctx.set_commitment_sentinel();
+        err
+    })?;
+    let approve = token::Approve::try_from_slice(&data[..])
+        .wrap_err("failed to decode token::Approve")?;
+    debug_log!("apply_tx called with approve: {:#?}", approve);
+
+    let amount = token::denom_to_amount(approve.amount, &approve.token, ctx)?;
+    token::approve(
+        ctx,
+        &approve.token,
+        &approve.owner,
+        &approve.spender,
+        amount,
+    )
+}
@@ -0,0 +1,27 @@
+//! A tx for a token transfer from an owner's allowance to a spender.
+//! This tx uses `token::TransferFrom` wrapped inside `SignedTxData`
+//! as its input as declared in `namada` crate.
+
+use namada_tx_prelude::*;
+
+#[transaction(gas = 1703358)] // TODO: needs to be benchmarked
+fn apply_tx(ctx: &mut Ctx, tx_data: Tx) -> TxResult {
+    let signed = tx_data;
+    let data = signed.data().ok_or_err_msg("Missing data").map_err(|err| {
+        ctx.set_commitment_sentinel();
+        err
+    })?;
+    let transfer = token::TransferFrom::try_from_slice(&data[..])
+        .wrap_err("failed to decode token::TransferFrom")?;
+    debug_log!("apply_tx called with transfer from: {:#?}", transfer);
+
+    let amount = token::denom_to_amount(transfer.amount, &transfer.token, ctx)?;
+    token::transfer_from(
+        ctx,
+        &transfer.token,
+        &transfer.spender,
+        &transfer.owner,
+        &transfer.target,
+        amount,
+    )
+}
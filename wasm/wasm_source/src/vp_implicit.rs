@@ -1,7 +1,9 @@
 //! Implicit account VP. All implicit accounts share this same VP.
 //!
 //! This VP currently provides a signature verification against a public key for
-//! sending tokens (receiving tokens is permissive).
+//! sending tokens (receiving tokens is permissive). Tokens can also be sent
+//! without it by a spender with an allowance approved by this account, in
+//! which case the spender's VP authorizes spending it.
 //!
 //! It allows to reveal a PK, as long as its address matches with the address
 //! that can be derived from the PK.
@@ -20,8 +22,14 @@ enum KeyType<'a> {
     /// Public key - written once revealed
     Pk(&'a Address),
     TokenBalance {
+        token: &'a Address,
         owner: &'a Address,
     },
+    TokenAllowance {
+        token: &'a Address,
+        owner: &'a Address,
+        spender: &'a Address,
+    },
     TokenMinted,
     TokenMinter(&'a Address),
     PoS,
@@ -36,10 +44,18 @@ impl<'a> From<&'a storage::Key> for KeyType<'a> {
     fn from(key: &'a storage::Key) -> KeyType<'a> {
         if let Some(address) = account::is_pks_key(key) {
             Self::Pk(address)
-        } else if let Some([_, owner]) =
+        } else if let Some([token, owner]) =
             token::storage_key::is_any_token_balance_key(key)
         {
-            Self::TokenBalance { owner }
+            Self::TokenBalance { token, owner }
+        } else if let Some([token, owner, spender]) =
+            token::storage_key::is_any_allowance_key(key)
+        {
+            Self::TokenAllowance {
+                token,
+                owner,
+                spender,
+            }
         } else if token::storage_key::is_any_minted_balance_key(key).is_some() {
             Self::TokenMinted
         } else if let Some(minter) = token::storage_key::is_any_minter_key(key)
@@ -113,15 +129,24 @@ fn validate_tx(
                 }
                 true
             }
-            KeyType::TokenBalance { owner, .. } => {
+            KeyType::TokenBalance { token, owner } => {
                 if owner == &addr {
                     let pre: token::Amount =
                         ctx.read_pre(key)?.unwrap_or_default();
                     let post: token::Amount =
                         ctx.read_post(key)?.unwrap_or_default();
                     let change = post.change() - pre.change();
-                    // debit has to signed, credit doesn't
-                    let valid = change.non_negative() || *valid_sig;
+                    // debit has to signed, credit doesn't, unless the debit is
+                    // spent from allowances
+                    let valid = change.non_negative()
+                        || *valid_sig
+                        || is_valid_allowance_spend(
+                            ctx,
+                            &addr,
+                            token,
+                            &keys_changed,
+                            &verifiers,
+                        )?;
                     let sign = if change.non_negative() { "" } else { "-" };
                     debug_log!(
                         "token key: {}, change: {}{:?}, valid_sig: {}, valid \
@@ -145,6 +170,34 @@ fn validate_tx(
                     true
                 }
             }
+            KeyType::TokenAllowance {
+                token,
+                owner,
+                spender,
+            } => {
+                if owner == &addr {
+                    // Allowance changes have to be signed, unless the
+                    // allowance is spent by the spender
+                    *valid_sig
+                        || is_valid_allowance_spend(
+                            ctx,
+                            &addr,
+                            token,
+                            &keys_changed,
+                            &verifiers,
+                        )?
+                } else if spender == &addr {
+                    let pre: token::Amount =
+                        ctx.read_pre(key)?.unwrap_or_default();
+                    let post: token::Amount =
+                        ctx.read_post(key)?.unwrap_or_default();
+                    // Spending an allowance has to be signed by the spender,
+                    // but the owner may revoke it without a debit
+                    post >= pre || *valid_sig || !is_debited(ctx, owner, token)?
+                } else {
+                    true
+                }
+            }
             KeyType::TokenMinted => verifiers.contains(&address::MULTITOKEN),
             KeyType::TokenMinter(minter) => minter != &addr || *valid_sig,
             KeyType::PoS => validate_pos_changes(ctx, &addr, key, &valid_sig)?,
@@ -165,6 +218,55 @@ fn validate_tx(
     accept()
 }
 
+/// Check that the debit of the owner's balance of the token is exactly covered
+/// by spending the owner's allowances. The VPs of the spenders check that
+/// spending their allowances is authorized.
+fn is_valid_allowance_spend(
+    ctx: &Ctx,
+    owner: &Address,
+    token: &Address,
+    keys_changed: &BTreeSet<storage::Key>,
+    verifiers: &BTreeSet<Address>,
+) -> VpResult {
+    let balance_key = token::storage_key::balance_key(token, owner);
+    let pre: token::Amount = ctx.read_pre(&balance_key)?.unwrap_or_default();
+    let post: token::Amount = ctx.read_post(&balance_key)?.unwrap_or_default();
+    let debit = match pre.checked_sub(post) {
+        Some(debit) if !debit.is_zero() => debit,
+        _ => return Ok(false),
+    };
+    let mut spent = token::Amount::zero();
+    for key in keys_changed {
+        match token::storage_key::is_any_allowance_key(key) {
+            Some([allowance_token, allowance_owner, spender])
+                if allowance_token == token && allowance_owner == owner =>
+            {
+                let pre: token::Amount = ctx.read_pre(key)?.unwrap_or_default();
+                let post: token::Amount =
+                    ctx.read_post(key)?.unwrap_or_default();
+                // Allowances can only be decreased by spending them
+                match pre
+                    .checked_sub(post)
+                    .and_then(|decrease| spent.checked_add(decrease))
+                {
+                    Some(total) if verifiers.contains(spender) => spent = total,
+                    _ => return Ok(false),
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(spent == debit)
+}
+
+/// Check if the owner's balance of the token is debited by the tx.
+fn is_debited(ctx: &Ctx, owner: &Address, token: &Address) -> VpResult {
+    let balance_key = token::storage_key::balance_key(token, owner);
+    let pre: token::Amount = ctx.read_pre(&balance_key)?.unwrap_or_default();
+    let post: token::Amount = ctx.read_post(&balance_key)?.unwrap_or_default();
+    Ok(post < pre)
+}
+
 fn validate_pos_changes(
     ctx: &Ctx,
     owner: &Address,
@@ -1,7 +1,9 @@
 //! A basic user VP supports both non-validator and validator accounts.
 //!
 //! This VP currently provides a signature verification against a public key for
-//! sending tokens (receiving tokens is permissive).
+//! sending tokens (receiving tokens is permissive). Tokens can also be sent
+//! without it by a spender with an allowance approved by this account, in
+//! which case the spender's VP authorizes spending it.
 //!
 //! It allows to bond, unbond and withdraw tokens to and from PoS system with a
 //! valid signature(s).
@@ -31,7 +33,15 @@ use proof_of_stake::storage_key::{
 };
 
 enum KeyType<'a> {
-    TokenBalance { owner: &'a Address },
+    TokenBalance {
+        token: &'a Address,
+        owner: &'a Address,
+    },
+    TokenAllowance {
+        token: &'a Address,
+        owner: &'a Address,
+        spender: &'a Address,
+    },
     TokenMinted,
     TokenMinter(&'a Address),
     PoS,
@@ -45,10 +55,18 @@ enum KeyType<'a> {
 
 impl<'a> From<&'a storage::Key> for KeyType<'a> {
     fn from(key: &'a storage::Key) -> KeyType<'a> {
-        if let Some([_, owner]) =
+        if let Some([token, owner]) =
             token::storage_key::is_any_token_balance_key(key)
         {
-            Self::TokenBalance { owner }
+            Self::TokenBalance { token, owner }
+        } else if let Some([token, owner, spender]) =
+            token::storage_key::is_any_allowance_key(key)
+        {
+            Self::TokenAllowance {
+                token,
+                owner,
+                spender,
+            }
         } else if token::storage_key::is_any_minted_balance_key(key).is_some() {
             Self::TokenMinted
         } else if let Some(minter) = token::storage_key::is_any_minter_key(key)
@@ -99,15 +117,24 @@ fn validate_tx(
     for key in keys_changed.iter() {
         let key_type: KeyType = key.into();
         let is_valid = match key_type {
-            KeyType::TokenBalance { owner, .. } => {
+            KeyType::TokenBalance { token, owner } => {
                 if owner == &addr {
                     let pre: token::Amount =
                         ctx.read_pre(key)?.unwrap_or_default();
                     let post: token::Amount =
                         ctx.read_post(key)?.unwrap_or_default();
                     let change = post.change() - pre.change();
-                    // debit has to signed, credit doesn't
-                    let valid = change.non_negative() || *valid_sig;
+                    // debit has to signed, credit doesn't, unless the debit is
+                    // spent from allowances
+                    let valid = change.non_negative()
+                        || *valid_sig
+                        || is_valid_allowance_spend(
+                            ctx,
+                            &addr,
+                            token,
+                            &keys_changed,
+                            &verifiers,
+                        )?;
                     debug_log!(
                         "token key: {}, change: {:?}, valid_sig: {}, valid \
                          modification: {}",
@@ -129,6 +156,34 @@ fn validate_tx(
                     true
                 }
             }
+            KeyType::TokenAllowance {
+                token,
+                owner,
+                spender,
+            } => {
+                if owner == &addr {
+                    // Allowance changes have to be signed, unless the
+                    // allowance is spent by the spender
+                    *valid_sig
+                        || is_valid_allowance_spend(
+                            ctx,
+                            &addr,
+                            token,
+                            &keys_changed,
+                            &verifiers,
+                        )?
+                } else if spender == &addr {
+                    let pre: token::Amount =
+                        ctx.read_pre(key)?.unwrap_or_default();
+                    let post: token::Amount =
+                        ctx.read_post(key)?.unwrap_or_default();
+                    // Spending an allowance has to be signed by the spender,
+                    // but the owner may revoke it without a debit
+                    post >= pre || *valid_sig || !is_debited(ctx, owner, token)?
+                } else {
+                    true
+                }
+            }
             KeyType::TokenMinted => verifiers.contains(&address::MULTITOKEN),
             KeyType::TokenMinter(minter) => minter != &addr || *valid_sig,
             KeyType::PoS => validate_pos_changes(ctx, &addr, key, &valid_sig)?,
@@ -157,6 +212,55 @@ fn validate_tx(
     accept()
 }
 
+/// Check that the debit of the owner's balance of the token is exactly covered
+/// by spending the owner's allowances. The VPs of the spenders check that
+/// spending their allowances is authorized.
+fn is_valid_allowance_spend(
+    ctx: &Ctx,
+    owner: &Address,
+    token: &Address,
+    keys_changed: &BTreeSet<storage::Key>,
+    verifiers: &BTreeSet<Address>,
+) -> VpResult {
+    let balance_key = token::storage_key::balance_key(token, owner);
+    let pre: token::Amount = ctx.read_pre(&balance_key)?.unwrap_or_default();
+    let post: token::Amount = ctx.read_post(&balance_key)?.unwrap_or_default();
+    let debit = match pre.checked_sub(post) {
+        Some(debit) if !debit.is_zero() => debit,
+        _ => return Ok(false),
+    };
+    let mut spent = token::Amount::zero();
+    for key in keys_changed {
+        match token::storage_key::is_any_allowance_key(key) {
+            Some([allowance_token, allowance_owner, spender])
+                if allowance_token == token && allowance_owner == owner =>
+            {
+                let pre: token::Amount = ctx.read_pre(key)?.unwrap_or_default();
+                let post: token::Amount =
+                    ctx.read_post(key)?.unwrap_or_default();
+                // Allowances can only be decreased by spending them
+                match pre
+                    .checked_sub(post)
+                    .and_then(|decrease| spent.checked_add(decrease))
+                {
+                    Some(total) if verifiers.contains(spender) => spent = total,
+                    _ => return Ok(false),
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(spent == debit)
+}
+
+/// Check if the owner's balance of the token is debited by the tx.
+fn is_debited(ctx: &Ctx, owner: &Address, token: &Address) -> VpResult {
+    let balance_key = token::storage_key::balance_key(token, owner);
+    let pre: token::Amount = ctx.read_pre(&balance_key)?.unwrap_or_default();
+    let post: token::Amount = ctx.read_post(&balance_key)?.unwrap_or_default();
+    Ok(post < pre)
+}
+
 fn validate_pos_changes(
     ctx: &Ctx,
     owner: &Address,
@@ -548,6 +652,94 @@ mod tests {
         );
     }
 
+    /// Test that a debit transfer without a valid signature is accepted when
+    /// it's spent from an allowance.
+    #[test]
+    fn test_unsigned_allowance_spend_accepted() {
+        // Initialize a tx environment
+        let mut tx_env = TestTxEnv::default();
+
+        let vp_owner = address::testing::established_address_1();
+        let spender = address::testing::established_address_2();
+        let token = address::nam();
+        let amount = token::Amount::from_uint(10_098_123, 0).unwrap();
+
+        // Spawn the accounts to be able to modify their storage
+        tx_env.spawn_accounts([&vp_owner, &spender, &token]);
+
+        // Credit the tokens to the VP owner and allow the spender to transfer
+        // them before running the transaction
+        tx_env.credit_tokens(&vp_owner, &token, amount);
+        token::approve(
+            &mut tx_env.wl_storage,
+            &token,
+            &vp_owner,
+            &spender,
+            amount,
+        )
+        .unwrap();
+
+        // Initialize VP environment from a transaction
+        vp_host_env::init_from_tx(vp_owner.clone(), tx_env, |address| {
+            // Spend the allowance in a transaction
+            token::transfer_from(
+                tx::ctx(),
+                &token,
+                &spender,
+                address,
+                &spender,
+                amount,
+            )
+            .unwrap();
+        });
+
+        let vp_env = vp_host_env::take();
+        let mut tx_data = Tx::from_type(TxType::Raw);
+        tx_data.set_data(Data::new(vec![]));
+        let keys_changed: BTreeSet<storage::Key> =
+            vp_env.all_touched_storage_keys();
+        let verifiers: BTreeSet<Address> = BTreeSet::from([spender]);
+        vp_host_env::set(vp_env);
+        assert!(
+            validate_tx(&CTX, tx_data, vp_owner, keys_changed, verifiers)
+                .unwrap()
+        );
+    }
+
+    /// Test that an allowance approval without a valid signature is rejected.
+    #[test]
+    fn test_unsigned_allowance_approval_rejected() {
+        // Initialize a tx environment
+        let mut tx_env = TestTxEnv::default();
+
+        let vp_owner = address::testing::established_address_1();
+        let spender = address::testing::established_address_2();
+        let token = address::nam();
+        let amount = token::Amount::from_uint(10_098_123, 0).unwrap();
+
+        // Spawn the accounts to be able to modify their storage
+        tx_env.spawn_accounts([&vp_owner, &spender, &token]);
+
+        // Initialize VP environment from a transaction
+        vp_host_env::init_from_tx(vp_owner.clone(), tx_env, |address| {
+            // Approve the allowance in a transaction
+            token::approve(tx::ctx(), &token, address, &spender, amount)
+                .unwrap();
+        });
+
+        let vp_env = vp_host_env::take();
+        let mut tx_data = Tx::from_type(TxType::Raw);
+        tx_data.set_data(Data::new(vec![]));
+        let keys_changed: BTreeSet<storage::Key> =
+            vp_env.all_touched_storage_keys();
+        let verifiers: BTreeSet<Address> = BTreeSet::default();
+        vp_host_env::set(vp_env);
+        assert!(
+            !validate_tx(&CTX, tx_data, vp_owner, keys_changed, verifiers)
+                .unwrap()
+        );
+    }
+
     /// Test that a non-validator PoS action that must be authorized is rejected
     /// without a valid signature.
     #[test]